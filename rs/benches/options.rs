@@ -0,0 +1,89 @@
+//! Parsing and rendering of option sets the size of the NixOS options.
+//!
+//! Run with `cargo bench --bench options`.
+
+use std::fs;
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use nixdoc::options::{
+    parse_options_file, parse_options_json, render_options_to_commonmark, RenderOptions,
+};
+
+/// Number of options, roughly that of a NixOS system.
+const OPTIONS: usize = 20_000;
+
+/// An options JSON of `count` options spread over a few hundred modules,
+/// with the attributes `nixos-render-docs` writes.
+fn options_json(count: usize) -> String {
+    let options: serde_json::Map<String, serde_json::Value> = (0..count)
+        .map(|i| {
+            let module = format!("services.service{}", i / 50);
+            let name = format!("{}.option{}", module, i % 50);
+            let option = serde_json::json!({
+                "loc": name.split('.').collect::<Vec<_>>(),
+                "description": format!(
+                    "Option {} of {}. See {{option}}`{}.enable`.\n\n\
+                     Set this to `true` to make things happen, e.g.\n\n\
+                     ```nix\n{{ {} = true; }}\n```",
+                    i, module, module, name
+                ),
+                "type": "attribute set of (submodule)",
+                "default": {
+                    "_type": "literalExpression",
+                    "text": "{\n  foo = \"bar\";\n  baz = [ 1 2 3 ];\n}"
+                },
+                "example": { "_type": "literalExpression", "text": "{ foo = \"qux\"; }" },
+                "declarations": [format!(
+                    "/nix/store/0123456789abcdfghijklmnpqrsvwxyz-source/nixos/modules/{}.nix",
+                    module.replace('.', "/")
+                )],
+                "readOnly": i % 100 == 0
+            });
+            (name, option)
+        })
+        .collect();
+    serde_json::to_string(&options).unwrap()
+}
+
+fn parse(c: &mut Criterion) {
+    let json = options_json(OPTIONS);
+    let path = std::env::temp_dir().join(format!("nixdoc-bench-{}.json", std::process::id()));
+    fs::write(&path, &json).unwrap();
+
+    let mut group = c.benchmark_group("parse");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(json.len() as u64));
+    group.bench_function("json", |b| {
+        b.iter(|| parse_options_json(black_box(&json)).unwrap())
+    });
+    group.bench_function("file", |b| {
+        b.iter(|| parse_options_file(black_box(&path)).unwrap())
+    });
+    group.finish();
+
+    fs::remove_file(&path).unwrap();
+}
+
+fn render(c: &mut Criterion) {
+    let options = parse_options_json(&options_json(OPTIONS)).unwrap();
+
+    let mut group = c.benchmark_group("render");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(options.len() as u64));
+    group.bench_function("commonmark", |b| {
+        b.iter(|| render_options_to_commonmark(black_box(&options), &RenderOptions::default()))
+    });
+    group.bench_function("commonmark grouped", |b| {
+        let render_opts = RenderOptions {
+            group_by_prefix: Some(2),
+            format_expressions: true,
+            ..Default::default()
+        };
+        b.iter(|| render_options_to_commonmark(black_box(&options), &render_opts))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse, render);
+criterion_main!(benches);
@@ -0,0 +1,240 @@
+//! Conversion between the syntaxes of admonitions.
+//!
+//! Nixpkgs writes admonitions as fenced divs (`::: {.note}`), GitHub as
+//! alerts (`> [!NOTE]`) and MyST as directives (```` ```{note} ```` or
+//! `:::{note}`). Admonitions in any of these syntaxes are recognized and
+//! written in the one of an [`AdmonitionStyle`], so documentation can be
+//! rendered for any of these renderers whatever its comments use.
+
+use crate::commonmark::ManualEntry;
+use crate::format::{code_block, get_fence};
+
+/// Kinds of admonitions all syntaxes support.
+const KINDS: [&str; 5] = ["note", "tip", "important", "warning", "caution"];
+
+/// Syntax admonitions are written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AdmonitionStyle {
+    /// Fenced divs, as used by nixpkgs: `::: {.note}`.
+    #[default]
+    Nixpkgs,
+    /// GitHub alerts: `> [!NOTE]`.
+    GitHub,
+    /// MyST directives: ```` ```{note} ````.
+    Myst,
+}
+
+impl AdmonitionStyle {
+    /// Look up a style by its command line name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "nixpkgs" => Some(Self::Nixpkgs),
+            "github" => Some(Self::GitHub),
+            "myst" => Some(Self::Myst),
+            _ => None,
+        }
+    }
+
+    /// Write an admonition of `kind` with the markdown `body`.
+    fn write(&self, kind: &str, body: &str, out: &mut String) {
+        match self {
+            Self::Nixpkgs => {
+                out.push_str(&format!("::: {{.{kind}}}\n{body}:::\n"));
+            }
+            Self::GitHub => {
+                out.push_str(&format!("> [!{}]\n", kind.to_uppercase()));
+                for line in body.lines() {
+                    if line.is_empty() {
+                        out.push_str(">\n");
+                    } else {
+                        out.push_str(&format!("> {line}\n"));
+                    }
+                }
+            }
+            Self::Myst => {
+                push_line(&code_block(&format!("{{{kind}}}"), body.trim_end()), out);
+            }
+        }
+    }
+}
+
+/// Rewrite the admonitions in `markdown` in `style`. Other fenced divs and
+/// blockquotes are kept, though admonitions within them are rewritten too.
+/// Only admonitions starting at the beginning of a line are recognized, not
+/// those nested in lists.
+pub fn convert_admonitions(markdown: &str, style: AdmonitionStyle) -> String {
+    let lines: Vec<&str> = markdown.lines().collect();
+    let mut out = convert_lines(&lines, style);
+    if !markdown.ends_with('\n') {
+        out.pop();
+    }
+    out
+}
+
+/// Rewrite the admonitions in the descriptions, section texts and examples of
+/// `entries`.
+pub fn convert_entries(entries: &mut [ManualEntry], style: AdmonitionStyle) {
+    for entry in entries {
+        let description = convert_admonitions(&entry.description.join("\n\n"), style);
+        entry.description = description.split("\n\n").map(str::to_string).collect();
+        if let Some(section) = &mut entry.section {
+            *section = convert_admonitions(section, style);
+        }
+        for example in &mut entry.examples {
+            example.body = convert_admonitions(&example.body, style);
+        }
+    }
+}
+
+fn convert_lines(lines: &[&str], style: AdmonitionStyle) -> String {
+    let mut out = String::new();
+    let mut code_fence: Option<(usize, char)> = None;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim();
+        i += 1;
+
+        if let Some((count, ch)) = code_fence {
+            if get_fence(trimmed, false).is_some_and(|(c, h)| h == ch && c >= count) {
+                code_fence = None;
+            }
+            push_line(line, &mut out);
+            continue;
+        }
+        if let Some((kind, count)) = myst_directive(line) {
+            let end = (i..lines.len()).find(|&j| {
+                get_fence(lines[j].trim(), false).is_some_and(|(c, h)| h == '`' && c >= count)
+            });
+            if let Some(end) = end {
+                let body = convert_lines(trim_blank(&lines[i..end]), style);
+                style.write(kind, &body, &mut out);
+                i = end + 1;
+                continue;
+            }
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            code_fence = get_fence(trimmed, true);
+            push_line(line, &mut out);
+            continue;
+        }
+        if line.starts_with(char::is_whitespace) {
+            push_line(line, &mut out);
+            continue;
+        }
+
+        if let Some(attrs) = div_attrs(line) {
+            if let Some(end) = closing_div(lines, i) {
+                let body = convert_lines(trim_blank(&lines[i..end]), style);
+                match div_kind(attrs) {
+                    Some(kind) => style.write(kind, &body, &mut out),
+                    None => {
+                        push_line(line, &mut out);
+                        out.push_str(&body);
+                        push_line(lines[end], &mut out);
+                    }
+                }
+                i = end + 1;
+                continue;
+            }
+        }
+
+        if let Some(kind) = alert_kind(line) {
+            let end = (i..lines.len())
+                .find(|&j| !lines[j].starts_with('>'))
+                .unwrap_or(lines.len());
+            let quoted: Vec<&str> = lines[i..end]
+                .iter()
+                .map(|line| {
+                    let line = &line[1..];
+                    line.strip_prefix(' ').unwrap_or(line)
+                })
+                .collect();
+            let body = convert_lines(trim_blank(&quoted), style);
+            style.write(kind, &body, &mut out);
+            i = end;
+            continue;
+        }
+
+        push_line(line, &mut out);
+    }
+    out
+}
+
+fn push_line(line: &str, out: &mut String) {
+    out.push_str(line);
+    out.push('\n');
+}
+
+/// Drop leading and trailing blank lines.
+fn trim_blank<'a, 'b>(lines: &'b [&'a str]) -> &'b [&'a str] {
+    let start = lines
+        .iter()
+        .position(|l| !l.trim().is_empty())
+        .unwrap_or(lines.len());
+    let end = lines
+        .iter()
+        .rposition(|l| !l.trim().is_empty())
+        .map_or(start, |p| p + 1);
+    &lines[start..end]
+}
+
+/// The attributes of an opening fenced div line, e.g. `{.note}` or, for
+/// MyST, `{note}`.
+fn div_attrs(line: &str) -> Option<&str> {
+    line.strip_prefix(":::")
+        .map(str::trim)
+        .filter(|attrs| !attrs.is_empty() && !attrs.starts_with(':'))
+}
+
+/// The admonition kind of a fenced div whose only attribute is an
+/// admonition class (`{.note}`), or whose MyST directive is one (`{note}`).
+fn div_kind(attrs: &str) -> Option<&'static str> {
+    let inner = attrs.strip_prefix('{')?.strip_suffix('}')?.trim();
+    let name = inner.strip_prefix('.').unwrap_or(inner);
+    KINDS.into_iter().find(|kind| *kind == name)
+}
+
+/// The index of the line closing the fenced div opened before `start`.
+fn closing_div(lines: &[&str], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut code_fence: Option<(usize, char)> = None;
+    for (j, line) in lines.iter().enumerate().skip(start) {
+        let trimmed = line.trim();
+        if let Some((count, ch)) = code_fence {
+            if get_fence(trimmed, false).is_some_and(|(c, h)| h == ch && c >= count) {
+                code_fence = None;
+            }
+        } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            code_fence = get_fence(trimmed, true);
+        } else if trimmed == ":::" {
+            if depth == 0 {
+                return Some(j);
+            }
+            depth -= 1;
+        } else if div_attrs(trimmed).is_some() {
+            depth += 1;
+        }
+    }
+    None
+}
+
+/// The kind and fence length of a MyST directive opening an admonition
+/// (```` ```{note} ````).
+fn myst_directive(line: &str) -> Option<(&'static str, usize)> {
+    let (count, ch) = get_fence(line, true)?;
+    if ch != '`' {
+        return None;
+    }
+    let kind = div_kind(line[count..].trim())?;
+    Some((kind, count))
+}
+
+/// The kind of a line opening a GitHub alert (`> [!NOTE]`).
+fn alert_kind(line: &str) -> Option<&'static str> {
+    let marker = line.strip_prefix('>')?.trim();
+    let name = marker.strip_prefix("[!")?.strip_suffix(']')?;
+    KINDS
+        .into_iter()
+        .find(|kind| kind.eq_ignore_ascii_case(name))
+}
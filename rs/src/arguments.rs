@@ -0,0 +1,259 @@
+//! Argument documentation of RFC145 doc comments.
+//!
+//! Functions document their arguments in an `# Arguments` or `# Inputs`
+//! section, either as a definition list or as a bullet list:
+//!
+//! ```markdown
+//! # Inputs
+//!
+//! `f` (`a -> b`)
+//!
+//! : Function to map
+//!
+//! - `list`: List of values
+//! ```
+//!
+//! A term is the argument's name, optionally followed by its type in
+//! parentheses or after `::`. The parsed arguments are included in the JSON
+//! output and checked against the arguments the function actually takes.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::commonmark::Argument;
+use crate::format::get_fence;
+
+/// A documented argument of a function.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ArgumentDoc {
+    pub name: String,
+    /// The argument's type, as written.
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub ty: Option<String>,
+    pub description: String,
+}
+
+/// A difference between the documented arguments of a function and the
+/// arguments it takes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArgumentMismatch {
+    /// A documented argument the function does not take.
+    Unknown(String),
+    /// An argument the function takes that is not documented.
+    Undocumented(String),
+}
+
+impl ArgumentMismatch {
+    pub fn message(&self) -> String {
+        match self {
+            Self::Unknown(name) => {
+                format!(
+                    "documents argument `{}`, which the function does not take",
+                    name
+                )
+            }
+            Self::Undocumented(name) => format!("does not document its argument `{}`", name),
+        }
+    }
+}
+
+/// The arguments documented in the `# Arguments` or `# Inputs` section of a
+/// doc comment. Items whose term is not an argument name are skipped.
+pub fn parse_arguments_section(doc: &str) -> Vec<ArgumentDoc> {
+    let mut arguments = vec![];
+    let mut section_level: Option<usize> = None;
+    let mut fence: Option<(usize, char)> = None;
+    // The argument being described, and whether a blank line followed it.
+    let mut current: Option<ArgumentDoc> = None;
+    let mut blank = false;
+    let mut term: Option<&str> = None;
+
+    for line in doc.lines() {
+        let trimmed = line.trim();
+        if let Some((count, ch)) = fence {
+            if get_fence(trimmed, false).is_some_and(|(c, h)| h == ch && c >= count) {
+                fence = None;
+            }
+            if let Some(arg) = &mut current {
+                push_line(&mut arg.description, line, &mut blank);
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = get_fence(trimmed, true);
+            if let Some(arg) = &mut current {
+                push_line(&mut arg.description, line, &mut blank);
+            }
+            continue;
+        }
+
+        let level = trimmed.chars().take_while(|&c| c == '#').count();
+        if level > 0 && trimmed[level..].starts_with(' ') {
+            if section_level.is_some_and(|l| level <= l) {
+                break;
+            }
+            arguments.extend(current.take());
+            term = None;
+            let title = trimmed[level..].trim();
+            if section_level.is_none()
+                && (title.eq_ignore_ascii_case("arguments") || title.eq_ignore_ascii_case("inputs"))
+            {
+                section_level = Some(level);
+            }
+            continue;
+        }
+        if section_level.is_none() {
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            blank = true;
+        } else if let Some(definition) = trimmed.strip_prefix(':').filter(|_| term.is_some()) {
+            arguments.extend(current.take());
+            current = term
+                .take()
+                .and_then(parse_term)
+                .map(|(name, ty)| ArgumentDoc {
+                    name,
+                    ty,
+                    description: definition.trim().to_string(),
+                });
+            blank = false;
+        } else if let Some(item) = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "))
+            .filter(|_| !line.starts_with([' ', '\t']))
+        {
+            arguments.extend(current.take());
+            term = None;
+            current = parse_item(item);
+            blank = false;
+        } else if current.is_some() && (!blank || line.starts_with([' ', '\t'])) {
+            if let Some(arg) = &mut current {
+                push_line(&mut arg.description, line, &mut blank);
+            }
+        } else {
+            arguments.extend(current.take());
+            term = Some(trimmed);
+            blank = false;
+        }
+    }
+    arguments.extend(current);
+    arguments
+}
+
+/// Append a continuation line to a description, keeping paragraph breaks.
+fn push_line(description: &mut String, line: &str, blank: &mut bool) {
+    if !description.is_empty() {
+        description.push_str(if *blank { "\n\n" } else { "\n" });
+    }
+    description.push_str(line.trim());
+    *blank = false;
+}
+
+/// Parse a definition list term: a name in backticks or a plain identifier,
+/// optionally followed by a type in parentheses or after `::`.
+fn parse_term(term: &str) -> Option<(String, Option<String>)> {
+    let (name, rest) = split_name(term)?;
+    let rest = rest.trim();
+    let ty = if let Some(ty) = rest.strip_prefix("::") {
+        Some(ty)
+    } else if let Some(ty) = rest.strip_prefix('(').and_then(|r| r.strip_suffix(')')) {
+        Some(ty)
+    } else if rest.is_empty() {
+        None
+    } else {
+        return None;
+    };
+    Some((name, ty.map(strip_code).filter(|ty| !ty.is_empty())))
+}
+
+/// Parse a bullet list item: `` `name`: description `` or
+/// `` `name` (`type`): description ``.
+fn parse_item(item: &str) -> Option<ArgumentDoc> {
+    let (name, rest) = split_name(item)?;
+    let mut rest = rest.trim_start();
+    let mut ty = None;
+    if let Some(inner) = rest.strip_prefix('(') {
+        let end = inner.find(')')?;
+        ty = Some(strip_code(&inner[..end])).filter(|ty| !ty.is_empty());
+        rest = inner[end + 1..].trim_start();
+    }
+    let description = rest
+        .strip_prefix(':')
+        .or_else(|| rest.strip_prefix('-'))
+        .or_else(|| rest.strip_prefix('–'))?;
+    Some(ArgumentDoc {
+        name,
+        ty,
+        description: description.trim().to_string(),
+    })
+}
+
+/// Split an argument name (in backticks or a plain identifier) off the
+/// start of `text`.
+fn split_name(text: &str) -> Option<(String, &str)> {
+    let (name, rest) = match text.strip_prefix('`') {
+        Some(quoted) => {
+            let end = quoted.find('`')?;
+            (&quoted[..end], &quoted[end + 1..])
+        }
+        None => {
+            let end = text
+                .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '\'' | '-')))
+                .unwrap_or(text.len());
+            text.split_at(end)
+        }
+    };
+    let is_ident = name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '\'' | '-'));
+    is_ident.then(|| (name.to_string(), rest))
+}
+
+/// Remove surrounding whitespace and backticks.
+fn strip_code(text: &str) -> String {
+    text.trim().trim_matches('`').trim().to_string()
+}
+
+/// Compare documented arguments with the arguments a function takes.
+/// Pattern attributes need not be documented if the whole pattern is, by
+/// the name it is bound to. Nothing is reported if either list is empty.
+pub fn check_arguments(documented: &[ArgumentDoc], args: &[Argument]) -> Vec<ArgumentMismatch> {
+    if documented.is_empty() || args.is_empty() {
+        return vec![];
+    }
+    let is_documented = |name: &str| documented.iter().any(|d| d.name == name);
+
+    let mut taken: Vec<&str> = vec![];
+    let mut mismatches = vec![];
+    for arg in args {
+        match arg {
+            Argument::Flat(arg) => {
+                taken.push(&arg.name);
+                if !is_documented(&arg.name) {
+                    mismatches.push(ArgumentMismatch::Undocumented(arg.name.clone()));
+                }
+            }
+            Argument::Pattern { args, bind, .. } => {
+                taken.extend(bind.as_deref());
+                taken.extend(args.iter().map(|arg| arg.name.as_str()));
+                if !bind.as_deref().is_some_and(is_documented) {
+                    mismatches.extend(
+                        args.iter()
+                            .filter(|arg| !is_documented(&arg.name))
+                            .map(|arg| ArgumentMismatch::Undocumented(arg.name.clone())),
+                    );
+                }
+            }
+        }
+    }
+    mismatches.extend(
+        documented
+            .iter()
+            .filter(|d| !taken.contains(&d.name.as_str()))
+            .map(|d| ArgumentMismatch::Unknown(d.name.clone())),
+    );
+    mismatches
+}
@@ -0,0 +1,246 @@
+//! Rendering of generated markdown as AsciiDoc, for Asciidoctor and Antora.
+//!
+//! Headings with an `{#anchor}` attribute and `[]{#anchor}` lines get a
+//! block anchor (`[[anchor]]`), and links to `#anchor`s become cross
+//! references (`<<anchor,text>>`). Code blocks become `source` blocks,
+//! fenced divs admonition or example blocks, and blocks spanning several
+//! paragraphs of list items are attached with list continuations (`+`).
+//! Markdown heading levels start at AsciiDoc section level 1 (`==`), so the
+//! output can be included below a page title; headings inside of blocks are
+//! rendered as discrete headings.
+
+use crate::format::{divs_to_html, strip_role};
+use crate::markup::{render, Frame, List, Syntax, Writer};
+
+/// AsciiDoc markup, for [`render`].
+struct AsciiDoc;
+
+impl Syntax for AsciiDoc {
+    const SEPARATOR: &'static str = "{empty}";
+    const EMPHASIS: &'static str = "_";
+    const STRONG: &'static str = "*";
+    const HARD_BREAK: &'static str = " +\n";
+    const RULE: &'static str = "'''";
+
+    fn escape(text: &str) -> String {
+        escape(text)
+    }
+
+    /// Append a block. Blocks are separated by a blank line, or attached
+    /// with a list continuation inside of list items.
+    fn block(parent: &Frame, buf: &mut String, block: &str) {
+        let continuation = matches!(parent, Frame::Item { .. } | Frame::Definition);
+        if !buf.is_empty() {
+            let len = buf.trim_end_matches('\n').len();
+            buf.truncate(len);
+            buf.push_str(if continuation { "\n+\n" } else { "\n\n" });
+        }
+        buf.push_str(block.trim_end_matches('\n'));
+        buf.push('\n');
+    }
+
+    fn item_marker(lists: &[List]) -> String {
+        let depth = lists.len();
+        match lists.last() {
+            Some(List { start: Some(_), .. }) => ".".repeat(depth),
+            _ => "*".repeat(depth),
+        }
+    }
+
+    fn anchor(id: &str) -> String {
+        format!("[[{}]]", id)
+    }
+
+    fn code(w: &mut Writer<Self>, code: &str) {
+        // AsciiDoc has no roles; all of them are rendered as literals.
+        // The role's brace was escaped as an attribute reference.
+        if strip_role(w.buf()).is_some() && w.buf().ends_with('\\') {
+            w.buf().pop();
+        }
+        w.markup(&format!("`+{}+`", code));
+    }
+
+    fn close(w: &mut Writer<Self>, frame: Frame, content: String) {
+        match frame {
+            Frame::Heading { level, id } => {
+                let mut heading = String::new();
+                if let Some(id) = id {
+                    heading.push_str(&Self::anchor(&id));
+                    heading.push('\n');
+                }
+                if w.nested() {
+                    heading.push_str("[discrete]\n");
+                }
+                heading.push_str(&format!(
+                    "{} {}",
+                    "=".repeat(level.min(5) + 1),
+                    content.trim().replace('\n', " ")
+                ));
+                w.block(&heading);
+            }
+            Frame::CodeBlock { language } => {
+                let delimiter = delimiter('-', &content);
+                let attributes = if language.is_empty() {
+                    String::new()
+                } else {
+                    format!("[source,{}]\n", language)
+                };
+                w.block(&format!(
+                    "{}{}\n{}\n{}",
+                    attributes,
+                    delimiter,
+                    content.trim_end_matches('\n'),
+                    delimiter
+                ));
+            }
+            Frame::BlockQuote => {
+                let delimiter = delimiter('_', &content);
+                w.block(&format!(
+                    "{}\n{}\n{}",
+                    delimiter,
+                    content.trim_end(),
+                    delimiter
+                ));
+            }
+            Frame::Item { marker } => {
+                let Some(&List { start, items }) = w.lists.last() else {
+                    return;
+                };
+                let first = items == 1;
+                let mut item = String::new();
+                if let Some(start) = start.filter(|&start| first && start != 1) {
+                    item.push_str(&format!("[start={}]\n", start));
+                }
+                item.push_str(&format!("{} {}\n", marker, content.trim()));
+                // Nested lists and further items directly follow the previous
+                // line; other lists are blocks of their own.
+                if first && !matches!(w.stack.last(), Some((Frame::Item { .. }, _))) {
+                    w.block(&item);
+                } else {
+                    let buf = w.buf();
+                    if !buf.is_empty() && !buf.ends_with('\n') {
+                        buf.push('\n');
+                    }
+                    buf.push_str(&item);
+                }
+            }
+            Frame::DefinitionTitle => w.block(&format!("{}::", content.trim())),
+            Frame::Definition => {
+                let buf = w.buf();
+                buf.push_str(content.trim());
+                buf.push('\n');
+            }
+            Frame::Link { dest } => {
+                let text = content.trim().replace(']', "\\]");
+                let link = match dest.strip_prefix('#') {
+                    Some(id) if text.is_empty() => format!("<<{}>>", id),
+                    Some(id) => format!("<<{},{}>>", id, text),
+                    None if text.is_empty() || text == dest => dest,
+                    None => format!("link:{}[{}]", dest, text),
+                };
+                w.markup(&link);
+            }
+            Frame::Image { dest } => {
+                w.markup(&format!(
+                    "image:{}[{}]",
+                    dest,
+                    content.trim().replace(']', "\\]")
+                ));
+            }
+            Frame::Table { rows, header } => {
+                let mut table = String::new();
+                if header {
+                    table.push_str("[%header]\n");
+                }
+                table.push_str("|===\n");
+                for (i, row) in rows.iter().enumerate() {
+                    let cells: Vec<String> = row.iter().map(|cell| format!("|{}", cell)).collect();
+                    table.push_str(&cells.join(" "));
+                    table.push('\n');
+                    if header && i == 0 {
+                        table.push('\n');
+                    }
+                }
+                table.push_str("|===");
+                w.block(&table);
+            }
+            Frame::Div(block) => {
+                // Example blocks nested in each other need delimiters of
+                // different lengths.
+                let delimiter = "=".repeat(4 + w.divs());
+                let attributes = if block == "example" {
+                    String::new()
+                } else {
+                    format!("[{}]\n", block)
+                };
+                w.block(&format!(
+                    "{}{}\n{}\n{}",
+                    attributes,
+                    delimiter,
+                    content.trim_end(),
+                    delimiter
+                ));
+            }
+            _ => (),
+        }
+    }
+
+    fn cell(content: &str) -> String {
+        content.replace('|', "\\|")
+    }
+}
+
+/// Convert markdown to AsciiDoc.
+pub fn markdown_to_asciidoc(markdown: &str) -> String {
+    let source = divs_to_html(markdown, |class| match class {
+        "note" | "tip" | "warning" | "important" | "caution" => Some(class.to_uppercase()),
+        "example" => Some(class.to_string()),
+        _ => None,
+    });
+    render::<AsciiDoc>(&source)
+}
+
+/// Delimiter of a block made of `ch`, longer than any line of `content` that
+/// would end the block early.
+fn delimiter(ch: char, content: &str) -> String {
+    let mut delimiter = ch.to_string().repeat(4);
+    while content.lines().any(|line| line.trim_end() == delimiter) {
+        delimiter.push(ch);
+    }
+    delimiter
+}
+
+/// Escape characters that start inline markup or attribute references.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut previous: Option<char> = None;
+    for (i, c) in text.char_indices() {
+        match c {
+            '*' => out.push_str("{asterisk}"),
+            '`' => out.push_str("{backtick}"),
+            '+' => out.push_str("{plus}"),
+            '^' => out.push_str("{caret}"),
+            '~' => out.push_str("{tilde}"),
+            // Only at the start of a word, elsewhere they do not start markup.
+            '_' | '#' if !previous.is_some_and(char::is_alphanumeric) => {
+                out.push_str(&format!("pass:[{}]", c));
+            }
+            '{' if is_attribute_reference(&text[i + 1..]) => out.push_str("\\{"),
+            c => out.push(c),
+        }
+        previous = Some(c);
+    }
+    out
+}
+
+/// Whether `rest`, following a `{`, completes an attribute reference.
+fn is_attribute_reference(rest: &str) -> bool {
+    let Some(end) = rest.find('}') else {
+        return false;
+    };
+    let name = &rest[..end];
+    name.starts_with(|c: char| c.is_alphanumeric() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-'))
+}
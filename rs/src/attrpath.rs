@@ -0,0 +1,89 @@
+//! Names of attribute bindings.
+//!
+//! Besides identifiers, attribute names can be strings (`"foo bar" = ...;`)
+//! and interpolations (`${name} = ...;`). String names are shown quoted
+//! where they are not valid identifiers, as they would be written in Nix.
+//! Names containing interpolations are only known when evaluating, so such
+//! bindings are not documented; [`dynamic_bindings`] lists the documented
+//! ones to warn about.
+
+use rnix::ast::{self, Attr, Attrpath, Expr};
+use rnix::{SyntaxKind, SyntaxNode};
+use rowan::ast::AstNode;
+
+use crate::meta::literal_string;
+use crate::retrieve_doc_comment;
+
+/// Keywords, which must be quoted when used as attribute names.
+const KEYWORDS: [&str; 9] = [
+    "assert", "else", "if", "in", "inherit", "let", "rec", "then", "with",
+];
+
+/// A documented binding whose name contains an interpolation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DynamicBinding {
+    /// The attribute path as written, e.g. `${name}.enable`.
+    pub attrpath: String,
+    /// Byte offset of the binding in the source.
+    pub offset: usize,
+}
+
+/// The name of an attribute: an identifier, or a string quoted unless it is
+/// a valid identifier. Returns nothing for names with interpolations.
+pub fn attr_name(attr: &Attr) -> Option<String> {
+    match attr {
+        Attr::Ident(ident) => Some(ident.syntax().text().to_string()),
+        Attr::Str(s) => literal_string(&Expr::Str(s.clone())).map(|name| quote(&name)),
+        // `${"foo"}` is just a string.
+        Attr::Dynamic(dynamic) => dynamic
+            .expr()
+            .and_then(|expr| literal_string(&expr))
+            .map(|name| quote(&name)),
+    }
+}
+
+/// The dotted name of an attribute path, see [`attr_name`].
+pub fn attrpath_name(path: &Attrpath) -> Option<String> {
+    path.attrs()
+        .map(|attr| attr_name(&attr))
+        .collect::<Option<Vec<_>>>()
+        .map(|names| names.join("."))
+}
+
+/// Quote `name` as a string if it is not a valid identifier.
+fn quote(name: &str) -> String {
+    let is_ident = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '\'' | '-'))
+        && !KEYWORDS.contains(&name);
+    if is_ident {
+        return name.to_string();
+    }
+    let escaped = name
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace("${", "\\${")
+        .replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
+
+/// Returns the bindings below `root` that have a doc comment but a name
+/// with interpolations.
+pub fn dynamic_bindings(root: &SyntaxNode) -> Vec<DynamicBinding> {
+    root.descendants()
+        .filter(|node| node.kind() == SyntaxKind::NODE_ATTRPATH_VALUE)
+        .filter_map(ast::AttrpathValue::cast)
+        .filter_map(|apv| {
+            let path = apv.attrpath()?;
+            if attrpath_name(&path).is_some() {
+                return None;
+            }
+            retrieve_doc_comment(apv.syntax(), None)?;
+            Some(DynamicBinding {
+                attrpath: path.syntax().text().to_string(),
+                offset: apv.syntax().text_range().start().into(),
+            })
+        })
+        .collect()
+}
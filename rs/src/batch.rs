@@ -0,0 +1,436 @@
+//! Rendering of many Nix files in one invocation.
+//!
+//! Each input file becomes one category, named after the file stem
+//! (`lib/strings.nix` → `strings`). References in doc comments are resolved
+//! across all inputs, so entries can link to entries of other categories.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+use rowan::ast::AstNode;
+
+use crate::admonition::{convert_admonitions, convert_entries, AdmonitionStyle};
+use crate::arguments::check_arguments;
+use crate::attrpath::dynamic_bindings;
+use crate::cache::EntryCache;
+use crate::comment::{oversized_doc_comments, MAX_DOC_COMMENT_LEN};
+use crate::commonmark::{
+    disambiguate_identifiers, sort_entries, write_entries_with, EntryLabels, EntrySection,
+    ManualEntry,
+};
+use crate::format::{code_span, line_column, HeadingLevels};
+use crate::glob::glob_match;
+use crate::options::SortOrder;
+use crate::slug::AnchorCollision;
+use crate::walk::files_with_extension;
+use crate::xref::{resolve_references, DanglingReference};
+use crate::{collect_entries, read_source, retrieve_description, CollectOptions};
+
+/// Settings shared by all files of a batch.
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    /// Prefix for the categories (e.g. 'lib').
+    pub prefix: String,
+    /// Prefix for anchor IDs.
+    pub anchor_prefix: String,
+    /// Glob patterns of entry names to include.
+    pub include: Vec<String>,
+    /// Glob patterns of entry names to exclude.
+    pub exclude: Vec<String>,
+    /// Directory of the [entry cache](crate::cache), if any.
+    pub cache_dir: Option<PathBuf>,
+    /// Order of the parts of rendered entries.
+    pub layout: Vec<EntrySection>,
+    /// Syntax to rewrite admonitions in, if any.
+    pub admonition_style: Option<AdmonitionStyle>,
+    /// Order of the entries of each file.
+    pub sort: SortOrder,
+    /// Levels of the category and entry headings.
+    pub heading_levels: HeadingLevels,
+    /// Labels of the parts of rendered entries and of the index.
+    pub labels: EntryLabels,
+    /// Language of examples whose heading names none, see
+    /// [`CollectOptions::default_example_language`].
+    pub default_example_language: Option<String>,
+    /// Extension of the pages categories are written to, e.g. `md` for
+    /// `<category>.md`. References to entries of other categories link to
+    /// their page; all categories are taken to share one document if unset.
+    pub page_extension: Option<String>,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            prefix: "lib".to_string(),
+            anchor_prefix: "function-library-".to_string(),
+            include: vec![],
+            exclude: vec![],
+            cache_dir: None,
+            layout: EntrySection::ALL.to_vec(),
+            admonition_style: None,
+            sort: SortOrder::Source,
+            heading_levels: HeadingLevels::default(),
+            labels: EntryLabels::default(),
+            default_example_language: None,
+            page_extension: None,
+        }
+    }
+}
+
+impl BatchOptions {
+    /// The page a category is written to, empty if categories share one
+    /// document.
+    fn page(&self, category: &str) -> String {
+        self.page_extension
+            .as_ref()
+            .map(|ext| format!("{}.{}", category, ext))
+            .unwrap_or_default()
+    }
+}
+
+/// The rendered documentation of a single input file.
+#[derive(Debug, Clone)]
+pub struct BatchOutput {
+    pub input: PathBuf,
+    pub category: String,
+    pub markdown: String,
+    /// References to entries that exist in no input of the batch.
+    pub dangling: Vec<DanglingReference>,
+    /// Problems with the input that did not stop it from being rendered,
+    /// such as invalid UTF-8 or truncated doc comments.
+    pub warnings: Vec<String>,
+    /// The entries of the page, for the [index](render_index).
+    pub index: Vec<IndexEntry>,
+}
+
+/// An entry of the index of all functions of a batch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexEntry {
+    pub name: String,
+    /// Identifier of the entry, e.g. `lib.strings.concat`.
+    pub ident: String,
+    pub title: String,
+    pub summary: Option<String>,
+}
+
+impl IndexEntry {
+    fn new(entry: &ManualEntry) -> Self {
+        let (ident, title) = entry.get_ident_title();
+        Self {
+            name: entry.name.clone(),
+            ident,
+            title,
+            summary: entry.summary.clone(),
+        }
+    }
+
+    /// The letter the entry is listed under, or `None` for names starting
+    /// with another character.
+    fn letter(&self) -> Option<char> {
+        self.name
+            .chars()
+            .next()
+            .filter(char::is_ascii_alphabetic)
+            .map(|c| c.to_ascii_uppercase())
+    }
+}
+
+/// Expand a list of inputs into Nix files. Directories are searched
+/// recursively and paths containing `*` or `?` are matched as globs against
+/// the files below their longest literal parent directory.
+pub fn expand_inputs(inputs: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for input in inputs {
+        let pattern = input.to_string_lossy();
+        if pattern.contains(['*', '?']) {
+            let base: PathBuf = input
+                .components()
+                .take_while(|c| !c.as_os_str().to_string_lossy().contains(['*', '?']))
+                .collect();
+            let root = if base.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                &base
+            };
+            files.extend(
+                files_with_extension(root, "nix")?
+                    .into_iter()
+                    .filter(|f| glob_match(&pattern, &f.to_string_lossy())),
+            );
+        } else if input.is_dir() {
+            files.extend(files_with_extension(input, "nix")?);
+        } else {
+            files.push(input.clone());
+        }
+    }
+    // Inputs may overlap; keep each file at its first position.
+    let mut seen = HashSet::new();
+    files.retain(|file| seen.insert(file.clone()));
+    Ok(files)
+}
+
+/// Category name for an input file.
+pub fn category_for(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// An input whose entries have been collected but not yet rendered.
+struct CollectedInput {
+    input: PathBuf,
+    category: String,
+    /// Category heading and file documentation.
+    header: String,
+    entries: Vec<ManualEntry>,
+    warnings: Vec<String>,
+}
+
+/// Parse a source and collect its entries, returning the category header,
+/// the entries and warnings about truncated doc comments.
+fn collect_source(
+    source: &str,
+    category: &str,
+    opts: &BatchOptions,
+) -> Result<(String, Vec<ManualEntry>, Vec<String>), String> {
+    let nix = rnix::Root::parse(source)
+        .ok()
+        .map_err(|e| format!("failed to parse input: {}", e))?;
+    let mut warnings: Vec<String> = oversized_doc_comments(nix.syntax())
+        .into_iter()
+        .map(|comment| {
+            let (line, column) = line_column(source, comment.offset);
+            format!(
+                "{}:{}: doc comment of {} bytes truncated to {} bytes",
+                line, column, comment.len, MAX_DOC_COMMENT_LEN
+            )
+        })
+        .collect();
+    warnings.extend(dynamic_bindings(nix.syntax()).into_iter().map(|binding| {
+        let (line, column) = line_column(source, binding.offset);
+        format!(
+            "{}:{}: `{}` has a dynamic name and is not documented",
+            line, column, binding.attrpath
+        )
+    }));
+    let mut header = retrieve_description(&nix, category, category) + "\n";
+    let collect_opts = CollectOptions {
+        prefix: opts.prefix.clone(),
+        category: category.to_string(),
+        include: opts.include.clone(),
+        exclude: opts.exclude.clone(),
+        default_example_language: opts.default_example_language.clone(),
+        ..Default::default()
+    };
+    let mut entries = match &opts.cache_dir {
+        Some(dir) => EntryCache::new(dir).collect(source, &collect_opts)?.0,
+        None => collect_entries(nix, &collect_opts),
+    };
+    sort_entries(&mut entries, opts.sort);
+    warnings.extend(
+        disambiguate_identifiers(&mut entries)
+            .iter()
+            .map(AnchorCollision::message),
+    );
+    for entry in &entries {
+        warnings.extend(
+            check_arguments(&entry.arguments, &entry.args)
+                .into_iter()
+                .map(|mismatch| format!("`{}` {}", entry.name, mismatch.message())),
+        );
+    }
+    if let Some(style) = opts.admonition_style {
+        header = convert_admonitions(&header, style);
+        convert_entries(&mut entries, style);
+    }
+    Ok((header, entries, warnings))
+}
+
+/// Resolve the references of collected entries of `category` against
+/// `symbols` and render them, returning the page and the dangling references.
+fn render_collected(
+    header: String,
+    category: &str,
+    mut entries: Vec<ManualEntry>,
+    symbols: &HashMap<String, String>,
+    opts: &BatchOptions,
+) -> (String, Vec<DanglingReference>) {
+    let dangling = resolve_references(
+        &mut entries,
+        symbols,
+        &opts.page(category),
+        &opts.prefix,
+        &opts.anchor_prefix,
+    );
+    let mut output = header;
+    write_entries_with(
+        entries,
+        &opts.anchor_prefix,
+        &opts.layout,
+        &opts.labels,
+        &mut output,
+    );
+    (opts.heading_levels.apply(&output), dangling)
+}
+
+/// Identifiers of all entries of each category, which references may point
+/// to, with the page of their category.
+fn symbol_table<'a>(
+    categories: impl Iterator<Item = (&'a str, &'a [ManualEntry])>,
+    opts: &BatchOptions,
+) -> HashMap<String, String> {
+    categories
+        .flat_map(|(category, entries)| {
+            let page = opts.page(category);
+            entries
+                .iter()
+                .map(move |e| (e.get_ident_title().0, page.clone()))
+        })
+        .collect()
+}
+
+/// Render a single Nix source as a category page. References may only point
+/// to entries of the source itself.
+pub fn render_source(source: &str, category: &str, opts: &BatchOptions) -> Result<String, String> {
+    let (header, entries, _) = collect_source(source, category, opts)?;
+    let symbols = symbol_table([(category, entries.as_slice())].into_iter(), opts);
+    Ok(render_collected(header, category, entries, &symbols, opts).0)
+}
+
+/// Render every input file; the outputs are in the order of `inputs`.
+///
+/// Inputs go through two stages on the rayon thread pool. All inputs are
+/// read, parsed and collected first, so the symbol table of every entry of
+/// the batch is complete before any input is rendered. Rendering then
+/// resolves references across categories against the shared, read-only
+/// table.
+///
+/// Inputs with the same file stem, e.g. `a/default.nix` and
+/// `b/default.nix`, would be documented as the same category and written to
+/// the same page, so they are rejected.
+pub fn render_batch(inputs: &[PathBuf], opts: &BatchOptions) -> Result<Vec<BatchOutput>, String> {
+    let mut categories: HashMap<String, &PathBuf> = HashMap::new();
+    for input in inputs {
+        let category = category_for(input);
+        if let Some(other) = categories.get(&category) {
+            return Err(format!(
+                "{} and {} would both be documented as category '{}'",
+                other.display(),
+                input.display(),
+                category
+            ));
+        }
+        categories.insert(category, input);
+    }
+
+    let collected: Vec<CollectedInput> = inputs
+        .par_iter()
+        .map(|input| {
+            let (source, lossy) =
+                read_source(input).map_err(|e| format!("{}: {}", input.display(), e))?;
+            let category = category_for(input);
+            let (header, entries, mut warnings) = collect_source(&source, &category, opts)
+                .map_err(|e| format!("{}: {}", input.display(), e))?;
+            if lossy {
+                warnings.insert(0, "invalid UTF-8 sequences were replaced".to_string());
+            }
+            Ok(CollectedInput {
+                input: input.clone(),
+                category,
+                header,
+                entries,
+                warnings,
+            })
+        })
+        .collect::<Result<_, String>>()?;
+
+    let symbols = symbol_table(
+        collected
+            .iter()
+            .map(|c| (c.category.as_str(), c.entries.as_slice())),
+        opts,
+    );
+
+    Ok(collected
+        .into_par_iter()
+        .map(|collected| {
+            let index = collected.entries.iter().map(IndexEntry::new).collect();
+            let (markdown, dangling) = render_collected(
+                collected.header,
+                &collected.category,
+                collected.entries,
+                &symbols,
+                opts,
+            );
+            BatchOutput {
+                input: collected.input,
+                category: collected.category,
+                markdown,
+                dangling,
+                warnings: collected.warnings,
+                index,
+            }
+        })
+        .collect())
+}
+
+/// Render an index of the functions of all outputs, sorted by name and
+/// grouped by their first letter. Names starting with anything but a letter
+/// are listed last. With `pages`, entries link to the anchors of their
+/// entries on the `<category>.md` pages written by [`write_outputs`];
+/// otherwise they link to anchors in the same document.
+pub fn render_index(outputs: &[BatchOutput], opts: &BatchOptions, pages: bool) -> String {
+    let mut entries: Vec<(&str, &IndexEntry)> = outputs
+        .iter()
+        .flat_map(|out| out.index.iter().map(|entry| (out.category.as_str(), entry)))
+        .collect();
+    entries.sort_by_cached_key(|(_, entry)| {
+        (
+            entry.letter().is_none(),
+            entry.name.to_lowercase(),
+            entry.title.clone(),
+        )
+    });
+
+    let mut output = format!(
+        "# {} {{#sec-functions-library-{}-all}}\n",
+        opts.labels.all_functions, opts.prefix
+    );
+    let mut group = None;
+    for (category, entry) in entries {
+        let letter = entry.letter();
+        if group != Some(letter) {
+            group = Some(letter);
+            let heading = letter.map_or(opts.labels.other.clone(), String::from);
+            output.push_str(&format!("\n## {}\n\n", heading));
+        }
+        let page = if pages {
+            format!("{}.md", category)
+        } else {
+            String::new()
+        };
+        output.push_str(&format!(
+            "- [{}]({}#{}{})",
+            code_span(&entry.title),
+            page,
+            opts.anchor_prefix,
+            entry.ident
+        ));
+        if let Some(summary) = &entry.summary {
+            output.push_str(&format!(": {}", summary));
+        }
+        output.push('\n');
+    }
+    opts.heading_levels.apply(&output)
+}
+
+/// Write every output to `<dir>/<category>.md`, in parallel.
+pub fn write_outputs(outputs: &[BatchOutput], dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    outputs
+        .par_iter()
+        .try_for_each(|out| fs::write(dir.join(format!("{}.md", out.category)), &out.markdown))
+}
@@ -0,0 +1,149 @@
+//! On-disk cache of collected entries.
+//!
+//! Regenerating the documentation of a large library mostly processes files
+//! that did not change since the last run. The cache stores the entries
+//! collected from a source as a JSON file named after a hash of the source
+//! and the collection settings, so unchanged files are neither parsed nor
+//! walked again.
+//!
+//! Entries of sources importing other files (`inherit (import ./foo.nix) bar;`
+//! or [`follow_imports`](CollectOptions::follow_imports)) depend on files the
+//! key does not cover, so such sources are never cached.
+
+use std::fs;
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::commonmark::ManualEntry;
+use crate::format::Fnv1a;
+use crate::{collect_entries_with_duplicates, CollectOptions, Duplicate};
+
+/// Version of the cached entries, part of every cache key.
+///
+/// Bump this whenever [`ManualEntry`] or [`Duplicate`] change in a way that
+/// affects their serialized form, so caches written by an older build are
+/// not read back as entries of the new layout.
+const SCHEMA_VERSION: u32 = 1;
+
+/// A directory of cached entries.
+#[derive(Debug, Clone)]
+pub struct EntryCache {
+    dir: PathBuf,
+}
+
+/// The cached result of collecting the entries of a source.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedEntries {
+    entries: Vec<ManualEntry>,
+    duplicates: Vec<Duplicate>,
+}
+
+impl EntryCache {
+    /// Cache in `dir`, which is created on the first write.
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// Like [`collect_entries_with_duplicates`], but returns the cached
+    /// entries if `source` was collected with the same settings before.
+    ///
+    /// The cache is best effort: unreadable cache files are treated as
+    /// missing and failures to write them are ignored.
+    pub fn collect(
+        &self,
+        source: &str,
+        opts: &CollectOptions,
+    ) -> Result<(Vec<ManualEntry>, Vec<Duplicate>), String> {
+        let path = cache_key(source, opts).map(|key| self.dir.join(format!("{}.json", key)));
+        if let Some(cached) = path.as_ref().and_then(|p| read_cached(p)) {
+            return Ok((cached.entries, cached.duplicates));
+        }
+
+        let nix = rnix::Root::parse(source)
+            .ok()
+            .map_err(|e| format!("failed to parse input: {}", e))?;
+        let (entries, duplicates) = collect_entries_with_duplicates(nix, opts);
+        if let Some(path) = path {
+            let cached = CachedEntries {
+                entries,
+                duplicates,
+            };
+            let _ = fs::create_dir_all(&self.dir)
+                .and_then(|_| fs::write(&path, serde_json::to_string(&cached).unwrap_or_default()));
+            return Ok((cached.entries, cached.duplicates));
+        }
+        Ok((entries, duplicates))
+    }
+}
+
+fn read_cached(path: &Path) -> Option<CachedEntries> {
+    let json = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+/// Hash of everything the entries of `source` depend on, or `None` if they
+/// may depend on other files.
+fn cache_key(source: &str, opts: &CollectOptions) -> Option<String> {
+    if opts.follow_imports || (opts.base_dir.is_some() && source.contains("import")) {
+        return None;
+    }
+
+    let mut hasher = Fnv1a::default();
+    let mut field = |bytes: &[u8]| {
+        hasher.write(&bytes.len().to_le_bytes());
+        hasher.write(bytes);
+    };
+    // Entries may change between releases, and between commits of one.
+    field(env!("CARGO_PKG_VERSION").as_bytes());
+    field(&SCHEMA_VERSION.to_le_bytes());
+    field(source.as_bytes());
+    field(opts.prefix.as_bytes());
+    field(opts.category.as_bytes());
+    let mut locs: Vec<_> = opts.locs.iter().collect();
+    locs.sort();
+    for (ident, loc) in locs {
+        field(ident.as_bytes());
+        field(loc.as_bytes());
+    }
+    field(opts.file.as_deref().unwrap_or_default().as_bytes());
+    field(&opts.line_locations.to_string().into_bytes());
+    let links = opts.source_links.as_ref();
+    field(links.map_or("", |l| l.base_url.as_str()).as_bytes());
+    field(
+        links
+            .and_then(|l| l.revision.as_deref())
+            .unwrap_or_default()
+            .as_bytes(),
+    );
+    for names in [
+        opts.export.as_deref().unwrap_or_default(),
+        &opts.include,
+        &opts.exclude,
+        &opts.wrappers,
+        &opts.custom_tags,
+    ] {
+        field(&names.len().to_le_bytes());
+        names.iter().for_each(|name| field(name.as_bytes()));
+    }
+    field(&opts.export.is_some().to_string().into_bytes());
+    field(&opts.depth.to_le_bytes());
+    field(&opts.hash_anchors.to_string().into_bytes());
+    field(
+        opts.default_example_language
+            .as_deref()
+            .unwrap_or_default()
+            .as_bytes(),
+    );
+    let mut type_links: Vec<_> = opts.type_links.iter().collect();
+    type_links.sort();
+    for (name, link) in type_links {
+        field(name.as_bytes());
+        field(link.as_bytes());
+    }
+
+    Some(format!("{:016x}", hasher.finish()))
+}
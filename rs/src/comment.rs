@@ -1,5 +1,5 @@
 use rnix::ast::{self, AstToken};
-use rnix::{SyntaxNode, match_ast};
+use rnix::{match_ast, SyntaxNode};
 use rowan::ast::AstNode;
 
 /// Implements functions for doc-comments according to rfc145.
@@ -50,16 +50,18 @@ impl DocComment for ast::Comment {
 /// If the doc-comment is not found in place (1) the search continues at place (2)
 /// More precisely before the NODE_ATTRPATH_VALUE (ast)
 /// If no doc-comment was found in place (1) or (2) this function returns None.
+///
+/// Doc-comments longer than [`MAX_DOC_COMMENT_LEN`] are truncated.
 pub fn get_expr_docs(expr: &SyntaxNode) -> Option<String> {
     if let Some(doc) = get_doc_comment(expr) {
         // Found in place (1)
-        doc.doc_text().map(|v| v.to_owned())
+        doc.doc_text().map(truncate_doc)
     } else if let Some(ref parent) = expr.parent() {
         match_ast! {
             match parent {
                 ast::AttrpathValue(_) => {
                     if let Some(doc_comment) = get_doc_comment(parent) {
-                        doc_comment.doc_text().map(|v| v.to_owned())
+                        doc_comment.doc_text().map(truncate_doc)
                     }else{
                         None
                     }
@@ -79,6 +81,48 @@ pub fn get_expr_docs(expr: &SyntaxNode) -> Option<String> {
     }
 }
 
+/// Maximum length in bytes of a doc-comment. Longer doc-comments are
+/// truncated, so a single huge comment in an untrusted source cannot make
+/// rendering take unbounded time and memory.
+pub const MAX_DOC_COMMENT_LEN: usize = 64 * 1024;
+
+/// A doc-comment longer than [`MAX_DOC_COMMENT_LEN`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OversizedComment {
+    /// Byte offset of the comment in the source.
+    pub offset: usize,
+    /// Length of the comment's contents in bytes.
+    pub len: usize,
+}
+
+/// Cut `doc` down to at most [`MAX_DOC_COMMENT_LEN`] bytes, at the last line
+/// break before the limit if there is one.
+fn truncate_doc(doc: &str) -> String {
+    if doc.len() <= MAX_DOC_COMMENT_LEN {
+        return doc.to_owned();
+    }
+    let mut end = MAX_DOC_COMMENT_LEN;
+    while !doc.is_char_boundary(end) {
+        end -= 1;
+    }
+    let end = doc[..end].rfind('\n').unwrap_or(end);
+    doc[..end].to_owned()
+}
+
+/// Returns the doc-comments below `root` that are truncated when extracted.
+pub fn oversized_doc_comments(root: &SyntaxNode) -> Vec<OversizedComment> {
+    root.descendants_with_tokens()
+        .filter_map(|element| {
+            let comment = ast::Comment::cast(element.into_token()?)?;
+            let len = comment.doc_text()?.len();
+            (len > MAX_DOC_COMMENT_LEN).then(|| OversizedComment {
+                offset: comment.syntax().text_range().start().into(),
+                len,
+            })
+        })
+        .collect()
+}
+
 /// Looks backwards from the given expression
 /// Only whitespace or non-doc-comments are allowed in between an expression and the doc-comment.
 /// Any other Node or Token stops the peek.
@@ -108,3 +152,115 @@ fn get_doc_comment(expr: &SyntaxNode) -> Option<ast::Comment> {
         };
     }
 }
+
+/// The doc-comment directly in front of a node, without looking at the
+/// binding it is the value of like [`get_expr_docs`] does.
+pub fn get_leading_doc(node: &SyntaxNode) -> Option<String> {
+    get_doc_comment(node)
+        .as_ref()
+        .and_then(DocComment::doc_text)
+        .map(truncate_doc)
+}
+
+/// Looks backwards from the given node for a regular (non-doc) comment such as
+/// `# Doc` or `/* Doc */` directly in front of it. Used to find documentation
+/// that predates RFC145 doc-comments.
+pub fn get_legacy_comment(node: &SyntaxNode) -> Option<ast::Comment> {
+    let mut prev = node.prev_sibling_or_token();
+    loop {
+        match prev {
+            Some(rnix::NodeOrToken::Token(ref token)) => {
+                match_ast! { match token {
+                    ast::Whitespace(_) => {
+                        prev = token.prev_sibling_or_token();
+                    },
+                    ast::Comment(it) => {
+                        break it.doc_text().is_none().then_some(it);
+                    },
+                    _ => {
+                        break None;
+                    }
+                }}
+            }
+            _ => break None,
+        };
+    }
+}
+
+/// Text of the comment marking a binding as intentionally undocumented.
+pub const IGNORE_MARKER: &str = "nixdoc-ignore";
+
+/// Whether a binding or `inherit` statement is preceded by a
+/// `# nixdoc-ignore` comment on its own line. The marker may be combined
+/// with other comments, such as a doc comment:
+///
+/// ```nix
+/// {
+///   # nixdoc-ignore
+///   /** Escape hatch, subject to change. */
+///   unsafeFoo = x: x;
+/// }
+/// ```
+pub fn has_ignore_marker(node: &SyntaxNode) -> bool {
+    let mut prev = node.prev_sibling_or_token();
+    while let Some(rnix::NodeOrToken::Token(token)) = prev {
+        prev = token.prev_sibling_or_token();
+        let Some(comment) = ast::Comment::cast(token.clone()) else {
+            if ast::Whitespace::can_cast(token.kind()) {
+                continue;
+            }
+            break;
+        };
+        // A comment trailing the previous binding belongs to that one.
+        let own_line = match &prev {
+            None => true,
+            Some(rnix::NodeOrToken::Token(t)) => {
+                !ast::Whitespace::can_cast(t.kind()) || t.text().contains('\n')
+            }
+            Some(rnix::NodeOrToken::Node(_)) => false,
+        };
+        if own_line && comment.text().trim() == IGNORE_MARKER {
+            return true;
+        }
+    }
+    false
+}
+
+/// Returns the contents of a doc-comment token that starts a section of the
+/// surrounding attribute set rather than documenting a binding: a doc-comment
+/// starting with a markdown heading that is directly followed by another
+/// doc-comment.
+///
+/// ```nix
+/// {
+///   /** ## Assertions */
+///
+///   /** Doc */
+///   assertMsg = pred: msg: pred || throw msg;
+/// }
+/// ```
+pub fn get_section_comment(token: &rnix::SyntaxToken) -> Option<String> {
+    let text = ast::Comment::cast(token.clone())?.doc_text()?.to_owned();
+    if !text.trim_start().starts_with('#') {
+        return None;
+    }
+    let mut next = token.next_sibling_or_token();
+    loop {
+        match next {
+            Some(rnix::NodeOrToken::Token(ref t)) => {
+                match_ast! { match t {
+                    ast::Whitespace(_) => {
+                        next = t.next_sibling_or_token();
+                    },
+                    ast::Comment(it) => {
+                        break it.doc_text().is_some().then_some(text);
+                    },
+                    _ => {
+                        break None;
+                    }
+                }}
+            }
+            _ => break None,
+        };
+    }
+}
@@ -16,32 +16,82 @@
 //! This module implements CommonMark output for a struct
 //! representing a single entry in the manual.
 
-use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::hash::Hasher;
+use std::io::{self, Write};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::arguments::ArgumentDoc;
+use crate::examples::ExampleDoc;
+use crate::format::{code_block, code_span, headings, Fnv1a};
+use crate::options::SortOrder;
+use crate::signature::TypeSignature;
+use crate::slug::{assign_anchors, AnchorCollision};
+use crate::tags::Stability;
 
 /// Represent a single function argument name and its (optional)
 /// doc-string.
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SingleArg {
     pub name: String,
     pub doc: Option<String>,
+    /// Source text of the default value of a pattern argument (`bar ? 3`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
 }
 
 /// Represent a function argument, which is either a flat identifier
 /// or a pattern set.
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Argument {
     /// Flat function argument (e.g. `n: n * 2`).
     Flat(SingleArg),
 
-    /// Pattern function argument (e.g. `{ name, age }: ...`)
-    Pattern(Vec<SingleArg>),
+    /// Pattern function argument (e.g. `{ name, age ? 0, ... }@args: ...`)
+    Pattern {
+        args: Vec<SingleArg>,
+        /// Whether the pattern accepts additional attributes (`...`).
+        ellipsis: bool,
+        /// Name the whole argument set is bound to (`@args`).
+        bind: Option<String>,
+    },
 }
 
 impl Argument {
+    /// The part of this argument an `# Arguments` section does not document,
+    /// if any. Flat arguments are documented by their name, patterns by the
+    /// name they are bound to or attribute by attribute.
+    fn undocumented(&self, documented: &[ArgumentDoc]) -> Option<Argument> {
+        let is_documented = |name: &str| documented.iter().any(|d| d.name == name);
+        match self {
+            Argument::Flat(arg) => (!is_documented(&arg.name)).then(|| self.clone()),
+            Argument::Pattern { bind, .. } if bind.as_deref().is_some_and(is_documented) => None,
+            Argument::Pattern {
+                args,
+                ellipsis,
+                bind,
+            } => {
+                let rest: Vec<SingleArg> = args
+                    .iter()
+                    .filter(|arg| !is_documented(&arg.name))
+                    .cloned()
+                    .collect();
+                (rest.len() == args.len() || !rest.is_empty()).then(|| Argument::Pattern {
+                    args: rest,
+                    ellipsis: *ellipsis,
+                    bind: bind.clone(),
+                })
+            }
+        }
+    }
+
     /// Write CommonMark structure for a single function argument.
     /// We use the definition list extension, which prepends each argument with `: `.
     /// For pattern arguments, we create a nested definition list.
-    fn format_argument(self) -> String {
+    fn format_argument(&self) -> String {
         match self {
             // Write a flat argument entry, e.g. `id = x: x`
             //
@@ -49,40 +99,80 @@ impl Argument {
             // : Function argument
             Argument::Flat(arg) => {
                 format!(
-                    "`{}`\n\n: {}\n\n",
-                    arg.name,
-                    handle_indentation(arg.doc.unwrap_or("Function argument".into()).trim())
+                    "{}\n\n: {}\n\n",
+                    code_span(&arg.name),
+                    handle_indentation(arg.doc.as_deref().unwrap_or("Function argument").trim())
                 )
             }
 
-            // Write a pattern argument entry and its individual
-            // parameters as a nested structure, e.g.:
+            // Write a pattern argument as a table of its attributes, e.g.:
             //
-            // `foo = { a }: a`
+            // `foo = { a, b ? 1, ... }: a`
             //
             // structured function argument
-            // : `a`
-            //   : Function argument
-            Argument::Pattern(pattern_args) => {
-                let mut inner = String::new();
-                for pattern_arg in pattern_args {
-                    inner += &Argument::Flat(pattern_arg).format_argument();
+            //
+            // | Name | Default | Description |
+            // | ---- | ------- | ----------- |
+            // | `a` | | Function argument |
+            // | `b` | `1` | Function argument |
+            //
+            // Additional attributes are accepted (`...`).
+            Argument::Pattern {
+                args,
+                ellipsis,
+                bind,
+            } => {
+                let with_defaults = args.iter().any(|a| a.default.is_some());
+                let mut out = String::from("structured function argument\n\n");
+                if with_defaults {
+                    out.push_str(
+                        "| Name | Default | Description |\n| ---- | ------- | ----------- |\n",
+                    );
+                } else {
+                    out.push_str("| Name | Description |\n| ---- | ----------- |\n");
                 }
-
-                let indented = textwrap::indent(&inner, "  ");
-
-                format!(
-                    // The `:` creates another definition list of which `indented` is the term.
-                    "structured function argument\n\n: {}",
-                    // drop leading indentation on the first line, the `: ` serves this function
-                    // already.
-                    indented.trim_start()
-                )
+                for arg in args {
+                    let doc = table_cell(arg.doc.as_deref().unwrap_or("Function argument"));
+                    if with_defaults {
+                        let default = arg
+                            .default
+                            .as_ref()
+                            .map(|d| code_span(&table_cell(d)))
+                            .unwrap_or_default();
+                        out.push_str(&format!(
+                            "| {} | {} | {} |\n",
+                            code_span(&arg.name),
+                            default,
+                            doc
+                        ));
+                    } else {
+                        out.push_str(&format!("| {} | {} |\n", code_span(&arg.name), doc));
+                    }
+                }
+                if *ellipsis {
+                    out.push_str("\nAdditional attributes are accepted (`...`).\n");
+                }
+                if let Some(bind) = bind {
+                    out.push_str(&format!(
+                        "\nThe whole argument set is bound to {}.\n",
+                        code_span(bind)
+                    ));
+                }
+                out.push('\n');
+                out
             }
         }
     }
 }
 
+/// Flatten text into a single table cell, escaping pipes.
+pub(crate) fn table_cell(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('|', "\\|")
+}
+
 /// Since the first line starts with `: `, indent every other line by 2 spaces, so
 /// that the text aligns, to result in:
 ///
@@ -99,29 +189,50 @@ fn handle_indentation(raw: &str) -> String {
 
 /// Generate the identifier for CommonMark.
 /// ident is used as URL Encoded link to the function and has thus stricter rules (i.e. "' " in "lib.map' "  is not allowed).
-pub(crate) fn get_identifier(prefix: &String, category: &String, name: &String) -> String {
-    let name_prime = name.replace('\'', "-prime");
-    vec![prefix, category, &name_prime]
+pub(crate) fn get_identifier(prefix: &str, category: &str, name: &str) -> String {
+    // Quoted names (`"foo bar"`) may contain characters not allowed in
+    // anchors.
+    let name_prime: String = name
+        .replace('\'', "-prime")
+        .chars()
+        .filter(|&c| c != '"')
+        .map(|c| match c {
+            c if c.is_alphanumeric() || matches!(c, '.' | '-' | '_') => c,
+            _ => '_',
+        })
+        .collect();
+    [prefix, category, &name_prime]
         .into_iter()
         .filter(|x| !x.is_empty())
-        .cloned()
-        .collect::<Vec<String>>()
+        .collect::<Vec<&str>>()
         .join(".")
 }
 
 /// Generate the title for CommonMark.
 /// the title is the human-readable name of the function.
-pub(crate) fn get_title(prefix: &String, category: &String, name: &String) -> String {
-    vec![prefix, category, name]
+pub(crate) fn get_title(prefix: &str, category: &str, name: &str) -> String {
+    [prefix, category, name]
         .into_iter()
         .filter(|x| !x.is_empty())
-        .cloned()
-        .collect::<Vec<String>>()
+        .collect::<Vec<&str>>()
         .join(".")
 }
 
+/// Anchor of an entry derived from a hash of its category and name, e.g.
+/// `id-3f2a9c0b1d4e`. Unlike the identifier anchor it does not depend on the
+/// category prefix or the anchor prefix, so links to it survive manual
+/// reorganizations.
+pub fn hash_anchor(category: &str, name: &str) -> String {
+    let mut hasher = Fnv1a::default();
+    hasher.write(format!("{}.{}", category, name).as_bytes());
+    format!("id-{:012x}", hasher.finish() >> 16)
+}
+
 /// Represents a single manual section describing a library function.
-#[derive(Clone, Debug, Serialize)]
+///
+/// Entries are cached on disk; changing their fields requires bumping the
+/// schema version in `cache.rs`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ManualEntry {
     /// Prefix for the category (e.g. 'lib' or 'utils').
     pub prefix: String,
@@ -135,24 +246,306 @@ pub struct ManualEntry {
     /// Name of the section (used as the title).
     pub name: String,
 
+    /// Identifier used instead of the one derived from the name, because
+    /// that collides with another entry's, see [`disambiguate_identifiers`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ident: Option<String>,
+
     /// Type signature (if provided). This is not actually a checked
     /// type signature in any way.
     pub fn_type: Option<String>,
 
+    /// The type signature parsed from a `# Type` section.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<TypeSignature>,
+
     /// Primary description of the entry. Each entry is written as a
     /// separate paragraph.
     pub description: Vec<String>,
 
+    /// First sentence of the description, see [`crate::format::summary`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+
     /// Usage example for the entry.
     pub example: Option<String>,
 
+    /// Examples split off the doc comment, see [`crate::examples`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<ExampleDoc>,
+
     /// Arguments of the function.
     pub args: Vec<Argument>,
+
+    /// The value of an entry that is not a function.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub constant: Option<Constant>,
+
+    /// Arguments documented in an `# Arguments` or `# Inputs` section.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub arguments: Vec<ArgumentDoc>,
+
+    /// Conditions under which the entry fails, from a `# Throws` or
+    /// `# Errors` section.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub throws: Vec<String>,
+
+    /// Maintainers of the entry (from `meta.maintainers`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub maintainers: Vec<String>,
+
+    /// Keywords of the entry, from a `Tags:` line or a `# Keywords` section,
+    /// see [`crate::tagindex`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+
+    /// Version the entry is available since.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+
+    /// Deprecation notice, empty if the entry is deprecated without a message.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<String>,
+
+    /// Maturity of the entry (from a `@stability` tag).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stability: Option<Stability>,
+
+    /// Fields of custom tags, keyed by tag, see [`crate::plugin`].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, serde_json::Value>,
+
+    /// Markdown of the fields, rendered by [`TagTemplates`](crate::plugin::TagTemplates).
+    #[serde(skip)]
+    pub rendered_fields: Vec<String>,
+
+    /// Markdown of the section (a standalone doc comment starting with a
+    /// heading) the entry is grouped under.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+
+    /// Hash-based anchor emitted next to the identifier anchor, see
+    /// [`hash_anchor`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash_anchor: Option<String>,
+
+    /// Position of the binding in its source file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span: Option<Span>,
+
+    /// Link to the binding in a hosted copy of the source, see
+    /// [`SourceLinks`](crate::source::SourceLinks).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_url: Option<String>,
+
+    /// The doc comment as written, before its headings are shifted and its
+    /// sections are split off, see [`crate::extract`].
+    #[serde(skip)]
+    pub raw_doc: Option<String>,
+}
+
+/// The value of an entry that is not a function, e.g. `lib.maxInt`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Constant {
+    /// Kind of the value, e.g. `number` or `attribute set`.
+    pub kind: String,
+    /// Source of the value, if it fits on a line of at most
+    /// [`MAX_CONSTANT_LENGTH`] characters.
+    pub value: Option<String>,
+}
+
+/// Length of the longest constant value shown in the documentation.
+pub const MAX_CONSTANT_LENGTH: usize = 80;
+
+/// Position of an entry in its source file. Lines and columns are 1-based,
+/// the end is exclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Span {
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+    /// Byte offset of the start in the file.
+    #[serde(default)]
+    pub start_byte: usize,
+    /// Byte offset of the end in the file.
+    #[serde(default)]
+    pub end_byte: usize,
+}
+
+/// Write the sections of several entries, adding the markdown of the section
+/// an entry is grouped under before the first entry of each section.
+pub fn write_entries(entries: Vec<ManualEntry>, anchor_prefix: &str, output: &mut String) {
+    write_entries_with(
+        entries,
+        anchor_prefix,
+        &EntrySection::ALL,
+        &EntryLabels::default(),
+        output,
+    )
+}
+
+/// Like [`write_entries`], but with the parts of entries in the order of
+/// `layout`, labelled with `labels`.
+pub fn write_entries_with(
+    entries: Vec<ManualEntry>,
+    anchor_prefix: &str,
+    layout: &[EntrySection],
+    labels: &EntryLabels,
+    output: &mut String,
+) {
+    let Ok(()) = stream_entries(entries, anchor_prefix, layout, labels, |markdown| {
+        output.push_str(markdown);
+        Ok::<_, Infallible>(())
+    });
+}
+
+/// Like [`write_entries_with`], but writing to `out`, see [`stream_entries`].
+pub fn write_entries_to(
+    entries: impl IntoIterator<Item = ManualEntry>,
+    anchor_prefix: &str,
+    layout: &[EntrySection],
+    labels: &EntryLabels,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    stream_entries(entries, anchor_prefix, layout, labels, |markdown| {
+        out.write_all(markdown.as_bytes())
+    })
+}
+
+/// Render entries one at a time, handing the markdown of each entry (and of
+/// the section it starts, if any) to `write` as soon as it is rendered, so
+/// the markdown of all entries is never held in memory at once.
+pub fn stream_entries<E>(
+    entries: impl IntoIterator<Item = ManualEntry>,
+    anchor_prefix: &str,
+    layout: &[EntrySection],
+    labels: &EntryLabels,
+    mut write: impl FnMut(&str) -> Result<(), E>,
+) -> Result<(), E> {
+    let mut current_section: Option<String> = None;
+    for entry in entries {
+        let mut markdown = String::new();
+        if entry.section.is_some() && entry.section != current_section {
+            current_section = entry.section.clone();
+            markdown.push_str(current_section.as_deref().unwrap_or_default());
+            markdown.push_str("\n\n");
+        }
+        entry.write_section_with(anchor_prefix, layout, labels, &mut markdown);
+        write(&markdown)?;
+    }
+    Ok(())
+}
+
+/// Sort entries in `order`. Entries grouped under a section stay together,
+/// with the sections in the order they first appear in.
+pub fn sort_entries(entries: &mut [ManualEntry], order: SortOrder) {
+    if order == SortOrder::Source {
+        return;
+    }
+    let mut sections: Vec<Option<String>> = vec![];
+    for entry in entries.iter() {
+        if !sections.contains(&entry.section) {
+            sections.push(entry.section.clone());
+        }
+    }
+    let section_index = |entry: &ManualEntry| sections.iter().position(|s| *s == entry.section);
+    entries.sort_by(|a, b| {
+        section_index(a)
+            .cmp(&section_index(b))
+            .then_with(|| order.compare(&a.name, &b.name))
+    });
+}
+
+/// Give entries whose identifiers collide (e.g. `foo'` and `foo-prime`,
+/// which both become `foo-prime`) unique identifiers, and return the
+/// collisions.
+pub fn disambiguate_identifiers(entries: &mut [ManualEntry]) -> Vec<AnchorCollision> {
+    let derived: HashMap<String, String> = entries
+        .iter()
+        .map(|entry| {
+            let (ident, title) = entry.get_ident_title();
+            (title, ident)
+        })
+        .collect();
+    let anchors = assign_anchors(derived.keys().map(String::as_str), |title| {
+        derived[title].clone()
+    });
+    for entry in entries.iter_mut() {
+        let (ident, title) = entry.get_ident_title();
+        match anchors.get(&title) {
+            Some(assigned) if assigned != ident => entry.ident = Some(assigned.to_string()),
+            _ => (),
+        }
+    }
+    anchors.collisions
+}
+
+/// Write an appendix listing the deprecated entries of a category with links
+/// to them and the first line of their deprecation notice. Nothing is written
+/// if no entry is deprecated.
+pub fn write_deprecated_appendix(
+    entries: &[ManualEntry],
+    anchor_prefix: &str,
+    labels: &EntryLabels,
+    output: &mut String,
+) {
+    let deprecated: Vec<&ManualEntry> = entries.iter().filter(|e| e.deprecated.is_some()).collect();
+    let Some(first) = deprecated.first() else {
+        return;
+    };
+    output.push_str(&format!(
+        "## {} {{#sec-functions-library-{}-deprecated}}\n\n",
+        labels.deprecated_functions, first.category
+    ));
+    for entry in deprecated {
+        let (ident, title) = entry.get_ident_title();
+        output.push_str(&format!("- [`{}`](#{}{})", title, anchor_prefix, ident));
+        match entry.deprecated.as_deref().and_then(|n| n.lines().next()) {
+            Some(notice) if !notice.trim().is_empty() => {
+                output.push_str(&format!(": {}\n", notice.trim()))
+            }
+            _ => output.push('\n'),
+        }
+    }
+    output.push('\n');
+}
+
+/// Write a table of entries with links to them and their summaries. Nothing
+/// is written if there are no entries.
+pub fn write_summary_table(
+    entries: &[ManualEntry],
+    anchor_prefix: &str,
+    labels: &EntryLabels,
+    output: &mut String,
+) {
+    if entries.is_empty() {
+        return;
+    }
+    output.push_str(&format!(
+        "| {} | {} |\n| -------- | ------- |\n",
+        labels.function, labels.summary
+    ));
+    for entry in entries {
+        let (ident, title) = entry.get_ident_title();
+        let summary = entry.summary.as_deref().map(table_cell).unwrap_or_default();
+        output.push_str(&format!(
+            "| [{}](#{}{}) | {} |\n",
+            code_span(&title),
+            anchor_prefix,
+            ident,
+            summary
+        ));
+    }
+    output.push('\n');
 }
 
 impl ManualEntry {
-    pub(crate) fn get_ident_title(&self) -> (String, String) {
-        let ident = get_identifier(&self.prefix, &self.category, &self.name);
+    pub fn get_ident_title(&self) -> (String, String) {
+        let ident = self
+            .ident
+            .clone()
+            .unwrap_or_else(|| get_identifier(&self.prefix, &self.category, &self.name));
         let title = get_title(&self.prefix, &self.category, &self.name);
         (ident, title)
     }
@@ -165,55 +558,359 @@ impl ManualEntry {
     ///   In Nixpkgs this would be "function-library-".
     /// - `output`: The output string to append the CommonMark onto.
     pub fn write_section(self, anchor_prefix: &str, output: &mut String) -> String {
+        self.write_section_with(
+            anchor_prefix,
+            &EntrySection::ALL,
+            &EntryLabels::default(),
+            output,
+        )
+    }
+
+    /// Like [`write_section`](Self::write_section), but with the parts
+    /// following the heading in the order of `layout`, labelled with
+    /// `labels`. Parts that are not listed are omitted.
+    pub fn write_section_with(
+        self,
+        anchor_prefix: &str,
+        layout: &[EntrySection],
+        labels: &EntryLabels,
+        output: &mut String,
+    ) -> String {
         let (ident, title) = self.get_ident_title();
         output.push_str(&format!(
-            "## `{}` {{#{}{}}}\n\n",
-            title, anchor_prefix, ident
+            "## {} {{#{}{}}}\n\n",
+            code_span(&title),
+            anchor_prefix,
+            ident
         ));
 
-        // <subtitle> (type signature)
-        if let Some(t) = &self.fn_type {
-            if t.lines().count() > 1 {
-                output.push_str(&format!("**Type**:\n```\n{}\n```\n\n", t));
-            } else {
-                output.push_str(&format!("**Type**: `{}`\n\n", t));
-            }
+        if let Some(hash_anchor) = &self.hash_anchor {
+            output.push_str(&format!("[]{{#{}}}\n\n", hash_anchor));
         }
 
-        // Primary doc string
-        // TODO: Split paragraphs?
-        for paragraph in &self.description {
-            output.push_str(&format!("{}\n\n", paragraph));
+        for section in layout {
+            match section {
+                EntrySection::Stability => {
+                    if let Some(stability) = &self.stability {
+                        output.push_str(&format!(
+                            "**{}:** *{}*\n\n",
+                            labels.stability,
+                            stability.as_str()
+                        ));
+                    }
+                }
+                EntrySection::Deprecated => {
+                    if let Some(notice) = &self.deprecated {
+                        if notice.is_empty() {
+                            output.push_str(&format!(
+                                "::: {{.warning}}\n**{}.**\n:::\n\n",
+                                labels.deprecated
+                            ));
+                        } else {
+                            output.push_str(&format!(
+                                "::: {{.warning}}\n**{}:** {}\n:::\n\n",
+                                labels.deprecated, notice
+                            ));
+                        }
+                    }
+                }
+                // <subtitle> (type signature)
+                // Signatures documented in a `# Type` section are rendered as
+                // part of the description.
+                EntrySection::Type => {
+                    if let Some(t) = self.fn_type.as_ref().filter(|_| self.signature.is_none()) {
+                        if t.lines().count() > 1 {
+                            output.push_str(&format!(
+                                "**{}**:\n{}\n\n",
+                                labels.entry_type,
+                                code_block("", t)
+                            ));
+                        } else {
+                            output.push_str(&format!(
+                                "**{}**: {}\n\n",
+                                labels.entry_type,
+                                code_span(t)
+                            ));
+                        }
+                    }
+                }
+                // The value of a constant, or its kind if it is too long to
+                // show.
+                EntrySection::Value => {
+                    if let Some(constant) = &self.constant {
+                        match &constant.value {
+                            Some(value) => output.push_str(&format!(
+                                "**{}:** {}\n\n",
+                                labels.value,
+                                code_span(value)
+                            )),
+                            None => output.push_str(&format!(
+                                "**{}:** *{}*\n\n",
+                                labels.value, constant.kind
+                            )),
+                        }
+                    }
+                }
+                // Primary doc string
+                // TODO: Split paragraphs?
+                EntrySection::Description => {
+                    for paragraph in &self.description {
+                        output.push_str(&format!("{}\n\n", paragraph));
+                    }
+                }
+                // Function argument names, except those the Arguments or Inputs section
+                // of the description documents (to avoid duplication). Sections that
+                // don't list arguments by name are taken to document all of them.
+                EntrySection::Arguments => {
+                    let has_args_section = headings(&self.description.join("\n\n"))
+                        .iter()
+                        .any(|(_, title)| title == "Arguments" || title == "Inputs");
+                    if self.arguments.is_empty() && has_args_section {
+                        continue;
+                    }
+                    for arg in self
+                        .args
+                        .iter()
+                        .filter_map(|a| a.undocumented(&self.arguments))
+                    {
+                        output.push_str(&format!("{}\n", arg.format_argument()));
+                    }
+                }
+                EntrySection::Throws => {
+                    if !self.throws.is_empty() {
+                        output.push_str(&format!("**{}:**\n\n", labels.throws));
+                        for condition in &self.throws {
+                            output.push_str(&format!("- {}\n", condition));
+                        }
+                        output.push('\n');
+                    }
+                }
+                // Example program listings, each in its own example block.
+                // Examples after the first get numbered anchors.
+                EntrySection::Example => {
+                    let usage = labels.usage_example.replace("{name}", &code_span(&title));
+                    let legacy = self
+                        .example
+                        .as_ref()
+                        .map(|example| (usage.clone(), code_block("nix", example.trim())));
+                    let examples = self.examples.iter().map(|example| {
+                        let title = example.title.clone().unwrap_or_else(|| usage.clone());
+                        (title, example.body.clone())
+                    });
+                    for (i, (example_title, body)) in legacy.into_iter().chain(examples).enumerate()
+                    {
+                        let suffix = if i == 0 {
+                            String::new()
+                        } else {
+                            format!("-{}", i + 1)
+                        };
+                        output.push_str(&format!(
+                            "::: {{.example #{}example-{}{}}}\n",
+                            anchor_prefix, ident, suffix
+                        ));
+                        output.push_str(&format!("# {}\n\n", example_title));
+                        output.push_str(&format!("{}\n:::\n\n", body));
+                    }
+                }
+                EntrySection::Fields => {
+                    for field in &self.rendered_fields {
+                        output.push_str(&format!("{}\n\n", field));
+                    }
+                }
+                EntrySection::Since => {
+                    if let Some(since) = &self.since {
+                        output
+                            .push_str(&format!("{}\n\n", labels.since.replace("{version}", since)));
+                    }
+                }
+                EntrySection::Maintainers => {
+                    if !self.maintainers.is_empty() {
+                        output.push_str(&format!(
+                            "**{}:** {}\n\n",
+                            labels.maintainers,
+                            self.maintainers.join(", ")
+                        ));
+                    }
+                }
+                EntrySection::Location => {
+                    if let Some(loc) = &self.location {
+                        output.push_str(&format!(
+                            "{}\n\n",
+                            labels.located_at.replace("{location}", loc)
+                        ));
+                    }
+                }
+            }
         }
 
-        // Function argument names - only output if the description doesn't already
-        // contain an Arguments section (to avoid duplication)
-        let has_args_section = self.description.iter().any(|p| {
-            p.contains("# Arguments") || p.contains("## Arguments") || p.contains("### Arguments")
-        });
-        if !self.args.is_empty() && !has_args_section {
-            for arg in self.args {
-                output.push_str(&format!("{}\n", arg.format_argument()));
+        output.to_string()
+    }
+}
+
+/// Labels and sentences written around the documentation of entries. Some
+/// have placeholders in braces for the text they surround.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryLabels {
+    pub stability: String,
+    pub deprecated: String,
+    pub entry_type: String,
+    pub value: String,
+    pub throws: String,
+    pub maintainers: String,
+    /// Title of an untitled example, with `{name}` standing for the entry.
+    pub usage_example: String,
+    /// With `{version}` standing for the version the entry is available since.
+    pub since: String,
+    /// With `{location}` standing for the location of the entry.
+    pub located_at: String,
+    pub deprecated_functions: String,
+    pub function: String,
+    pub summary: String,
+    pub all_functions: String,
+    /// Group of the index of all functions for names not starting with a
+    /// letter.
+    pub other: String,
+}
+
+impl EntryLabels {
+    /// Names of the labels on the command line and in strings files.
+    pub const NAMES: [&str; 14] = [
+        "stability",
+        "deprecated",
+        "type",
+        "value",
+        "throws",
+        "maintainers",
+        "usage-example",
+        "since",
+        "located-at",
+        "deprecated-functions",
+        "function",
+        "summary",
+        "all-functions",
+        "other",
+    ];
+
+    /// Set a label by its name.
+    pub fn set(&mut self, name: &str, text: &str) -> Result<(), String> {
+        let label = match name {
+            "stability" => &mut self.stability,
+            "deprecated" => &mut self.deprecated,
+            "type" => &mut self.entry_type,
+            "value" => &mut self.value,
+            "throws" => &mut self.throws,
+            "maintainers" => &mut self.maintainers,
+            "usage-example" => &mut self.usage_example,
+            "since" => &mut self.since,
+            "located-at" => &mut self.located_at,
+            "deprecated-functions" => &mut self.deprecated_functions,
+            "function" => &mut self.function,
+            "summary" => &mut self.summary,
+            "all-functions" => &mut self.all_functions,
+            "other" => &mut self.other,
+            _ => {
+                return Err(format!(
+                    "unknown label '{}', expected one of: {}",
+                    name,
+                    Self::NAMES.join(", ")
+                ));
             }
-        }
+        };
+        *label = text.to_string();
+        Ok(())
+    }
+}
 
-        // Example program listing (if applicable)
-        //
-        // TODO: In grhmc's version there are multiple (named)
-        // examples, how can this be achieved automatically?
-        if let Some(example) = &self.example {
-            output.push_str(&format!(
-                "::: {{.example #{}example-{}}}\n",
-                anchor_prefix, ident
-            ));
-            output.push_str(&format!("# `{}` usage example\n\n", title));
-            output.push_str(&format!("```nix\n{}\n```\n:::\n\n", example.trim()));
+impl Default for EntryLabels {
+    fn default() -> Self {
+        Self {
+            stability: "Stability".to_string(),
+            deprecated: "Deprecated".to_string(),
+            entry_type: "Type".to_string(),
+            value: "Value".to_string(),
+            throws: "Throws".to_string(),
+            maintainers: "Maintainers".to_string(),
+            usage_example: "{name} usage example".to_string(),
+            since: "Available since {version}.".to_string(),
+            located_at: "Located at {location}.".to_string(),
+            deprecated_functions: "Deprecated functions".to_string(),
+            function: "Function".to_string(),
+            summary: "Summary".to_string(),
+            all_functions: "All functions".to_string(),
+            other: "Other".to_string(),
         }
+    }
+}
+
+/// A part of a rendered entry, following its heading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntrySection {
+    Stability,
+    Deprecated,
+    Type,
+    Value,
+    Description,
+    Arguments,
+    Throws,
+    Example,
+    Fields,
+    Since,
+    Maintainers,
+    Location,
+}
 
-        if let Some(loc) = self.location {
-            output.push_str(&String::from(format!("Located at {loc}.\n\n")));
+impl EntrySection {
+    /// All parts, in their default order.
+    pub const ALL: [EntrySection; 12] = [
+        Self::Stability,
+        Self::Deprecated,
+        Self::Type,
+        Self::Value,
+        Self::Description,
+        Self::Arguments,
+        Self::Throws,
+        Self::Example,
+        Self::Fields,
+        Self::Since,
+        Self::Maintainers,
+        Self::Location,
+    ];
+
+    /// Look up a part by its command line name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.as_str() == name)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Stability => "stability",
+            Self::Deprecated => "deprecated",
+            Self::Type => "type",
+            Self::Value => "value",
+            Self::Description => "description",
+            Self::Arguments => "arguments",
+            Self::Throws => "throws",
+            Self::Example => "example",
+            Self::Fields => "fields",
+            Self::Since => "since",
+            Self::Maintainers => "maintainers",
+            Self::Location => "location",
         }
+    }
 
-        output.to_string()
+    /// Parse a layout from part names, e.g. `["description", "example"]`.
+    pub fn parse_layout<S: AsRef<str>>(names: &[S]) -> Result<Vec<Self>, String> {
+        names
+            .iter()
+            .map(|name| {
+                Self::from_name(name.as_ref()).ok_or_else(|| {
+                    format!(
+                        "unknown entry section '{}', expected one of: {}",
+                        name.as_ref(),
+                        Self::ALL.map(|s| s.as_str()).join(", ")
+                    )
+                })
+            })
+            .collect()
     }
 }
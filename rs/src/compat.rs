@@ -0,0 +1,140 @@
+//! Compatibility matrix of options across several releases.
+//!
+//! Given the `options.json` of a number of versions (oldest first), lists for
+//! every option the versions it exists in and where its type or default
+//! changed, so users upgrading across several releases have a single
+//! reference.
+
+use crate::commonmark::table_cell;
+use crate::options::{
+    compare_option_names, format_option_value, OptionDef, OptionsMap, RenderOptions,
+};
+use std::collections::BTreeSet;
+
+/// A change of an option attribute between two consecutive versions it
+/// exists in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// Version in which the new value first appears.
+    pub version: String,
+    /// The attribute that changed (`type` or `default`).
+    pub field: &'static str,
+    pub from: String,
+    pub to: String,
+}
+
+/// Availability and changes of one option across all versions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptionHistory {
+    pub name: String,
+    /// Whether the option exists, one entry per version.
+    pub present: Vec<bool>,
+    pub changes: Vec<Change>,
+}
+
+/// Type and formatted default of an option, as compared between versions.
+fn signature(opt: &OptionDef) -> [(&'static str, String); 2] {
+    let opts = RenderOptions::default();
+    [
+        ("type", opt.option_type.clone().unwrap_or_default()),
+        (
+            "default",
+            opt.default
+                .as_ref()
+                .map(|d| format_option_value(d, &opts))
+                .unwrap_or_default(),
+        ),
+    ]
+}
+
+/// Build the history of every option found in any of `versions`, given as
+/// `(label, options)` pairs from oldest to newest.
+pub fn build_matrix(versions: &[(String, OptionsMap)]) -> Vec<OptionHistory> {
+    let mut names: Vec<&String> = versions
+        .iter()
+        .flat_map(|(_, options)| options.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    names.sort_by(|a, b| compare_option_names(a, b));
+
+    names
+        .into_iter()
+        .map(|name| {
+            let mut changes = vec![];
+            let mut previous: Option<[(&'static str, String); 2]> = None;
+            for (label, options) in versions {
+                let Some(current) = options.get(name).map(signature) else {
+                    continue;
+                };
+                if let Some(previous) = &previous {
+                    for ((field, from), (_, to)) in previous.iter().zip(&current) {
+                        if from != to {
+                            changes.push(Change {
+                                version: label.clone(),
+                                field,
+                                from: from.clone(),
+                                to: to.clone(),
+                            });
+                        }
+                    }
+                }
+                previous = Some(current);
+            }
+
+            OptionHistory {
+                name: name.clone(),
+                present: versions
+                    .iter()
+                    .map(|(_, options)| options.contains_key(name))
+                    .collect(),
+                changes,
+            }
+        })
+        .collect()
+}
+
+/// Render the matrix as a markdown table with one column per version.
+pub fn render_matrix(title: &str, versions: &[String], histories: &[OptionHistory]) -> String {
+    let mut output = format!("# {}\n\n", title);
+
+    output.push_str("| Option |");
+    for version in versions {
+        output.push_str(&format!(" {} |", table_cell(version)));
+    }
+    output.push_str(" Changes |\n|");
+    for _ in 0..versions.len() + 2 {
+        output.push_str(" --- |");
+    }
+    output.push('\n');
+
+    for history in histories {
+        output.push_str(&format!("| `{}` |", history.name));
+        for present in &history.present {
+            output.push_str(if *present { " ✓ |" } else { "  |" });
+        }
+        let changes: Vec<String> = history
+            .changes
+            .iter()
+            .map(|c| {
+                let value = |v: &str| {
+                    if v.is_empty() {
+                        "*none*".to_string()
+                    } else {
+                        v.to_string()
+                    }
+                };
+                table_cell(&format!(
+                    "{}: {} {} → {}",
+                    c.version,
+                    c.field,
+                    value(&c.from),
+                    value(&c.to)
+                ))
+            })
+            .collect();
+        output.push_str(&format!(" {} |\n", changes.join("<br>")));
+    }
+
+    output
+}
@@ -0,0 +1,246 @@
+//! Problems found while documenting a file.
+//!
+//! Doc comments that cannot be parsed, bindings that are skipped because of
+//! their dynamic names or defined twice, entries missing from the location
+//! data, unresolved `inherit`s, references to unknown entries, broken links
+//! and problems with options do not stop the rendering.
+//! They are collected as [`Diagnostic`]s and printed in the style of rustc:
+//!
+//! ```text
+//! warning[dynamic-name]: `${name}` has a dynamic name and is not documented
+//!   --> lib/strings.nix:12:3
+//! ```
+//!
+//! or as one JSON object per line. In strict mode they are errors, and the
+//! run fails once they are printed.
+
+use serde::Serialize;
+
+use crate::format::line_column;
+
+/// How bad a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// The kind of problem a diagnostic reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiagnosticKind {
+    /// A doc comment too long to be documented in full, or with a `# Type`
+    /// section whose signature does not parse.
+    UnparsableDocComment,
+    /// A documented binding whose name contains an interpolation.
+    DynamicName,
+    /// An entry without an entry in the `--locs` file.
+    MissingLocation,
+    /// An inherited attribute whose source does not exist.
+    UnresolvedInherit,
+    /// A reference to an unknown entry in a doc comment.
+    DanglingReference,
+    /// A link of the rendered output that does not lead anywhere.
+    BrokenLink,
+    /// A source that is not valid UTF-8.
+    InvalidUtf8,
+    /// An attribute of the evaluated file without a binding to document.
+    UnboundAttribute,
+    /// A binding defined more than once.
+    DuplicateBinding,
+    /// Two names mapping to the same anchor.
+    AnchorCollision,
+    /// Documented arguments not matching those of the function.
+    ArgumentMismatch,
+    /// An option defined differently by two merged options files.
+    ConflictingOption,
+    /// Markdown of an option description the target flavor does not support.
+    InvalidMarkdown,
+    /// An option value or description longer than its budget.
+    OverBudget,
+    /// An option type the types appendix does not describe.
+    UnknownOptionType,
+}
+
+impl DiagnosticKind {
+    /// Code of the diagnostic, shown in brackets after its severity.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::UnparsableDocComment => "unparsable-doc-comment",
+            Self::DynamicName => "dynamic-name",
+            Self::MissingLocation => "missing-location",
+            Self::UnresolvedInherit => "unresolved-inherit",
+            Self::DanglingReference => "dangling-reference",
+            Self::BrokenLink => "broken-link",
+            Self::InvalidUtf8 => "invalid-utf8",
+            Self::UnboundAttribute => "unbound-attribute",
+            Self::DuplicateBinding => "duplicate-binding",
+            Self::AnchorCollision => "anchor-collision",
+            Self::ArgumentMismatch => "argument-mismatch",
+            Self::ConflictingOption => "conflicting-option",
+            Self::InvalidMarkdown => "invalid-markdown",
+            Self::OverBudget => "over-budget",
+            Self::UnknownOptionType => "unknown-option-type",
+        }
+    }
+}
+
+/// A problem with a 1-based position in a file, if it has one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    #[serde(rename = "code")]
+    pub kind: DiagnosticKind,
+    pub message: String,
+    pub file: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+}
+
+impl Diagnostic {
+    /// The diagnostic in the style of rustc, with its location on a second
+    /// line.
+    pub fn render(&self) -> String {
+        let location = match (self.line, self.column) {
+            (Some(line), Some(column)) => format!("{}:{}:{}", self.file, line, column),
+            (Some(line), None) => format!("{}:{}", self.file, line),
+            _ => self.file.clone(),
+        };
+        format!(
+            "{}[{}]: {}\n  --> {}\n",
+            self.severity.as_str(),
+            self.kind.as_str(),
+            self.message,
+            location
+        )
+    }
+}
+
+/// How diagnostics are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiagnosticsFormat {
+    /// In the style of rustc.
+    #[default]
+    Human,
+    /// One JSON object per line.
+    Json,
+}
+
+impl DiagnosticsFormat {
+    /// Look up a format by its name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "human" => Some(Self::Human),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// The diagnostics of one file.
+#[derive(Debug, Clone)]
+pub struct Diagnostics {
+    /// The file, as shown in locations.
+    file: String,
+    /// Its source, for turning byte offsets into lines and columns.
+    source: String,
+    /// Whether diagnostics are errors.
+    strict: bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new(file: &str, source: &str, strict: bool) -> Self {
+        Diagnostics {
+            file: file.to_string(),
+            source: source.to_string(),
+            strict,
+            diagnostics: vec![],
+        }
+    }
+
+    fn severity(&self) -> Severity {
+        if self.strict {
+            Severity::Error
+        } else {
+            Severity::Warning
+        }
+    }
+
+    /// Report a problem at a byte offset of the source.
+    pub fn at_offset(&mut self, kind: DiagnosticKind, offset: usize, message: String) {
+        let (line, column) = line_column(&self.source, offset);
+        self.push(kind, Some((line, column)), message);
+    }
+
+    /// Report a problem at a 1-based line and column, or with the file as
+    /// its only location.
+    pub fn push(
+        &mut self,
+        kind: DiagnosticKind,
+        position: Option<(usize, usize)>,
+        message: String,
+    ) {
+        let file = self.file.clone();
+        self.push_in(&file, kind, position, message);
+    }
+
+    /// Report a problem located somewhere else than the file of the
+    /// diagnostics, e.g. in the description of an option.
+    pub fn push_in(
+        &mut self,
+        file: &str,
+        kind: DiagnosticKind,
+        position: Option<(usize, usize)>,
+        message: String,
+    ) {
+        self.diagnostics.push(Diagnostic {
+            severity: self.severity(),
+            kind,
+            message,
+            file: file.to_string(),
+            line: position.map(|(line, _)| line),
+            column: position.map(|(_, column)| column),
+        });
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    /// Whether any diagnostic is an error, failing the run.
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|d| d.severity == Severity::Error)
+    }
+
+    /// All diagnostics in `format`, each ending in a newline.
+    pub fn render(&self, format: DiagnosticsFormat) -> String {
+        self.diagnostics
+            .iter()
+            .map(|diagnostic| match format {
+                DiagnosticsFormat::Human => diagnostic.render(),
+                DiagnosticsFormat::Json => {
+                    serde_json::to_string(diagnostic).expect("diagnostics serialize") + "\n"
+                }
+            })
+            .collect()
+    }
+}
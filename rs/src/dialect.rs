@@ -0,0 +1,302 @@
+//! Markdown dialects of the rendered documentation.
+//!
+//! Documentation is rendered in the markdown of nixpkgs, which extends
+//! CommonMark with heading attributes (`{#anchor}`), bracketed spans
+//! (`[]{#anchor}`), fenced divs (`::: {.example}`) and definition lists.
+//! Only Pandoc-like renderers understand these, so the rendered markdown can
+//! be rewritten in another [`MarkdownDialect`] for other renderers.
+
+use crate::admonition::{self, AdmonitionStyle};
+use crate::format::get_fence;
+
+/// Dialect of markdown documentation is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MarkdownDialect {
+    /// Markdown of nixpkgs, as rendered.
+    #[default]
+    Nixpkgs,
+    /// Plain CommonMark: anchors as HTML, admonitions as block quotes, and
+    /// definition lists and tables as lists.
+    CommonMark,
+    /// GitHub-flavored markdown: anchors as HTML, admonitions as alerts and
+    /// definition lists as lists.
+    Gfm,
+    /// MyST: anchors as targets, admonitions and examples as directives.
+    Myst,
+}
+
+impl MarkdownDialect {
+    /// Look up a dialect by its command line name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "nixpkgs" => Some(Self::Nixpkgs),
+            "commonmark" => Some(Self::CommonMark),
+            "gfm" => Some(Self::Gfm),
+            "myst" => Some(Self::Myst),
+            _ => None,
+        }
+    }
+
+    /// The syntax of admonitions in this dialect. Plain CommonMark renders
+    /// GitHub alerts as block quotes.
+    pub fn admonition_style(&self) -> AdmonitionStyle {
+        match self {
+            Self::Nixpkgs => AdmonitionStyle::Nixpkgs,
+            Self::CommonMark | Self::Gfm => AdmonitionStyle::GitHub,
+            Self::Myst => AdmonitionStyle::Myst,
+        }
+    }
+}
+
+/// An open fenced div.
+enum Div {
+    /// An example, closed like a MyST directive or not at all.
+    Example,
+    /// Any other div, which is kept.
+    Other,
+}
+
+/// Rewrite rendered nixpkgs markdown in `dialect`, with admonitions in
+/// `style`.
+pub fn convert_markdown(
+    markdown: &str,
+    dialect: MarkdownDialect,
+    style: AdmonitionStyle,
+) -> String {
+    let markdown = admonition::convert_admonitions(markdown, style);
+    if dialect == MarkdownDialect::Nixpkgs {
+        return markdown;
+    }
+    let lines: Vec<&str> = markdown.lines().collect();
+    let mut out: Vec<String> = vec![];
+    let mut divs: Vec<Div> = vec![];
+    let mut code_fence: Option<(usize, char)> = None;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim();
+        i += 1;
+
+        if let Some((count, ch)) = code_fence {
+            if get_fence(trimmed, false).is_some_and(|(c, h)| h == ch && c >= count) {
+                code_fence = None;
+            }
+            out.push(line.to_string());
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            code_fence = get_fence(trimmed, true);
+            out.push(line.to_string());
+            continue;
+        }
+
+        if let Some((level, title, id)) = attributed_heading(line) {
+            let hashes = "#".repeat(level);
+            match dialect {
+                MarkdownDialect::Myst => {
+                    out.push(format!("({})=", id));
+                    out.push(format!("{} {}", hashes, title));
+                }
+                _ => out.push(format!("{} {}{}", hashes, html_anchor(id), title)),
+            }
+        } else if let Some(id) = span_anchor(trimmed) {
+            out.push(anchor(id, dialect));
+        } else if let Some(attrs) = line.strip_prefix(":::").map(str::trim) {
+            if attrs.is_empty() {
+                match divs.pop() {
+                    Some(Div::Example) if dialect == MarkdownDialect::Myst => {
+                        out.push("::::".to_string())
+                    }
+                    Some(Div::Example) => (),
+                    _ => out.push(line.to_string()),
+                }
+            } else if is_example(attrs) {
+                // The heading starting the example is its title.
+                let next = (i..lines.len()).find(|&j| !lines[j].trim().is_empty());
+                let title = next.and_then(|j| example_title(lines[j]));
+                if title.is_some() {
+                    i = next.unwrap_or(i) + 1;
+                }
+                let id = attr_id(attrs);
+                match dialect {
+                    MarkdownDialect::Myst => {
+                        out.push(format!("::::{{admonition}} {}", title.unwrap_or("Example")));
+                        out.push(":class: example".to_string());
+                        if let Some(id) = id {
+                            out.push(format!(":name: {}", id));
+                        }
+                    }
+                    _ => {
+                        if let Some(id) = id {
+                            out.push(html_anchor(id));
+                            out.push(String::new());
+                        }
+                        if let Some(title) = title {
+                            out.push(format!("**{}**", title));
+                        }
+                    }
+                }
+                divs.push(Div::Example);
+            } else {
+                divs.push(Div::Other);
+                out.push(line.to_string());
+            }
+        } else if let Some(definition) = line
+            .strip_prefix(": ")
+            .filter(|_| dialect != MarkdownDialect::Myst)
+        {
+            definition_item(&mut out, definition);
+        } else if dialect == MarkdownDialect::CommonMark
+            && trimmed.starts_with('|')
+            && lines.get(i).is_some_and(|next| is_delimiter_row(next))
+        {
+            let header = table_cells(trimmed);
+            i += 1;
+            while let Some(row) = lines
+                .get(i)
+                .map(|l| l.trim())
+                .filter(|l| l.starts_with('|'))
+            {
+                out.push(table_item(&header, &table_cells(row)));
+                i += 1;
+            }
+        } else {
+            out.push(line.to_string());
+        }
+    }
+
+    let mut output = out.join("\n");
+    if markdown.ends_with('\n') {
+        output.push('\n');
+    }
+    output
+}
+
+/// The level, title and anchor of a heading ending with an attribute
+/// like `{#anchor}`.
+fn attributed_heading(line: &str) -> Option<(usize, &str, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let rest = line[level..].strip_prefix(' ')?;
+    if level == 0 || !rest.ends_with('}') {
+        return None;
+    }
+    let start = rest.rfind(" {")?;
+    let id = attr_id(&rest[start + 1..])?;
+    Some((level, rest[..start].trim_end(), id))
+}
+
+/// The anchor of a line that is only an empty bracketed span, `[]{#anchor}`.
+fn span_anchor(line: &str) -> Option<&str> {
+    attr_id(line.strip_prefix("[]")?)
+}
+
+/// The identifier of an attribute like `{#anchor .class}`.
+fn attr_id(attrs: &str) -> Option<&str> {
+    attrs
+        .strip_prefix('{')?
+        .strip_suffix('}')?
+        .split_whitespace()
+        .find_map(|attr| attr.strip_prefix('#'))
+        .filter(|id| !id.is_empty())
+}
+
+/// Whether the attributes of a fenced div include the `example` class.
+fn is_example(attrs: &str) -> bool {
+    attrs
+        .trim_start_matches('{')
+        .trim_end_matches('}')
+        .split_whitespace()
+        .any(|attr| attr == ".example")
+}
+
+/// The title of an ATX heading line.
+fn example_title(line: &str) -> Option<&str> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    (level > 0)
+        .then(|| line[level..].strip_prefix(' '))
+        .flatten()
+        .map(str::trim)
+}
+
+fn html_anchor(id: &str) -> String {
+    format!("<a id=\"{}\"></a>", id)
+}
+
+/// A standalone anchor.
+fn anchor(id: &str, dialect: MarkdownDialect) -> String {
+    match dialect {
+        MarkdownDialect::Myst => format!("({})=", id),
+        _ => html_anchor(id),
+    }
+}
+
+/// Turn the term preceding a definition into a list item with the
+/// definition as its second paragraph.
+fn definition_item(out: &mut Vec<String>, definition: &str) {
+    let term = out
+        .iter()
+        .rposition(|line| !line.trim().is_empty())
+        .filter(|&t| t == 0 || out[t - 1].trim().is_empty())
+        .filter(|&t| !out[t].starts_with("- "));
+    match term {
+        Some(t) => {
+            out[t] = format!("- {}", out[t]);
+            out.push(format!("  {}", definition));
+        }
+        None => out.push(format!(": {}", definition)),
+    }
+}
+
+/// Whether a line is the row delimiting the header of a table, like
+/// `| --- | :-: |`.
+fn is_delimiter_row(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with('|')
+        && line.contains('-')
+        && line.chars().all(|c| matches!(c, '|' | '-' | ':' | ' '))
+}
+
+/// The cells of a table row, with escaped pipes unescaped.
+fn table_cells(row: &str) -> Vec<String> {
+    let row = row.trim().trim_start_matches('|');
+    let row = row
+        .strip_suffix('|')
+        .filter(|r| !r.ends_with('\\'))
+        .unwrap_or(row);
+    let mut cells = vec![];
+    let mut cell = String::new();
+    let mut chars = row.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'|') => {
+                cell.push('|');
+                chars.next();
+            }
+            '|' => cells.push(std::mem::take(&mut cell).trim().to_string()),
+            _ => cell.push(c),
+        }
+    }
+    cells.push(cell.trim().to_string());
+    cells
+}
+
+/// A list item of a table row: its first cell, followed by the other
+/// non-empty cells labelled by their header.
+fn table_item(header: &[String], cells: &[String]) -> String {
+    let mut item = format!(
+        "- {}",
+        cells.first().map(String::as_str).unwrap_or_default()
+    );
+    let rest: Vec<String> = header
+        .iter()
+        .zip(cells)
+        .skip(1)
+        .filter(|(_, cell)| !cell.is_empty())
+        .map(|(label, cell)| format!("**{}:** {}", label, cell))
+        .collect();
+    if !rest.is_empty() {
+        item.push_str(": ");
+        item.push_str(&rest.join("; "));
+    }
+    item
+}
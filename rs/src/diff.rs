@@ -0,0 +1,248 @@
+//! Structured comparison of two `--json-output` documents, or of two sets
+//! of module options.
+//!
+//! Entries are matched by their identifier (e.g. `lib.strings.concat`) and
+//! compared field by field, so the result lists added, removed and changed
+//! entries together with the old and new value of every changed field.
+//! Options are matched by name and compared by their type, default and
+//! description, which is what release notes list.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::commonmark::{get_identifier, ManualEntry};
+use crate::format::code_span;
+use crate::options::{OptionDef, OptionValue, OptionsMap};
+use crate::JsonFormat;
+
+/// A field whose value differs between two versions of an entry.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+/// An entry present in both documents with at least one changed field.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EntryChange {
+    pub name: String,
+    pub fields: Vec<FieldChange>,
+}
+
+/// Differences between two documents, sorted by entry identifier.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Changeset {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<EntryChange>,
+}
+
+impl Changeset {
+    /// Whether the documents describe the same entries.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Human-readable summary with one line per entry, prefixed by `+`
+    /// (added), `-` (removed) or `~` (changed, followed by the fields).
+    pub fn summary(&self) -> String {
+        let mut out = String::new();
+        for name in &self.added {
+            out.push_str(&format!("+ {}\n", name));
+        }
+        for name in &self.removed {
+            out.push_str(&format!("- {}\n", name));
+        }
+        for change in &self.changed {
+            let fields: Vec<&str> = change.fields.iter().map(|f| f.field.as_str()).collect();
+            out.push_str(&format!("~ {}: {}\n", change.name, fields.join(", ")));
+        }
+        out
+    }
+}
+
+fn by_identifier(doc: &JsonFormat) -> BTreeMap<String, &ManualEntry> {
+    doc.entries
+        .iter()
+        .map(|e| (get_identifier(&e.prefix, &e.category, &e.name), e))
+        .collect()
+}
+
+/// Compare the fields of two entries. Fields missing on one side (e.g. ones
+/// that are skipped when empty) compare as `null`.
+fn diff_entry(old: &ManualEntry, new: &ManualEntry) -> Vec<FieldChange> {
+    let (Ok(Value::Object(old)), Ok(Value::Object(new))) =
+        (serde_json::to_value(old), serde_json::to_value(new))
+    else {
+        return vec![];
+    };
+    let fields: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    fields
+        .into_iter()
+        .filter_map(|field| {
+            let old = old.get(field).cloned().unwrap_or(Value::Null);
+            let new = new.get(field).cloned().unwrap_or(Value::Null);
+            (old != new).then(|| FieldChange {
+                field: field.clone(),
+                old,
+                new,
+            })
+        })
+        .collect()
+}
+
+/// Compare two documents.
+pub fn diff_json(old: &JsonFormat, new: &JsonFormat) -> Changeset {
+    let old = by_identifier(old);
+    let new = by_identifier(new);
+    let mut changeset = Changeset::default();
+
+    for (name, old_entry) in &old {
+        match new.get(name) {
+            None => changeset.removed.push(name.clone()),
+            Some(new_entry) => {
+                let fields = diff_entry(old_entry, new_entry);
+                if !fields.is_empty() {
+                    changeset.changed.push(EntryChange {
+                        name: name.clone(),
+                        fields,
+                    });
+                }
+            }
+        }
+    }
+    changeset.added = new
+        .keys()
+        .filter(|name| !old.contains_key(*name))
+        .cloned()
+        .collect();
+
+    changeset
+}
+
+/// Compare two sets of options. Hidden options are left out, as they are
+/// not documented either.
+pub fn diff_options(old: &OptionsMap, new: &OptionsMap) -> Changeset {
+    fn visible(options: &OptionsMap) -> BTreeMap<&String, &OptionDef> {
+        options
+            .iter()
+            .filter(|(_, option)| !option.is_hidden())
+            .collect()
+    }
+    let old = visible(old);
+    let new = visible(new);
+    let mut changeset = Changeset::default();
+
+    for (name, old_option) in &old {
+        match new.get(name) {
+            None => changeset.removed.push(name.to_string()),
+            Some(new_option) => {
+                let fields = diff_option(old_option, new_option);
+                if !fields.is_empty() {
+                    changeset.changed.push(EntryChange {
+                        name: name.to_string(),
+                        fields,
+                    });
+                }
+            }
+        }
+    }
+    changeset.added = new
+        .keys()
+        .filter(|name| !old.contains_key(*name))
+        .map(|name| name.to_string())
+        .collect();
+
+    changeset
+}
+
+/// Compare the type, default and description of two options.
+fn diff_option(old: &OptionDef, new: &OptionDef) -> Vec<FieldChange> {
+    let fields = |option: &OptionDef| {
+        [
+            ("type", option.option_type.clone()),
+            ("default", option.default.as_ref().map(value_text)),
+            (
+                "description",
+                option.description.as_ref().map(|d| d.as_str().to_string()),
+            ),
+        ]
+    };
+    fields(old)
+        .into_iter()
+        .zip(fields(new))
+        .filter(|((_, old), (_, new))| old != new)
+        .map(|((field, old), (_, new))| FieldChange {
+            field: field.to_string(),
+            old: old.map_or(Value::Null, Value::String),
+            new: new.map_or(Value::Null, Value::String),
+        })
+        .collect()
+}
+
+/// Source text of an option value: the text of literal expressions and
+/// markdown, the JSON of other values.
+fn value_text(value: &OptionValue) -> String {
+    match value {
+        OptionValue::Tagged(tagged) => tagged.text.clone().unwrap_or_default(),
+        OptionValue::String(s) => Value::String(s.clone()).to_string(),
+        OptionValue::Bool(b) => b.to_string(),
+        OptionValue::Number(n) => n.to_string(),
+        OptionValue::Array(items) => Value::Array(items.clone()).to_string(),
+        OptionValue::Object(obj) => Value::Object(obj.clone()).to_string(),
+        OptionValue::Null => "null".to_string(),
+    }
+}
+
+/// Render option changes as CommonMark, with a section each for added,
+/// removed and changed options. Changed options list how their type and
+/// default changed; description changes are only mentioned.
+pub fn render_option_changes(changeset: &Changeset) -> String {
+    if changeset.is_empty() {
+        return "No options changed.\n".to_string();
+    }
+    let mut sections = vec![];
+    let list = |names: &[String]| -> String {
+        names
+            .iter()
+            .map(|name| format!("- {}\n", code_span(name)))
+            .collect()
+    };
+    if !changeset.added.is_empty() {
+        sections.push(format!("## Added options\n\n{}", list(&changeset.added)));
+    }
+    if !changeset.removed.is_empty() {
+        sections.push(format!(
+            "## Removed options\n\n{}",
+            list(&changeset.removed)
+        ));
+    }
+    if !changeset.changed.is_empty() {
+        let mut section = String::from("## Changed options\n\n");
+        for change in &changeset.changed {
+            let changes: Vec<String> = change.fields.iter().map(describe_field_change).collect();
+            section.push_str(&format!(
+                "- {}: {}\n",
+                code_span(&change.name),
+                changes.join("; ")
+            ));
+        }
+        sections.push(section);
+    }
+    sections.join("\n")
+}
+
+fn describe_field_change(change: &FieldChange) -> String {
+    let value = |value: &Value| match value {
+        Value::String(s) => code_span(s),
+        _ => "none".to_string(),
+    };
+    match (change.field.as_str(), &change.old, &change.new) {
+        ("description", Value::Null, _) => "description added".to_string(),
+        ("description", _, Value::Null) => "description removed".to_string(),
+        ("description", _, _) => "description changed".to_string(),
+        (field, old, new) => format!("{} changed from {} to {}", field, value(old), value(new)),
+    }
+}
@@ -0,0 +1,365 @@
+//! Rendering as DocBook 5, for manual builds that still consume DocBook.
+//!
+//! Function documentation becomes a `<section>` with one `<refentry>` per
+//! entry, options become a `<variablelist>` with one `<varlistentry>` per
+//! option. Both carry the same `xml:id`s as the anchors of the markdown
+//! output, so links into the manual keep working.
+//!
+//! Descriptions are converted from markdown: fenced divs become admonitions
+//! (`::: {.warning}` → `<warning>`) or `<informalexample>`s, headings become
+//! `<bridgehead>`s, links to `#anchor`s become `<link linkend>` (or `<xref>`
+//! if they have no text), and roles (``{option}`foo` ``) become the matching
+//! inline elements.
+
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+
+use crate::commonmark::{EntryLabels, EntrySection, ManualEntry};
+use crate::format::{div_lines, inline_anchor, strip_role, DivLine};
+use crate::options::{option_anchors, render_option_body, OptionsMap, RenderOptions};
+use crate::search::plain_text;
+
+const NAMESPACES: &str =
+    r#"xmlns="http://docbook.org/ns/docbook" xmlns:xlink="http://www.w3.org/1999/xlink""#;
+
+/// Render the entries of a category as a section titled `title`, with the
+/// file's doc comment (`description`) as introduction.
+pub fn render_functions(
+    id: &str,
+    title: &str,
+    description: Option<&str>,
+    entries: Vec<ManualEntry>,
+    anchor_prefix: &str,
+    layout: &[EntrySection],
+    labels: &EntryLabels,
+) -> String {
+    let mut out = format!(
+        "<section {} xml:id=\"{}\">\n<title>{}</title>\n",
+        NAMESPACES,
+        escape(id),
+        escape(title)
+    );
+    if let Some(description) = description {
+        out.push_str(&markdown_to_docbook(description));
+    }
+    for entry in entries {
+        let (ident, title) = entry.get_ident_title();
+        let purpose = entry
+            .description
+            .first()
+            .map(|p| plain_text(p))
+            .unwrap_or_default();
+        let mut markdown = String::new();
+        entry.write_section_with(anchor_prefix, layout, labels, &mut markdown);
+        // The heading is replaced by the name of the refentry.
+        let body = markdown.split_once("\n\n").map_or("", |(_, body)| body);
+        out.push_str(&format!(
+            "<refentry xml:id=\"{}{}\">\n<refnamediv>\n<refname>{}</refname>\n<refpurpose>{}</refpurpose>\n</refnamediv>\n<refsection>\n<title>Description</title>\n{}</refsection>\n</refentry>\n",
+            escape(anchor_prefix),
+            escape(&ident),
+            escape(&title),
+            escape(&purpose),
+            markdown_to_docbook(body)
+        ));
+    }
+    out.push_str("</section>\n");
+    out
+}
+
+/// Render options as a section titled `title` holding a variable list.
+pub fn render_options(
+    options: &OptionsMap,
+    title: &str,
+    preamble: Option<&str>,
+    opts: &RenderOptions,
+) -> String {
+    let mut out = format!(
+        "<section {}>\n<title>{}</title>\n",
+        NAMESPACES,
+        escape(title)
+    );
+    if let Some(preamble) = preamble {
+        out.push_str(&markdown_to_docbook(preamble));
+    }
+
+    let names = opts.sort.sorted_names(options);
+    let anchors = option_anchors(options, &opts.anchor_prefix);
+    if !names.is_empty() {
+        out.push_str("<variablelist>\n");
+        for name in names {
+            out.push_str(&format!(
+                "<varlistentry xml:id=\"{}\">\n<term><option>{}</option></term>\n<listitem>\n{}</listitem>\n</varlistentry>\n",
+                escape(anchors.get(name).unwrap_or_default()),
+                escape(name),
+                markdown_to_docbook(&render_option_body(&options[name], &anchors, opts))
+            ));
+        }
+        out.push_str("</variablelist>\n");
+    }
+
+    if let Some(footer) = opts.provenance.footer() {
+        out.push_str(&format!(
+            "<para><emphasis>{}</emphasis></para>\n",
+            escape(&footer)
+        ));
+    }
+    out.push_str("</section>\n");
+    out
+}
+
+/// Convert markdown to a sequence of DocBook block elements.
+pub fn markdown_to_docbook(markdown: &str) -> String {
+    let source = fenced_divs_to_docbook(markdown);
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_HEADING_ATTRIBUTES
+        | Options::ENABLE_DEFINITION_LIST;
+
+    let mut out = String::new();
+    // Whether inline content directly inside a list item or definition needs
+    // a `<para>`, and whether one is open.
+    let mut para_pending = false;
+    let mut para_open = false;
+    let mut in_varlistentry = false;
+    let mut in_code_block = false;
+    // Start of the opening tag and of the content of open links.
+    let mut links: Vec<(usize, usize)> = vec![];
+
+    for event in Parser::new_ext(&source, options) {
+        let inline = is_inline(&event);
+        if inline && para_pending && !in_code_block {
+            out.push_str("<para>");
+            para_pending = false;
+            para_open = true;
+        } else if !inline && para_open {
+            out.push_str("</para>\n");
+            para_open = false;
+        }
+
+        match event {
+            Event::Start(Tag::Heading { level, id, .. }) => {
+                out.push_str(&format!(
+                    "<bridgehead renderas=\"sect{}\"",
+                    (level as usize).min(5)
+                ));
+                if let Some(id) = id {
+                    out.push_str(&format!(" xml:id=\"{}\"", escape(&id)));
+                }
+                out.push('>');
+            }
+            Event::End(TagEnd::Heading(_)) => out.push_str("</bridgehead>\n"),
+            Event::Start(Tag::Paragraph) => {
+                para_pending = false;
+                out.push_str("<para>");
+            }
+            Event::End(TagEnd::Paragraph) => out.push_str("</para>\n"),
+            Event::Start(Tag::CodeBlock(kind)) => {
+                para_pending = false;
+                in_code_block = true;
+                out.push_str("<programlisting");
+                if let CodeBlockKind::Fenced(info) = kind {
+                    if let Some(lang) = info.split_whitespace().next() {
+                        out.push_str(&format!(" language=\"{}\"", escape(lang)));
+                    }
+                }
+                out.push('>');
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                in_code_block = false;
+                out.push_str("</programlisting>\n");
+            }
+            Event::Start(Tag::List(None)) => {
+                para_pending = false;
+                out.push_str("<itemizedlist>\n");
+            }
+            Event::Start(Tag::List(Some(start))) => {
+                para_pending = false;
+                if start == 1 {
+                    out.push_str("<orderedlist>\n");
+                } else {
+                    out.push_str(&format!("<orderedlist startingnumber=\"{}\">\n", start));
+                }
+            }
+            Event::End(TagEnd::List(ordered)) => {
+                out.push_str(if ordered {
+                    "</orderedlist>\n"
+                } else {
+                    "</itemizedlist>\n"
+                });
+            }
+            Event::Start(Tag::Item) => {
+                out.push_str("<listitem>");
+                para_pending = true;
+            }
+            Event::End(TagEnd::Item) => {
+                para_pending = false;
+                out.push_str("</listitem>\n");
+            }
+            Event::Start(Tag::DefinitionList) => out.push_str("<variablelist>\n"),
+            Event::End(TagEnd::DefinitionList) => {
+                if in_varlistentry {
+                    in_varlistentry = false;
+                    out.push_str("</varlistentry>\n");
+                }
+                out.push_str("</variablelist>\n");
+            }
+            Event::Start(Tag::DefinitionListTitle) => {
+                if in_varlistentry {
+                    out.push_str("</varlistentry>\n");
+                }
+                in_varlistentry = true;
+                out.push_str("<varlistentry>\n<term>");
+            }
+            Event::End(TagEnd::DefinitionListTitle) => out.push_str("</term>\n"),
+            Event::Start(Tag::DefinitionListDefinition) => {
+                out.push_str("<listitem>");
+                para_pending = true;
+            }
+            Event::End(TagEnd::DefinitionListDefinition) => {
+                para_pending = false;
+                out.push_str("</listitem>\n");
+            }
+            Event::Start(Tag::BlockQuote(_)) => out.push_str("<blockquote>\n"),
+            Event::End(TagEnd::BlockQuote(_)) => out.push_str("</blockquote>\n"),
+            Event::Start(Tag::Table(alignments)) => {
+                out.push_str(&format!(
+                    "<informaltable>\n<tgroup cols=\"{}\">\n",
+                    alignments.len()
+                ));
+            }
+            Event::End(TagEnd::Table) => out.push_str("</tbody>\n</tgroup>\n</informaltable>\n"),
+            Event::Start(Tag::TableHead) => out.push_str("<thead>\n<row>"),
+            Event::End(TagEnd::TableHead) => out.push_str("</row>\n</thead>\n<tbody>\n"),
+            Event::Start(Tag::TableRow) => out.push_str("<row>"),
+            Event::End(TagEnd::TableRow) => out.push_str("</row>\n"),
+            Event::Start(Tag::TableCell) => out.push_str("<entry>"),
+            Event::End(TagEnd::TableCell) => out.push_str("</entry>"),
+            Event::Start(Tag::Emphasis) => out.push_str("<emphasis>"),
+            Event::End(TagEnd::Emphasis) => out.push_str("</emphasis>"),
+            Event::Start(Tag::Strong) => out.push_str("<emphasis role=\"strong\">"),
+            Event::End(TagEnd::Strong) => out.push_str("</emphasis>"),
+            Event::Start(Tag::Link { dest_url, .. }) => {
+                let start = out.len();
+                match dest_url.strip_prefix('#') {
+                    Some(id) => out.push_str(&format!("<link linkend=\"{}\">", escape(id))),
+                    None => out.push_str(&format!("<link xlink:href=\"{}\">", escape(&dest_url))),
+                }
+                links.push((start, out.len()));
+            }
+            Event::End(TagEnd::Link) => {
+                let (start, content) = links.pop().unwrap_or_default();
+                // Links to anchors without text are filled in with the
+                // target's title.
+                if content == out.len() && out[start..].starts_with("<link linkend=") {
+                    let id = out[start + "<link linkend=".len()..content - 1].to_string();
+                    out.truncate(start);
+                    out.push_str(&format!("<xref linkend={}/>", id));
+                } else {
+                    out.push_str("</link>");
+                }
+            }
+            Event::Start(Tag::Image { dest_url, .. }) => {
+                out.push_str(&format!(
+                    "<inlinemediaobject><imageobject><imagedata fileref=\"{}\"/></imageobject><textobject><phrase>",
+                    escape(&dest_url)
+                ));
+            }
+            Event::End(TagEnd::Image) => {
+                out.push_str("</phrase></textobject></inlinemediaobject>");
+            }
+            Event::Code(code) => {
+                let element = match strip_role(&mut out).as_deref() {
+                    Some("option") => "option",
+                    Some("command") => "command",
+                    Some("file") => "filename",
+                    Some("env") => "envar",
+                    Some("var") => "varname",
+                    _ => "literal",
+                };
+                out.push_str(&format!("<{0}>{1}</{0}>", element, escape(&code)));
+            }
+            Event::Text(text) => out.push_str(&escape(&text)),
+            Event::Html(html) | Event::InlineHtml(html) => out.push_str(&html),
+            Event::SoftBreak | Event::HardBreak => out.push('\n'),
+            _ => (),
+        }
+    }
+    if para_open {
+        out.push_str("</para>\n");
+    }
+
+    out
+}
+
+/// Whether an event is part of inline content.
+fn is_inline(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::Text(_)
+            | Event::Code(_)
+            | Event::InlineHtml(_)
+            | Event::SoftBreak
+            | Event::HardBreak
+            | Event::Start(Tag::Emphasis | Tag::Strong | Tag::Link { .. } | Tag::Image { .. })
+            | Event::End(TagEnd::Emphasis | TagEnd::Strong | TagEnd::Link | TagEnd::Image)
+    )
+}
+
+/// Replace the fences of fenced divs by the matching DocBook elements, and
+/// `[]{#id}` lines by anchors.
+fn fenced_divs_to_docbook(markdown: &str) -> String {
+    let mut out = String::new();
+    let mut open: Vec<&str> = vec![];
+    for line in div_lines(markdown) {
+        match line {
+            DivLine::Open(attrs) => {
+                let attrs = attrs.trim_start_matches('{').trim_end_matches('}');
+                let mut element = "";
+                let mut id = None;
+                for attr in attrs.split_whitespace() {
+                    if let Some(class) = attr.strip_prefix('.') {
+                        element = match class {
+                            "example" => "informalexample",
+                            "note" | "tip" | "warning" | "important" | "caution" => class,
+                            _ => element,
+                        };
+                    } else if let Some(anchor) = attr.strip_prefix('#') {
+                        id = Some(anchor);
+                    }
+                }
+                // Unknown divs are unwrapped.
+                if !element.is_empty() {
+                    out.push_str(&format!("<{}", element));
+                    if let Some(id) = id {
+                        out.push_str(&format!(" xml:id=\"{}\"", escape(id)));
+                    }
+                    out.push_str(">\n\n");
+                }
+                open.push(element);
+            }
+            DivLine::Close => {
+                if let Some(element) = open.pop().filter(|e| !e.is_empty()) {
+                    out.push_str(&format!("\n</{}>\n\n", element));
+                }
+            }
+            DivLine::Text(line, false) if inline_anchor(line).is_some() => {
+                let id = inline_anchor(line).unwrap_or_default();
+                out.push_str(&format!(
+                    "<para><anchor xml:id=\"{}\"/></para>\n\n",
+                    escape(id)
+                ));
+            }
+            DivLine::Text(line, _) => {
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+/// Escape text for use in XML content and attribute values.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,246 @@
+//! Examples as tests.
+//!
+//! Code blocks in the `# Examples` section of a doc comment can state the
+//! value an expression evaluates to on a line starting with `=>`:
+//!
+//! ````markdown
+//! # Examples
+//!
+//! ```nix
+//! concatStrings [ "foo" "bar" ]
+//! => "foobar"
+//! ```
+//! ````
+//!
+//! Such examples are evaluated with the attributes of a scope (by default the
+//! documented file, called with `{ }` if it is a function) in scope, and the
+//! result is compared with the expected value. Blocks may hold several
+//! examples; examples without an `=>` line are not checked.
+
+use std::path::Path;
+use std::process::Command;
+
+use crate::commonmark::ManualEntry;
+use crate::format::get_fence;
+
+/// An example with its expected result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Example {
+    /// Title of the entry the example belongs to.
+    pub entry: String,
+    /// The expression to evaluate.
+    pub expr: String,
+    /// The value it is expected to evaluate to, as written after `=>`.
+    pub expected: String,
+}
+
+/// Evaluates Nix expressions.
+pub trait Evaluator {
+    /// Evaluate `expr` strictly and return the printed value.
+    fn eval(&self, expr: &str) -> Result<String, String>;
+}
+
+/// Evaluates expressions with an external command, which is given the
+/// expression as its last argument.
+#[derive(Debug, Clone)]
+pub struct CommandEvaluator {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl CommandEvaluator {
+    /// `nix-instantiate --eval --strict -E`.
+    pub fn nix_instantiate() -> Self {
+        Self {
+            program: "nix-instantiate".to_string(),
+            args: vec!["--eval".into(), "--strict".into(), "-E".into()],
+        }
+    }
+
+    /// `nix eval --impure --expr`, which needs the `nix-command` feature.
+    pub fn nix_eval() -> Self {
+        Self {
+            program: "nix".to_string(),
+            args: vec!["eval".into(), "--impure".into(), "--expr".into()],
+        }
+    }
+
+    /// The evaluator named `nix-instantiate` or `nix`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "nix-instantiate" => Some(Self::nix_instantiate()),
+            "nix" => Some(Self::nix_eval()),
+            _ => None,
+        }
+    }
+}
+
+impl Evaluator for CommandEvaluator {
+    fn eval(&self, expr: &str) -> Result<String, String> {
+        let output = Command::new(&self.program)
+            .args(&self.args)
+            .arg(expr)
+            .output()
+            .map_err(|e| format!("could not run {}: {}", self.program, e))?;
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        } else {
+            Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+        }
+    }
+}
+
+/// Outcome of checking an example.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    /// The example evaluated to a different value.
+    Mismatch {
+        actual: String,
+    },
+    /// The example or its expected value failed to evaluate.
+    Error(String),
+}
+
+/// The scope examples of the file at `path` are evaluated in: the file's
+/// value, applied to `{ }` if it is a function.
+///
+/// The path is made absolute and written as a string appended to `/.`, as
+/// path literals cannot hold spaces and other characters, and relative path
+/// literals must start with `./`.
+pub fn default_scope(path: &Path) -> String {
+    let path = path
+        .canonicalize()
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf());
+    let escaped = path
+        .display()
+        .to_string()
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace("${", "\\${");
+    format!(
+        "(let file = import (/. + \"{}\"); in if builtins.isFunction file then file {{ }} else file)",
+        escaped
+    )
+}
+
+/// Collect the checkable examples of `entries`.
+pub fn extract_examples(entries: &[ManualEntry]) -> Vec<Example> {
+    entries
+        .iter()
+        .flat_map(|entry| {
+            let title = entry.get_ident_title().1;
+            entry
+                .examples
+                .iter()
+                .flat_map(|example| code_examples(&example.body))
+                .map(move |(expr, expected)| Example {
+                    entry: title.clone(),
+                    expr,
+                    expected,
+                })
+        })
+        .collect()
+}
+
+/// The `(expression, expected)` pairs in the Nix code blocks of an
+/// example's markdown.
+fn code_examples(body: &str) -> Vec<(String, String)> {
+    let mut examples = vec![];
+    // The open code block, and whether it holds Nix code.
+    let mut fence: Option<((usize, char), bool)> = None;
+    let mut block: Vec<&str> = vec![];
+    for line in body.lines() {
+        let trimmed = line.trim();
+        if let Some(((count, ch), is_nix)) = fence {
+            if get_fence(trimmed, false).is_some_and(|(c, h)| h == ch && c >= count) {
+                fence = None;
+                if is_nix {
+                    examples.extend(block_examples(&block));
+                }
+                block.clear();
+            } else {
+                block.push(line);
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            let info = trimmed.trim_start_matches(['`', '~']).trim();
+            fence = get_fence(trimmed, true).map(|f| (f, info.is_empty() || info == "nix"));
+        }
+    }
+    examples
+}
+
+/// Split the lines of a code block into examples. Lines up to an `=>` line
+/// form the expression; the expected value continues up to the next blank
+/// line.
+fn block_examples(lines: &[&str]) -> Vec<(String, String)> {
+    let mut examples = vec![];
+    let mut expr: Vec<&str> = vec![];
+    let mut expected: Option<Vec<&str>> = None;
+    for line in lines {
+        if let Some(value) = line.trim_start().strip_prefix("=>") {
+            push_example(&mut examples, &mut expr, expected.take());
+            expected = Some(vec![value.trim()]);
+        } else if let Some(value) = &mut expected {
+            if line.trim().is_empty() {
+                push_example(&mut examples, &mut expr, expected.take());
+            } else {
+                value.push(line.trim());
+            }
+        } else {
+            expr.push(line);
+        }
+    }
+    push_example(&mut examples, &mut expr, expected);
+    examples
+}
+
+fn push_example(
+    examples: &mut Vec<(String, String)>,
+    expr: &mut Vec<&str>,
+    expected: Option<Vec<&str>>,
+) {
+    let Some(expected) = expected else {
+        return;
+    };
+    let code = expr.join("\n").trim().to_string();
+    if !code.is_empty() {
+        examples.push((code, expected.join("\n").trim().to_string()));
+    }
+    expr.clear();
+}
+
+/// Evaluate an example in `scope` and compare the result with its expected
+/// value. Values that are printed differently than written (e.g. `[1 2]` and
+/// `[ 1 2 ]`) are compared by Nix.
+pub fn check_example(example: &Example, scope: &str, evaluator: &dyn Evaluator) -> Outcome {
+    let actual = match evaluator.eval(&format!("with {}; ({})", scope, example.expr)) {
+        Ok(actual) => actual,
+        Err(e) => return Outcome::Error(e),
+    };
+    if normalize(&actual) == normalize(&example.expected) {
+        return Outcome::Passed;
+    }
+    if rnix::Root::parse(&example.expected).ok().is_ok() {
+        let comparison = format!(
+            "with {}; ({}) == ({})",
+            scope, example.expr, example.expected
+        );
+        if evaluator
+            .eval(&comparison)
+            .is_ok_and(|equal| equal == "true")
+        {
+            return Outcome::Passed;
+        }
+    }
+    Outcome::Mismatch { actual }
+}
+
+/// Collapse runs of whitespace, so values spanning several lines compare
+/// equal to their printed form.
+fn normalize(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,115 @@
+//! Entries found by evaluating a file.
+//!
+//! Files constructing their attributes programmatically, e.g. with
+//! `lib.genAttrs` or `mapAttrs` over a list, define no bindings the static
+//! analysis could find. Such files can be evaluated with `nix eval` for the
+//! names of their attributes and, via `builtins.unsafeGetAttrPos`, the
+//! positions of the bindings defining them. Bindings of the file at those
+//! positions are documented by their doc comments as usual.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use rnix::ast::AttrpathValue;
+use rowan::ast::AstNode;
+use serde::Deserialize;
+
+use crate::commonmark::ManualEntry;
+use crate::doctest::{CommandEvaluator, Evaluator};
+use crate::{collect_entry_information, glob, position_of, CollectOptions};
+
+/// Where an attribute is defined, as reported by `builtins.unsafeGetAttrPos`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AttrPosition {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// An attribute of an evaluated attribute set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvaluatedAttr {
+    pub name: String,
+    /// Position of the binding, if the attribute was defined by one.
+    pub position: Option<AttrPosition>,
+}
+
+/// `nix eval --json --impure --expr`, which needs the `nix-command`
+/// feature.
+pub fn json_evaluator() -> CommandEvaluator {
+    CommandEvaluator {
+        program: "nix".to_string(),
+        args: vec![
+            "eval".into(),
+            "--json".into(),
+            "--impure".into(),
+            "--expr".into(),
+        ],
+    }
+}
+
+/// Evaluate the attribute set `scope` evaluates to for its attributes and
+/// their positions, with an evaluator printing JSON.
+pub fn evaluate_attrs(
+    evaluator: &dyn Evaluator,
+    scope: &str,
+) -> Result<Vec<EvaluatedAttr>, String> {
+    let expr = format!(
+        "let set = {}; in builtins.mapAttrs (name: _: builtins.unsafeGetAttrPos name set) set",
+        scope
+    );
+    let json = evaluator.eval(&expr)?;
+    let positions: BTreeMap<String, Option<AttrPosition>> =
+        serde_json::from_str(&json).map_err(|e| format!("unexpected evaluation result: {}", e))?;
+    Ok(positions
+        .into_iter()
+        .map(|(name, position)| EvaluatedAttr { name, position })
+        .collect())
+}
+
+/// Document the evaluated attributes defined by documented bindings of
+/// `file`, parsed as `root`. Also returns the names of the attributes that
+/// are not defined by a binding of the file, which can't be documented.
+pub fn evaluated_entries(
+    root: &rnix::Root,
+    file: &Path,
+    attrs: &[EvaluatedAttr],
+    opts: &CollectOptions,
+) -> (Vec<ManualEntry>, Vec<String>) {
+    let file = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+    let bindings: Vec<AttrpathValue> = root
+        .syntax()
+        .descendants()
+        .filter_map(AttrpathValue::cast)
+        .collect();
+
+    let mut entries = vec![];
+    let mut undefined = vec![];
+    for attr in attrs {
+        if !glob::filter_name(&attr.name, &opts.include, &opts.exclude) {
+            continue;
+        }
+        let binding = attr
+            .position
+            .as_ref()
+            .filter(|position| Path::new(&position.file) == file)
+            .and_then(|position| {
+                bindings.iter().find(|binding| {
+                    binding.attrpath().is_some_and(|path| {
+                        let syntax = path.syntax();
+                        position_of(syntax, syntax.text_range().start())
+                            == (position.line, position.column)
+                    })
+                })
+            });
+        let Some(binding) = binding else {
+            undefined.push(attr.name.clone());
+            continue;
+        };
+        if let Some(mut item) = collect_entry_information(binding.clone(), opts) {
+            item.name = attr.name.clone();
+            entries.push(item.into_entry(opts));
+        }
+    }
+    (entries, undefined)
+}
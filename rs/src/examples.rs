@@ -0,0 +1,288 @@
+//! Examples of RFC145 doc comments.
+//!
+//! Examples are written in an `# Example` or `# Examples` section, where
+//! every `::: {.example}` fenced div is a separate example titled by its
+//! first heading, or under headings naming them:
+//!
+//! ````markdown
+//! # Example: basic usage
+//!
+//! ```nix
+//! concat "a" "b"
+//! ```
+//!
+//! # Example: lists
+//!
+//! ```nix
+//! concat [ 1 ] [ 2 ]
+//! ```
+//! ````
+//!
+//! The examples are split off the description, rendered as separate
+//! subsections and listed with their titles in the JSON output.
+//!
+//! Examples are assumed to be Nix unless their heading names a language,
+//! e.g. `# Example (bash): building` or `# Examples (json)`. The code
+//! blocks of an example without an info string are given its language, see
+//! [`apply_language`], so they are highlighted as such.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::format::{div_lines, get_fence, DivLine};
+use crate::tags::collapse_blank_lines;
+
+/// An example of a doc comment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ExampleDoc {
+    /// Title of the example, if it is named.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Markdown of the example, usually a code block.
+    pub body: String,
+    /// Language of the example's code, if its heading names one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+}
+
+/// Where the example being collected ends.
+enum End {
+    /// At the next heading of the same or a higher level.
+    Heading(usize),
+    /// At the end of the examples section it is part of.
+    Section,
+    /// At the fence closing its div, below this many nested divs.
+    Div(usize),
+}
+
+struct Current<'a> {
+    title: Option<String>,
+    language: Option<&'a str>,
+    lines: Vec<&'a str>,
+    end: End,
+}
+
+impl Current<'_> {
+    fn finish(self, examples: &mut Vec<ExampleDoc>) {
+        let body = self.lines.join("\n").trim_matches('\n').to_string();
+        if self.title.is_some() || !body.trim().is_empty() {
+            examples.push(ExampleDoc {
+                title: self.title,
+                body,
+                language: self.language.map(str::to_string),
+            });
+        }
+    }
+}
+
+/// Split the examples off a doc comment, returning the remaining text and
+/// the examples in order. Text outside of examples in an examples section
+/// forms an untitled example.
+pub fn split_examples(doc: &str) -> (String, Vec<ExampleDoc>) {
+    let mut text: Vec<&str> = vec![];
+    let mut examples = vec![];
+    // Level of the `# Examples` heading of the section we are in, and the
+    // language it names.
+    let mut section: Option<usize> = None;
+    let mut section_language: Option<&str> = None;
+    let mut current: Option<Current> = None;
+
+    for (raw, line) in doc.lines().zip(div_lines(doc)) {
+        if let Some(Current {
+            end: End::Div(depth),
+            title,
+            lines,
+            ..
+        }) = &mut current
+        {
+            match line {
+                DivLine::Open(_) => {
+                    *depth += 1;
+                    lines.push(raw);
+                }
+                DivLine::Close if *depth == 0 => {
+                    if let Some(example) = current.take() {
+                        example.finish(&mut examples);
+                    }
+                }
+                DivLine::Close => {
+                    *depth -= 1;
+                    lines.push(raw);
+                }
+                DivLine::Text(_, false)
+                    if title.is_none() && lines.iter().all(|l| l.trim().is_empty()) =>
+                {
+                    match heading(raw) {
+                        Some((_, heading_title)) => *title = Some(heading_title.to_string()),
+                        None => lines.push(raw),
+                    }
+                }
+                DivLine::Text(..) => lines.push(raw),
+            }
+            continue;
+        }
+
+        match line {
+            DivLine::Open(attrs) if is_example_div(attrs) => {
+                if let Some(example) = current.take() {
+                    example.finish(&mut examples);
+                }
+                current = Some(Current {
+                    title: None,
+                    language: section_language,
+                    lines: vec![],
+                    end: End::Div(0),
+                });
+            }
+            DivLine::Text(_, false) if heading(raw).is_some() => {
+                let (level, title) = heading(raw).unwrap_or_default();
+                let ends_section = section.is_some_and(|l| level <= l);
+                let ends_current = match &current {
+                    Some(Current {
+                        end: End::Heading(l),
+                        ..
+                    }) => level <= *l,
+                    Some(Current {
+                        end: End::Section, ..
+                    }) => ends_section,
+                    _ => false,
+                };
+                if ends_current {
+                    if let Some(example) = current.take() {
+                        example.finish(&mut examples);
+                    }
+                }
+                if ends_section {
+                    section = None;
+                    section_language = None;
+                }
+
+                match example_heading(title) {
+                    Some((Some(name), language)) => {
+                        if let Some(example) = current.take() {
+                            example.finish(&mut examples);
+                        }
+                        current = Some(Current {
+                            title: Some(name.to_string()),
+                            language: language.or(section_language),
+                            lines: vec![],
+                            end: End::Heading(level),
+                        });
+                    }
+                    Some((None, language)) => {
+                        if let Some(example) = current.take() {
+                            example.finish(&mut examples);
+                        }
+                        section = Some(level);
+                        section_language = language;
+                    }
+                    None => push(raw, &mut current, section, section_language, &mut text),
+                }
+            }
+            _ => push(raw, &mut current, section, section_language, &mut text),
+        }
+    }
+    if let Some(example) = current {
+        example.finish(&mut examples);
+    }
+
+    if examples.is_empty() {
+        return (doc.to_string(), examples);
+    }
+    (collapse_blank_lines(&text.join("\n")), examples)
+}
+
+/// Add a line to the example being collected, or start an untitled example
+/// with it in an examples section, or keep it in the description.
+fn push<'a>(
+    raw: &'a str,
+    current: &mut Option<Current<'a>>,
+    section: Option<usize>,
+    section_language: Option<&'a str>,
+    text: &mut Vec<&'a str>,
+) {
+    match current {
+        Some(example) => example.lines.push(raw),
+        None if section.is_some() => {
+            if !raw.trim().is_empty() {
+                *current = Some(Current {
+                    title: None,
+                    language: section_language,
+                    lines: vec![raw],
+                    end: End::Section,
+                });
+            }
+        }
+        None => text.push(raw),
+    }
+}
+
+/// The level and title of an ATX heading line.
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let trimmed = line.trim_start();
+    let level = trimmed.chars().take_while(|&c| c == '#').count();
+    (level > 0 && trimmed[level..].starts_with(' ')).then(|| (level, trimmed[level..].trim()))
+}
+
+/// The name and language of an example heading like `Example: basic usage`
+/// or `Example (bash): building`, or of an examples section heading like
+/// `Examples` or `Example (json)`, which has no name.
+fn example_heading(title: &str) -> Option<(Option<&str>, Option<&str>)> {
+    let (label, name) = match title.split_once(':') {
+        Some((label, name)) => (label.trim(), Some(name.trim()).filter(|n| !n.is_empty())),
+        None => (title, None),
+    };
+    let (label, language) = match label.strip_suffix(')').and_then(|l| l.split_once('(')) {
+        Some((label, language)) => (
+            label.trim(),
+            Some(language.trim()).filter(|l| !l.is_empty()),
+        ),
+        None => (label, None),
+    };
+    let section = name.is_none() && label.eq_ignore_ascii_case("examples");
+    (label.eq_ignore_ascii_case("example") || section).then_some((name, language))
+}
+
+/// Give the code blocks of an example without an info string its language,
+/// or `default` if its heading names none, and record the language it got.
+pub fn apply_language(example: &mut ExampleDoc, default: Option<&str>) {
+    let Some(language) = example.language.as_deref().or(default) else {
+        return;
+    };
+    let mut body = String::new();
+    let mut fence: Option<(usize, char)> = None;
+    for line in example.body.split_inclusive('\n') {
+        let trimmed = line.trim();
+        match fence {
+            Some((count, ch)) => {
+                if get_fence(trimmed, false).is_some_and(|(c, h)| h == ch && c >= count) {
+                    fence = None;
+                }
+            }
+            None => {
+                if let Some((count, ch)) = get_fence(trimmed, true).filter(|(c, _)| *c >= 3) {
+                    fence = Some((count, ch));
+                    if trimmed.chars().count() == count {
+                        body.push_str(line.trim_end_matches(['\n', '\r']));
+                        body.push_str(language);
+                        body.push_str(&line[line.trim_end_matches(['\n', '\r']).len()..]);
+                        continue;
+                    }
+                }
+            }
+        }
+        body.push_str(line);
+    }
+    example.body = body;
+    example.language = Some(language.to_string());
+}
+
+/// Whether the attributes of a fenced div (`{.example #id}`) include the
+/// `example` class.
+fn is_example_div(attrs: &str) -> bool {
+    attrs
+        .trim_start_matches('{')
+        .trim_end_matches('}')
+        .split_whitespace()
+        .any(|attr| attr == ".example")
+}
@@ -0,0 +1,147 @@
+//! Raw data of documented Nix files, for custom renderers.
+//!
+//! Unlike the JSON output of the default mode, an extraction holds the doc
+//! comments as written: their headings are not shifted, and `# Type`,
+//! `# Examples` or tag sections are not split off. Each entry is identified
+//! by its attribute path and located by its span in the file.
+
+use serde::Serialize;
+
+use crate::commonmark::{Argument, Constant, ManualEntry, Span};
+use crate::{collect_entries, extract_file_doc, CollectOptions};
+
+/// Version of the extraction format, increased on incompatible changes.
+pub const VERSION: u32 = 1;
+
+/// A documented binding.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtractedEntry {
+    /// Full attribute path, e.g. `["lib", "strings", "concat"]`.
+    pub attr_path: Vec<String>,
+    /// The doc comment as written, without its delimiters and indentation.
+    pub doc: String,
+    /// Arguments of the function, if the binding is one.
+    pub args: Vec<Argument>,
+    /// The value, if the binding is not a function.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub constant: Option<Constant>,
+    /// Position of the binding, in the file the doc comment was taken from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<Span>,
+}
+
+/// The documented bindings of a file.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtractedFile {
+    pub file: String,
+    pub category: String,
+    /// The doc comment at the top of the file, as written.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
+    pub entries: Vec<ExtractedEntry>,
+}
+
+/// The documented files of an extraction.
+#[derive(Debug, Clone, Serialize)]
+pub struct Extraction {
+    pub version: u32,
+    pub files: Vec<ExtractedFile>,
+}
+
+impl Extraction {
+    pub fn new(files: Vec<ExtractedFile>) -> Self {
+        Extraction {
+            version: VERSION,
+            files,
+        }
+    }
+}
+
+/// How an extraction is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExtractFormat {
+    #[default]
+    Json,
+    Cbor,
+}
+
+impl ExtractFormat {
+    /// Look up a format by its name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(Self::Json),
+            "cbor" => Some(Self::Cbor),
+            _ => None,
+        }
+    }
+
+    /// Encode `extraction` in the format.
+    pub fn encode(&self, extraction: &Extraction) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Json => serde_json::to_vec(extraction).map_err(|e| e.to_string()),
+            Self::Cbor => {
+                let mut bytes = vec![];
+                ciborium::into_writer(extraction, &mut bytes).map_err(|e| e.to_string())?;
+                Ok(bytes)
+            }
+        }
+    }
+}
+
+/// Split a dotted entry name into its attributes, keeping dots of quoted
+/// attributes, e.g. `"a.b".c` into `"a.b"` and `c`.
+fn split_name(name: &str) -> Vec<String> {
+    let mut attrs = vec![];
+    let mut attr = String::new();
+    let (mut quoted, mut escaped) = (false, false);
+    for c in name.chars() {
+        match c {
+            '.' if !quoted => attrs.push(std::mem::take(&mut attr)),
+            _ => {
+                if c == '"' && !escaped {
+                    quoted = !quoted;
+                }
+                escaped = quoted && !escaped && c == '\\';
+                attr.push(c);
+            }
+        }
+    }
+    attrs.push(attr);
+    attrs
+}
+
+/// The raw data of a collected entry.
+pub fn extract_entry(entry: &ManualEntry) -> ExtractedEntry {
+    let attr_path = [&entry.prefix, &entry.category]
+        .into_iter()
+        .filter(|attr| !attr.is_empty())
+        .cloned()
+        .chain(split_name(&entry.name))
+        .collect();
+    ExtractedEntry {
+        attr_path,
+        doc: entry.raw_doc.clone().unwrap_or_default(),
+        args: entry.args.clone(),
+        constant: entry.constant.clone(),
+        span: entry.span,
+    }
+}
+
+/// Extract the documented bindings of the Nix `source` of `file`.
+pub fn extract_source(
+    file: &str,
+    source: &str,
+    opts: &CollectOptions,
+) -> Result<ExtractedFile, String> {
+    let nix = rnix::Root::parse(source)
+        .ok()
+        .map_err(|e| format!("failed to parse input: {}", e))?;
+    let doc = extract_file_doc(&nix);
+    let entries = collect_entries(nix, opts);
+    Ok(ExtractedFile {
+        file: file.to_string(),
+        category: opts.category.clone(),
+        doc,
+        entries: entries.iter().map(extract_entry).collect(),
+    })
+}
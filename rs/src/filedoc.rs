@@ -0,0 +1,205 @@
+//! Structured representation of file-level documentation comments.
+//!
+//! The `file-doc` command emits the raw comment, but index pages usually only
+//! want a title and a one-paragraph summary. [FileDoc::from_doc] splits the
+//! comment into these parts so downstream consumers don't have to,
+//! [collect_file_docs] collects the comments of a whole tree of files and
+//! [collect_module_index] splits them for an index of its modules.
+
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::commonmark::table_cell;
+use crate::format::get_fence;
+use crate::{extract_file_doc, walk};
+
+/// A file-level doc comment split into its leading heading, first paragraph
+/// and the remaining markdown.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct FileDoc {
+    /// Text of the heading the comment opens with (without the `#` markers).
+    pub title: Option<String>,
+    /// First paragraph of prose.
+    pub summary: Option<String>,
+    /// Everything after the summary paragraph.
+    pub body: Option<String>,
+}
+
+impl FileDoc {
+    /// Split a (dedented) doc comment into title, summary and body.
+    ///
+    /// The title is only taken from a heading that opens the comment, so a
+    /// comment starting with prose has no title. Code blocks are never used as
+    /// the summary.
+    pub fn from_doc(doc: &str) -> Self {
+        let mut blocks = split_blocks(doc).into_iter().peekable();
+        let mut file_doc = FileDoc::default();
+
+        if let Some(heading) = blocks.peek().and_then(|b| heading_text(b)) {
+            file_doc.title = Some(heading);
+            blocks.next();
+        }
+
+        if blocks
+            .peek()
+            .is_some_and(|b| heading_text(b).is_none() && get_fence(b.trim_start(), true).is_none())
+        {
+            file_doc.summary = blocks.next();
+        }
+
+        let rest: Vec<String> = blocks.collect();
+        if !rest.is_empty() {
+            file_doc.body = Some(rest.join("\n\n"));
+        }
+
+        file_doc
+    }
+}
+
+/// The file-level doc comment of a file in a tree of files.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TreeFileDoc {
+    /// Path of the file relative to the root of the tree.
+    pub path: String,
+    pub doc: Option<String>,
+}
+
+/// Collect the file-level doc comments of all `.nix` files below `root`,
+/// sorted by path. Files that fail to parse have no doc comment.
+pub fn collect_file_docs(root: &Path) -> Result<Vec<TreeFileDoc>, String> {
+    let files = walk::files_with_extension(root, "nix")
+        .map_err(|e| format!("Failed to read {}: {}", root.display(), e))?;
+    let mut docs = vec![];
+    for file in files {
+        let src = fs::read_to_string(&file)
+            .map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+        let doc = rnix::Root::parse(&src)
+            .ok()
+            .ok()
+            .and_then(|nix| extract_file_doc(&nix));
+        let path = file
+            .strip_prefix(root)
+            .unwrap_or(&file)
+            .to_string_lossy()
+            .into_owned();
+        docs.push(TreeFileDoc { path, doc });
+    }
+    Ok(docs)
+}
+
+/// A module of a module tree, as listed in a module index.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ModuleIndexEntry {
+    /// Path of the module relative to the root of the tree.
+    pub path: String,
+    pub title: Option<String>,
+    pub summary: Option<String>,
+    /// Link to the module source.
+    pub link: String,
+}
+
+/// Collect the file-level doc comments of all `.nix` files below `root`,
+/// sorted by path. Links point to `base_url` followed by the relative path,
+/// or to the relative path itself.
+pub fn collect_module_index(
+    root: &Path,
+    base_url: Option<&str>,
+) -> Result<Vec<ModuleIndexEntry>, String> {
+    let entries = collect_file_docs(root)?
+        .into_iter()
+        .map(|file| {
+            let doc = file
+                .doc
+                .as_deref()
+                .map(FileDoc::from_doc)
+                .unwrap_or_default();
+            let link = match base_url {
+                Some(base) => format!("{}/{}", base.trim_end_matches('/'), file.path),
+                None => file.path.clone(),
+            };
+            ModuleIndexEntry {
+                path: file.path,
+                title: doc.title,
+                summary: doc.summary,
+                link,
+            }
+        })
+        .collect();
+    Ok(entries)
+}
+
+/// Render a module index as a table of modules with their title, linking to
+/// the source, and summary.
+pub fn render_module_index(title: &str, entries: &[ModuleIndexEntry]) -> String {
+    let mut out = format!(
+        "# {}\n\n| Module | Summary |\n| ------ | ------- |\n",
+        title
+    );
+    for entry in entries {
+        let module = match &entry.title {
+            Some(title) => format!("[{}]({}) (`{}`)", table_cell(title), entry.link, entry.path),
+            None => format!("[`{}`]({})", entry.path, entry.link),
+        };
+        let summary = entry.summary.as_deref().map(table_cell).unwrap_or_default();
+        out.push_str(&format!("| {} | {} |\n", module, summary));
+    }
+    out
+}
+
+/// Returns the text of an ATX heading if the block consists of a single one.
+fn heading_text(block: &str) -> Option<String> {
+    if block.contains('\n') {
+        return None;
+    }
+    let line = block.trim_start();
+    let text = line.trim_start_matches('#');
+    let level = line.len() - text.len();
+    if (1..=6).contains(&level) && (text.is_empty() || text.starts_with(' ')) {
+        Some(text.trim().trim_end_matches('#').trim_end().to_string())
+    } else {
+        None
+    }
+}
+
+/// Split markdown into blank-line separated blocks, keeping fenced code blocks
+/// (which may contain blank lines) intact. Headings always form their own block.
+fn split_blocks(doc: &str) -> Vec<String> {
+    let mut blocks = vec![];
+    let mut current: Vec<&str> = vec![];
+    let mut fence: Option<(usize, char)> = None;
+
+    let mut flush = |current: &mut Vec<&str>| {
+        if !current.is_empty() {
+            blocks.push(current.join("\n"));
+            current.clear();
+        }
+    };
+
+    for line in doc.lines() {
+        let trimmed = line.trim_start();
+        if let Some((start_count, start_char)) = fence {
+            current.push(line);
+            if let Some((count, ch)) = get_fence(trimmed, false) {
+                if ch == start_char && count >= start_count {
+                    fence = None;
+                }
+            }
+        } else if trimmed.is_empty() {
+            flush(&mut current);
+        } else if trimmed.starts_with('#') && heading_text(trimmed).is_some() {
+            flush(&mut current);
+            current.push(line);
+            flush(&mut current);
+        } else {
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                fence = get_fence(trimmed, true);
+            }
+            current.push(line);
+        }
+    }
+    flush(&mut current);
+
+    blocks
+}
@@ -1,3 +1,4 @@
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
 use textwrap::dedent;
 
 /// Ensure all lines in a multi-line doc-comments have the same indentation.
@@ -16,7 +17,7 @@ use textwrap::dedent;
 ///
 /// The parser turns this into:
 ///
-/// ```
+/// ```text
 /// foo is
 ///   the value:
 ///     10
@@ -28,7 +29,7 @@ use textwrap::dedent;
 ///
 /// What we want instead is:
 ///
-/// ```
+/// ```text
 /// foo is
 /// the value:
 ///   10
@@ -49,12 +50,19 @@ pub fn handle_indentation(raw: &str) -> Option<String> {
 
 /// Shift down markdown headings
 ///
-/// Performs a line-wise matching to '# Heading '
+/// Adds `levels` to the level of every ATX heading (`# Heading`), as found by
+/// a CommonMark parser, so lines starting with `#` in code blocks, HTML
+/// blocks or within paragraphs are left alone:
 ///
-/// Counts the current numbers of '#' and adds levels: [usize] to them
 /// levels := 1; gives
 /// '# Heading' -> '## Heading'
 ///
+/// Setext headings (underlined with `===` or `---`) only have two levels, so
+/// they are turned into ATX headings of the shifted level:
+///
+/// levels := 1; gives
+/// 'Heading\n===' -> '## Heading'
+///
 /// Commonmark markdown has 6 levels of headings. Everything beyond that (e.g., H7) is not supported and may produce unexpected renderings.
 /// by default this function makes sure, headings don't exceed the H6 boundary.
 /// levels := 2;
@@ -63,63 +71,269 @@ pub fn handle_indentation(raw: &str) -> Option<String> {
 /// H6 -> H6
 ///
 pub fn shift_headings(raw: &str, levels: usize) -> String {
-    let mut result = String::new();
-
-    let mut curr_fence: Option<(usize, char)> = None;
-    for raw_line in raw.split_inclusive('\n') {
-        // Code blocks can only start with backticks or tildes
-        // code fences can be indented by 0-3 spaces see commonmark spec.
-        let fence_line = &trim_leading_whitespace(raw_line, 3);
-        if fence_line.starts_with("```") | fence_line.starts_with("~~~") {
-            let fence_info = get_fence(fence_line, true);
-            if curr_fence.is_none() {
-                // Start of code block
-                curr_fence = fence_info;
+    remap_headings(raw, |level| level.saturating_add(levels).min(6))
+}
+
+/// Change the level of every heading to the level `f` maps it to, turning
+/// setext headings changing level into ATX headings. Like
+/// [`shift_headings`], headings are found by a CommonMark parser.
+pub fn remap_headings(raw: &str, f: impl Fn(usize) -> usize) -> String {
+    let mut replacements = vec![];
+    for (event, range) in Parser::new_ext(raw, markdown_options()).into_offset_iter() {
+        let Event::Start(Tag::Heading { level, .. }) = event else {
+            continue;
+        };
+        let level = level as usize;
+        let mapped = f(level).clamp(1, 6);
+        if mapped == level {
+            continue;
+        }
+        if let Some(title) = setext_title(&raw[range.clone()]) {
+            let end = range.start + raw[range.clone()].trim_end_matches(['\n', '\r']).len();
+            replacements.push((
+                range.start..end,
+                format!("{} {}", "#".repeat(mapped), title),
+            ));
+        } else if let Some(offset) = atx_marker(raw, range.start) {
+            replacements.push((offset..offset + level, "#".repeat(mapped)));
+        }
+    }
+
+    let mut result = raw.to_string();
+    for (range, replacement) in replacements.into_iter().rev() {
+        result.replace_range(range, &replacement);
+    }
+    result
+}
+
+/// The title of a setext heading, with its lines joined and block quote
+/// markers of lines after the first removed. Nothing for ATX headings.
+fn setext_title(heading: &str) -> Option<String> {
+    let mut lines: Vec<&str> = heading.trim_end_matches(['\n', '\r']).lines().collect();
+    let underline = lines.pop()?.trim_start_matches([' ', '>']).trim();
+    if lines.is_empty()
+        || !(underline.chars().all(|c| c == '=') || underline.chars().all(|c| c == '-'))
+    {
+        return None;
+    }
+    let title = lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            if i == 0 {
+                line
             } else {
-                // Possible end of code block. Ending fences cannot have info strings
-                match (curr_fence, get_fence(fence_line, false)) {
-                    // End of code block must have the same fence type as the start (~~~ or ```)
-                    // Code blocks must be ended with at least the same number of backticks or tildes as the start fence
-                    (Some((start_count, start_char)), Some((end_count, end_char))) => {
-                        if start_count <= end_count && start_char == end_char {
-                            // End of code block (same fence as start)
-                            curr_fence = None;
-                        }
-                    }
-                    _ => {}
+                line.trim_start_matches([' ', '>'])
+            }
+            .trim()
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    // A trailing `#` would be taken as the closing sequence of an ATX
+    // heading, unless it is followed by one.
+    Some(if title.ends_with('#') {
+        title + " #"
+    } else {
+        title
+    })
+}
+
+/// Levels of the headings of a rendered document, so it can be embedded at
+/// any depth of a larger manual.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeadingLevels {
+    /// Level of the title of the document, e.g. of a function category.
+    pub category: usize,
+    /// Level of the headings of entries, i.e. of functions or options. The
+    /// headings of their descriptions are shifted along.
+    pub entry: usize,
+}
+
+impl Default for HeadingLevels {
+    fn default() -> Self {
+        Self {
+            category: 1,
+            entry: 2,
+        }
+    }
+}
+
+impl HeadingLevels {
+    /// Heading levels, if entries are below the category and within the
+    /// six levels of markdown.
+    pub fn new(category: usize, entry: usize) -> Result<Self, String> {
+        if category < 1 || entry <= category || entry > 6 {
+            return Err(format!(
+                "heading levels must satisfy 1 <= category < entry <= 6, got {} and {}",
+                category, entry
+            ));
+        }
+        Ok(Self { category, entry })
+    }
+
+    /// Move the headings of a document rendered with the default levels
+    /// (the title at level 1, entries at level 2) to these levels.
+    pub fn apply(&self, markdown: &str) -> String {
+        if *self == Self::default() {
+            return markdown.to_string();
+        }
+        remap_headings(markdown, |level| match level {
+            1 => self.category,
+            level => level + self.entry - 2,
+        })
+    }
+}
+
+/// The extensions of the markdown nixdoc reads and writes.
+pub(crate) fn markdown_options() -> Options {
+    Options::ENABLE_TABLES | Options::ENABLE_HEADING_ATTRIBUTES | Options::ENABLE_DEFINITION_LIST
+}
+
+/// The summary of a description: the first sentence of its first paragraph,
+/// or the whole paragraph if it has no sentence end. Line breaks are turned
+/// into spaces, so the summary fits into a table cell or list item. Fenced
+/// divs and anchors are not taken as the first paragraph.
+pub fn summary(markdown: &str) -> Option<String> {
+    let flatten = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut depth = 0;
+    let mut paragraph: Option<usize> = None;
+    for (event, range) in Parser::new_ext(markdown, markdown_options()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Paragraph) if depth == 0 => {
+                let text = &markdown[range.clone()];
+                let first_line = text.lines().next().unwrap_or_default();
+                if !first_line.starts_with(":::") && inline_anchor(first_line).is_none() {
+                    paragraph = Some(range.start);
+                }
+                depth += 1;
+            }
+            Event::Start(_) => depth += 1,
+            Event::Text(_) => {
+                let Some(start) = paragraph else {
+                    continue;
                 };
+                if let Some(end) = sentence_end(markdown, range) {
+                    return Some(flatten(&markdown[start..end]));
+                }
+            }
+            Event::End(TagEnd::Paragraph) if depth == 1 && paragraph.is_some() => {
+                return paragraph.map(|start| flatten(&markdown[start..range.end]));
             }
+            Event::End(_) => depth -= 1,
+            _ => (),
+        }
+    }
+    None
+}
+
+/// The offset after the first sentence end (`.`, `!` or `?` followed by
+/// whitespace) in the text at `range`, skipping abbreviations like `e.g.`.
+fn sentence_end(markdown: &str, range: std::ops::Range<usize>) -> Option<usize> {
+    let text = &markdown[range.clone()];
+    text.char_indices().find_map(|(i, c)| {
+        if !matches!(c, '.' | '!' | '?') {
+            return None;
         }
+        let end = range.start + i + 1;
+        let word = text[..i].rsplit([' ', '(']).next().unwrap_or_default();
+        let abbreviation = c == '.' && matches!(word, "e.g" | "i.e" | "cf" | "vs");
+        (markdown[end..]
+            .chars()
+            .next()
+            .is_none_or(char::is_whitespace)
+            && !abbreviation)
+            .then_some(end)
+    })
+}
+
+/// Offset of the `#`s of the ATX heading starting at `start`, or nothing for
+/// setext headings. The heading's range may begin with indentation or
+/// container markers (`>`).
+fn atx_marker(raw: &str, start: usize) -> Option<usize> {
+    let line = raw[start..].lines().next().unwrap_or_default();
+    let hashes = line.find('#')?;
+    line[..hashes]
+        .chars()
+        .all(|c| c == ' ' || c == '>')
+        .then_some(start + hashes)
+}
 
-        // Remove up to 0-3 leading whitespaces.
-        // If the line has 4 or more whitespaces it is not a heading according to commonmark spec.
-        let heading_line = &trim_leading_whitespace(raw_line, 3);
-        if curr_fence.is_none() && heading_line.starts_with('#') {
-            let heading = handle_heading(heading_line, levels);
-            result.push_str(&heading);
-        } else {
-            result.push_str(raw_line);
+/// Levels and texts of the headings in `markdown`.
+pub(crate) fn headings(markdown: &str) -> Vec<(usize, String)> {
+    let mut headings = vec![];
+    let mut current: Option<(usize, String)> = None;
+    for event in Parser::new_ext(markdown, markdown_options()) {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                current = Some((level as usize, String::new()))
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, title)) = &mut current {
+                    title.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::Heading(_)) => headings.extend(current.take()),
+            _ => (),
         }
     }
-    result
+    headings
 }
 
-/// Removes leading whitespaces from code fences if present
-/// However maximum of [max] whitespaces are removed.
-/// This is useful for code fences may have leading whitespaces (0-3).
-fn trim_leading_whitespace(input: &str, max: usize) -> String {
-    let mut count = 0;
-    input
-        .trim_start_matches(|c: char| {
-            if c.is_whitespace() && count < max {
-                count += 1;
-                true
-            } else {
-                false
+/// Split the first heading off `markdown`, returning its text and the
+/// markdown without it.
+pub fn split_title(markdown: &str) -> Option<(String, String)> {
+    let mut title: Option<(String, std::ops::Range<usize>)> = None;
+    for (event, range) in Parser::new_ext(markdown, markdown_options()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading { .. }) => title = Some((String::new(), range)),
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((title, _)) = &mut title {
+                    title.push_str(&text);
+                }
             }
-        })
-        .to_string()
+            Event::End(TagEnd::Heading(_)) => break,
+            _ => (),
+        }
+    }
+    let (title, range) = title?;
+    let rest = format!("{}{}", &markdown[..range.start], &markdown[range.end..]);
+    Some((title.trim().to_string(), rest.trim().to_string()))
+}
+
+/// Wrap text in a code span. The fence is made longer than any run of
+/// backticks in the text and padded with spaces where the text starts or ends
+/// with a backtick, so names like ``foo`bar`` stay one span. Line breaks are
+/// turned into spaces, so the span can be used in headings.
+pub(crate) fn code_span(text: &str) -> String {
+    let text = text.replace(['\r', '\n'], " ");
+    let longest_run = text
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let fence = "`".repeat(longest_run + 1);
+    let pad = if text.starts_with('`') || text.ends_with('`') {
+        " "
+    } else {
+        ""
+    };
+    format!("{fence}{pad}{text}{pad}{fence}")
+}
+
+/// Wrap code in a fenced code block. The fence is made longer than any
+/// backtick fence within the code.
+pub(crate) fn code_block(info: &str, code: &str) -> String {
+    let longest_fence = code
+        .lines()
+        .filter_map(|line| get_fence(line.trim_start(), true))
+        .filter(|(_, c)| *c == '`')
+        .map(|(count, _)| count)
+        .max()
+        .unwrap_or_default();
+    let fence = "`".repeat(longest_fence.max(2) + 1);
+    format!("{fence}{info}\n{code}\n{fence}")
 }
+
 /// A function that returns the count of a code fence line.
 /// Param [allow_info] allows to keep info strings in code fences.
 /// Ending fences cannot have info strings
@@ -145,26 +359,140 @@ pub fn get_fence(line: &str, allow_info: bool) -> Option<(usize, char)> {
     }
     None
 }
-// Dumb heading parser.
-pub fn handle_heading(line: &str, levels: usize) -> String {
-    let chars = line.chars();
-
-    let mut hashes = String::new();
-    let mut rest = String::new();
-    for char in chars {
-        match char {
-            '#' if rest.is_empty() => {
-                // only collect hashes if no other tokens
-                hashes.push(char)
+/// Returns the 1-based line and column (in characters) of a byte offset.
+pub fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    // Offsets past the end or inside a character refer to the character
+    // containing them.
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rfind('\n')
+        .map_or(before, |nl| &before[nl + 1..])
+        .chars()
+        .count()
+        + 1;
+    (line, column)
+}
+
+/// A line of markdown, with the fences of nixpkgs-style fenced divs
+/// (`::: {.example}` ... `:::`) recognized outside of code blocks.
+pub(crate) enum DivLine<'a> {
+    /// The opening fence of a div, with the rest of the line (e.g. `{.example}`).
+    Open(&'a str),
+    /// The closing fence of a div.
+    Close,
+    /// Any other line, and whether it is part of a code block.
+    Text(&'a str, bool),
+}
+
+/// Split markdown into lines, recognizing fenced div fences.
+pub(crate) fn div_lines(markdown: &str) -> Vec<DivLine<'_>> {
+    let mut fence: Option<(usize, char)> = None;
+    markdown
+        .lines()
+        .map(|line| {
+            let trimmed = line.trim();
+            if let Some((count, ch)) = fence {
+                if get_fence(trimmed, false).is_some_and(|(c, h)| h == ch && c >= count) {
+                    fence = None;
+                }
+                return DivLine::Text(line, true);
+            }
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                fence = get_fence(trimmed, true);
+                return DivLine::Text(line, true);
+            }
+            match trimmed.strip_prefix(":::").map(str::trim) {
+                Some("") => DivLine::Close,
+                Some(attrs) => DivLine::Open(attrs),
+                None => DivLine::Text(line, false),
+            }
+        })
+        .collect()
+}
+
+/// Replace fenced divs by `<div>`s and `[]{#id}` lines by `<a id>`s, so they
+/// are picked up as HTML blocks by converters to other formats. The class of
+/// a `<div>` is what `block` maps the first of the div's classes to that it
+/// knows; divs without such a class are unwrapped.
+pub(crate) fn divs_to_html(markdown: &str, block: impl Fn(&str) -> Option<String>) -> String {
+    let mut out = String::new();
+    let mut open: Vec<bool> = vec![];
+    for line in div_lines(markdown) {
+        match line {
+            DivLine::Open(attrs) => {
+                let attrs = attrs.trim_start_matches('{').trim_end_matches('}');
+                let class = attrs
+                    .split_whitespace()
+                    .filter_map(|attr| attr.strip_prefix('.'))
+                    .find_map(&block);
+                if let Some(class) = &class {
+                    out.push_str(&format!("\n<div class=\"{}\">\n\n", class));
+                }
+                open.push(class.is_some());
+            }
+            DivLine::Close => {
+                if open.pop().unwrap_or_default() {
+                    out.push_str("\n</div>\n\n");
+                }
+            }
+            DivLine::Text(line, false) if inline_anchor(line).is_some() => {
+                let id = inline_anchor(line).unwrap_or_default();
+                out.push_str(&format!("\n<a id=\"{}\"></a>\n\n", id));
+            }
+            DivLine::Text(line, _) => {
+                out.push_str(line);
+                out.push('\n');
             }
-            _ => rest.push(char),
         }
     }
-    let new_hashes = match hashes.len() + levels {
-        // We reached the maximum heading size.
-        6.. => "#".repeat(6),
-        _ => "#".repeat(hashes.len() + levels),
-    };
+    out
+}
+
+/// Remove a role (``{option}`foo` ``) from the end of `out`, where it
+/// precedes inline code, and return its name.
+pub(crate) fn strip_role(out: &mut String) -> Option<String> {
+    let inner = out.strip_suffix('}')?;
+    let start = inner.rfind('{')?;
+    let role = inner[start + 1..].to_string();
+    if role.is_empty() || !role.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return None;
+    }
+    out.truncate(start);
+    Some(role)
+}
+
+/// Returns the id of a line consisting only of an inline anchor (`[]{#id}`).
+pub(crate) fn inline_anchor(line: &str) -> Option<&str> {
+    line.trim()
+        .strip_prefix("[]{#")?
+        .strip_suffix('}')
+        .filter(|id| !id.is_empty() && !id.contains(char::is_whitespace))
+}
+
+/// 64-bit FNV-1a hasher. Unlike `DefaultHasher`, its output is stable across
+/// releases, so it can be used for anchors and cache keys.
+pub(crate) struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl std::hash::Hasher for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
 
-    format!("{new_hashes}{rest}")
+    fn finish(&self) -> u64 {
+        self.0
+    }
 }
@@ -0,0 +1,53 @@
+//! Minimal glob matching for entry and option names.
+//!
+//! Supports `*` (any sequence of characters, including none) and `?` (any
+//! single character). There are no character classes and no special handling
+//! of separators, since names like `services.nginx.*` are matched as plain
+//! strings.
+
+/// Returns whether `text` matches the glob `pattern` in its entirety.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` in the pattern and the text position it was tried at.
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some('?') => {
+                p += 1;
+                t += 1;
+            }
+            Some(c) if *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` swallow one more character and retry.
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    backtrack = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Returns whether `name` passes a set of include and exclude globs.
+///
+/// A name is kept if it matches any include pattern (or no include patterns are
+/// given) and matches none of the exclude patterns.
+pub fn filter_name(name: &str, include: &[String], exclude: &[String]) -> bool {
+    (include.is_empty() || include.iter().any(|p| glob_match(p, name)))
+        && !exclude.iter().any(|p| glob_match(p, name))
+}
@@ -0,0 +1,218 @@
+//! Golden corpus runner for the extraction and rendering pipeline.
+//!
+//! A corpus is a directory of `.nix` fixtures, each accompanied by a file with
+//! the expected output of a renderer, e.g. `strings.nix` and `strings.md`. The
+//! renderer decides which extension its expected outputs use, so a single
+//! corpus can hold expectations for several output flavors side by side.
+//!
+//! ```no_run
+//! use nixdoc::harness::{MarkdownRenderer, run_corpus};
+//!
+//! let report = run_corpus("test/corpus".as_ref(), &MarkdownRenderer::default()).unwrap();
+//! assert!(report.is_success(), "{}", report.summary());
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::batch::{render_source, BatchOptions};
+
+/// A single input file of a corpus.
+#[derive(Debug, Clone)]
+pub struct Fixture {
+    /// File stem of the fixture, used as the category when rendering.
+    pub name: String,
+    /// Path to the `.nix` source.
+    pub source: PathBuf,
+}
+
+impl Fixture {
+    /// Path of the expected output for a renderer using `extension`.
+    pub fn expected_path(&self, extension: &str) -> PathBuf {
+        self.source.with_extension(extension)
+    }
+}
+
+/// Turns a fixture into output that is compared against the expected file.
+pub trait Renderer {
+    /// Extension of the expected output files (without the leading dot).
+    fn extension(&self) -> &str;
+
+    /// Render the fixture source.
+    fn render(&self, fixture: &Fixture, source: &str) -> Result<String, String>;
+}
+
+/// Renders fixtures to CommonMark, using the fixture name as category.
+#[derive(Debug, Clone)]
+pub struct MarkdownRenderer {
+    pub prefix: String,
+    pub anchor_prefix: String,
+}
+
+impl Default for MarkdownRenderer {
+    fn default() -> Self {
+        Self {
+            prefix: "lib".to_string(),
+            anchor_prefix: "function-library-".to_string(),
+        }
+    }
+}
+
+impl Renderer for MarkdownRenderer {
+    fn extension(&self) -> &str {
+        "md"
+    }
+
+    fn render(&self, fixture: &Fixture, source: &str) -> Result<String, String> {
+        let opts = BatchOptions {
+            prefix: self.prefix.clone(),
+            anchor_prefix: self.anchor_prefix.clone(),
+            ..Default::default()
+        };
+        render_source(source, &fixture.name, &opts)
+            .map_err(|e| format!("{}: {}", fixture.source.display(), e))
+    }
+}
+
+/// Outcome of running a single fixture.
+#[derive(Debug, Clone)]
+pub enum Outcome {
+    /// The rendered output matched the expected file.
+    Passed,
+    /// The rendered output differs from the expected file.
+    Mismatch { expected: String, actual: String },
+    /// No expected file exists for the renderer.
+    MissingExpected { actual: String },
+    /// The fixture could not be read or rendered.
+    Error(String),
+}
+
+/// Result of running a single fixture.
+#[derive(Debug, Clone)]
+pub struct FixtureResult {
+    pub fixture: Fixture,
+    pub outcome: Outcome,
+}
+
+/// Results of running a whole corpus.
+#[derive(Debug, Clone, Default)]
+pub struct CorpusReport {
+    pub results: Vec<FixtureResult>,
+}
+
+impl CorpusReport {
+    /// Whether every fixture passed.
+    pub fn is_success(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    /// Iterate over the fixtures that did not pass.
+    pub fn failures(&self) -> impl Iterator<Item = &FixtureResult> {
+        self.results
+            .iter()
+            .filter(|r| !matches!(r.outcome, Outcome::Passed))
+    }
+
+    /// Human-readable summary listing every failing fixture.
+    pub fn summary(&self) -> String {
+        let mut out = format!(
+            "{} of {} fixtures passed\n",
+            self.results.len() - self.failures().count(),
+            self.results.len()
+        );
+        for failure in self.failures() {
+            let reason = match &failure.outcome {
+                Outcome::Passed => continue,
+                Outcome::Mismatch { expected, actual } => first_difference(expected, actual),
+                Outcome::MissingExpected { .. } => "expected output is missing".to_string(),
+                Outcome::Error(e) => e.clone(),
+            };
+            out.push_str(&format!(
+                "{}: {}\n",
+                failure.fixture.source.display(),
+                reason
+            ));
+        }
+        out
+    }
+
+    /// Write the actual output of every failing fixture as its new expected
+    /// output.
+    pub fn bless(&self, renderer: &dyn Renderer) -> io::Result<()> {
+        for failure in self.failures() {
+            if let Outcome::Mismatch { actual, .. } | Outcome::MissingExpected { actual } =
+                &failure.outcome
+            {
+                fs::write(failure.fixture.expected_path(renderer.extension()), actual)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Describe the first line in which two outputs differ.
+fn first_difference(expected: &str, actual: &str) -> String {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    let mut line = 1;
+    loop {
+        match (expected_lines.next(), actual_lines.next()) {
+            (Some(e), Some(a)) if e == a => line += 1,
+            (e, a) => {
+                return format!(
+                    "line {}: expected {:?}, got {:?}",
+                    line,
+                    e.unwrap_or("<end of output>"),
+                    a.unwrap_or("<end of output>")
+                );
+            }
+        }
+    }
+}
+
+/// Find all `.nix` fixtures in a directory, sorted by path.
+pub fn discover(dir: &Path) -> io::Result<Vec<Fixture>> {
+    let mut fixtures = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "nix") {
+            let name = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            fixtures.push(Fixture { name, source: path });
+        }
+    }
+    fixtures.sort_by(|a, b| a.source.cmp(&b.source));
+    Ok(fixtures)
+}
+
+/// Run a single fixture through a renderer.
+pub fn run_fixture(fixture: &Fixture, renderer: &dyn Renderer) -> Outcome {
+    let source = match fs::read_to_string(&fixture.source) {
+        Ok(source) => source,
+        Err(e) => return Outcome::Error(e.to_string()),
+    };
+    let actual = match renderer.render(fixture, &source) {
+        Ok(actual) => actual,
+        Err(e) => return Outcome::Error(e),
+    };
+    match fs::read_to_string(fixture.expected_path(renderer.extension())) {
+        Ok(expected) if expected == actual => Outcome::Passed,
+        Ok(expected) => Outcome::Mismatch { expected, actual },
+        Err(_) => Outcome::MissingExpected { actual },
+    }
+}
+
+/// Run every fixture in `dir` through a renderer.
+pub fn run_corpus(dir: &Path, renderer: &dyn Renderer) -> io::Result<CorpusReport> {
+    let results = discover(dir)?
+        .into_iter()
+        .map(|fixture| {
+            let outcome = run_fixture(&fixture, renderer);
+            FixtureResult { fixture, outcome }
+        })
+        .collect();
+    Ok(CorpusReport { results })
+}
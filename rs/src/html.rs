@@ -0,0 +1,211 @@
+//! Rendering of generated markdown as an HTML fragment.
+//!
+//! The fragment has no `<html>`/`<head>` wrapper and is meant to be included
+//! into existing pages. Every element carries a class named after the element
+//! with a configurable prefix (`<p class="nixdoc-p">`), so the fragment can
+//! be styled without clashing with the surrounding page. Headings are shifted
+//! down by a configurable offset to fit the page's outline.
+//!
+//! `{#anchor}` attributes and `[]{#anchor}` lines become `id`s, fenced divs (`::: {.example}`)
+//! become `<div>`s with their classes prefixed, and roles
+//! (``{option}`foo` ``) add a `<prefix>role-<role>` class to the code span.
+
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+
+use crate::format::{div_lines, inline_anchor, strip_role, DivLine};
+
+/// Settings for the HTML fragment.
+#[derive(Debug, Clone)]
+pub struct HtmlOptions {
+    /// Number of levels headings are shifted down by; `# Foo` is rendered
+    /// as `<h3>` with an offset of 2. Headings never go below `<h6>`.
+    pub heading_offset: usize,
+    /// Prefix of the class names of all elements.
+    pub class_prefix: String,
+}
+
+impl Default for HtmlOptions {
+    fn default() -> Self {
+        Self {
+            heading_offset: 0,
+            class_prefix: "nixdoc-".to_string(),
+        }
+    }
+}
+
+/// Convert markdown to an HTML fragment.
+pub fn markdown_to_html(markdown: &str, opts: &HtmlOptions) -> String {
+    let source = fenced_divs_to_html(markdown, &opts.class_prefix);
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_HEADING_ATTRIBUTES
+        | Options::ENABLE_DEFINITION_LIST;
+
+    let class = |element: &str| format!("class=\"{}{}\"", opts.class_prefix, element);
+    let mut out = String::new();
+    let mut in_table_head = false;
+
+    for event in Parser::new_ext(&source, options) {
+        match event {
+            Event::Start(Tag::Heading { level, id, .. }) => {
+                let level = (level as usize + opts.heading_offset).min(6);
+                out.push_str(&format!("<h{} {}", level, class(&format!("h{}", level))));
+                if let Some(id) = id {
+                    out.push_str(&format!(" id=\"{}\"", escape(&id)));
+                }
+                out.push('>');
+            }
+            Event::End(TagEnd::Heading(level)) => {
+                let level = (level as usize + opts.heading_offset).min(6);
+                out.push_str(&format!("</h{}>\n", level));
+            }
+            Event::Start(Tag::Paragraph) => out.push_str(&format!("<p {}>", class("p"))),
+            Event::End(TagEnd::Paragraph) => out.push_str("</p>\n"),
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let mut classes = format!("{}code", opts.class_prefix);
+                if let CodeBlockKind::Fenced(info) = kind {
+                    if let Some(lang) = info.split_whitespace().next() {
+                        classes.push_str(&format!(" {}language-{}", opts.class_prefix, lang));
+                    }
+                }
+                out.push_str(&format!(
+                    "<pre {}><code class=\"{}\">",
+                    class("pre"),
+                    escape(&classes)
+                ));
+            }
+            Event::End(TagEnd::CodeBlock) => out.push_str("</code></pre>\n"),
+            Event::Start(Tag::List(None)) => out.push_str(&format!("<ul {}>\n", class("ul"))),
+            Event::Start(Tag::List(Some(1))) => out.push_str(&format!("<ol {}>\n", class("ol"))),
+            Event::Start(Tag::List(Some(start))) => {
+                out.push_str(&format!("<ol {} start=\"{}\">\n", class("ol"), start));
+            }
+            Event::End(TagEnd::List(ordered)) => {
+                out.push_str(if ordered { "</ol>\n" } else { "</ul>\n" });
+            }
+            Event::Start(Tag::Item) => out.push_str(&format!("<li {}>", class("li"))),
+            Event::End(TagEnd::Item) => out.push_str("</li>\n"),
+            Event::Start(Tag::DefinitionList) => out.push_str(&format!("<dl {}>\n", class("dl"))),
+            Event::End(TagEnd::DefinitionList) => out.push_str("</dl>\n"),
+            Event::Start(Tag::DefinitionListTitle) => {
+                out.push_str(&format!("<dt {}>", class("dt")));
+            }
+            Event::End(TagEnd::DefinitionListTitle) => out.push_str("</dt>\n"),
+            Event::Start(Tag::DefinitionListDefinition) => {
+                out.push_str(&format!("<dd {}>", class("dd")));
+            }
+            Event::End(TagEnd::DefinitionListDefinition) => out.push_str("</dd>\n"),
+            Event::Start(Tag::BlockQuote(_)) => {
+                out.push_str(&format!("<blockquote {}>\n", class("blockquote")));
+            }
+            Event::End(TagEnd::BlockQuote(_)) => out.push_str("</blockquote>\n"),
+            Event::Start(Tag::Table(_)) => out.push_str(&format!("<table {}>\n", class("table"))),
+            Event::End(TagEnd::Table) => out.push_str("</tbody></table>\n"),
+            Event::Start(Tag::TableHead) => {
+                in_table_head = true;
+                out.push_str(&format!("<thead {}><tr {}>", class("thead"), class("tr")));
+            }
+            Event::End(TagEnd::TableHead) => {
+                in_table_head = false;
+                out.push_str(&format!("</tr></thead>\n<tbody {}>\n", class("tbody")));
+            }
+            Event::Start(Tag::TableRow) => out.push_str(&format!("<tr {}>", class("tr"))),
+            Event::End(TagEnd::TableRow) => out.push_str("</tr>\n"),
+            Event::Start(Tag::TableCell) => {
+                let cell = if in_table_head { "th" } else { "td" };
+                out.push_str(&format!("<{} {}>", cell, class(cell)));
+            }
+            Event::End(TagEnd::TableCell) => {
+                out.push_str(if in_table_head { "</th>" } else { "</td>" });
+            }
+            Event::Start(Tag::Emphasis) => out.push_str(&format!("<em {}>", class("em"))),
+            Event::End(TagEnd::Emphasis) => out.push_str("</em>"),
+            Event::Start(Tag::Strong) => out.push_str(&format!("<strong {}>", class("strong"))),
+            Event::End(TagEnd::Strong) => out.push_str("</strong>"),
+            Event::Start(Tag::Link {
+                dest_url, title, ..
+            }) => {
+                out.push_str(&format!("<a {} href=\"{}\"", class("a"), escape(&dest_url)));
+                if !title.is_empty() {
+                    out.push_str(&format!(" title=\"{}\"", escape(&title)));
+                }
+                out.push('>');
+            }
+            Event::End(TagEnd::Link) => out.push_str("</a>"),
+            Event::Start(Tag::Image {
+                dest_url, title, ..
+            }) => {
+                out.push_str(&format!(
+                    "<img {} src=\"{}\" title=\"{}\" alt=\"",
+                    class("img"),
+                    escape(&dest_url),
+                    escape(&title)
+                ));
+            }
+            Event::End(TagEnd::Image) => out.push_str("\">"),
+            Event::Code(code) => {
+                let mut classes = format!("{}code", opts.class_prefix);
+                if let Some(role) = strip_role(&mut out) {
+                    classes.push_str(&format!(" {}role-{}", opts.class_prefix, role));
+                }
+                out.push_str(&format!(
+                    "<code class=\"{}\">{}</code>",
+                    classes,
+                    escape(&code)
+                ));
+            }
+            Event::Text(text) => out.push_str(&escape(&text)),
+            Event::Html(html) | Event::InlineHtml(html) => out.push_str(&html),
+            Event::SoftBreak => out.push('\n'),
+            Event::HardBreak => out.push_str(&format!("<br {}>\n", class("br"))),
+            Event::Rule => out.push_str(&format!("<hr {}>\n", class("hr"))),
+            _ => (),
+        }
+    }
+
+    out
+}
+
+/// Replace the fences of fenced divs by `<div>` tags carrying their id and
+/// prefixed classes.
+fn fenced_divs_to_html(markdown: &str, class_prefix: &str) -> String {
+    let mut out = String::new();
+    for line in div_lines(markdown) {
+        match line {
+            DivLine::Open(attrs) => {
+                let attrs = attrs.trim_start_matches('{').trim_end_matches('}');
+                let mut classes = vec![format!("{}div", class_prefix)];
+                let mut id = None;
+                for attr in attrs.split_whitespace() {
+                    if let Some(class) = attr.strip_prefix('.') {
+                        classes.push(format!("{}{}", class_prefix, class));
+                    } else if let Some(anchor) = attr.strip_prefix('#') {
+                        id = Some(anchor);
+                    }
+                }
+                out.push_str(&format!("<div class=\"{}\"", escape(&classes.join(" "))));
+                if let Some(id) = id {
+                    out.push_str(&format!(" id=\"{}\"", escape(id)));
+                }
+                out.push_str(">\n\n");
+            }
+            DivLine::Close => out.push_str("</div>\n\n"),
+            DivLine::Text(line, false) if inline_anchor(line).is_some() => {
+                let id = inline_anchor(line).unwrap_or_default();
+                out.push_str(&format!("<div id=\"{}\"></div>\n\n", escape(id)));
+            }
+            DivLine::Text(line, _) => {
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+/// Escape text for use in HTML content and attribute values.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,126 @@
+//! Object inventories for linking into the documentation from elsewhere.
+//!
+//! An inventory maps the full names of functions and options (e.g.
+//! `lib.strings.concat` or `services.nginx.enable`) to the URIs of their
+//! documentation, relative to the root of the rendered manual. It is
+//! written as JSON or as a Sphinx `objects.inv`, which
+//! [intersphinx](https://www.sphinx-doc.org/en/master/usage/extensions/intersphinx.html)
+//! reads to link `` :nix:function:`lib.strings.concat` `` references:
+//!
+//! ```text
+//! # Sphinx inventory version 2
+//! # Project: nixpkgs
+//! # Version: 24.05
+//! # The remainder of this file is compressed using zlib.
+//! lib.strings.concat nix:function 1 strings.html#function-library-lib.strings.concat -
+//! ```
+
+use std::io::Write;
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use serde::Serialize;
+
+use crate::commonmark::ManualEntry;
+use crate::options::{option_anchors, OptionsMap};
+use crate::search::DocumentKind;
+
+/// A documented object.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InventoryItem {
+    /// Full name, e.g. `lib.strings.concat`.
+    pub name: String,
+    pub kind: DocumentKind,
+    /// Anchor of the object on its page.
+    pub anchor: String,
+    /// URI of the object, relative to the root of the manual.
+    pub uri: String,
+}
+
+/// The objects of a manual.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Inventory {
+    pub project: String,
+    pub version: String,
+    pub items: Vec<InventoryItem>,
+}
+
+/// The URI of an anchor on a page, which is the current page if empty.
+fn uri(page: &str, anchor: &str) -> String {
+    format!("{}#{}", page, anchor)
+}
+
+/// Items for the entries of a category, documented on the page `page`, in
+/// which `{category}` stands for the category of an entry.
+pub fn function_items(
+    entries: &[ManualEntry],
+    anchor_prefix: &str,
+    page: &str,
+) -> Vec<InventoryItem> {
+    entries
+        .iter()
+        .map(|entry| {
+            let (ident, title) = entry.get_ident_title();
+            let anchor = format!("{}{}", anchor_prefix, ident);
+            InventoryItem {
+                name: title,
+                kind: DocumentKind::Function,
+                uri: uri(&page.replace("{category}", &entry.category), &anchor),
+                anchor,
+            }
+        })
+        .collect()
+}
+
+/// Items for options documented on the page `page`, sorted by name.
+pub fn option_items(options: &OptionsMap, anchor_prefix: &str, page: &str) -> Vec<InventoryItem> {
+    let mut names: Vec<&String> = options.keys().collect();
+    names.sort();
+    let anchors = option_anchors(options, anchor_prefix);
+    names
+        .into_iter()
+        .map(|name| {
+            let anchor = anchors.get(name).unwrap_or_default().to_string();
+            InventoryItem {
+                name: name.clone(),
+                kind: DocumentKind::Option,
+                uri: uri(page, &anchor),
+                anchor,
+            }
+        })
+        .collect()
+}
+
+impl Inventory {
+    /// The inventory as a Sphinx `objects.inv` (version 2), with the objects
+    /// in the `nix` domain.
+    pub fn to_objects_inv(&self) -> Vec<u8> {
+        let mut output = format!(
+            "# Sphinx inventory version 2\n# Project: {}\n# Version: {}\n# The remainder of this file is compressed using zlib.\n",
+            self.project, self.version
+        )
+        .into_bytes();
+
+        let mut lines = String::new();
+        for item in &self.items {
+            let role = match item.kind {
+                DocumentKind::Function => "function",
+                DocumentKind::Option => "option",
+            };
+            // `$` abbreviates the name at the end of the URI, and `-` a
+            // display name equal to the name.
+            let uri = match item.uri.strip_suffix(&item.name) {
+                Some(base) => format!("{}$", base),
+                None => item.uri.clone(),
+            };
+            lines.push_str(&format!("{} nix:{} 1 {} -\n", item.name, role, uri));
+        }
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(lines.as_bytes())
+            .and_then(|_| encoder.finish())
+            .map(|compressed| output.extend(compressed))
+            .expect("compressing to memory does not fail");
+        output
+    }
+}
@@ -0,0 +1,202 @@
+//! Version 2 of the `--json-output` format.
+//!
+//! Compared to version 1, which serializes [`ManualEntry`] as is, entries
+//! carry their identifier and title, source span, raw and rendered markdown,
+//! tagged argument metadata and the category they belong to. The format is
+//! described by a JSON Schema, printed by the `schema` subcommand.
+
+use std::collections::BTreeMap;
+
+use schemars::{schema_for, JsonSchema, Schema};
+use serde::{Deserialize, Serialize};
+
+use crate::arguments::ArgumentDoc;
+use crate::commonmark::{Argument, Constant, ManualEntry, SingleArg, Span};
+use crate::examples::ExampleDoc;
+use crate::signature::TypeSignature;
+use crate::tags::Stability;
+
+/// Top-level structure of the version 2 JSON output.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Document {
+    /// Always `2`.
+    pub version: u32,
+    pub category: Category,
+    pub entries: Vec<Entry>,
+}
+
+/// The category a file is documented as.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Category {
+    /// Prefix of the category (e.g. `lib`).
+    pub prefix: String,
+    /// Name of the category (e.g. `strings`).
+    pub name: String,
+    /// Raw markdown of the file-level doc comment.
+    pub description: Option<String>,
+}
+
+/// Markdown in its source form and rendered as part of the manual.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Markdown {
+    pub raw: String,
+    pub rendered: String,
+}
+
+/// A single function argument.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ArgumentInfo {
+    pub name: String,
+    pub doc: Option<String>,
+    /// Source text of the default value of a pattern argument.
+    pub default: Option<String>,
+}
+
+/// An argument of a documented function.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ArgumentEntry {
+    /// A plain argument (`x: ...`).
+    Flat(ArgumentInfo),
+    /// An attribute set pattern (`{ a, b ? 1, ... }@args: ...`).
+    Pattern {
+        args: Vec<ArgumentInfo>,
+        ellipsis: bool,
+        bind: Option<String>,
+    },
+}
+
+/// A documented entry.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Entry {
+    /// Anchor identifier (e.g. `lib.strings.concat`).
+    pub id: String,
+    /// Anchor of the entry's heading: the anchor prefix followed by `id`.
+    pub anchor: String,
+    /// Displayed title (e.g. `lib.strings.concat`).
+    pub title: String,
+    pub name: String,
+    /// Position of the binding in its source file.
+    pub span: Option<Span>,
+    /// Location from `--locs` data, or the source file and line.
+    pub location: Option<String>,
+    /// Link to the binding, if `--source-base-url` is set.
+    pub source_url: Option<String>,
+    /// The doc comment without tags, and the entry's rendered section.
+    pub markdown: Markdown,
+    /// First sentence of the description, for listings.
+    pub summary: Option<String>,
+    /// Type signature, if one is documented.
+    pub type_signature: Option<String>,
+    /// The type signature of a `# Type` section, if it could be parsed.
+    pub signature: Option<TypeSignature>,
+    pub example: Option<String>,
+    /// Examples of an `# Examples` section or `# Example: <title>` headings.
+    pub examples: Vec<ExampleDoc>,
+    pub args: Vec<ArgumentEntry>,
+    /// The value of an entry that is not a function.
+    pub constant: Option<Constant>,
+    /// Arguments documented in an `# Arguments` or `# Inputs` section.
+    pub arguments: Vec<ArgumentDoc>,
+    /// Conditions under which the entry fails, from a `# Throws` or
+    /// `# Errors` section.
+    pub throws: Vec<String>,
+    pub maintainers: Vec<String>,
+    /// Keywords from a `Tags:` line or a `# Keywords` section.
+    pub keywords: Vec<String>,
+    pub since: Option<String>,
+    pub deprecated: Option<String>,
+    pub stability: Option<Stability>,
+    /// Fields of custom tags, keyed by tag.
+    pub fields: BTreeMap<String, serde_json::Value>,
+    /// Markdown of the section the entry is grouped under.
+    pub section: Option<String>,
+    /// Hash-based anchor, if enabled with `--hash-anchors`.
+    pub hash_id: Option<String>,
+}
+
+impl From<SingleArg> for ArgumentInfo {
+    fn from(arg: SingleArg) -> Self {
+        Self {
+            name: arg.name,
+            doc: arg.doc,
+            default: arg.default,
+        }
+    }
+}
+
+impl From<Argument> for ArgumentEntry {
+    fn from(arg: Argument) -> Self {
+        match arg {
+            Argument::Flat(arg) => Self::Flat(arg.into()),
+            Argument::Pattern {
+                args,
+                ellipsis,
+                bind,
+            } => Self::Pattern {
+                args: args.into_iter().map(Into::into).collect(),
+                ellipsis,
+                bind,
+            },
+        }
+    }
+}
+
+impl Entry {
+    /// Convert an entry, rendering its section with `anchor_prefix`.
+    pub fn from_manual_entry(entry: ManualEntry, anchor_prefix: &str) -> Self {
+        let (id, title) = entry.get_ident_title();
+        let mut rendered = String::new();
+        entry.clone().write_section(anchor_prefix, &mut rendered);
+
+        Self {
+            anchor: format!("{}{}", anchor_prefix, id),
+            id,
+            title,
+            span: entry.span,
+            location: entry.location,
+            source_url: entry.source_url,
+            markdown: Markdown {
+                raw: entry.description.join("\n\n"),
+                rendered,
+            },
+            summary: entry.summary,
+            type_signature: entry.fn_type,
+            signature: entry.signature,
+            example: entry.example,
+            examples: entry.examples,
+            args: entry.args.into_iter().map(Into::into).collect(),
+            constant: entry.constant,
+            arguments: entry.arguments,
+            throws: entry.throws,
+            maintainers: entry.maintainers,
+            keywords: entry.keywords,
+            since: entry.since,
+            deprecated: entry.deprecated,
+            stability: entry.stability,
+            fields: entry.fields,
+            section: entry.section,
+            hash_id: entry.hash_anchor,
+            name: entry.name,
+        }
+    }
+}
+
+impl Document {
+    /// Build a document from the entries of a category.
+    pub fn new(category: Category, entries: Vec<ManualEntry>, anchor_prefix: &str) -> Self {
+        Self {
+            version: 2,
+            category,
+            entries: entries
+                .into_iter()
+                .map(|e| Entry::from_manual_entry(e, anchor_prefix))
+                .collect(),
+        }
+    }
+
+    /// JSON Schema of the format.
+    pub fn schema() -> Schema {
+        schema_for!(Document)
+    }
+}
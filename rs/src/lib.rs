@@ -0,0 +1,1287 @@
+// Copyright (C) 2018 Vincent Ambo <mail@tazj.in>
+//
+// nixdoc is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! This library extracts documentation from Nix files defining library
+//! functions, such as the files in `lib/` in the nixpkgs repository, and
+//! renders it to CommonMark. It also renders NixOS-style module options.
+//!
+//! The `docgen` command line tool is a thin wrapper around this crate.
+
+pub mod admonition;
+pub mod arguments;
+pub mod asciidoc;
+pub mod attrpath;
+pub mod batch;
+pub mod cache;
+pub mod comment;
+pub mod commonmark;
+pub mod compat;
+pub mod diagnostics;
+pub mod dialect;
+pub mod diff;
+pub mod docbook;
+pub mod doctest;
+pub mod eval;
+pub mod examples;
+pub mod extract;
+pub mod filedoc;
+pub mod format;
+pub mod glob;
+pub mod harness;
+pub mod html;
+pub mod inventory;
+pub mod jsonv2;
+pub mod linkcheck;
+pub mod linkgraph;
+pub mod lint;
+pub mod locale;
+pub mod locs;
+pub mod man;
+mod markup;
+pub mod mdbook;
+pub mod mdcheck;
+pub mod meta;
+pub mod module;
+pub mod nixfmt;
+pub mod options;
+pub mod optiontypes;
+pub mod plugin;
+pub mod rst;
+pub mod search;
+pub mod serve;
+pub mod signature;
+pub mod slug;
+pub mod source;
+pub mod tagindex;
+pub mod tags;
+pub mod template;
+pub mod walk;
+pub mod xref;
+
+use crate::format::handle_indentation;
+
+use self::attrpath::{attr_name, attrpath_name};
+use self::comment::get_expr_docs;
+use self::commonmark::*;
+use self::source::SourceLinks;
+use format::shift_headings;
+use rnix::{
+    ast::{
+        AttrSet, AttrpathValue, BinOpKind, Expr, HasEntry, Ident, Inherit, Lambda, LetIn,
+        LiteralKind, Param, Select,
+    },
+    SyntaxKind, SyntaxNode,
+};
+use rowan::{ast::AstNode, WalkEvent};
+
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Debug)]
+struct DocComment {
+    /// Primary documentation string.
+    doc: String,
+    /// The doc comment as written, with its own heading levels.
+    raw: String,
+}
+
+#[derive(Debug)]
+struct DocItem {
+    name: String,
+    comment: DocComment,
+    args: Vec<Argument>,
+    constant: Option<Constant>,
+    meta: meta::EntryMeta,
+    /// Position of the binding in the source.
+    span: Span,
+    /// File the documentation was taken from, if not the documented file
+    /// itself (for bindings to `import ./file.nix`).
+    file: Option<String>,
+}
+
+/// Top-level structure of the `--json-output` format.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JsonFormat {
+    pub version: u32,
+    pub entries: Vec<ManualEntry>,
+}
+
+/// Settings for extracting entries from a Nix source.
+#[derive(Debug, Clone, Default)]
+pub struct CollectOptions {
+    /// Prefix for the category (e.g. 'lib' or 'utils').
+    pub prefix: String,
+    /// Name of the function category (e.g. 'strings', 'attrsets').
+    pub category: String,
+    /// Location data keyed by entry identifier.
+    pub locs: HashMap<String, String>,
+    /// Path of the source file, shown as location (with the line of the
+    /// binding) for entries without location data if `line_locations` is set.
+    pub file: Option<String>,
+    /// Whether entries without location data in `locs` are located by their
+    /// line in `file`.
+    pub line_locations: bool,
+    /// Directory relative imports in `inherit (import ./foo.nix) bar;` are
+    /// resolved against. Imports are not followed if unset.
+    pub base_dir: Option<PathBuf>,
+    /// Let bindings to document instead of the returned attribute set.
+    pub export: Option<Vec<String>>,
+    /// Glob patterns of entry names to include (all if empty).
+    pub include: Vec<String>,
+    /// Glob patterns of entry names to exclude.
+    pub exclude: Vec<String>,
+    /// Glob patterns of wrapper functions (e.g. `lib.flip`) that are looked
+    /// through when collecting the arguments of an entry.
+    pub wrappers: Vec<String>,
+    /// How many levels of nested attribute sets are walked. Entries of
+    /// nested sets are named by their dotted path (e.g. `strings.concat`).
+    pub depth: usize,
+    /// Whether entries get a hash-based anchor next to their identifier
+    /// anchor, see [`commonmark::hash_anchor`].
+    pub hash_anchors: bool,
+    /// Whether undocumented bindings to `import ./file.nix` are documented by
+    /// the doc comment of the imported file.
+    pub follow_imports: bool,
+    /// Link targets of type names (e.g. `Derivation`) in type signatures.
+    pub type_links: HashMap<String, String>,
+    /// Where entries link to their source. Entries without location data
+    /// are located by such a link instead of the plain line.
+    pub source_links: Option<SourceLinks>,
+    /// Language of the code blocks of examples whose heading names none,
+    /// see [`examples::apply_language`]. Their code blocks are left without
+    /// an info string if unset.
+    pub default_example_language: Option<String>,
+    /// Names of the custom `@tag`s that become fields of their entry, see
+    /// [`plugin`]. Lines naming other unknown tags are kept in the text.
+    pub custom_tags: Vec<String>,
+}
+
+impl CollectOptions {
+    /// Options for a category without locations, exports or filters.
+    pub fn new(prefix: &str, category: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            category: category.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Read a Nix source file. Invalid UTF-8 sequences are replaced with U+FFFD
+/// instead of failing, since a single stray byte in a comment should not stop
+/// the documentation of a whole library; the flag tells whether any were.
+pub fn read_source(path: &Path) -> std::io::Result<(String, bool)> {
+    let bytes = fs::read(path)?;
+    match String::from_utf8(bytes) {
+        Ok(source) => Ok((source, false)),
+        Err(e) => Ok((String::from_utf8_lossy(e.as_bytes()).into_owned(), true)),
+    }
+}
+
+/// Parse a Nix source held in memory and collect its documented entries.
+///
+/// This is the entry point for tools that work on unsaved buffers or
+/// generated sources and never touch the filesystem.
+pub fn collect_entries_from_str(
+    source: &str,
+    opts: &CollectOptions,
+) -> Result<Vec<ManualEntry>, String> {
+    let nix = rnix::Root::parse(source)
+        .ok()
+        .map_err(|e| format!("failed to parse input: {}", e))?;
+    Ok(collect_entries(nix, opts))
+}
+
+/// Returns a RFC145 doc-comment if one is present
+pub fn retrieve_doc_comment(node: &SyntaxNode, shift_headings_by: Option<usize>) -> Option<String> {
+    retrieve_raw_doc_comment(node).map(|doc_comment| {
+        shift_headings(
+            &doc_comment,
+            // H1 to H4 can be used in the doc-comment with the current rendering.
+            // They will be shifted to H3, H6
+            // H1 and H2 are currently used by the outer rendering. (category and function name)
+            shift_headings_by.unwrap_or(2),
+        )
+    })
+}
+
+/// Returns a RFC145 doc-comment if one is present, without shifting its
+/// headings.
+pub fn retrieve_raw_doc_comment(node: &SyntaxNode) -> Option<String> {
+    get_expr_docs(node).map(|doc_comment| handle_indentation(&doc_comment).unwrap_or_default())
+}
+
+/// Traverse directly chained nix lambdas and collect the identifiers of all lambda arguments.
+fn collect_lambda_args(mut lambda: Lambda) -> Vec<Argument> {
+    let mut args = vec![];
+
+    loop {
+        match lambda.param().unwrap() {
+            Param::IdentParam(id) => {
+                args.push(Argument::Flat(SingleArg {
+                    name: id.to_string(),
+                    // Comments in front of the parameter precede the lambda.
+                    doc: argument_doc(lambda.syntax()),
+                    default: None,
+                }));
+            }
+            Param::Pattern(pat) => {
+                let pattern_vec: Vec<_> = pat
+                    .pat_entries()
+                    .map(|entry| SingleArg {
+                        name: entry.ident().unwrap().to_string(),
+                        doc: argument_doc(entry.syntax()),
+                        default: entry.default().map(|d| d.syntax().text().to_string()),
+                    })
+                    .collect();
+
+                args.push(Argument::Pattern {
+                    args: pattern_vec,
+                    ellipsis: pat.ellipsis_token().is_some(),
+                    bind: pat
+                        .pat_bind()
+                        .and_then(|b| b.ident())
+                        .map(|i| i.to_string()),
+                });
+            }
+        }
+
+        match lambda.body() {
+            Some(Expr::Lambda(inner)) => lambda = inner,
+            _ => break,
+        }
+    }
+
+    args
+}
+
+/// The documentation of a lambda argument: its doc comment or, as in
+/// comments predating RFC145, a regular comment in front of it.
+fn argument_doc(node: &SyntaxNode) -> Option<String> {
+    let doc = comment::get_leading_doc(node)
+        .map(|doc| shift_headings(&handle_indentation(&doc).unwrap_or_default(), 1))
+        .or_else(|| {
+            comment::get_legacy_comment(node).map(|comment| comment.text().trim().to_string())
+        });
+    handle_indentation(&doc.unwrap_or_default())
+}
+
+/// Transforms an AST node into a `DocItem` if it has a leading
+/// documentation comment.
+/// Bindings with interpolations in their name are skipped.
+fn retrieve_doc_item(node: &AttrpathValue) -> Option<DocItem> {
+    let item_name = attrpath_name(&node.attrpath()?)?;
+
+    let raw = retrieve_raw_doc_comment(node.syntax())?;
+
+    Some(DocItem {
+        name: item_name,
+        comment: DocComment {
+            doc: shift_headings(&raw, 2),
+            raw,
+        },
+        args: vec![],
+        constant: None,
+        meta: Default::default(),
+        span: span_of(node.syntax()),
+        file: None,
+    })
+}
+
+/// Returns the 1-based line and column of an offset in the file containing
+/// `node`.
+fn position_of(node: &SyntaxNode, offset: rnix::TextSize) -> (usize, usize) {
+    let root = node.ancestors().last().unwrap_or_else(|| node.clone());
+    let (mut line, mut column) = (1, 1);
+    root.text().slice(..offset).for_each_chunk(|chunk| {
+        for c in chunk.chars() {
+            if c == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+    });
+    (line, column)
+}
+
+/// Returns the span a node covers in its file.
+fn span_of(node: &SyntaxNode) -> Span {
+    let range = node.text_range();
+    let (start_line, start_column) = position_of(node, range.start());
+    let (end_line, end_column) = position_of(node, range.end());
+    Span {
+        start_line,
+        start_column,
+        end_line,
+        end_column,
+        start_byte: usize::from(range.start()),
+        end_byte: usize::from(range.end()),
+    }
+}
+
+impl DocItem {
+    /// Turn the item into a manual entry. The location is looked up in
+    /// `opts.locs`, falling back to the line in `opts.file` if
+    /// `opts.line_locations` is set, linked to the source if
+    /// `opts.source_links` is set.
+    fn into_entry(self, opts: &CollectOptions) -> ManualEntry {
+        let (prefix, category) = (opts.prefix.as_str(), opts.category.as_str());
+        let ident = get_identifier(prefix, category, &self.name);
+        let file = opts
+            .file
+            .as_ref()
+            .map(|file| self.file.as_ref().unwrap_or(file));
+        let source_url = file
+            .zip(opts.source_links.as_ref())
+            .map(|(file, links)| links.url(file, &self.span));
+        let location = opts.locs.get(&ident).cloned().or_else(|| {
+            let file = file.filter(|_| opts.line_locations)?;
+            let line = format!("`{}:{}`", file, self.span.start_line);
+            Some(match &source_url {
+                Some(url) => format!("[{}]({})", line, url),
+                None => line,
+            })
+        });
+        let (doc, tags) = tags::extract_tags(&self.comment.doc, &opts.custom_tags);
+        let (doc, deprecated_section) = tags::extract_deprecated_section(&doc);
+        let (doc, since_section) = tags::extract_since(&doc);
+        let (doc, throws) = tags::extract_throws(&doc);
+        let (doc, keywords) = tags::extract_keywords(&doc);
+        let (doc, mut examples) = examples::split_examples(&doc);
+        for example in &mut examples {
+            examples::apply_language(example, opts.default_example_language.as_deref());
+        }
+        let (doc, signature) = match signature::extract_signature(&doc, &opts.type_links) {
+            Some((doc, signature)) => (doc, Some(signature)),
+            None => (doc, None),
+        };
+        let deprecated = self
+            .meta
+            .deprecated
+            .or_else(|| {
+                tags.iter()
+                    .find(|t| t.name == "deprecated")
+                    .map(|t| t.value.clone())
+            })
+            .or(deprecated_section);
+        let since = self
+            .meta
+            .since
+            .or_else(|| {
+                tags.iter()
+                    .find(|t| t.name == "since")
+                    .map(|t| t.value.clone())
+            })
+            .or(since_section);
+        let stability = tags
+            .iter()
+            .filter(|t| t.name == "stability")
+            .find_map(|t| tags::Stability::from_name(&t.value));
+        let anchor = opts.hash_anchors.then(|| hash_anchor(category, &self.name));
+
+        ManualEntry {
+            prefix: prefix.to_string(),
+            category: category.to_string(),
+            location,
+            name: self.name,
+            ident: None,
+            description: doc.split("\n\n").map(|s| s.to_string()).collect(),
+            summary: format::summary(&doc),
+            fn_type: signature.as_ref().map(ToString::to_string),
+            signature,
+            example: None,
+            examples,
+            args: self.args,
+            constant: self.constant,
+            arguments: arguments::parse_arguments_section(&doc),
+            throws,
+            maintainers: self.meta.maintainers,
+            keywords,
+            since,
+            deprecated,
+            stability,
+            fields: plugin::tag_fields(&tags),
+            rendered_fields: vec![],
+            section: None,
+            hash_anchor: anchor,
+            span: Some(self.span),
+            source_url,
+            raw_doc: Some(self.comment.raw),
+        }
+    }
+}
+
+/// Strip applications of wrapper functions matching one of `wrappers` off a
+/// binding value, e.g. `memoize (x: ...)` yields `x: ...`. The wrapped value
+/// is the last argument of the application.
+fn unwrap_wrappers(mut value: Expr, wrappers: &[String]) -> Expr {
+    if wrappers.is_empty() {
+        return value;
+    }
+    loop {
+        match value {
+            Expr::Paren(ref paren) => match paren.expr() {
+                Some(inner) => value = inner,
+                None => return value,
+            },
+            Expr::Apply(ref apply) => {
+                let mut head = apply.lambda();
+                while let Some(Expr::Apply(inner)) = head {
+                    head = inner.lambda();
+                }
+                let is_wrapper = head.is_some_and(|h| {
+                    let name = h.syntax().text().to_string();
+                    wrappers.iter().any(|w| glob::glob_match(w, &name))
+                });
+                match apply.argument() {
+                    Some(argument) if is_wrapper => value = argument,
+                    _ => return value,
+                }
+            }
+            _ => return value,
+        }
+    }
+}
+
+/// Traverse the arena from a top-level SetEntry and collect, where
+/// possible:
+///
+/// 1. The identifier of the set entry itself.
+/// 2. The attached doc comment on the entry.
+/// 3. The argument names of any curried functions.
+/// 4. Metadata from `meta`/`__internal` attributes of the value.
+///
+/// Attribute sets with a `__functor` are documented as functions, taking the
+/// doc comment of the `__functor` binding if the set itself has none.
+/// Entries marked as internal are skipped.
+pub(crate) fn collect_entry_information(
+    entry: AttrpathValue,
+    opts: &CollectOptions,
+) -> Option<DocItem> {
+    let value = entry
+        .value()
+        .map(|value| unwrap_wrappers(value, &opts.wrappers));
+    let functor = value.as_ref().and_then(functor_binding);
+    let doc_item = retrieve_doc_item(&entry).or_else(|| {
+        let mut item = retrieve_doc_item(functor.as_ref()?)?;
+        item.name = attrpath_name(&entry.attrpath()?)?;
+        item.span = span_of(entry.syntax());
+        Some(item)
+    });
+    let Some(mut doc_item) = doc_item else {
+        return opts
+            .follow_imports
+            .then(|| imported_doc_item(&entry, opts))
+            .flatten();
+    };
+
+    match value {
+        Some(Expr::Lambda(l)) => doc_item.args = collect_lambda_args(l),
+        Some(value) => {
+            doc_item.meta = meta::EntryMeta::from_value(&value);
+            if let Some(Expr::Lambda(l)) = functor.and_then(|f| f.value()) {
+                // The first argument is the set itself.
+                doc_item.args = collect_lambda_args(l).into_iter().skip(1).collect();
+            } else {
+                doc_item.constant = constant(&value);
+            }
+        }
+        None => (),
+    }
+
+    if doc_item.meta.internal {
+        return None;
+    }
+
+    Some(doc_item)
+}
+
+/// The kind of a value that is evidently not a function, such as a number,
+/// a string or an attribute set, or an arithmetic or concatenation of these.
+/// Attribute sets with documented bindings are namespaces, not constants.
+fn constant_kind(value: &Expr) -> Option<&'static str> {
+    match value {
+        Expr::Paren(paren) => constant_kind(&paren.expr()?),
+        Expr::Literal(literal) => Some(match literal.kind() {
+            LiteralKind::Float(_) | LiteralKind::Integer(_) => "number",
+            LiteralKind::Uri(_) => "string",
+        }),
+        Expr::Str(_) => Some("string"),
+        Expr::Path(_) => Some("path"),
+        Expr::List(_) => Some("list"),
+        Expr::AttrSet(set) => set
+            .attrpath_values()
+            .all(|binding| retrieve_doc_comment(binding.syntax(), Some(2)).is_none())
+            .then_some("attribute set"),
+        Expr::Ident(ident) => match ident.to_string().as_str() {
+            "true" | "false" => Some("boolean"),
+            "null" => Some("null"),
+            _ => None,
+        },
+        Expr::UnaryOp(op) => constant_kind(&op.expr()?),
+        Expr::BinOp(op) => {
+            use BinOpKind::*;
+            if !matches!(op.operator()?, Concat | Update | Add | Sub | Mul | Div) {
+                return None;
+            }
+            let kind = constant_kind(&op.lhs()?)?;
+            (constant_kind(&op.rhs()?)? == kind).then_some(kind)
+        }
+        _ => None,
+    }
+}
+
+/// The constant a binding value is, with its source if it is short.
+fn constant(value: &Expr) -> Option<Constant> {
+    let source = value.to_string();
+    Some(Constant {
+        kind: constant_kind(value)?.to_string(),
+        value: Some(source)
+            .filter(|s| !s.contains('\n') && s.chars().count() <= MAX_CONSTANT_LENGTH),
+    })
+}
+
+/// The `__functor` binding of an attribute set, which makes the set callable.
+pub(crate) fn functor_binding(value: &Expr) -> Option<AttrpathValue> {
+    let Expr::AttrSet(set) = value else {
+        return None;
+    };
+    let functor = meta::attr_value(set, "__functor")?;
+    AttrpathValue::cast(functor.syntax().parent()?)
+}
+
+/// Returns the path of an `import ./path` expression and the number of
+/// arguments the imported value is applied to, e.g. 1 for
+/// `import ./foo.nix { inherit lib; }`.
+fn import_path(expr: &Expr) -> Option<(String, usize)> {
+    let mut expr = expr.clone();
+    let mut applied = 0;
+    loop {
+        match expr {
+            Expr::Paren(paren) => expr = paren.expr()?,
+            Expr::Apply(apply) => {
+                let lambda = apply.lambda()?;
+                if matches!(lambda.to_string().as_str(), "import" | "builtins.import") {
+                    return match apply.argument()? {
+                        Expr::Path(path) => Some((path.to_string(), applied)),
+                        _ => None,
+                    };
+                }
+                applied += 1;
+                expr = lambda;
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// Resolve a relative import path against `opts.base_dir`, using the
+/// `default.nix` of directories.
+fn resolve_import(path: &str, opts: &CollectOptions) -> Option<PathBuf> {
+    let base_dir = opts.base_dir.as_ref()?;
+    if !(path.starts_with("./") || path.starts_with("../")) {
+        return None;
+    }
+    let mut file: PathBuf = base_dir
+        .join(path)
+        .components()
+        .filter(|c| *c != Component::CurDir)
+        .collect();
+    if file.is_dir() {
+        file.push("default.nix");
+    }
+    Some(file)
+}
+
+/// Document an undocumented binding to `import ./file.nix` by the doc comment
+/// of the imported file's top-level expression. Arguments are taken from the
+/// lambdas of the imported file that are not applied by the binding.
+fn imported_doc_item(entry: &AttrpathValue, opts: &CollectOptions) -> Option<DocItem> {
+    let (path, applied) = import_path(&entry.value()?)?;
+    let file = resolve_import(&path, opts)?;
+    let source = fs::read_to_string(&file).ok()?;
+    let root = rnix::Root::parse(&source).ok().ok()?;
+    let top = root.expr()?;
+
+    // The doc comment is either in front of the file or in front of the
+    // value the binding evaluates to, e.g. after `{ lib }:`.
+    let mut value = top.clone();
+    for _ in 0..applied {
+        value = match value {
+            Expr::Lambda(lambda) => lambda.body()?,
+            _ => return None,
+        };
+    }
+    let (raw, documented) = [&top, &value]
+        .into_iter()
+        .find_map(|node| Some((retrieve_raw_doc_comment(node.syntax())?, node.clone())))?;
+    let mut doc_item = DocItem {
+        name: attrpath_name(&entry.attrpath()?)?,
+        comment: DocComment {
+            doc: shift_headings(&raw, 2),
+            raw,
+        },
+        args: vec![],
+        constant: None,
+        meta: Default::default(),
+        span: span_of(documented.syntax()),
+        file: Some(file.display().to_string()),
+    };
+    match unwrap_wrappers(value, &opts.wrappers) {
+        Expr::Lambda(l) => {
+            let documents_lambda = l.syntax() == documented.syntax();
+            doc_item.args = collect_lambda_args(l);
+            // The doc comment in front of the lambda documents the entry,
+            // not its first argument.
+            if let (true, Some(Argument::Flat(arg))) = (documents_lambda, doc_item.args.first_mut())
+            {
+                arg.doc = None;
+            }
+        }
+        value => {
+            doc_item.meta = meta::EntryMeta::from_value(&value);
+            if functor_binding(&value).is_none() {
+                doc_item.constant = constant(&value);
+            }
+        }
+    }
+    (!doc_item.meta.internal).then_some(doc_item)
+}
+
+/// Bindings visible to `inherit` statements of the documented attribute set.
+#[derive(Default)]
+struct Scope {
+    /// Documented let bindings.
+    entries: HashMap<String, ManualEntry>,
+    /// Values of all let bindings and of the members of enclosing `rec`
+    /// attribute sets, for resolving `inherit (x) ...`.
+    values: HashMap<String, Expr>,
+    /// Files currently being imported, to avoid following import cycles.
+    imported: Vec<PathBuf>,
+    /// Bindings whose values are currently being resolved, to avoid
+    /// following self-references such as `x = { inherit (x) y; }`.
+    resolving: RefCell<Vec<String>>,
+}
+
+impl Scope {
+    /// The scope within the `rec` attribute set `set`, whose members shadow
+    /// the bindings of the enclosing scope.
+    fn with_rec_members(&self, set: &AttrSet) -> Scope {
+        let mut values = self.values.clone();
+        values.extend(
+            set.attrpath_values()
+                .filter_map(|apv| Some((attrpath_name(&apv.attrpath()?)?, apv.value()?))),
+        );
+        Scope {
+            entries: self.entries.clone(),
+            values,
+            imported: self.imported.clone(),
+            resolving: self.resolving.clone(),
+        }
+    }
+
+    /// Resolve the value bound to `name` with `resolve`, or return nothing if
+    /// `name` is unbound or already being resolved.
+    fn resolve_value(
+        &self,
+        name: &str,
+        resolve: impl FnOnce(&Expr) -> Vec<ManualEntry>,
+    ) -> Vec<ManualEntry> {
+        let Some(value) = self.values.get(name) else {
+            return vec![];
+        };
+        if self.resolving.borrow().iter().any(|n| n == name) {
+            return vec![];
+        }
+        self.resolving.borrow_mut().push(name.to_string());
+        let entries = resolve(value);
+        self.resolving.borrow_mut().pop();
+        entries
+    }
+}
+
+/// Strip `assert cond;` and `with scope;` off an expression, which evaluates
+/// to the same attribute set as their body. Their conditions and scopes are
+/// not the documented set even if they contain attribute sets themselves.
+fn unwrap_scopes(expr: Expr) -> Expr {
+    match &expr {
+        Expr::Assert(assert) => assert.body().map(unwrap_scopes),
+        Expr::With(with) => with.body().map(unwrap_scopes),
+        _ => None,
+    }
+    .unwrap_or(expr)
+}
+
+/// Whether `node` is an `assert` or `with` expression, see [`unwrap_scopes`].
+fn is_scope(node: &SyntaxNode) -> bool {
+    matches!(node.kind(), SyntaxKind::NODE_ASSERT | SyntaxKind::NODE_WITH)
+}
+
+/// Find the attribute sets `node` evaluates to the update of. Both sides of
+/// `//` are followed, the right one last.
+fn update_sets(node: &SyntaxNode) -> Vec<AttrSet> {
+    match Expr::cast(node.clone()) {
+        Some(Expr::Paren(paren)) => {
+            if let Some(inner) = paren.expr() {
+                return update_sets(inner.syntax());
+            }
+        }
+        Some(Expr::BinOp(op)) if op.operator() == Some(BinOpKind::Update) => {
+            return [op.lhs(), op.rhs()]
+                .into_iter()
+                .flatten()
+                .flat_map(|side| update_sets(side.syntax()))
+                .collect();
+        }
+        Some(expr @ (Expr::Assert(_) | Expr::With(_))) => {
+            let body = unwrap_scopes(expr);
+            if body.syntax() != node {
+                return update_sets(body.syntax());
+            }
+        }
+        _ => (),
+    }
+
+    node.preorder()
+        .find_map(|ev| match ev {
+            WalkEvent::Enter(n) => AttrSet::cast(n),
+            _ => None,
+        })
+        .into_iter()
+        .collect()
+}
+
+/// Collect the entries of the attribute set `node` evaluates to. Both sides
+/// of `//` are collected, the right one last.
+fn collect_bindings(node: &SyntaxNode, opts: &CollectOptions, scope: &Scope) -> Vec<ManualEntry> {
+    update_sets(node)
+        .iter()
+        .flat_map(|set| collect_set_entries(set.syntax(), opts, scope, "", 0))
+        .collect()
+}
+
+/// Collect the entries of an attribute set, naming them `path` followed by
+/// their attribute path. Nested attribute sets are walked until `opts.depth`
+/// levels below the top-level set.
+fn collect_set_entries(
+    set: &SyntaxNode,
+    opts: &CollectOptions,
+    scope: &Scope,
+    path: &str,
+    depth: usize,
+) -> Vec<ManualEntry> {
+    // Members of `rec` sets can be inherited from by their siblings.
+    let rec_scope;
+    let scope = match AttrSet::cast(set.clone()) {
+        Some(attrs) if attrs.rec_token().is_some() => {
+            rec_scope = scope.with_rec_members(&attrs);
+            &rec_scope
+        }
+        _ => scope,
+    };
+    let mut entries: Vec<ManualEntry> = vec![];
+    let mut section: Option<String> = None;
+    for element in set.children_with_tokens() {
+        let child = match element {
+            rnix::NodeOrToken::Node(node) => node,
+            rnix::NodeOrToken::Token(token) => {
+                if let Some(text) = comment::get_section_comment(&token) {
+                    section = handle_indentation(&text);
+                }
+                continue;
+            }
+        };
+        let first_new = entries.len();
+        if comment::has_ignore_marker(&child) {
+            continue;
+        }
+        if let Some(apv) = AttrpathValue::cast(child.clone()) {
+            let nested = match apv.value() {
+                Some(Expr::AttrSet(nested)) if depth < opts.depth => Some(nested),
+                _ => None,
+            };
+            let Some(name) = apv.attrpath().as_ref().and_then(attrpath_name) else {
+                continue;
+            };
+            // Documented as part of the callable set it belongs to.
+            if name == "__functor" {
+                continue;
+            }
+            let selection = match apv.value() {
+                Some(Expr::Select(select)) => Some(select),
+                _ => None,
+            };
+            match collect_entry_information(apv, opts) {
+                Some(mut di) => {
+                    di.name = format!("{}{}", path, di.name);
+                    entries.push(di.into_entry(opts));
+                }
+                // `name = helpers.name;` is documented where it is selected from.
+                None => entries.extend(
+                    selection
+                        .and_then(|select| selected_entry(&select, opts, scope))
+                        .map(|mut entry| {
+                            entry.name = format!("{}{}", path, name);
+                            entry
+                        }),
+                ),
+            }
+            if let Some(nested) = nested {
+                if !meta::EntryMeta::from_value(&Expr::AttrSet(nested.clone())).internal {
+                    let path = format!("{}{}.", path, name);
+                    entries.extend(collect_set_entries(
+                        nested.syntax(),
+                        opts,
+                        scope,
+                        &path,
+                        depth + 1,
+                    ));
+                }
+            }
+        } else if let Some(inh) = Inherit::cast(child) {
+            let names = inh.attrs().filter_map(|attr| attr_name(&attr));
+            let inherited: Vec<ManualEntry> = match inh.from().and_then(|from| from.expr()) {
+                None => names
+                    .filter_map(|name| scope.entries.get(&name).cloned())
+                    .collect(),
+                Some(from) => {
+                    let available = resolve_inherit_source(from, opts, scope);
+                    names
+                        .filter_map(|name| available.iter().find(|e| e.name == name).cloned())
+                        .collect()
+                }
+            };
+            entries.extend(inherited.into_iter().map(|mut entry| {
+                entry.name = format!("{}{}", path, entry.name);
+                entry
+            }));
+        }
+        if section.is_some() {
+            for entry in &mut entries[first_new..] {
+                entry
+                    .section
+                    .get_or_insert_with(|| section.clone().unwrap_or_default());
+            }
+        }
+    }
+    entries
+}
+
+/// Collect the entries of the attribute set an `inherit (from)` statement
+/// takes its attributes from. Supported are imports of relative paths
+/// (resolved against `opts.base_dir`), let-bound attribute sets and
+/// attribute selections from those.
+fn resolve_inherit_source(from: Expr, opts: &CollectOptions, scope: &Scope) -> Vec<ManualEntry> {
+    match from {
+        Expr::Paren(paren) => paren
+            .expr()
+            .map(|inner| resolve_inherit_source(inner, opts, scope))
+            .unwrap_or_default(),
+        Expr::AttrSet(set) => collect_set_entries(set.syntax(), opts, scope, "", 0),
+        Expr::Ident(ident) => resolve_selection(&ident.to_string(), "", opts, scope),
+        Expr::Select(select) => {
+            let (Some(Expr::Ident(base)), Some(path)) = (select.expr(), select.attrpath()) else {
+                return vec![];
+            };
+            resolve_selection(&base.to_string(), &path.to_string(), opts, scope)
+        }
+        Expr::Apply(_) => match import_path(&from) {
+            Some((path, 0)) => import_entries(&path, opts, scope),
+            _ => vec![],
+        },
+        _ => vec![],
+    }
+}
+
+/// Collect the entries of the attribute set at the dotted `path` (empty for
+/// the binding itself) within the binding `base` of the scope.
+fn resolve_selection(
+    base: &str,
+    path: &str,
+    opts: &CollectOptions,
+    scope: &Scope,
+) -> Vec<ManualEntry> {
+    scope.resolve_value(base, |value| match value {
+        _ if path.is_empty() => resolve_inherit_source(value.clone(), opts, scope),
+        Expr::AttrSet(set) => meta::attr_value(set, path)
+            .map(|value| resolve_inherit_source(value, opts, scope))
+            .unwrap_or_default(),
+        _ => vec![],
+    })
+}
+
+/// The entry an attribute selection such as `helpers.nested.id` refers to,
+/// if it selects from a binding of the scope.
+fn selected_entry(select: &Select, opts: &CollectOptions, scope: &Scope) -> Option<ManualEntry> {
+    let Expr::Ident(base) = select.expr()? else {
+        return None;
+    };
+    let attrs: Vec<String> = select.attrpath()?.attrs().map(|a| a.to_string()).collect();
+    let (name, path) = attrs.split_last()?;
+    resolve_selection(&base.to_string(), &path.join("."), opts, scope)
+        .into_iter()
+        .find(|entry| entry.name == *name)
+}
+
+/// Collect the entries of a file imported by a relative path.
+fn import_entries(path: &str, opts: &CollectOptions, scope: &Scope) -> Vec<ManualEntry> {
+    let Some(file) = resolve_import(path, opts) else {
+        return vec![];
+    };
+    if scope.imported.contains(&file) {
+        return vec![];
+    }
+    let Some(root) = fs::read_to_string(&file)
+        .ok()
+        .and_then(|source| rnix::Root::parse(&source).ok().ok())
+    else {
+        return vec![];
+    };
+
+    let opts = CollectOptions {
+        file: opts.file.as_ref().map(|_| file.display().to_string()),
+        base_dir: file.parent().map(Path::to_path_buf),
+        export: None,
+        ..opts.clone()
+    };
+    let mut imported = scope.imported.clone();
+    imported.push(file);
+    collect_root_entries(root, &opts, imported)
+}
+
+/// Given a let-in expression and an identifier name, find the corresponding
+/// AttrpathValue binding in the let block.
+fn find_let_binding(let_in: &LetIn, name: &str) -> Option<AttrpathValue> {
+    for entry in let_in.entries() {
+        if let Some(apv) = AttrpathValue::cast(entry.syntax().clone()) {
+            if let Some(path) = apv.attrpath() {
+                if attrpath_name(&path).as_deref() == Some(name) {
+                    return Some(apv);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Resolve an identifier in the context of a let-in expression.
+fn resolve_let_ident(let_in: &LetIn, ident: &Ident) -> Option<SyntaxNode> {
+    let name = ident.to_string();
+    let apv = find_let_binding(let_in, &name)?;
+    let value = unwrap_scopes(apv.value()?);
+
+    if let Expr::Ident(ref inner_ident) = value {
+        resolve_let_ident(let_in, inner_ident)
+    } else {
+        Some(value.syntax().clone())
+    }
+}
+
+/// Collect all documented entries of a parsed Nix file that pass the
+/// include and exclude filters of `opts`.
+///
+/// If `opts.export` is given, the named bindings of the top-level let block
+/// are documented instead of the attribute set the file evaluates to.
+///
+/// Bindings defined more than once are documented by their last definition;
+/// see [`collect_entries_with_duplicates`] to report them.
+pub fn collect_entries(root: rnix::Root, opts: &CollectOptions) -> Vec<ManualEntry> {
+    collect_entries_with_duplicates(root, opts).0
+}
+
+/// A binding defined more than once, e.g. on both sides of `//`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Duplicate {
+    pub name: String,
+    /// Location of the definition that is overridden.
+    pub shadowed: String,
+    /// Location of the definition that is documented.
+    pub effective: String,
+}
+
+/// Like [`collect_entries`], but also returns the bindings that are defined
+/// more than once.
+pub fn collect_entries_with_duplicates(
+    root: rnix::Root,
+    opts: &CollectOptions,
+) -> (Vec<ManualEntry>, Vec<Duplicate>) {
+    let (mut entries, duplicates) = dedupe_entries(collect_root_entries(root, opts, vec![]));
+    entries.retain(|entry| glob::filter_name(&entry.name, &opts.include, &opts.exclude));
+    (entries, duplicates)
+}
+
+/// Replace every entry defined more than once by its last definition, kept
+/// in the position of the first one so anchors stay unique.
+fn dedupe_entries(entries: Vec<ManualEntry>) -> (Vec<ManualEntry>, Vec<Duplicate>) {
+    let mut deduped: Vec<ManualEntry> = vec![];
+    let mut duplicates = vec![];
+    for entry in entries {
+        match deduped.iter_mut().find(|e| e.name == entry.name) {
+            Some(existing) => {
+                duplicates.push(Duplicate {
+                    name: entry.name.clone(),
+                    shadowed: describe_location(existing),
+                    effective: describe_location(&entry),
+                });
+                *existing = entry;
+            }
+            None => deduped.push(entry),
+        }
+    }
+    (deduped, duplicates)
+}
+
+/// The location of an entry, or its line if it has none.
+fn describe_location(entry: &ManualEntry) -> String {
+    match (&entry.location, &entry.span) {
+        (Some(location), _) => location.trim_matches('`').to_string(),
+        (None, Some(span)) => format!("line {}", span.start_line),
+        (None, None) => "unknown location".to_string(),
+    }
+}
+
+/// Find the expression evaluating to the documented attribute set of a
+/// file, and the top-level let block it is the body of, if any. Let bodies
+/// naming one of the let bindings resolve to that binding's value.
+fn documented_expr(root: &rnix::Root) -> Option<(Option<LetIn>, SyntaxNode)> {
+    let mut preorder = root.syntax().preorder();
+    while let Some(ev) = preorder.next() {
+        match ev {
+            WalkEvent::Enter(n) if n.kind() == SyntaxKind::NODE_PATTERN => {
+                preorder.skip_subtree();
+            }
+            WalkEvent::Enter(n) if n.kind() == SyntaxKind::NODE_LET_IN => {
+                let let_in = LetIn::cast(n).unwrap();
+                let body = unwrap_scopes(let_in.body()?);
+                let body = match &body {
+                    Expr::Ident(ident) => resolve_let_ident(&let_in, ident),
+                    _ => None,
+                }
+                .unwrap_or_else(|| body.syntax().clone());
+                return Some((Some(let_in), body));
+            }
+            WalkEvent::Enter(n) if is_scope(&n) => {
+                let body = unwrap_scopes(Expr::cast(n.clone()).unwrap());
+                if body.syntax() != &n {
+                    preorder = body.syntax().preorder();
+                }
+            }
+            WalkEvent::Enter(n)
+                if n.kind() == SyntaxKind::NODE_ATTR_SET || n.kind() == SyntaxKind::NODE_BIN_OP =>
+            {
+                return Some((None, n));
+            }
+            _ => (),
+        }
+    }
+
+    None
+}
+
+fn collect_root_entries(
+    root: rnix::Root,
+    opts: &CollectOptions,
+    imported: Vec<PathBuf>,
+) -> Vec<ManualEntry> {
+    let Some((let_in, body)) = documented_expr(&root) else {
+        return vec![];
+    };
+    let Some(let_in) = let_in else {
+        let scope = Scope {
+            imported,
+            ..Default::default()
+        };
+        return collect_bindings(&body, opts, &scope);
+    };
+
+    let n = let_in.syntax();
+    let scope = Scope {
+        entries: n
+            .children()
+            .filter(|child| !comment::has_ignore_marker(child))
+            .filter_map(AttrpathValue::cast)
+            .filter_map(|apv| collect_entry_information(apv, opts))
+            .map(|di| (di.name.to_string(), di.into_entry(opts)))
+            .collect(),
+        values: n
+            .children()
+            .filter_map(AttrpathValue::cast)
+            .filter_map(|apv| Some((attrpath_name(&apv.attrpath()?)?, apv.value()?)))
+            .collect(),
+        imported,
+        ..Default::default()
+    };
+
+    if let Some(ref exports) = opts.export {
+        return exports
+            .iter()
+            .filter_map(|name| scope.entries.get(name).cloned())
+            .collect();
+    }
+
+    collect_bindings(&body, opts, &scope)
+}
+
+/// Find the bindings that [`collect_entries`] would document: the bindings of
+/// the attribute sets the file evaluates to, or the named let bindings if
+/// `export` is given. Bindings marked with `# nixdoc-ignore` are left out.
+pub(crate) fn exported_bindings(
+    root: &rnix::Root,
+    export: &Option<Vec<String>>,
+) -> Vec<AttrpathValue> {
+    let Some((let_in, body)) = documented_expr(root) else {
+        return vec![];
+    };
+    if let (Some(let_in), Some(exports)) = (let_in, export) {
+        return exports
+            .iter()
+            .filter_map(|name| find_let_binding(&let_in, name))
+            .filter(|apv| !comment::has_ignore_marker(apv.syntax()))
+            .collect();
+    }
+
+    update_sets(&body)
+        .iter()
+        .flat_map(|set| {
+            set.syntax()
+                .children()
+                .filter(|child| !comment::has_ignore_marker(child))
+                .filter_map(AttrpathValue::cast)
+        })
+        .collect()
+}
+
+/// An attribute of the documented attribute set inherited from a binding or
+/// file that does not exist, so it cannot be documented.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnresolvedInherit {
+    pub name: String,
+    /// The source it is inherited from, e.g. `(import ./missing.nix)`, or
+    /// nothing for a plain `inherit name;`.
+    pub from: Option<String>,
+    /// Byte offset of the `inherit` statement in the source.
+    pub offset: usize,
+}
+
+/// Find the names `inherit` statements of the documented attribute set take
+/// from nowhere: plain inherits of names not bound by the top-level let
+/// block (or a `rec` set), and inherits from relative imports of files that
+/// cannot be read. Inherits from function arguments such as `lib` are
+/// assumed to resolve.
+pub fn unresolved_inherits(root: &rnix::Root, opts: &CollectOptions) -> Vec<UnresolvedInherit> {
+    if opts.export.is_some() {
+        return vec![];
+    }
+    let Some((let_in, body)) = documented_expr(root) else {
+        return vec![];
+    };
+    let mut bound: Vec<String> = vec![];
+    for entry in let_in.iter().flat_map(|let_in| let_in.entries()) {
+        match entry {
+            rnix::ast::Entry::AttrpathValue(apv) => {
+                bound.extend(apv.attrpath().as_ref().and_then(attrpath_name));
+            }
+            rnix::ast::Entry::Inherit(inh) => {
+                bound.extend(inh.attrs().filter_map(|attr| attr_name(&attr)));
+            }
+        }
+    }
+
+    let mut unresolved = vec![];
+    for set in update_sets(&body) {
+        // Members of a `rec` set are only in scope of that set.
+        let mut bound = bound.clone();
+        if set.rec_token().is_some() {
+            bound.extend(
+                set.attrpath_values()
+                    .filter_map(|apv| attrpath_name(&apv.attrpath()?)),
+            );
+        }
+        for inh in set.inherits() {
+            let from = inh.from().and_then(|from| from.expr());
+            let missing_import = from
+                .as_ref()
+                .and_then(import_path)
+                .is_some_and(|(path, _)| {
+                    resolve_import(&path, opts).is_some_and(|file| {
+                        fs::read_to_string(file)
+                            .ok()
+                            .and_then(|source| rnix::Root::parse(&source).ok().ok())
+                            .is_none()
+                    })
+                });
+            if from.is_some() && !missing_import {
+                continue;
+            }
+            for name in inh.attrs().filter_map(|attr| attr_name(&attr)) {
+                if from.is_none() && bound.contains(&name) {
+                    continue;
+                }
+                unresolved.push(UnresolvedInherit {
+                    name,
+                    from: inh.from().map(|from| from.syntax().to_string()),
+                    offset: inh.syntax().text_range().start().into(),
+                });
+            }
+        }
+    }
+    unresolved
+}
+
+/// Extract just the file-level documentation comment from a Nix file.
+pub fn extract_file_doc(nix: &rnix::Root) -> Option<String> {
+    nix.syntax()
+        .first_child()
+        .and_then(|node| retrieve_doc_comment(&node, Some(0)))
+        .and_then(|doc_item| handle_indentation(&doc_item))
+}
+
+/// Render the category heading followed by the file-level doc comment.
+pub fn retrieve_description(nix: &rnix::Root, description: &str, category: &str) -> String {
+    describe_category(description, category, extract_file_doc(nix).as_deref())
+}
+
+/// Render the category heading followed by a file-level doc comment.
+pub fn describe_category(description: &str, category: &str, file_doc: Option<&str>) -> String {
+    if description.is_empty() && category.is_empty() {
+        return String::new();
+    }
+    format!(
+        "# {} {{#sec-functions-library-{}}}\n{}\n",
+        description,
+        category,
+        file_doc.unwrap_or_default()
+    )
+}
+
+/// Infer the category of a file from its name, e.g. `strings` for
+/// `strings.nix`, and its description from the first heading of its
+/// file-level doc comment, falling back to the category. Returns the
+/// category, the description and the doc comment without that heading.
+pub fn infer_category(path: &Path, nix: &rnix::Root) -> (String, String, Option<String>) {
+    let category = batch::category_for(path);
+    match extract_file_doc(nix) {
+        Some(doc) => match format::split_title(&doc) {
+            Some((title, rest)) => (category, title, Some(rest).filter(|r| !r.is_empty())),
+            None => (category.clone(), category, Some(doc)),
+        },
+        None => (category.clone(), category, None),
+    }
+}
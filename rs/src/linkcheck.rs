@@ -0,0 +1,99 @@
+//! Validation of the links of rendered markdown.
+//!
+//! Links to anchors of the same document (`[foo](#opt-foo)`) must point to
+//! an anchor the document defines, see [`check_anchors`]. Links to other
+//! websites can additionally be checked by requesting them with a
+//! [`LinkChecker`], which [`CurlChecker`] does with `curl`.
+
+use std::collections::BTreeSet;
+use std::process::Command;
+
+use rayon::prelude::*;
+
+use crate::linkgraph::{collect_anchors, collect_link_targets};
+
+/// A link that does not lead anywhere.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenLink {
+    /// The destination as written, e.g. `#opt-foo`.
+    pub target: String,
+    /// Why the destination could not be reached.
+    pub reason: String,
+}
+
+impl BrokenLink {
+    /// Human-readable description of the problem.
+    pub fn message(&self) -> String {
+        format!("broken link to `{}`: {}", self.target, self.reason)
+    }
+}
+
+/// Find the links to anchors of the document itself that it does not
+/// define. Each missing anchor is reported once.
+pub fn check_anchors(markdown: &str) -> Vec<BrokenLink> {
+    let anchors: BTreeSet<String> = collect_anchors(markdown).into_iter().collect();
+    let mut seen = BTreeSet::new();
+    collect_link_targets(markdown)
+        .into_iter()
+        .filter(|target| {
+            target
+                .strip_prefix('#')
+                .is_some_and(|anchor| !anchors.contains(anchor))
+        })
+        .filter(|target| seen.insert(target.clone()))
+        .map(|target| BrokenLink {
+            target,
+            reason: "no such anchor in the document".to_string(),
+        })
+        .collect()
+}
+
+/// Checks whether a URL can be reached.
+pub trait LinkChecker: Sync {
+    fn check(&self, url: &str) -> Result<(), String>;
+}
+
+/// Requests URLs with `curl`, following redirects and failing on HTTP
+/// errors or after `timeout` seconds.
+#[derive(Debug, Clone)]
+pub struct CurlChecker {
+    pub timeout: u64,
+}
+
+impl LinkChecker for CurlChecker {
+    fn check(&self, url: &str) -> Result<(), String> {
+        let output = Command::new("curl")
+            .args(["--silent", "--show-error", "--fail", "--location", "--head"])
+            .args(["--output", "/dev/null", "--max-time"])
+            .arg(self.timeout.to_string())
+            .arg(url)
+            .output()
+            .map_err(|e| format!("could not run curl: {}", e))?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(String::from_utf8_lossy(&output.stderr)
+                .trim()
+                .trim_start_matches("curl: ")
+                .to_string())
+        }
+    }
+}
+
+/// Check the `http` and `https` links of a document with `checker`, in
+/// parallel. Each URL is requested once.
+pub fn check_external(markdown: &str, checker: &dyn LinkChecker) -> Vec<BrokenLink> {
+    let urls: BTreeSet<String> = collect_link_targets(markdown)
+        .into_iter()
+        .filter(|target| target.starts_with("http://") || target.starts_with("https://"))
+        .collect();
+    urls.into_par_iter()
+        .filter_map(|url| {
+            let reason = checker.check(&url).err()?;
+            Some(BrokenLink {
+                target: url,
+                reason,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,197 @@
+//! Link graph of a set of rendered markdown documents.
+//!
+//! Collects the anchors every document defines (`{#id}` attributes on
+//! headings and fenced divs) and the links between documents, so the
+//! navigation structure of a manual can be exported as JSON or DOT.
+
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use serde::Serialize;
+
+use crate::format::get_fence;
+
+/// A rendered document and the anchors it defines.
+#[derive(Debug, Clone, Serialize)]
+pub struct Document {
+    pub path: String,
+    pub anchors: Vec<String>,
+}
+
+/// A link from one document to a document and/or anchor.
+#[derive(Debug, Clone, Serialize)]
+pub struct Link {
+    pub from: String,
+    pub to: String,
+    pub anchor: Option<String>,
+    /// Whether the link points into the document it appears in.
+    pub internal: bool,
+    /// Whether the target document (and anchor, if any) exists.
+    pub resolved: bool,
+}
+
+/// All documents of a manual and the links between them.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LinkGraph {
+    pub documents: Vec<Document>,
+    pub links: Vec<Link>,
+    /// Documents that no other document links to.
+    pub orphans: Vec<String>,
+}
+
+impl LinkGraph {
+    /// Build the graph from `(path, markdown)` pairs. Paths are used as
+    /// document identifiers and to resolve relative links.
+    pub fn build(documents: &[(String, String)]) -> Self {
+        let mut graph = LinkGraph {
+            documents: documents
+                .iter()
+                .map(|(path, text)| Document {
+                    path: path.clone(),
+                    anchors: collect_anchors(text),
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        for (path, text) in documents {
+            for target in collect_link_targets(text) {
+                if target.contains("://") || target.starts_with("mailto:") {
+                    continue;
+                }
+                let (file, anchor) = match target.split_once('#') {
+                    Some((file, anchor)) => (file, Some(anchor.to_string())),
+                    None => (target.as_str(), None),
+                };
+                let to = if file.is_empty() {
+                    path.clone()
+                } else {
+                    resolve_relative(path, file)
+                };
+                let resolved = graph
+                    .documents
+                    .iter()
+                    .any(|d| d.path == to && anchor.as_ref().is_none_or(|a| d.anchors.contains(a)));
+                graph.links.push(Link {
+                    internal: &to == path,
+                    from: path.clone(),
+                    to,
+                    anchor,
+                    resolved,
+                });
+            }
+        }
+
+        let linked: BTreeSet<&str> = graph
+            .links
+            .iter()
+            .filter(|l| !l.internal)
+            .map(|l| l.to.as_str())
+            .collect();
+        graph.orphans = graph
+            .documents
+            .iter()
+            .filter(|d| !linked.contains(d.path.as_str()))
+            .map(|d| d.path.clone())
+            .collect();
+
+        graph
+    }
+
+    /// Render the graph in Graphviz DOT format, with one node per document
+    /// and one edge per inter-document link.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph manual {\n");
+        for doc in &self.documents {
+            out.push_str(&format!(
+                "  {} [label={}];\n",
+                dot_id(&doc.path),
+                dot_id(&format!("{} ({} anchors)", doc.path, doc.anchors.len()))
+            ));
+        }
+        for link in self.links.iter().filter(|l| !l.internal) {
+            let style = if link.resolved { "" } else { " [style=dashed]" };
+            out.push_str(&format!(
+                "  {} -> {}{};\n",
+                dot_id(&link.from),
+                dot_id(&link.to),
+                style
+            ));
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Quote `id` as a DOT identifier. Only quotes and backslashes are escaped;
+/// other characters, including non-ASCII ones, are allowed in quoted IDs.
+fn dot_id(id: &str) -> String {
+    format!("\"{}\"", id.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Collect the ids of all `{#id}` / `{.class #id}` attribute blocks outside of
+/// code blocks.
+pub fn collect_anchors(text: &str) -> Vec<String> {
+    let mut anchors = vec![];
+    let mut fence: Option<(usize, char)> = None;
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if let Some((count, ch)) = fence {
+            if get_fence(trimmed, false).is_some_and(|(c, h)| h == ch && c >= count) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = get_fence(trimmed, true);
+            continue;
+        }
+
+        let mut rest = line;
+        while let Some(start) = rest.find('{') {
+            let Some(len) = rest[start..].find('}') else {
+                break;
+            };
+            let attrs = &rest[start + 1..start + len];
+            anchors.extend(
+                attrs
+                    .split_whitespace()
+                    .filter_map(|a| a.strip_prefix('#'))
+                    .filter(|a| !a.is_empty())
+                    .map(str::to_string),
+            );
+            rest = &rest[start + len + 1..];
+        }
+    }
+
+    anchors
+}
+
+/// Collect the destinations of all markdown links.
+pub fn collect_link_targets(text: &str) -> Vec<String> {
+    Parser::new_ext(text, Options::empty())
+        .filter_map(|event| match event {
+            Event::Start(Tag::Link { dest_url, .. }) => Some(dest_url.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Resolve `target` relative to the directory of the document at `from`,
+/// normalizing `.` and `..` components.
+fn resolve_relative(from: &str, target: &str) -> String {
+    let base = Path::new(from).parent().unwrap_or(Path::new(""));
+    let mut resolved = PathBuf::new();
+    for component in base.join(target).components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+    resolved.to_string_lossy().into_owned()
+}
@@ -0,0 +1,348 @@
+//! Documentation coverage checks for Nix files.
+//!
+//! Reports exported bindings that have no doc comment, whose doc comment is
+//! empty, or that are documented with a regular comment which could be
+//! upgraded to an RFC145 doc comment. In strict mode, documented bindings
+//! without an `# Example` section are reported as well. Bindings marked with
+//! a `# nixdoc-ignore` comment are intentionally undocumented and not checked.
+//! [`coverage_source`] counts the bindings by how they are documented.
+//!
+//! Checks can be suppressed with an `@allow` tag listing check names, either
+//! in the doc comment of an entry, in a regular comment made up only of tags
+//! (for undocumented entries), or in the file doc comment for the whole file:
+//!
+//! ```nix
+//! {
+//!   /**
+//!     Frobnicate a value.
+//!
+//!     @allow missing-example
+//!   */
+//!   frobnicate = x: x;
+//!
+//!   # @allow missing-doc
+//!   helper = x: x;
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use rnix::ast::AttrpathValue;
+use rowan::ast::AstNode;
+use serde::Serialize;
+
+use crate::comment::{get_expr_docs, get_legacy_comment};
+use crate::examples::split_examples;
+use crate::format::line_column;
+use crate::glob::glob_match;
+use crate::meta::EntryMeta;
+use crate::tags::{extract_tags, Tag};
+use crate::{exported_bindings, extract_file_doc, functor_binding};
+
+/// The kind of problem found with a binding's documentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LintKind {
+    /// The binding has no comment at all.
+    MissingDoc,
+    /// The binding has a doc comment without any text.
+    EmptyDoc,
+    /// The binding is documented with a `#` or `/* */` comment.
+    LegacyComment,
+    /// The doc comment has no `# Example` section (strict mode only).
+    MissingExample,
+}
+
+impl LintKind {
+    /// Look up a check by its name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "missing-doc" => Some(Self::MissingDoc),
+            "empty-doc" => Some(Self::EmptyDoc),
+            "legacy-comment" => Some(Self::LegacyComment),
+            "missing-example" => Some(Self::MissingExample),
+            _ => None,
+        }
+    }
+
+    /// Name of the check, as used in output and `@allow` tags.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::MissingDoc => "missing-doc",
+            Self::EmptyDoc => "empty-doc",
+            Self::LegacyComment => "legacy-comment",
+            Self::MissingExample => "missing-example",
+        }
+    }
+}
+
+/// A suppressed check for the entries matching a glob.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Allow {
+    pub pattern: String,
+    pub kind: LintKind,
+}
+
+/// Settings for [`lint_source`].
+#[derive(Debug, Clone, Default)]
+pub struct LintOptions {
+    /// Let bindings to check instead of the returned attribute set.
+    pub export: Option<Vec<String>>,
+    /// Also report documented bindings without an example.
+    pub strict: bool,
+    /// Checks suppressed by configuration rather than `@allow` tags.
+    pub allow: Vec<Allow>,
+}
+
+/// Load suppressions from a JSON file mapping entry name globs to the
+/// names of the checks allowed for them, e.g.
+/// `{ "*": ["missing-example"], "internal*": ["missing-doc"] }`.
+pub fn load_allow_config(path: &Path) -> Result<Vec<Allow>, String> {
+    let json = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let config: HashMap<String, Vec<String>> =
+        serde_json::from_str(&json).map_err(|e| e.to_string())?;
+    let mut allow = vec![];
+    for (pattern, names) in config {
+        for name in names {
+            let kind = LintKind::from_name(&name)
+                .ok_or_else(|| format!("unknown check '{}' for '{}'", name, pattern))?;
+            allow.push(Allow {
+                pattern: pattern.clone(),
+                kind,
+            });
+        }
+    }
+    allow.sort_by(|a, b| a.pattern.cmp(&b.pattern));
+    Ok(allow)
+}
+
+/// Checks named in the `@allow` tags among `tags`.
+fn allowed_kinds(tags: &[Tag]) -> Vec<LintKind> {
+    tags.iter()
+        .filter(|t| t.name == "allow")
+        .flat_map(|t| t.value.split([',', ' ']))
+        .filter_map(LintKind::from_name)
+        .collect()
+}
+
+/// Whether a doc comment has examples, see [`split_examples`].
+fn has_example(doc: &str) -> bool {
+    !split_examples(doc).1.is_empty()
+}
+
+/// A documentation problem of a binding, with a 1-based position.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub name: String,
+    pub line: usize,
+    pub column: usize,
+    pub kind: LintKind,
+}
+
+impl Diagnostic {
+    /// Human-readable description of the problem.
+    pub fn message(&self) -> String {
+        match self.kind {
+            LintKind::MissingDoc => format!("`{}` has no doc comment", self.name),
+            LintKind::EmptyDoc => format!("`{}` has an empty doc comment", self.name),
+            LintKind::LegacyComment => format!(
+                "`{}` is documented with a regular comment, use a `/** */` doc comment",
+                self.name
+            ),
+            LintKind::MissingExample => format!("`{}` has no example", self.name),
+        }
+    }
+}
+
+/// Check the documentation of every exported binding of a Nix source.
+///
+/// Bindings marked as internal (see [`crate::meta`]) are not checked.
+pub fn lint_source(source: &str, opts: &LintOptions) -> Result<Vec<Diagnostic>, String> {
+    let nix = rnix::Root::parse(source)
+        .ok()
+        .map_err(|e| format!("failed to parse input: {}", e))?;
+    let file_allowed = extract_file_doc(&nix)
+        .map(|doc| allowed_kinds(&extract_tags(&doc, &[]).1))
+        .unwrap_or_default();
+
+    let mut diagnostics = vec![];
+    for apv in exported_bindings(&nix, &opts.export) {
+        let Some(name) = apv.attrpath().map(|p| p.to_string()) else {
+            continue;
+        };
+        let Some((kind, allowed)) = check_binding(&apv, opts.strict) else {
+            continue;
+        };
+        let configured = opts
+            .allow
+            .iter()
+            .any(|a| a.kind == kind && glob_match(&a.pattern, &name));
+        if configured || allowed.contains(&kind) || file_allowed.contains(&kind) {
+            continue;
+        }
+
+        let (line, column) = line_column(source, apv.syntax().text_range().start().into());
+        diagnostics.push(Diagnostic {
+            name,
+            line,
+            column,
+            kind,
+        });
+    }
+    Ok(diagnostics)
+}
+
+/// Counts of the exported bindings of one or more sources by how they are
+/// documented.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Coverage {
+    /// All exported bindings, except internal ones.
+    pub total: usize,
+    /// Bindings with a doc comment or a legacy comment.
+    pub documented: usize,
+    /// Bindings without a comment or with an empty doc comment.
+    pub undocumented: usize,
+    /// Bindings documented with a `#` or `/* */` comment.
+    pub legacy: usize,
+    /// Bindings documented with an RFC145 `/** */` doc comment.
+    pub rfc145: usize,
+}
+
+impl Coverage {
+    /// Percentage of documented bindings; 100 if there are no bindings.
+    pub fn percent(&self) -> f64 {
+        if self.total == 0 {
+            100.0
+        } else {
+            self.documented as f64 * 100.0 / self.total as f64
+        }
+    }
+
+    /// Add the counts of `other`, e.g. of another file.
+    pub fn add(&mut self, other: &Coverage) {
+        self.total += other.total;
+        self.documented += other.documented;
+        self.undocumented += other.undocumented;
+        self.legacy += other.legacy;
+        self.rfc145 += other.rfc145;
+    }
+}
+
+/// Count the exported bindings of a Nix source by how they are documented.
+/// `@allow` tags are not taken into account.
+pub fn coverage_source(source: &str, export: &Option<Vec<String>>) -> Result<Coverage, String> {
+    let nix = rnix::Root::parse(source)
+        .ok()
+        .map_err(|e| format!("failed to parse input: {}", e))?;
+
+    let mut coverage = Coverage::default();
+    for apv in exported_bindings(&nix, export) {
+        match check_binding(&apv, false) {
+            None if is_internal(&apv) => continue,
+            None => coverage.rfc145 += 1,
+            Some((LintKind::LegacyComment, _)) => coverage.legacy += 1,
+            Some(_) => coverage.undocumented += 1,
+        }
+        coverage.total += 1;
+    }
+    coverage.documented = coverage.rfc145 + coverage.legacy;
+    Ok(coverage)
+}
+
+/// Render the coverage of each file and their total as an aligned table.
+pub fn coverage_table(files: &[(String, Coverage)], total: &Coverage) -> String {
+    let header = [
+        "File",
+        "Total",
+        "Documented",
+        "Undocumented",
+        "Legacy",
+        "RFC145",
+        "Coverage",
+    ]
+    .map(str::to_string);
+    let row = |name: &str, c: &Coverage| {
+        [
+            name.to_string(),
+            c.total.to_string(),
+            c.documented.to_string(),
+            c.undocumented.to_string(),
+            c.legacy.to_string(),
+            c.rfc145.to_string(),
+            format!("{:.1}%", c.percent()),
+        ]
+    };
+    let mut rows = vec![header];
+    rows.extend(files.iter().map(|(name, c)| row(name, c)));
+    rows.push(row("total", total));
+
+    let widths: Vec<usize> = (0..rows[0].len())
+        .map(|i| rows.iter().map(|r| r[i].chars().count()).max().unwrap_or(0))
+        .collect();
+    let mut out = String::new();
+    for row in &rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(i, (cell, &width))| {
+                // Names are left-aligned, numbers right-aligned.
+                if i == 0 {
+                    format!("{:<width$}", cell)
+                } else {
+                    format!("{:>width$}", cell)
+                }
+            })
+            .collect();
+        out.push_str(cells.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+/// Whether a binding is marked as internal and exempt from checks.
+fn is_internal(apv: &AttrpathValue) -> bool {
+    apv.value()
+        .is_some_and(|value| EntryMeta::from_value(&value).internal)
+}
+
+/// Find the problem with a binding's documentation, if any, together with
+/// the checks its comment allows.
+fn check_binding(apv: &AttrpathValue, strict: bool) -> Option<(LintKind, Vec<LintKind>)> {
+    if is_internal(apv) {
+        return None;
+    }
+
+    // Callable sets may be documented on their `__functor`.
+    let functor_doc = || {
+        let functor = functor_binding(&apv.value()?)?;
+        get_expr_docs(functor.syntax())
+    };
+    if let Some(doc) = get_expr_docs(apv.syntax()).or_else(functor_doc) {
+        let (text, tags) = extract_tags(&doc, &[]);
+        let allowed = allowed_kinds(&tags);
+        return if text.trim().is_empty() {
+            Some((LintKind::EmptyDoc, allowed))
+        } else if strict && !has_example(&text) {
+            Some((LintKind::MissingExample, allowed))
+        } else {
+            None
+        };
+    }
+
+    match get_legacy_comment(apv.syntax()) {
+        Some(comment) => {
+            let (text, tags) = extract_tags(comment.text(), &[]);
+            // A comment made up only of tags is not documentation.
+            let kind = if text.trim().is_empty() && !tags.is_empty() {
+                LintKind::MissingDoc
+            } else {
+                LintKind::LegacyComment
+            };
+            Some((kind, allowed_kinds(&tags)))
+        }
+        None => Some((LintKind::MissingDoc, vec![])),
+    }
+}
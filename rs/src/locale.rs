@@ -0,0 +1,136 @@
+//! Translations of the labels and sentences written around documentation,
+//! such as "Default:" or "Located at ...".
+//!
+//! A [`Locale`] provides the built-in translations of all of them. Single
+//! strings can be overridden from a TOML file with a table per kind of
+//! documentation, keyed by the names of [`EntryLabels`] and [`Labels`]:
+//!
+//! ```toml
+//! [functions]
+//! located-at = "Definiert in {location}."
+//!
+//! [options]
+//! declared-by = "Deklariert in"
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::commonmark::EntryLabels;
+use crate::options::Labels;
+
+/// A language with built-in translations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    English,
+    German,
+}
+
+impl Locale {
+    /// Look up a locale by its language code, e.g. `de`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "en" => Some(Self::English),
+            "de" => Some(Self::German),
+            _ => None,
+        }
+    }
+
+    /// Labels of the parts of library function entries.
+    pub fn entry_labels(&self) -> EntryLabels {
+        match self {
+            Self::English => EntryLabels::default(),
+            Self::German => EntryLabels {
+                stability: "Stabilität".to_string(),
+                deprecated: "Veraltet".to_string(),
+                entry_type: "Typ".to_string(),
+                value: "Wert".to_string(),
+                throws: "Fehlerfälle".to_string(),
+                maintainers: "Betreuer".to_string(),
+                usage_example: "Anwendungsbeispiel für {name}".to_string(),
+                since: "Verfügbar seit {version}.".to_string(),
+                located_at: "Definiert in {location}.".to_string(),
+                deprecated_functions: "Veraltete Funktionen".to_string(),
+                function: "Funktion".to_string(),
+                summary: "Zusammenfassung".to_string(),
+                all_functions: "Alle Funktionen".to_string(),
+                other: "Sonstige".to_string(),
+            },
+        }
+    }
+
+    /// Labels of the attributes of options.
+    pub fn option_labels(&self) -> Labels {
+        match self {
+            Self::English => Labels::default(),
+            Self::German => Labels {
+                option_type: "Typ".to_string(),
+                read_only: "schreibgeschützt".to_string(),
+                default: "Standardwert".to_string(),
+                example: "Beispiel".to_string(),
+                related_packages: "Verwandte Pakete".to_string(),
+                declared_by: "Deklariert in".to_string(),
+                origin: "Modulsammlung".to_string(),
+                contents: "Inhalt".to_string(),
+                index: "Index".to_string(),
+                types: "Typen".to_string(),
+                folded: "Alle {lines} Zeilen anzeigen".to_string(),
+                read_only_notice:
+                    "Diese Option ist schreibgeschützt und kann nicht in einer Konfiguration \
+                     gesetzt werden."
+                        .to_string(),
+                internal_notice: "Diese Option ist intern im Modulsystem und nicht dafür \
+                                  gedacht, von Nutzern gesetzt zu werden."
+                    .to_string(),
+                hidden_notice: "Diese Option ist in der Dokumentation verborgen.".to_string(),
+                experimental_notice: "Diese Option ist experimentell und kann sich ohne \
+                                      Ankündigung ändern oder entfernt werden."
+                    .to_string(),
+                stability: "Stabilität".to_string(),
+                alias_of: "Alias von {option}.".to_string(),
+                aliases: "Aliase".to_string(),
+            },
+        }
+    }
+}
+
+/// Contents of a strings file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StringsFile {
+    #[serde(default)]
+    functions: BTreeMap<String, String>,
+    #[serde(default)]
+    options: BTreeMap<String, String>,
+}
+
+/// Override the labels named in the TOML `source` of a strings file.
+pub fn parse_strings(
+    source: &str,
+    entry_labels: &mut EntryLabels,
+    option_labels: &mut Labels,
+) -> Result<(), String> {
+    let strings: StringsFile = toml::from_str(source).map_err(|e| e.to_string())?;
+    for (name, text) in &strings.functions {
+        entry_labels.set(name, text)?;
+    }
+    for (name, text) in &strings.options {
+        option_labels.set(name, text)?;
+    }
+    Ok(())
+}
+
+/// Override the labels named in the strings file at `path`.
+pub fn load_strings(
+    path: &Path,
+    entry_labels: &mut EntryLabels,
+    option_labels: &mut Labels,
+) -> Result<(), String> {
+    let source = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    parse_strings(&source, entry_labels, option_labels)
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
@@ -0,0 +1,83 @@
+//! Location data for `--locs`.
+//!
+//! nixpkgs generates its location data as a map from entry identifiers to
+//! rendered markdown links:
+//!
+//! ```json
+//! { "lib.strings.concat": "[lib/strings.nix:42](https://github.com/NixOS/nixpkgs/blob/master/lib/strings.nix#L42) in `<nixpkgs>`" }
+//! ```
+//!
+//! Other projects can provide structured locations instead, which are
+//! rendered like the locations nixdoc finds itself:
+//!
+//! ```json
+//! { "lib.strings.concat": { "file": "lib/strings.nix", "line": 42, "column": 3, "url": "https://example.org/strings.nix#L42" } }
+//! ```
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+/// Schema of a location data file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LocsFormat {
+    /// Rendered markdown, as generated by nixpkgs.
+    #[default]
+    Nixpkgs,
+    /// [`Location`] objects.
+    Structured,
+}
+
+impl LocsFormat {
+    /// Look up a format by its command line name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "nixpkgs" => Some(Self::Nixpkgs),
+            "structured" => Some(Self::Structured),
+            _ => None,
+        }
+    }
+}
+
+/// The location of an entry in a structured location data file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Location {
+    pub file: String,
+    #[serde(default)]
+    pub line: Option<usize>,
+    #[serde(default)]
+    pub column: Option<usize>,
+    /// Link to the location, e.g. in a source browser.
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+impl Location {
+    /// Render the location as markdown, e.g. `` [`lib/strings.nix:42`](url) ``.
+    pub fn to_markdown(&self) -> String {
+        let mut position = self.file.clone();
+        for n in [self.line, self.column].into_iter().map_while(|n| n) {
+            position.push_str(&format!(":{}", n));
+        }
+        let position = format!("`{}`", position);
+        match &self.url {
+            Some(url) => format!("[{}]({})", position, url),
+            None => position,
+        }
+    }
+}
+
+/// Parse location data, returning the markdown of each entry's location.
+pub fn parse_locs(json: &str, format: LocsFormat) -> Result<HashMap<String, String>, String> {
+    match format {
+        LocsFormat::Nixpkgs => serde_json::from_str(json).map_err(|e| e.to_string()),
+        LocsFormat::Structured => {
+            let locs: HashMap<String, Location> =
+                serde_json::from_str(json).map_err(|e| e.to_string())?;
+            Ok(locs
+                .into_iter()
+                .map(|(ident, location)| (ident, location.to_markdown()))
+                .collect())
+        }
+    }
+}
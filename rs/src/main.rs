@@ -16,30 +16,39 @@
 //! This tool generates CommonMark from a Nix file defining library
 //! functions, such as the files in `lib/` in the nixpkgs repository.
 
-mod comment;
-mod commonmark;
-mod format;
-mod options;
 #[cfg(test)]
 mod test;
 
-use crate::format::handle_indentation;
-
-use self::comment::get_expr_docs;
-use self::commonmark::*;
-use format::shift_headings;
-use rnix::{
-    SyntaxKind, SyntaxNode,
-    ast::{Attr, AttrpathValue, Expr, HasEntry, Ident, Inherit, Lambda, LetIn, Param},
+use nixdoc::admonition::{self, AdmonitionStyle};
+use nixdoc::cache::EntryCache;
+use nixdoc::commonmark::{
+    disambiguate_identifiers, sort_entries, stream_entries, write_deprecated_appendix,
+    write_entries_with, write_summary_table, EntryLabels, EntrySection, ManualEntry,
+};
+use nixdoc::diagnostics::{DiagnosticKind, Diagnostics, DiagnosticsFormat};
+use nixdoc::dialect::{self, MarkdownDialect};
+use nixdoc::format::{shift_headings, HeadingLevels};
+use nixdoc::linkgraph::LinkGraph;
+use nixdoc::mdcheck::MarkdownFlavor;
+use nixdoc::options::SortOrder;
+use nixdoc::source::{self, SourceLinks, SourceSpec};
+use nixdoc::template::{OptionsContext, Template};
+use nixdoc::{
+    arguments, asciidoc, attrpath, batch, collect_entries_with_duplicates, comment, compat,
+    describe_category, diff, docbook, doctest, eval, extract, extract_file_doc, filedoc, html,
+    infer_category, inventory, jsonv2, linkcheck, lint, locale, locs, man, mdbook, module, options,
+    optiontypes, plugin, read_source, rst, search, serve, signature, tagindex, tags, walk, xref,
+    CollectOptions, Duplicate, JsonFormat,
 };
-use rowan::{WalkEvent, ast::AstNode};
-use std::fs;
-
-use serde::Serialize;
 use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::net::TcpListener;
 
-use clap::Parser;
-use std::path::PathBuf;
+use clap::{CommandFactory, Parser};
+use rayon::prelude::*;
+use rowan::ast::AstNode;
+use std::path::{Path, PathBuf};
 
 /// Command line arguments for docgen
 #[derive(Debug, Parser)]
@@ -59,6 +68,10 @@ struct Args {
     #[arg(short, long, default_value_t = false)]
     json_output: bool,
 
+    /// Version of the JSON output format: 1 or 2. See the `schema` subcommand for version 2.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..=2))]
+    json_version: u32,
+
     /// Name of the function category (e.g. 'strings', 'attrsets').
     #[arg(short, long, default_value_t = String::new())]
     category: String,
@@ -67,27 +80,276 @@ struct Args {
     #[arg(short, long, default_value_t = String::new())]
     description: String,
 
+    /// Infer the category from the file name (e.g. 'strings' for 'strings.nix') and the
+    /// description from the first heading of the file-level doc comment. --category and
+    /// --description take precedence.
+    #[arg(long)]
+    infer_category: bool,
+
     /// Nix file to process.
     #[arg(short, long)]
     file: Option<PathBuf>,
 
-    /// Path to a file containing location data as JSON.
+    /// Nix file to process from a local path, tarball or git repository, e.g.
+    /// `github:NixOS/nixpkgs/nixos-24.05#lib/strings.nix`. Fetched sources are kept
+    /// in --cache-dir or in `$XDG_CACHE_HOME/nixdoc/sources` (`~/.cache` if unset)
+    #[arg(long, conflicts_with = "file")]
+    source: Option<String>,
+
+    /// Path to a file containing location data as JSON. Entries without location data are
+    /// located by their line in the input file.
     #[arg(short, long)]
     locs: Option<PathBuf>,
 
+    /// Locate entries by their line in the input file, without a --locs file
+    #[arg(long)]
+    locations: bool,
+
+    /// Schema of the --locs file: nixpkgs (entry identifiers mapped to markdown) or
+    /// structured (entry identifiers mapped to `{file, line, column, url}` objects)
+    #[arg(long, default_value = "nixpkgs")]
+    locs_format: String,
+
+    /// Also document the attributes found by evaluating the file with `nix eval`, for
+    /// files constructing their attributes programmatically. Attributes are documented by
+    /// the doc comments of the bindings `builtins.unsafeGetAttrPos` locates them at
+    #[arg(long)]
+    eval: bool,
+
+    /// Nix expression evaluating to the attribute set --eval documents [default: the file,
+    /// called with `{ }` if it is a function]
+    #[arg(long, requires = "eval")]
+    eval_scope: Option<String>,
+
+    /// Version entries without a `Since:` line, `# Since` section, `@since` tag or
+    /// `meta.since` attribute are available since
+    #[arg(long)]
+    default_since: Option<String>,
+
+    /// Only document entries available since a version newer than this one, e.g. to
+    /// generate a changelog. Entries without a version are omitted
+    #[arg(long)]
+    added_after: Option<String>,
+
+    /// Custom `@tag` annotation that becomes a field of its entry (repeatable). Tags
+    /// with a --tag-template are registered as well; lines naming other tags are kept
+    /// in the doc comment
+    #[arg(long = "tag", value_name = "NAME")]
+    tags: Vec<String>,
+
+    /// Command parsing the fields of custom `@tag` annotations, run with a shell-like
+    /// command line. It reads a JSON array of objects with `entry`, `tag` and `value`
+    /// keys and prints a JSON array of their new values (repeatable, run in order)
+    #[arg(long = "plugin", value_name = "COMMAND")]
+    plugins: Vec<String>,
+
+    /// Handlebars template rendering the field of a custom tag, as `tag=file`. The
+    /// template gets the field's `tag`, its `value` and the identifier of its `entry`
+    /// (repeatable)
+    #[arg(long = "tag-template", value_name = "TAG=FILE")]
+    tag_templates: Vec<String>,
+
+    /// Path to a JSON object mapping type names used in `# Type` signatures (e.g.
+    /// `Derivation`) to the links of their documentation
+    #[arg(long)]
+    type_links: Option<PathBuf>,
+
+    /// Link entries without location data to their lines below this URL, e.g.
+    /// `https://github.com/NixOS/nixpkgs/blob`. The URL may instead be a template with
+    /// `{path}`, `{revision}`, `{line}` and `{end_line}` placeholders.
+    #[arg(long)]
+    source_base_url: Option<String>,
+
+    /// Revision (e.g. a commit) to link to with --source-base-url
+    #[arg(long, requires = "source_base_url")]
+    revision: Option<String>,
+
     /// Comma-separated list of bindings to export (documents only these from let block).
     /// When specified, ignores what the file returns and documents only these bindings.
     #[arg(short, long, value_delimiter = ',')]
     export: Option<Vec<String>>,
+
+    /// Comma-separated list of glob patterns; only entries whose name matches one are documented.
+    #[arg(long, value_delimiter = ',')]
+    include: Vec<String>,
+
+    /// Comma-separated list of glob patterns; entries whose name matches one are skipped.
+    #[arg(long, value_delimiter = ',')]
+    exclude: Vec<String>,
+
+    /// Comma-separated list of glob patterns of wrapper functions (e.g. `lib.flip`) to look
+    /// through when collecting arguments.
+    #[arg(long, value_delimiter = ',')]
+    wrapper: Vec<String>,
+
+    /// Number of levels of nested attribute sets to document, using dotted names for their entries.
+    #[arg(long, default_value_t = 0)]
+    depth: usize,
+
+    /// Emit a hash-based anchor (e.g. `id-3f2a9c0b1d4e`) for each entry next to its
+    /// readable anchor. It only depends on the category and the entry name.
+    #[arg(long)]
+    hash_anchors: bool,
+
+    /// List the deprecated entries in an appendix after all entries
+    #[arg(long)]
+    deprecated_appendix: bool,
+
+    /// Order of the entries: source (as in the file), alphabetical, or nixos (like
+    /// alphabetical, with `enable` and `package` first). Entries under a section stay
+    /// together.
+    #[arg(long, default_value_t = String::from("source"))]
+    sort: String,
+
+    /// List the entries with the first sentence of their description in a table
+    /// before all entries
+    #[arg(long)]
+    summary_table: bool,
+
+    /// Document undocumented bindings to `import ./file.nix` by the doc comment of the
+    /// imported file's top-level expression.
+    #[arg(long)]
+    follow_imports: bool,
+
+    /// Directory caching the entries of unchanged files between runs
+    #[arg(long)]
+    cache_dir: Option<PathBuf>,
+
+    /// Comma-separated parts of an entry in the order they are rendered in; parts that
+    /// are not listed are omitted. Parts: stability, deprecated, type, value, description,
+    /// arguments, throws, example, fields, since, maintainers, location [default: all, in
+    /// this order]
+    #[arg(long, value_delimiter = ',')]
+    layout: Vec<String>,
+
+    /// Language of the labels and sentences around entries: en or de [default: en]
+    #[arg(long)]
+    locale: Option<String>,
+
+    /// Language given to the code blocks without an info string of examples whose
+    /// heading names none (like `# Example (bash): ...` does), e.g. `nix`
+    #[arg(long, value_name = "LANGUAGE")]
+    default_example_language: Option<String>,
+
+    /// TOML file overriding single strings of the --locale, in a `[functions]` and an
+    /// `[options]` table keyed by label name (see --label of the options command)
+    #[arg(long, value_name = "TOML")]
+    strings: Option<PathBuf>,
+
+    /// Fail on unparsable doc comments, documented bindings with dynamic names (e.g.
+    /// `${name} = ...;`) or defined twice, entries missing from --locs, unresolved
+    /// inherits, references to unknown entries and the other problems of files and
+    /// options that are otherwise reported as warnings.
+    #[arg(long)]
+    strict: bool,
+
+    /// Format of warnings and errors: human (in the style of rustc) or json (one object
+    /// per line)
+    #[arg(long, default_value = "human")]
+    diagnostics_format: String,
+
+    /// Report links of the rendered markdown to anchors it does not define
+    #[arg(long)]
+    validate_links: bool,
+
+    /// Also request the external links of the rendered markdown with `curl` and report
+    /// those that cannot be reached. Implies --validate-links
+    #[arg(long)]
+    check_links: bool,
+
+    /// Seconds to wait for each external link with --check-links
+    #[arg(long, value_name = "SECONDS", default_value_t = 10)]
+    link_timeout: u64,
+
+    /// Handlebars template to render the output with, instead of the built-in layout. The
+    /// template receives the version 2 JSON document as context.
+    #[arg(long)]
+    template: Option<PathBuf>,
+
+    /// Output file (defaults to stdout)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Output format: markdown, man, html (a fragment without `<html>`/`<head>`),
+    /// docbook (a DocBook 5 `<section>` of `<refentry>`s), rst (reStructuredText for
+    /// Sphinx) or asciidoc (for Asciidoctor and Antora).
+    #[arg(long, default_value_t = String::from("markdown"))]
+    output_format: String,
+
+    /// Manual section of the generated man page.
+    #[arg(long, default_value_t = String::from("3"))]
+    man_section: String,
+
+    /// Number of levels to shift headings down by in HTML output.
+    #[arg(long, default_value_t = 0)]
+    heading_offset: usize,
+
+    /// Prefix of the class names of all elements in HTML output.
+    #[arg(long, default_value_t = String::from("nixdoc-"))]
+    class_prefix: String,
+
+    /// Syntax to write admonitions in: nixpkgs (`::: {.note}`), github (`> [!NOTE]`) or
+    /// myst (```` ```{note} ````). Admonitions in any of these syntaxes are recognized.
+    /// Output formats other than markdown always get nixpkgs-style admonitions.
+    #[arg(long)]
+    admonition_style: Option<String>,
+
+    /// Markdown dialect to write: nixpkgs (`{#anchor}` attributes and fenced divs, as
+    /// understood by Pandoc-like renderers), commonmark, gfm (GitHub) or myst. Other
+    /// dialects get anchors, examples, definition lists and, for commonmark, tables
+    /// in their own syntax, and admonitions in their style unless --admonition-style is
+    /// given.
+    #[arg(long)]
+    markdown_dialect: Option<String>,
+
+    /// Heading level of the category title, for embedding the output in a larger
+    /// manual
+    #[arg(long, default_value_t = 1)]
+    category_heading_level: usize,
+
+    /// Heading level of the entries, below the category title. The headings of their
+    /// doc comments are shifted along
+    #[arg(long, default_value_t = 2)]
+    entry_heading_level: usize,
+
+    /// Print the structure of the command line interface (its subcommands and their
+    /// arguments) as JSON, for wrappers to introspect it, and exit.
+    #[arg(long)]
+    dump_cli_json: bool,
 }
 
+// Parsed once at startup, so the size of the largest variant doesn't matter.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Parser)]
 enum Command {
     /// Render NixOS-style module options from JSON to CommonMark
     Options {
-        /// Input JSON file containing options (from lib.optionAttrSetToDocList)
-        #[arg(short, long)]
-        file: PathBuf,
+        /// Input JSON file containing options (from lib.optionAttrSetToDocList), or a
+        /// directory of them (repeatable). Options of several files are merged
+        #[arg(short, long = "file", required = true)]
+        files: Vec<PathBuf>,
+
+        /// Show the module set (the name of the input file) each option comes from
+        #[arg(long)]
+        tag_origin: bool,
+
+        /// Comma-separated glob patterns of option names to render (e.g. 'services.nginx.*')
+        #[arg(long, value_delimiter = ',')]
+        include_prefix: Vec<String>,
+
+        /// Comma-separated glob patterns of option names to leave out
+        #[arg(long, value_delimiter = ',')]
+        exclude_prefix: Vec<String>,
+
+        /// Render options marked `internal` or `visible = false`, which are hidden by default
+        #[arg(long)]
+        show_internal: bool,
+
+        /// How to render alias options, whose description is "Alias of ...": as any other
+        /// option (full), as a line linking to their target (compact), listed under their
+        /// target (fold), or not at all (hide)
+        #[arg(long, default_value = "compact")]
+        aliases: String,
 
         /// Output file (defaults to stdout)
         #[arg(short, long)]
@@ -101,424 +363,2777 @@ enum Command {
         #[arg(short, long)]
         preamble: Option<String>,
 
-        /// Prefix for anchor IDs
-        #[arg(long, default_value = "opt-")]
-        anchor_prefix: String,
+        /// Rendering profile: nixos-manual, home-manager or minimal. Other flags override
+        /// the profile's settings
+        #[arg(long, default_value = "nixos-manual")]
+        profile: String,
+
+        /// Prefix for anchor IDs [default: opt-]
+        #[arg(long)]
+        anchor_prefix: Option<String>,
 
-        /// Include declaration source links
-        #[arg(long, default_value_t = true)]
-        include_declarations: bool,
+        /// Include declaration source links [default: true]
+        #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+        include_declarations: Option<bool>,
 
         /// Base URL for declaration links (e.g., https://github.com/owner/repo)
         #[arg(long)]
         declarations_base_url: Option<String>,
 
-        /// Git revision for declaration links
+        /// Git revision for declaration links, also stated in the document footer.
+        /// Defaults to the revision in the `_meta` key of the options JSON
         #[arg(long)]
         revision: Option<String>,
+
+        /// URL of a package in the package index related packages link to, with `{attr}`
+        /// standing for the package's attribute path. An empty URL disables the links
+        #[arg(long, default_value = options::DEFAULT_PACKAGE_INDEX_URL)]
+        package_index_url: String,
+
+        /// Generation time stated in the document footer, overriding the `_meta` key
+        #[arg(long)]
+        generated: Option<String>,
+
+        /// Generator stated in the document footer, overriding the `_meta` key
+        #[arg(long)]
+        generator: Option<String>,
+
+        /// Prefix to strip from declaration paths, instead of detecting Nix store prefixes
+        #[arg(long)]
+        store_path_strip: Option<String>,
+
+        /// Format for values with an unknown `_type` tag, as `tag=template`.
+        /// `{text}` and `{type}` in the template are substituted (repeatable)
+        #[arg(long = "tag-format", value_name = "TAG=TEMPLATE")]
+        tag_formats: Vec<String>,
+
+        /// JSON file with an object mapping `_type` tags to format templates
+        #[arg(long)]
+        tag_formats_file: Option<PathBuf>,
+
+        /// Directory of `<namespace>.md` files (e.g. `services.nginx.md`) whose
+        /// contents are inserted as intro sections before the namespace's options
+        #[arg(long)]
+        namespace_intros: Option<PathBuf>,
+
+        /// Group options under headings for the first N segments of their `loc`
+        #[arg(long, value_name = "DEPTH")]
+        group_by_prefix: Option<usize>,
+
+        /// Group options some other way: declaration (under a heading per file declaring
+        /// them, e.g. to review the options a module documents)
+        #[arg(long, conflicts_with = "group_by_prefix")]
+        group_by: Option<String>,
+
+        /// Comma-separated parts of an option in the order they are rendered in; parts
+        /// that are not listed are omitted. Parts: type, badges, stability, origin,
+        /// default, description, example, related-packages, declarations
+        /// [default: all, in this order]
+        #[arg(long, value_delimiter = ',')]
+        layout: Vec<String>,
+
+        /// Report option descriptions containing markdown that renders badly, and links
+        /// of the rendered markdown to anchors it does not define
+        #[arg(long, default_value_t = false)]
+        validate: bool,
+
+        /// Also request the external links of the rendered markdown with `curl` and report
+        /// those that cannot be reached
+        #[arg(long)]
+        check_links: bool,
+
+        /// Seconds to wait for each external link with --check-links
+        #[arg(long, value_name = "SECONDS", default_value_t = 10)]
+        link_timeout: u64,
+
+        /// Markdown flavor to validate against: nixpkgs or commonmark [default: nixpkgs]
+        #[arg(long)]
+        flavor: Option<String>,
+
+        /// Warn about options whose rendered default is longer than this
+        #[arg(long)]
+        max_default_length: Option<usize>,
+
+        /// Warn about options whose rendered example is longer than this
+        #[arg(long)]
+        max_example_length: Option<usize>,
+
+        /// Warn about options whose description is longer than this
+        #[arg(long)]
+        max_description_length: Option<usize>,
+
+        /// Write statistics (counts and budget violations) as JSON to this file
+        #[arg(long)]
+        stats: Option<PathBuf>,
+
+        /// Write the anchor of every option as a JSON object to this file
+        #[arg(long)]
+        anchors: Option<PathBuf>,
+
+        /// Handlebars template to render the options with, instead of the built-in layout
+        #[arg(long)]
+        template: Option<PathBuf>,
+
+        /// Output format: markdown, man, html (a fragment without `<html>`/`<head>`),
+        /// docbook (a DocBook 5 `<section>` holding a `<variablelist>`), rst
+        /// (reStructuredText for Sphinx) or asciidoc (for Asciidoctor and Antora)
+        #[arg(long, default_value = "markdown")]
+        output_format: String,
+
+        /// Manual section of generated man pages
+        #[arg(long, default_value = "5")]
+        man_section: String,
+
+        /// Number of levels to shift headings down by in HTML output
+        #[arg(long, default_value_t = 0)]
+        heading_offset: usize,
+
+        /// Prefix of the class names of all elements in HTML output
+        #[arg(long, default_value = "nixdoc-")]
+        class_prefix: String,
+
+        /// Directory to write one man page per option namespace (the first
+        /// --group-by-prefix segments of the option's `loc`, default 1) into
+        #[arg(long)]
+        output_dir: Option<PathBuf>,
+
+        /// Syntax to write admonitions in, see the top-level --admonition-style
+        #[arg(long)]
+        admonition_style: Option<String>,
+
+        /// Markdown dialect to write, see the top-level --markdown-dialect
+        #[arg(long)]
+        markdown_dialect: Option<String>,
+
+        /// Heading level of the document title, see the top-level --category-heading-level
+        #[arg(long, default_value_t = 1)]
+        category_heading_level: usize,
+
+        /// Heading level of the options, see the top-level --entry-heading-level
+        #[arg(long, default_value_t = 2)]
+        entry_heading_level: usize,
+
+        /// Start the document with a table of contents, nested by option prefix
+        #[arg(long)]
+        toc: bool,
+
+        /// End the document with an alphabetical index of all options
+        #[arg(long)]
+        index: bool,
+
+        /// Link the base types of option types to a types appendix following the
+        /// options, and warn about types it does not describe
+        #[arg(long)]
+        link_types: bool,
+
+        /// Reformat literal Nix expressions of defaults and examples in the style of
+        /// nixfmt. Expressions with comments are kept as written
+        #[arg(long)]
+        format_expressions: bool,
+
+        /// Fold defaults longer than this many lines into a collapsed `<details>`
+        /// element, in markdown and HTML output
+        #[arg(long, value_name = "LINES")]
+        fold_defaults: Option<usize>,
+
+        /// Wording of a label as NAME=TEXT, e.g. `read-only-notice=Set by the system.`
+        /// (repeatable). Labels: type, read-only, default, example, related-packages,
+        /// declared-by, origin, contents, index, types, folded, read-only-notice,
+        /// internal-notice, hidden-notice, experimental-notice, stability, alias-of,
+        /// aliases
+        #[arg(long = "label", value_name = "NAME=TEXT")]
+        labels: Vec<String>,
+
+        /// Language of the labels: en or de [default: the profile's]. Single labels can
+        /// be changed with --strings and --label
+        #[arg(long)]
+        locale: Option<String>,
+
+        /// TOML file overriding labels of the --locale in an `[options]` table
+        #[arg(long, value_name = "TOML")]
+        strings: Option<PathBuf>,
+
+        /// Order of the options: nixos (alphabetical, with `enable` and `package` first
+        /// in their namespace), alphabetical, or source (as in the options JSON, in the
+        /// order of the files) [default: the profile's]
+        #[arg(long)]
+        sort: Option<String>,
+    },
+
+    /// Render the options declared by a NixOS module with `mkOption`, `mkEnableOption` or
+    /// `mkPackageOption`, read from its source without evaluating it
+    Module {
+        /// Nix file of the module
+        #[arg(short, long)]
+        file: PathBuf,
+
+        /// Output file (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Document title
+        #[arg(short, long, default_value = "Module Options")]
+        title: String,
+
+        /// Prefix for anchor IDs
+        #[arg(long, default_value = "opt-")]
+        anchor_prefix: String,
+
+        /// Render options marked `internal` or `visible = false`, which are hidden by default
+        #[arg(long)]
+        show_internal: bool,
     },
 
     /// Extract just the file-level documentation comment from a Nix file
     FileDoc {
-        /// Nix file to extract documentation from
+        /// Nix file to extract documentation from, or a directory to extract the
+        /// documentation of every `.nix` file below it from
         #[arg(short, long)]
         file: PathBuf,
 
-        /// Output format: markdown, json, or plain
+        /// Output format: markdown, json, or plain.
+        /// JSON output additionally splits the comment into title, summary and body,
+        /// and is an array of the files' documentation for a directory.
         #[arg(long, default_value = "markdown")]
         format: String,
 
+        /// Directory to write one `.md` file per documented `.nix` file of a directory
+        /// into, mirroring its layout. Required for markdown and plain output of a
+        /// directory
+        #[arg(long)]
+        output_dir: Option<PathBuf>,
+
         /// Shift heading levels by this amount (e.g., 2 turns # into ###)
         #[arg(long, default_value_t = 0)]
         shift_headings: usize,
+
+        /// Syntax to write admonitions in, see the top-level --admonition-style
+        #[arg(long)]
+        admonition_style: Option<String>,
     },
-}
 
-#[derive(Debug)]
-struct DocComment {
-    /// Primary documentation string.
-    doc: String,
-}
+    /// Render documentation for many Nix files at once, one category per file
+    Batch {
+        /// Nix files, directories or glob patterns to process (repeatable)
+        #[arg(short, long = "file", required = true)]
+        files: Vec<PathBuf>,
 
-#[derive(Debug)]
-struct DocItem {
-    name: String,
-    comment: DocComment,
-    args: Vec<Argument>,
-}
+        /// Directory to write one `<category>.md` per input into.
+        /// Without it, a combined manual is written to --output or stdout.
+        #[arg(long)]
+        output_dir: Option<PathBuf>,
 
-#[derive(Debug, Serialize)]
-struct JsonFormat {
-    version: u32,
-    entries: Vec<ManualEntry>,
-}
+        /// Output file for the combined manual (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
 
-/// Returns a RFC145 doc-comment if one is present
-pub fn retrieve_doc_comment(node: &SyntaxNode, shift_headings_by: Option<usize>) -> Option<String> {
-    let doc_comment = get_expr_docs(node);
-
-    doc_comment.map(|doc_comment| {
-        shift_headings(
-            &handle_indentation(&doc_comment).unwrap_or(String::new()),
-            // H1 to H4 can be used in the doc-comment with the current rendering.
-            // They will be shifted to H3, H6
-            // H1 and H2 are currently used by the outer rendering. (category and function name)
-            shift_headings_by.unwrap_or(2),
-        )
-    })
-}
+        /// Prefix for the categories (e.g. 'lib' or 'utils')
+        #[arg(short, long, default_value = "lib")]
+        prefix: String,
 
-/// Traverse directly chained nix lambdas and collect the identifiers of all lambda arguments.
-fn collect_lambda_args(mut lambda: Lambda) -> Vec<Argument> {
-    let mut args = vec![];
-
-    loop {
-        match lambda.param().unwrap() {
-            Param::IdentParam(id) => {
-                args.push(Argument::Flat(SingleArg {
-                    name: id.to_string(),
-                    doc: handle_indentation(
-                        &retrieve_doc_comment(id.syntax(), Some(1)).unwrap_or_default(),
-                    ),
-                }));
-            }
-            Param::Pattern(pat) => {
-                let pattern_vec: Vec<_> = pat
-                    .pat_entries()
-                    .map(|entry| SingleArg {
-                        name: entry.ident().unwrap().to_string(),
-                        doc: handle_indentation(
-                            &retrieve_doc_comment(entry.syntax(), Some(1)).unwrap_or_default(),
-                        ),
-                    })
-                    .collect();
+        /// Prefix for anchor IDs
+        #[arg(long, default_value = "function-library-")]
+        anchor_prefix: String,
 
-                args.push(Argument::Pattern(pattern_vec));
-            }
-        }
+        /// Comma-separated glob patterns of entry names to include
+        #[arg(long, value_delimiter = ',')]
+        include: Vec<String>,
 
-        match lambda.body() {
-            Some(Expr::Lambda(inner)) => lambda = inner,
-            _ => break,
-        }
-    }
+        /// Comma-separated glob patterns of entry names to exclude
+        #[arg(long, value_delimiter = ',')]
+        exclude: Vec<String>,
 
-    args
-}
+        /// Directory caching the entries of unchanged files between runs
+        #[arg(long)]
+        cache_dir: Option<PathBuf>,
 
-/// Transforms an AST node into a `DocItem` if it has a leading
-/// documentation comment.
-fn retrieve_doc_item(node: &AttrpathValue) -> Option<DocItem> {
-    let ident = node.attrpath().unwrap();
-    let item_name = ident.to_string();
+        /// Number of files rendered in parallel [default: number of CPUs]
+        #[arg(short, long)]
+        jobs: Option<usize>,
 
-    let doc_comment = retrieve_doc_comment(node.syntax(), Some(2))?;
+        /// Comma-separated parts of an entry in the order they are rendered in, see the
+        /// top-level --layout
+        #[arg(long, value_delimiter = ',')]
+        layout: Vec<String>,
 
-    Some(DocItem {
-        name: item_name,
-        comment: DocComment { doc: doc_comment },
-        args: vec![],
-    })
-}
+        /// Syntax to write admonitions in, see the top-level --admonition-style
+        #[arg(long)]
+        admonition_style: Option<String>,
 
-impl DocItem {
-    fn into_entry(
-        self,
-        prefix: &str,
-        category: &str,
-        locs: &HashMap<String, String>,
-    ) -> ManualEntry {
-        let ident = get_identifier(
-            &prefix.to_string(),
-            &category.to_string(),
-            &self.name.to_string(),
-        );
+        /// Order of the entries of each file, see the top-level --sort
+        #[arg(long, default_value = "source")]
+        sort: String,
 
-        ManualEntry {
-            prefix: prefix.to_string(),
-            category: category.to_string(),
-            location: locs.get(&ident).cloned(),
-            name: self.name,
-            description: self
-                .comment
-                .doc
-                .split("\n\n")
-                .map(|s| s.to_string())
-                .collect(),
-            fn_type: None,
-            example: None,
-            args: self.args,
-        }
-    }
-}
+        /// Heading level of the category titles, see the top-level
+        /// --category-heading-level
+        #[arg(long, default_value_t = 1)]
+        category_heading_level: usize,
 
-/// Traverse the arena from a top-level SetEntry and collect, where
-/// possible:
-///
-/// 1. The identifier of the set entry itself.
-/// 2. The attached doc comment on the entry.
-/// 3. The argument names of any curried functions.
-fn collect_entry_information(entry: AttrpathValue) -> Option<DocItem> {
-    let mut doc_item = retrieve_doc_item(&entry)?;
-
-    if let Some(Expr::Lambda(l)) = entry.value() {
-        doc_item.args = collect_lambda_args(l);
-    }
+        /// Heading level of the entries, see the top-level --entry-heading-level
+        #[arg(long, default_value_t = 2)]
+        entry_heading_level: usize,
 
-    Some(doc_item)
-}
+        /// Also render an index of all functions, sorted by name and grouped by their first
+        /// letter. It is written to `all-functions.md` in --output-dir, or appended to the
+        /// combined manual.
+        #[arg(long)]
+        index: bool,
 
-fn collect_bindings(
-    node: &SyntaxNode,
-    prefix: &str,
-    category: &str,
-    locs: &HashMap<String, String>,
-    scope: HashMap<String, ManualEntry>,
-) -> Vec<ManualEntry> {
-    for ev in node.preorder() {
-        match ev {
-            WalkEvent::Enter(n) if n.kind() == SyntaxKind::NODE_ATTR_SET => {
-                let mut entries = vec![];
-                for child in n.children() {
-                    if let Some(apv) = AttrpathValue::cast(child.clone()) {
-                        entries.extend(
-                            collect_entry_information(apv)
-                                .map(|di| di.into_entry(prefix, category, locs)),
-                        );
-                    } else if let Some(inh) = Inherit::cast(child) {
-                        if inh.from().is_some() {
-                            continue;
-                        }
-                        entries.extend(inh.attrs().filter_map(|a| match a {
-                            Attr::Ident(i) => scope.get(&i.syntax().text().to_string()).cloned(),
-                            _ => None,
-                        }));
-                    }
-                }
-                return entries;
-            }
-            _ => (),
-        }
-    }
+        /// Language of the labels and sentences around entries, see the top-level
+        /// --locale
+        #[arg(long)]
+        locale: Option<String>,
 
-    vec![]
-}
+        /// Language of examples whose heading names none, see the top-level
+        /// --default-example-language
+        #[arg(long, value_name = "LANGUAGE")]
+        default_example_language: Option<String>,
 
-/// Given a let-in expression and an identifier name, find the corresponding
-/// AttrpathValue binding in the let block.
-fn find_let_binding(let_in: &LetIn, name: &str) -> Option<AttrpathValue> {
-    for entry in let_in.entries() {
-        if let Some(apv) = AttrpathValue::cast(entry.syntax().clone()) {
-            if let Some(path) = apv.attrpath() {
-                if path.to_string() == name {
-                    return Some(apv);
-                }
-            }
-        }
-    }
-    None
-}
+        /// TOML file overriding single strings of the --locale, see the top-level
+        /// --strings
+        #[arg(long, value_name = "TOML")]
+        strings: Option<PathBuf>,
+    },
 
-/// Resolve an identifier in the context of a let-in expression.
-fn resolve_let_ident(let_in: &LetIn, ident: &Ident) -> Option<SyntaxNode> {
-    let name = ident.to_string();
-    let apv = find_let_binding(let_in, &name)?;
-    let value = apv.value()?;
+    /// Preview rendered documentation in a browser, rebuilding it when inputs change
+    Serve {
+        /// Nix files or directories to document (repeatable)
+        #[arg(short, long = "file")]
+        files: Vec<PathBuf>,
 
-    if let Expr::Ident(ref inner_ident) = value {
-        resolve_let_ident(let_in, inner_ident)
-    } else {
-        Some(value.syntax().clone())
-    }
-}
+        /// Options JSON files or directories of them to document (repeatable)
+        #[arg(long)]
+        options: Vec<PathBuf>,
 
-fn collect_entries(
-    root: rnix::Root,
-    prefix: &str,
-    category: &str,
-    locs: &HashMap<String, String>,
-    export: &Option<Vec<String>>,
-) -> Vec<ManualEntry> {
-    let mut preorder = root.syntax().preorder();
-    while let Some(ev) = preorder.next() {
-        match ev {
-            WalkEvent::Enter(n) if n.kind() == SyntaxKind::NODE_PATTERN => {
-                preorder.skip_subtree();
-            }
-            WalkEvent::Enter(n) if n.kind() == SyntaxKind::NODE_LET_IN => {
-                let let_in = LetIn::cast(n.clone()).unwrap();
-                let scope: HashMap<String, ManualEntry> = n
-                    .children()
-                    .filter_map(AttrpathValue::cast)
-                    .filter_map(collect_entry_information)
-                    .map(|di| (di.name.to_string(), di.into_entry(prefix, category, locs)))
-                    .collect();
-
-                if let Some(ref exports) = export {
-                    return exports
-                        .iter()
-                        .filter_map(|name| scope.get(name).cloned())
-                        .collect();
-                }
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: String,
 
-                let body = let_in.body().unwrap();
+        /// Port to listen on
+        #[arg(long, default_value_t = 8000)]
+        port: u16,
 
-                if let Expr::Ident(ref ident) = body {
-                    if let Some(resolved) = resolve_let_ident(&let_in, ident) {
-                        return collect_bindings(&resolved, prefix, category, locs, scope);
-                    }
-                }
+        /// Prefix for the categories (e.g. 'lib' or 'utils')
+        #[arg(short, long, default_value = "lib")]
+        prefix: String,
 
-                return collect_bindings(body.syntax(), prefix, category, locs, scope);
-            }
-            WalkEvent::Enter(n) if n.kind() == SyntaxKind::NODE_ATTR_SET => {
-                return collect_bindings(&n, prefix, category, locs, Default::default());
-            }
-            _ => (),
-        }
-    }
+        /// Prefix for anchor IDs
+        #[arg(long, default_value = "function-library-")]
+        anchor_prefix: String,
+    },
 
-    vec![]
-}
+    /// Generate an mdBook `src` directory with one chapter per category and options namespace
+    Mdbook {
+        /// Nix files or directories to document (repeatable)
+        #[arg(short, long = "file")]
+        files: Vec<PathBuf>,
 
-/// Extract just the file-level documentation comment from a Nix file.
-fn extract_file_doc(nix: &rnix::Root) -> Option<String> {
-    nix.syntax()
-        .first_child()
-        .and_then(|node| retrieve_doc_comment(&node, Some(0)))
-        .and_then(|doc_item| handle_indentation(&doc_item))
-}
+        /// Options JSON files or directories of them to document (repeatable)
+        #[arg(long)]
+        options: Vec<PathBuf>,
 
-fn retrieve_description(nix: &rnix::Root, description: &str, category: &str) -> String {
-    if description.is_empty() && category.is_empty() {
-        return String::new();
-    }
-    format!(
-        "# {} {{#sec-functions-library-{}}}\n{}\n",
-        description,
-        category,
-        extract_file_doc(nix).unwrap_or_default()
-    )
-}
+        /// The book's `src` directory to write `SUMMARY.md` and the chapters into
+        #[arg(long)]
+        output_dir: PathBuf,
 
-fn main_with_args(args: &Args) -> String {
-    let file = args.file.as_ref().expect("--file is required");
-    let src = fs::read_to_string(file).unwrap();
-    let locs = match &args.locs {
-        None => Default::default(),
-        Some(p) => fs::read_to_string(p)
-            .map_err(|e| e.to_string())
-            .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
-            .expect("could not read location information"),
-    };
-    let nix = rnix::Root::parse(&src).ok().expect("failed to parse input");
-    let description = retrieve_description(&nix, &args.description, &args.category);
+        /// Title of the options chapter
+        #[arg(long, default_value = "Module Options")]
+        options_title: String,
 
-    let entries = collect_entries(nix, &args.prefix, &args.category, &locs, &args.export);
+        /// Prefix for the categories (e.g. 'lib' or 'utils')
+        #[arg(short, long, default_value = "lib")]
+        prefix: String,
 
-    if args.json_output {
-        serde_json::to_string(&JsonFormat {
-            version: 1,
-            entries,
-        })
-        .expect("Problem converting entries to JSON")
-    } else {
-        let mut output = description + "\n";
-        for entry in entries {
-            entry.write_section(args.anchor_prefix.as_str(), &mut output);
-        }
-        output
-    }
-}
+        /// Prefix for anchor IDs
+        #[arg(long, default_value = "function-library-")]
+        anchor_prefix: String,
+    },
 
-fn main() {
-    let args = Args::parse();
+    /// Export the documents, anchors and links of rendered markdown as a graph
+    LinkGraph {
+        /// Markdown files or directories of markdown files (repeatable)
+        #[arg(short, long = "file", required = true)]
+        files: Vec<PathBuf>,
 
-    match args.command {
-        Some(Command::Options {
-            file,
-            output,
-            title,
-            preamble,
-            anchor_prefix,
-            include_declarations,
-            declarations_base_url,
-            revision,
-        }) => {
-            let render_opts = options::RenderOptions {
-                anchor_prefix,
-                include_declarations,
-                declarations_base_url,
-                revision,
-            };
+        /// Output format: json or dot
+        #[arg(long, default_value = "json")]
+        format: String,
 
-            let parsed = options::parse_options_file(&file).unwrap_or_else(|e| {
-                eprintln!("Error: {}", e);
-                std::process::exit(1);
-            });
+        /// Output file (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
 
-            let result = options::render_options_document(
-                &parsed,
-                &title,
-                preamble.as_deref(),
-                &render_opts,
-            );
+    /// Report exported bindings with missing, empty or legacy documentation
+    Lint {
+        /// Nix file to check
+        #[arg(short, long)]
+        file: PathBuf,
 
-            if let Some(out_path) = output {
-                fs::write(&out_path, &result).unwrap_or_else(|e| {
-                    eprintln!("Error writing output: {}", e);
-                    std::process::exit(1);
-                });
-            } else {
-                println!("{}", result);
-            }
-        }
-        Some(Command::FileDoc {
-            file,
-            format,
-            shift_headings: shift_amount,
-        }) => {
-            let src = fs::read_to_string(&file).unwrap_or_else(|e| {
-                eprintln!("Error reading file: {}", e);
-                std::process::exit(1);
-            });
-            let nix = rnix::Root::parse(&src).ok().expect("failed to parse input");
+        /// Output format: text or json
+        #[arg(long, default_value = "text")]
+        format: String,
 
-            let doc = extract_file_doc(&nix).map(|d| {
-                if shift_amount > 0 {
-                    shift_headings(&d, shift_amount)
-                } else {
-                    d
-                }
-            });
+        /// Comma-separated list of let bindings to check instead of the returned attribute set
+        #[arg(long, value_delimiter = ',')]
+        export: Option<Vec<String>>,
 
-            match format.as_str() {
-                "json" => {
-                    let json_obj = serde_json::json!({
-                        "file": file.to_string_lossy(),
-                        "doc": doc
-                    });
-                    println!("{}", serde_json::to_string_pretty(&json_obj).unwrap());
-                }
-                "plain" => {
-                    if let Some(d) = doc {
-                        println!("{}", d);
-                    }
-                }
-                "markdown" | _ => {
+        /// Also report documented bindings without an example
+        #[arg(long)]
+        strict: bool,
+
+        /// Comma-separated list of checks to suppress for all entries
+        #[arg(long, value_delimiter = ',')]
+        allow: Vec<String>,
+
+        /// JSON file mapping entry name globs to lists of checks to suppress
+        #[arg(long)]
+        allow_config: Option<PathBuf>,
+    },
+
+    /// Summarize how many exported bindings of Nix files are documented, and how
+    Coverage {
+        /// Nix files, directories or glob patterns to check (repeatable)
+        #[arg(short, long = "file", required = true)]
+        files: Vec<PathBuf>,
+
+        /// Output format: text or json
+        #[arg(long, default_value = "text")]
+        format: String,
+
+        /// Fail if less than this percentage of the bindings is documented
+        #[arg(long, value_name = "PERCENT")]
+        fail_under: Option<f64>,
+    },
+
+    /// Evaluate the examples of doc comments that state their result with `=>` and
+    /// report those evaluating to something else
+    TestExamples {
+        /// Nix files, directories or glob patterns to check (repeatable)
+        #[arg(short, long = "file", required = true)]
+        files: Vec<PathBuf>,
+
+        /// Nix expression whose attributes are in scope of the examples [default: the
+        /// checked file, called with `{ }` if it is a function]
+        #[arg(long)]
+        scope: Option<String>,
+
+        /// Program evaluating the examples: nix-instantiate or nix
+        #[arg(long, default_value = "nix-instantiate")]
+        evaluator: String,
+    },
+
+    /// Compare two JSON outputs and list added, removed and changed entries
+    DiffJson {
+        /// JSON output of the old version
+        #[arg(long)]
+        old: PathBuf,
+
+        /// JSON output of the new version
+        #[arg(long)]
+        new: PathBuf,
+
+        /// Output format: json or text
+        #[arg(long, default_value = "json")]
+        format: String,
+
+        /// Output file (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Compare two options JSON files and list added, removed and changed options
+    OptionsDiff {
+        /// Options JSON of the old version
+        old: PathBuf,
+
+        /// Options JSON of the new version
+        new: PathBuf,
+
+        /// Output format: markdown or json
+        #[arg(long, default_value = "markdown")]
+        format: String,
+
+        /// Output file (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Print the JSON Schema of the version 2 JSON output
+    Schema,
+
+    /// Print a shell completion script, e.g. `nixdoc completions bash > nixdoc.bash`
+    Completions {
+        /// Shell to complete in
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+
+    /// Render an index of a module tree from the file-level doc comments of its `.nix` files
+    ModuleIndex {
+        /// Root directory of the module tree
+        #[arg(short, long)]
+        dir: PathBuf,
+
+        /// Document title
+        #[arg(short, long, default_value = "Module index")]
+        title: String,
+
+        /// URL the module paths are appended to for links (e.g. a repository's tree URL).
+        /// Without it, links are relative paths
+        #[arg(long)]
+        base_url: Option<String>,
+
+        /// Output file (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Emit a client-side search index over function and option documentation
+    SearchIndex {
+        /// Nix files, directories or glob patterns of library functions (repeatable)
+        #[arg(short, long = "file")]
+        files: Vec<PathBuf>,
+
+        /// Options JSON files (repeatable)
+        #[arg(long = "options")]
+        options: Vec<PathBuf>,
+
+        /// Prefix for the function categories (e.g. 'lib' or 'utils')
+        #[arg(short, long, default_value = "lib")]
+        prefix: String,
+
+        /// Prefix for function anchor IDs
+        #[arg(long, default_value = "function-library-")]
+        anchor_prefix: String,
+
+        /// Prefix for option anchor IDs
+        #[arg(long, default_value = "opt-")]
+        option_anchor_prefix: String,
+
+        /// Index format: documents (a list of documents to build a lunr or elasticlunr
+        /// index from) or inverted (the documents and a map of terms to them)
+        #[arg(long, default_value = "documents")]
+        format: String,
+
+        /// Output file (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Emit the raw data of documented functions for custom renderers: the doc comments
+    /// as written (headings not shifted, sections not split off), the arguments, and the
+    /// attribute paths and spans of the bindings
+    Extract {
+        /// Nix files, directories or glob patterns to process (repeatable)
+        #[arg(short, long = "file", required = true)]
+        files: Vec<PathBuf>,
+
+        /// Prefix for the categories (e.g. 'lib' or 'utils')
+        #[arg(short, long, default_value = "lib")]
+        prefix: String,
+
+        /// How many levels of nested attribute sets to document, see the top-level --depth
+        #[arg(long, default_value_t = 0)]
+        depth: usize,
+
+        /// Encoding: json or cbor
+        #[arg(long, default_value = "json")]
+        format: String,
+
+        /// Output file (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Emit an inventory of the documented functions and options, mapping their full
+    /// names to the URIs of their documentation for other projects to link to
+    Inventory {
+        /// Nix files, directories or glob patterns of library functions (repeatable)
+        #[arg(short, long = "file")]
+        files: Vec<PathBuf>,
+
+        /// Options JSON files (repeatable)
+        #[arg(long = "options")]
+        options: Vec<PathBuf>,
+
+        /// Prefix for the function categories (e.g. 'lib' or 'utils')
+        #[arg(short, long, default_value = "lib")]
+        prefix: String,
+
+        /// Prefix for function anchor IDs
+        #[arg(long, default_value = "function-library-")]
+        anchor_prefix: String,
+
+        /// Prefix for option anchor IDs
+        #[arg(long, default_value = "opt-")]
+        option_anchor_prefix: String,
+
+        /// Page documenting the functions of a category, relative to the root of the
+        /// manual. `{category}` is replaced by the category
+        #[arg(long, default_value = "{category}.html")]
+        function_page: String,
+
+        /// Page documenting the options, relative to the root of the manual
+        #[arg(long, default_value = "options.html")]
+        options_page: String,
+
+        /// Project name recorded in the inventory
+        #[arg(long, default_value = "")]
+        project: String,
+
+        /// Project version recorded in the inventory
+        #[arg(long, default_value = "")]
+        version: String,
+
+        /// Inventory format: json or objects.inv (a Sphinx inventory for intersphinx)
+        #[arg(long, default_value = "json")]
+        format: String,
+
+        /// Output file (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Render an index of the functions and options tagged with keywords (in a `Tags:`
+    /// line or a `# Keywords` section of their documentation), grouped by keyword
+    TagIndex {
+        /// Nix files, directories or glob patterns of library functions (repeatable)
+        #[arg(short, long = "file")]
+        files: Vec<PathBuf>,
+
+        /// Options JSON files (repeatable)
+        #[arg(long = "options")]
+        options: Vec<PathBuf>,
+
+        /// Prefix for the function categories (e.g. 'lib' or 'utils')
+        #[arg(short, long, default_value = "lib")]
+        prefix: String,
+
+        /// Prefix for function anchor IDs
+        #[arg(long, default_value = "function-library-")]
+        anchor_prefix: String,
+
+        /// Prefix for option anchor IDs
+        #[arg(long, default_value = "opt-")]
+        option_anchor_prefix: String,
+
+        /// Page documenting the functions of a category, relative to the index.
+        /// `{category}` is replaced by the category
+        #[arg(long, default_value = "{category}.html")]
+        function_page: String,
+
+        /// Page documenting the options, relative to the index
+        #[arg(long, default_value = "options.html")]
+        options_page: String,
+
+        /// Title of the index page
+        #[arg(long, default_value = "Tags")]
+        title: String,
+
+        /// Index format: markdown or json (the items of every keyword)
+        #[arg(long, default_value = "markdown")]
+        format: String,
+
+        /// Output file (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Render a matrix of the versions each option exists in and where it changed
+    Compat {
+        /// Options JSON of a version, as VERSION=PATH, oldest first (repeatable)
+        #[arg(short, long = "file", value_name = "VERSION=PATH", required = true)]
+        files: Vec<String>,
+
+        /// Document title
+        #[arg(short, long, default_value = "Option compatibility")]
+        title: String,
+
+        /// Output file (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+/// Parse the `--layout` of entries, defaulting to all parts.
+fn entry_layout(names: &[String]) -> Vec<EntrySection> {
+    if names.is_empty() {
+        return EntrySection::ALL.to_vec();
+    }
+    EntrySection::parse_layout(names).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    })
+}
+
+/// Labels of the renderers in the `--locale` named `name`, overridden by the
+/// `--strings` file. Without a locale, the options keep `option_labels`.
+fn renderer_labels(
+    name: Option<&str>,
+    strings: Option<&Path>,
+    mut option_labels: options::Labels,
+) -> (EntryLabels, options::Labels) {
+    let mut entry_labels = EntryLabels::default();
+    if let Some(name) = name {
+        let locale = locale::Locale::from_name(name).unwrap_or_else(|| {
+            eprintln!("Error: unknown locale '{}'", name);
+            std::process::exit(1);
+        });
+        entry_labels = locale.entry_labels();
+        option_labels = locale.option_labels();
+    }
+    if let Some(path) = strings {
+        locale::load_strings(path, &mut entry_labels, &mut option_labels).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        });
+    }
+    (entry_labels, option_labels)
+}
+
+/// Look up the `--sort` order named `name`.
+fn sort_order(name: &str) -> SortOrder {
+    SortOrder::from_name(name).unwrap_or_else(|| {
+        eprintln!("Error: unknown sort order '{}'", name);
+        std::process::exit(1);
+    })
+}
+
+/// Look up a diagnostics format, exiting on unknown names.
+fn diagnostics_format(name: &str) -> DiagnosticsFormat {
+    DiagnosticsFormat::from_name(name).unwrap_or_else(|| {
+        eprintln!("Error: unknown diagnostics format '{}'", name);
+        std::process::exit(1);
+    })
+}
+
+/// Print `diagnostics`, exiting if any of them is an error.
+fn report_diagnostics(diagnostics: &Diagnostics, format: DiagnosticsFormat) {
+    eprint!("{}", diagnostics.render(format));
+    if diagnostics.has_errors() {
+        std::process::exit(1);
+    }
+}
+
+/// Heading levels of the category and its entries, exiting on levels out of
+/// order.
+fn heading_levels(category: usize, entry: usize) -> HeadingLevels {
+    HeadingLevels::new(category, entry).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    })
+}
+
+/// The admonition style to convert to for an output format. Converters to
+/// other formats than markdown read nixpkgs-style admonitions.
+fn admonition_style(name: Option<&str>, output_format: &str) -> Option<AdmonitionStyle> {
+    if output_format != "markdown" {
+        return Some(AdmonitionStyle::Nixpkgs);
+    }
+    name.map(|name| {
+        AdmonitionStyle::from_name(name).unwrap_or_else(|| {
+            eprintln!("Error: unknown admonition style '{}'", name);
+            std::process::exit(1);
+        })
+    })
+}
+
+/// The markdown dialect to convert to for an output format. Converters to
+/// other formats than markdown read the nixpkgs dialect.
+fn markdown_dialect(name: Option<&str>, output_format: &str) -> Option<MarkdownDialect> {
+    if output_format != "markdown" {
+        return None;
+    }
+    name.map(|name| {
+        MarkdownDialect::from_name(name).unwrap_or_else(|| {
+            eprintln!("Error: unknown markdown dialect '{}'", name);
+            std::process::exit(1);
+        })
+    })
+}
+
+/// Rewrite rendered markdown in `dialect`, if any, with admonitions in
+/// `style` or else in the dialect's.
+fn convert_dialect(
+    text: String,
+    dialect: Option<MarkdownDialect>,
+    style: Option<AdmonitionStyle>,
+) -> String {
+    match dialect {
+        Some(dialect) => {
+            dialect::convert_markdown(&text, dialect, style.unwrap_or(dialect.admonition_style()))
+        }
+        None => text,
+    }
+}
+
+/// Rewrite the admonitions of `text` in `style`, if any.
+fn convert_admonitions(text: String, style: Option<AdmonitionStyle>) -> String {
+    match style {
+        Some(style) => admonition::convert_admonitions(&text, style),
+        None => text,
+    }
+}
+
+/// The structure of a command line interface as JSON: its name, help texts,
+/// arguments and, recursively, subcommands.
+fn cli_json(command: &mut clap::Command) -> serde_json::Value {
+    command.build();
+    let arguments: Vec<_> = command
+        .get_arguments()
+        .filter(|arg| !arg.is_hide_set())
+        .map(|arg| {
+            serde_json::json!({
+                "name": arg.get_id().as_str(),
+                "long": arg.get_long(),
+                "short": arg.get_short(),
+                "positional": arg.is_positional(),
+                "help": arg.get_help().map(|help| help.to_string()),
+                "required": arg.is_required_set(),
+                "takes_value": arg.get_action().takes_values(),
+                "multiple": matches!(arg.get_action(), clap::ArgAction::Append),
+                "value_names": arg.get_value_names().map(|names| {
+                    names.iter().map(|name| name.as_str()).collect::<Vec<_>>()
+                }),
+                "default": arg
+                    .get_default_values()
+                    .iter()
+                    .map(|value| value.to_string_lossy())
+                    .collect::<Vec<_>>(),
+                "possible_values": arg
+                    .get_possible_values()
+                    .iter()
+                    .map(|value| value.get_name().to_string())
+                    .collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+    let subcommands: Vec<_> = command
+        .get_subcommands_mut()
+        .filter(|subcommand| !subcommand.is_hide_set())
+        .map(cli_json)
+        .collect();
+    serde_json::json!({
+        "name": command.get_name(),
+        "version": command.get_version(),
+        "about": command.get_about().map(|about| about.to_string()),
+        "arguments": arguments,
+        "subcommands": subcommands,
+    })
+}
+
+/// Render the documentation of `args.file` into a string.
+#[cfg(test)]
+fn main_with_args(args: &Args) -> String {
+    let mut output = vec![];
+    write_with_args(args, &mut output).expect("writing to a buffer cannot fail");
+    String::from_utf8(output).expect("rendered documentation is UTF-8")
+}
+
+/// Render the documentation of `args.file` to `out`. Markdown is written
+/// entry by entry as it is rendered.
+fn write_with_args(args: &Args, out: &mut dyn Write) -> io::Result<()> {
+    let file = args.file.as_ref().expect("--file is required");
+    let (src, lossy) = read_source(file).unwrap();
+    // Fetched sources are located by their path within the source tree.
+    let display = args
+        .source
+        .as_deref()
+        .and_then(|spec| SourceSpec::parse(spec).ok()?.subpath)
+        .unwrap_or_else(|| file.display().to_string());
+    let locs_format = locs::LocsFormat::from_name(&args.locs_format).unwrap_or_else(|| {
+        eprintln!("Error: unknown locs format '{}'", args.locs_format);
+        std::process::exit(1);
+    });
+    let locs = match &args.locs {
+        None => Default::default(),
+        Some(p) => fs::read_to_string(p)
+            .map_err(|e| e.to_string())
+            .and_then(|json| locs::parse_locs(&json, locs_format))
+            .expect("could not read location information"),
+    };
+    let type_links = match &args.type_links {
+        None => Default::default(),
+        Some(p) => fs::read_to_string(p)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+            .expect("could not read type links"),
+    };
+    let diagnostics_format = diagnostics_format(&args.diagnostics_format);
+    let mut diagnostics = Diagnostics::new(&display, &src, args.strict);
+    if lossy {
+        diagnostics.push(
+            DiagnosticKind::InvalidUtf8,
+            None,
+            "invalid UTF-8 sequences were replaced".to_string(),
+        );
+    }
+    let nix = rnix::Root::parse(&src).ok().expect("failed to parse input");
+    for comment in comment::oversized_doc_comments(nix.syntax()) {
+        diagnostics.at_offset(
+            DiagnosticKind::UnparsableDocComment,
+            comment.offset,
+            format!(
+                "doc comment of {} bytes truncated to {} bytes",
+                comment.len,
+                comment::MAX_DOC_COMMENT_LEN
+            ),
+        );
+    }
+    for binding in attrpath::dynamic_bindings(nix.syntax()) {
+        diagnostics.at_offset(
+            DiagnosticKind::DynamicName,
+            binding.offset,
+            format!(
+                "`{}` has a dynamic name and is not documented",
+                binding.attrpath
+            ),
+        );
+    }
+    let (category, description, file_doc) = if args.infer_category {
+        let (category, description, file_doc) = infer_category(file, &nix);
+        (
+            Some(args.category.clone())
+                .filter(|c| !c.is_empty())
+                .unwrap_or(category),
+            Some(args.description.clone())
+                .filter(|d| !d.is_empty())
+                .unwrap_or(description),
+            file_doc,
+        )
+    } else {
+        (
+            args.category.clone(),
+            args.description.clone(),
+            extract_file_doc(&nix),
+        )
+    };
+    let heading = describe_category(&description, &category, file_doc.as_deref());
+
+    let opts = CollectOptions {
+        prefix: args.prefix.clone(),
+        category: category.clone(),
+        locs,
+        file: Some(display.clone()),
+        line_locations: args.locations || args.locs.is_some() || args.source_base_url.is_some(),
+        base_dir: file.parent().map(Path::to_path_buf),
+        export: args.export.clone(),
+        include: args.include.clone(),
+        exclude: args.exclude.clone(),
+        wrappers: args.wrapper.clone(),
+        depth: args.depth,
+        hash_anchors: args.hash_anchors,
+        follow_imports: args.follow_imports,
+        default_example_language: args.default_example_language.clone(),
+        custom_tags: args
+            .tags
+            .iter()
+            .cloned()
+            .chain(
+                args.tag_templates
+                    .iter()
+                    .filter_map(|spec| Some(spec.split_once('=')?.0.to_string())),
+            )
+            .collect(),
+        type_links,
+        source_links: args.source_base_url.as_ref().map(|base_url| SourceLinks {
+            base_url: base_url.clone(),
+            revision: args.revision.clone(),
+        }),
+    };
+    for inherit in nixdoc::unresolved_inherits(&nix, &opts) {
+        let message = match &inherit.from {
+            Some(from) => format!(
+                "`{}` is inherited from {}, which cannot be read",
+                inherit.name, from
+            ),
+            None => format!("`{}` is inherited but not bound by the file", inherit.name),
+        };
+        diagnostics.at_offset(DiagnosticKind::UnresolvedInherit, inherit.offset, message);
+    }
+    let (mut entries, duplicates) = match &args.cache_dir {
+        Some(dir) => EntryCache::new(dir)
+            .collect(&src, &opts)
+            .expect("failed to parse input"),
+        None => collect_entries_with_duplicates(nix.clone(), &opts),
+    };
+    if args.eval {
+        let scope = args
+            .eval_scope
+            .clone()
+            .unwrap_or_else(|| doctest::default_scope(file));
+        let attrs = eval::evaluate_attrs(&eval::json_evaluator(), &scope).unwrap_or_else(|e| {
+            eprintln!("Error: could not evaluate {}: {}", display, e);
+            std::process::exit(1);
+        });
+        let (evaluated, undefined) = eval::evaluated_entries(&nix, file, &attrs, &opts);
+        for entry in evaluated {
+            if !entries.iter().any(|e| e.name == entry.name) {
+                entries.push(entry);
+            }
+        }
+        for name in undefined {
+            if !entries.iter().any(|e| e.name == name) {
+                diagnostics.push(
+                    DiagnosticKind::UnboundAttribute,
+                    None,
+                    format!(
+                        "`{}` is not defined by a binding and is not documented",
+                        name
+                    ),
+                );
+            }
+        }
+    }
+    if let Some(version) = &args.default_since {
+        for entry in entries.iter_mut().filter(|e| e.since.is_none()) {
+            entry.since = Some(version.clone());
+        }
+    }
+    if let Some(version) = &args.added_after {
+        entries.retain(|e| {
+            e.since
+                .as_ref()
+                .is_some_and(|since| tags::compare_versions(since, version).is_gt())
+        });
+    }
+    let plugins: Vec<Box<dyn plugin::TagPlugin>> = args
+        .plugins
+        .iter()
+        .map(|command| {
+            let plugin = plugin::CommandPlugin::from_command_line(command).unwrap_or_else(|| {
+                eprintln!("Error: --plugin expects a command");
+                std::process::exit(1);
+            });
+            Box::new(plugin) as Box<dyn plugin::TagPlugin>
+        })
+        .collect();
+    let mut templates = plugin::TagTemplates::default();
+    for spec in &args.tag_templates {
+        let Some((tag, path)) = spec.split_once('=') else {
+            eprintln!("Error: --tag-template expects TAG=FILE, got '{}'", spec);
+            std::process::exit(1);
+        };
+        let template = Template::from_file(Path::new(path)).unwrap_or_else(|e| {
+            eprintln!("Error: {}: {}", path, e);
+            std::process::exit(1);
+        });
+        templates.0.insert(tag.to_string(), template);
+    }
+    plugin::apply_plugins(&mut entries, &plugins)
+        .and_then(|_| templates.render(&mut entries))
+        .unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        });
+    sort_entries(&mut entries, sort_order(&args.sort));
+    report_entry_problems(&mut entries, &duplicates, &mut diagnostics);
+    for entry in &entries {
+        let position = entry_position(entry);
+        let (ident, _) = entry.get_ident_title();
+        if args.locs.is_some() && !opts.locs.contains_key(&ident) {
+            diagnostics.push(
+                DiagnosticKind::MissingLocation,
+                position,
+                format!("`{}` has no entry in the location data", ident),
+            );
+        }
+        if entry.signature.is_none() && signature::has_type_section(&entry.description.join("\n\n"))
+        {
+            diagnostics.push(
+                DiagnosticKind::UnparsableDocComment,
+                position,
+                format!("the type signature of `{}` does not parse", entry.name),
+            );
+        }
+    }
+
+    // References may point to entries of this file or, via location data, of
+    // other categories.
+    let known: HashMap<String, String> = entries
+        .iter()
+        .map(|e| e.get_ident_title().0)
+        .chain(opts.locs.keys().cloned())
+        .map(|ident| (ident, String::new()))
+        .collect();
+    let dangling =
+        xref::resolve_references(&mut entries, &known, "", &args.prefix, &args.anchor_prefix);
+    for reference in &dangling {
+        let position = entries
+            .iter()
+            .find(|e| e.get_ident_title().1 == reference.entry)
+            .and_then(|e| e.span.as_ref())
+            .map(|span| (span.start_line, span.start_column));
+        diagnostics.push(
+            DiagnosticKind::DanglingReference,
+            position,
+            format!(
+                "{}: reference to unknown entry `{}`",
+                reference.entry, reference.target
+            ),
+        );
+    }
+    report_diagnostics(&diagnostics, diagnostics_format);
+
+    let dialect = markdown_dialect(args.markdown_dialect.as_deref(), &args.output_format);
+    let style = admonition_style(args.admonition_style.as_deref(), &args.output_format)
+        .or(dialect.map(|dialect| dialect.admonition_style()));
+    if let Some(style) = style {
+        admonition::convert_entries(&mut entries, style);
+    }
+    let validate_links = args.validate_links || args.check_links;
+    // Links are validated in the rendered markdown, before it is converted
+    // to another dialect or format.
+    let mut validated = String::new();
+    let heading = convert_admonitions(heading, style);
+    let file_doc = || file_doc.clone().map(|doc| convert_admonitions(doc, style));
+
+    let document_category = || jsonv2::Category {
+        prefix: args.prefix.clone(),
+        name: category.clone(),
+        description: file_doc(),
+    };
+
+    let (labels, _) = renderer_labels(
+        args.locale.as_deref(),
+        args.strings.as_deref(),
+        options::Labels::default(),
+    );
+    let rendered = if let Some(path) = &args.template {
+        let document = jsonv2::Document::new(document_category(), entries, &args.anchor_prefix);
+        Template::from_file(path)
+            .and_then(|template| template.render(&document))
+            .unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            })
+    } else if args.json_output && args.json_version == 2 {
+        serde_json::to_string(&jsonv2::Document::new(
+            document_category(),
+            entries,
+            &args.anchor_prefix,
+        ))
+        .expect("Problem converting entries to JSON")
+    } else if args.json_output {
+        serde_json::to_string(&JsonFormat {
+            version: 1,
+            entries,
+        })
+        .expect("Problem converting entries to JSON")
+    } else if args.output_format == "docbook" {
+        docbook::render_functions(
+            &format!("sec-functions-library-{}", category),
+            &description,
+            file_doc().as_deref(),
+            entries,
+            &args.anchor_prefix,
+            &entry_layout(&args.layout),
+            &labels,
+        )
+    } else {
+        let levels = heading_levels(args.category_heading_level, args.entry_heading_level);
+        let mut appendix = String::new();
+        if args.deprecated_appendix {
+            write_deprecated_appendix(&entries, &args.anchor_prefix, &labels, &mut appendix);
+        }
+        let mut output = heading + "\n";
+        if args.summary_table {
+            write_summary_table(&entries, &args.anchor_prefix, &labels, &mut output);
+        }
+        if args.output_format == "markdown" {
+            // Headings and dialects are converted a part at a time, none of
+            // them spans entries.
+            let mut write = |markdown: &str| {
+                let markdown = levels.apply(markdown);
+                if validate_links {
+                    validated.push_str(&markdown);
+                }
+                out.write_all(convert_dialect(markdown, dialect, style).as_bytes())
+            };
+            write(&output)?;
+            stream_entries(
+                entries,
+                &args.anchor_prefix,
+                &entry_layout(&args.layout),
+                &labels,
+                &mut write,
+            )?;
+            write(&appendix)?;
+            report_broken_links(
+                args,
+                Diagnostics::new(&display, &src, args.strict),
+                &validated,
+            );
+            return Ok(());
+        }
+        write_entries_with(
+            entries,
+            &args.anchor_prefix,
+            &entry_layout(&args.layout),
+            &labels,
+            &mut output,
+        );
+        output.push_str(&appendix);
+        let output = levels.apply(&output);
+        if validate_links {
+            validated = output.clone();
+        }
+        match args.output_format.as_str() {
+            "man" => {
+                let name = format!("{}.{}", args.prefix, category);
+                man::render_man_page(&name, &args.man_section, &output)
+            }
+            "html" => {
+                let html_opts = html::HtmlOptions {
+                    heading_offset: args.heading_offset,
+                    class_prefix: args.class_prefix.clone(),
+                };
+                html::markdown_to_html(&output, &html_opts)
+            }
+            "rst" => rst::markdown_to_rst(&output),
+            "asciidoc" => asciidoc::markdown_to_asciidoc(&output),
+            other => {
+                eprintln!("Error: unknown output format '{}'", other);
+                std::process::exit(1);
+            }
+        }
+    };
+    out.write_all(rendered.as_bytes())?;
+    report_broken_links(
+        args,
+        Diagnostics::new(&display, &src, args.strict),
+        &validated,
+    );
+    Ok(())
+}
+
+/// Position of an entry in its file, if known.
+fn entry_position(entry: &ManualEntry) -> Option<(usize, usize)> {
+    entry
+        .span
+        .as_ref()
+        .map(|span| (span.start_line, span.start_column))
+}
+
+/// Report bindings defined more than once, entries whose anchors collide
+/// and documented arguments not matching those of the function.
+fn report_entry_problems(
+    entries: &mut [ManualEntry],
+    duplicates: &[Duplicate],
+    diagnostics: &mut Diagnostics,
+) {
+    for dup in duplicates {
+        let position = entries
+            .iter()
+            .find(|e| e.name == dup.name)
+            .and_then(entry_position);
+        diagnostics.push(
+            DiagnosticKind::DuplicateBinding,
+            position,
+            format!(
+                "`{}` is defined at {} and again at {}; documenting the latter",
+                dup.name, dup.shadowed, dup.effective
+            ),
+        );
+    }
+    for collision in disambiguate_identifiers(entries) {
+        diagnostics.push(DiagnosticKind::AnchorCollision, None, collision.message());
+    }
+    for entry in entries.iter() {
+        for mismatch in arguments::check_arguments(&entry.arguments, &entry.args) {
+            diagnostics.push(
+                DiagnosticKind::ArgumentMismatch,
+                entry_position(entry),
+                format!("`{}` {}", entry.name, mismatch.message()),
+            );
+        }
+    }
+}
+
+/// Report the broken links of the rendered `markdown` with --validate-links
+/// and --check-links, exiting if any of them is an error.
+fn report_broken_links(args: &Args, mut diagnostics: Diagnostics, markdown: &str) {
+    if !(args.validate_links || args.check_links) {
+        return;
+    }
+    let mut broken = linkcheck::check_anchors(markdown);
+    if args.check_links {
+        let checker = linkcheck::CurlChecker {
+            timeout: args.link_timeout,
+        };
+        broken.extend(linkcheck::check_external(markdown, &checker));
+    }
+    for link in broken {
+        diagnostics.push(DiagnosticKind::BrokenLink, None, link.message());
+    }
+    report_diagnostics(&diagnostics, diagnostics_format(&args.diagnostics_format));
+}
+
+fn main() {
+    let args = Args::parse();
+
+    if args.dump_cli_json {
+        let cli = cli_json(&mut Args::command());
+        println!("{}", serde_json::to_string_pretty(&cli).unwrap());
+        return;
+    }
+
+    match args.command {
+        Some(Command::Options {
+            files,
+            tag_origin,
+            include_prefix,
+            exclude_prefix,
+            show_internal,
+            aliases,
+            output,
+            title,
+            preamble,
+            profile,
+            anchor_prefix,
+            include_declarations,
+            declarations_base_url,
+            revision,
+            package_index_url,
+            generated,
+            generator,
+            store_path_strip,
+            tag_formats,
+            tag_formats_file,
+            namespace_intros,
+            group_by_prefix,
+            group_by,
+            layout,
+            validate,
+            check_links,
+            link_timeout,
+            flavor,
+            max_default_length,
+            max_example_length,
+            max_description_length,
+            stats,
+            anchors,
+            template,
+            output_format,
+            man_section,
+            heading_offset,
+            class_prefix,
+            output_dir,
+            admonition_style: style_name,
+            markdown_dialect: dialect_name,
+            category_heading_level,
+            entry_heading_level,
+            toc,
+            index,
+            link_types,
+            format_expressions,
+            fold_defaults,
+            labels,
+            locale,
+            strings,
+            sort,
+        }) => {
+            let mut formats: HashMap<String, String> = match tag_formats_file {
+                None => HashMap::new(),
+                Some(p) => fs::read_to_string(&p)
+                    .map_err(|e| e.to_string())
+                    .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+                    .unwrap_or_else(|e| {
+                        eprintln!("Error: could not read tag formats: {}", e);
+                        std::process::exit(1);
+                    }),
+            };
+            for spec in tag_formats {
+                let Some((tag, template)) = spec.split_once('=') else {
+                    eprintln!("Error: --tag-format expects TAG=TEMPLATE, got '{}'", spec);
+                    std::process::exit(1);
+                };
+                formats.insert(tag.to_string(), template.to_string());
+            }
+
+            let profile = options::Profile::from_name(&profile).unwrap_or_else(|| {
+                eprintln!("Error: unknown profile '{}'", profile);
+                std::process::exit(1);
+            });
+            let defaults = profile.render_options();
+            let hide_aliases = aliases == "hide";
+            let alias_style = if hide_aliases {
+                defaults.aliases
+            } else {
+                options::AliasStyle::from_name(&aliases).unwrap_or_else(|| {
+                    eprintln!("Error: unknown alias style '{}'", aliases);
+                    std::process::exit(1);
+                })
+            };
+
+            let mut render_opts = options::RenderOptions {
+                anchor_prefix: anchor_prefix.unwrap_or(defaults.anchor_prefix),
+                include_declarations: include_declarations.unwrap_or(defaults.include_declarations),
+                declarations_base_url,
+                revision,
+                tag_formats: formats,
+                namespace_intros: namespace_intros
+                    .map(|dir| options::load_namespace_intros(&dir))
+                    .transpose()
+                    .unwrap_or_else(|e| {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    })
+                    .unwrap_or_default(),
+                store_path_strip,
+                group_by_prefix,
+                group_by_declaration: match group_by.as_deref() {
+                    None => false,
+                    Some("declaration") => true,
+                    Some(other) => {
+                        eprintln!("Error: unknown grouping '{}'", other);
+                        std::process::exit(1);
+                    }
+                },
+                package_index_url: Some(package_index_url).filter(|url| !url.is_empty()),
+                layout: if layout.is_empty() {
+                    defaults.layout.clone()
+                } else {
+                    options::OptionSection::parse_layout(&layout).unwrap_or_else(|e| {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    })
+                },
+                toc,
+                index,
+                link_types,
+                format_expressions,
+                fold_defaults: fold_defaults
+                    .filter(|_| matches!(output_format.as_str(), "markdown" | "html")),
+                heading_levels: heading_levels(category_heading_level, entry_heading_level),
+                sort: sort.as_deref().map_or(defaults.sort, sort_order),
+                aliases: alias_style,
+                ..defaults
+            };
+            render_opts.labels =
+                renderer_labels(locale.as_deref(), strings.as_deref(), render_opts.labels).1;
+            for spec in labels {
+                let Some((name, text)) = spec.split_once('=') else {
+                    eprintln!("Error: --label expects NAME=TEXT, got '{}'", spec);
+                    std::process::exit(1);
+                };
+                render_opts.labels.set(name, text).unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                });
+            }
+
+            let mut provenance = options::Provenance {
+                revision: render_opts.revision.clone(),
+                generated,
+                generator,
+            };
+            let mut inputs = vec![];
+            for path in files {
+                if path.is_dir() {
+                    inputs.extend(
+                        walk::files_with_extension(&path, "json").unwrap_or_else(|e| {
+                            eprintln!("Error reading {}: {}", path.display(), e);
+                            std::process::exit(1);
+                        }),
+                    );
+                } else {
+                    inputs.push(path);
+                }
+            }
+            // Large option sets take a while to parse, so files are parsed in parallel.
+            let parsed_sets: Vec<_> = inputs
+                .par_iter()
+                .map(|file| {
+                    options::parse_options_file_with_provenance(file)
+                        .map_err(|e| format!("{}: {}", file.display(), e))
+                })
+                .collect::<Result<_, _>>()
+                .unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                });
+            let diagnostics_format = diagnostics_format(&args.diagnostics_format);
+            let display = inputs
+                .iter()
+                .map(|file| file.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let mut diagnostics = Diagnostics::new(&display, "", args.strict);
+            let mut sets = vec![];
+            for (file, (set, meta)) in inputs.iter().zip(parsed_sets) {
+                provenance = provenance.or(meta);
+                let origin = file
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                sets.push((origin, set));
+            }
+            render_opts.revision = provenance.revision.clone();
+            render_opts.provenance = provenance;
+            let (mut parsed, conflicts) = options::merge_options(sets, tag_origin);
+            for conflict in &conflicts {
+                diagnostics.push(
+                    DiagnosticKind::ConflictingOption,
+                    None,
+                    format!(
+                        "{} is defined differently by {} and {}; using the definition from {}",
+                        conflict.name, conflict.kept, conflict.dropped, conflict.kept
+                    ),
+                );
+            }
+            options::filter_options(
+                &mut parsed,
+                &options::OptionFilter {
+                    include: include_prefix,
+                    exclude: exclude_prefix,
+                    show_internal,
+                    hide_aliases,
+                },
+            );
+            if render_opts.aliases == options::AliasStyle::Fold {
+                options::fold_aliases(&mut parsed);
+            }
+
+            if validate {
+                let flavor = match flavor {
+                    None => profile.flavor(),
+                    Some(name) => MarkdownFlavor::from_name(&name).unwrap_or_else(|| {
+                        eprintln!("Error: unknown markdown flavor '{}'", name);
+                        std::process::exit(1);
+                    }),
+                };
+                // Issues are located in the description of their option.
+                for (name, issue) in options::validate_descriptions(&parsed, flavor) {
+                    diagnostics.push_in(
+                        &name,
+                        DiagnosticKind::InvalidMarkdown,
+                        Some((issue.line, issue.column)),
+                        issue.message(),
+                    );
+                }
+            }
+
+            let budgets = options::Budgets {
+                max_default_length,
+                max_example_length,
+                max_description_length,
+            };
+            let option_stats = options::collect_stats(&parsed, &budgets, &render_opts);
+            for v in &option_stats.budget_violations {
+                diagnostics.push(
+                    DiagnosticKind::OverBudget,
+                    None,
+                    format!(
+                        "{}: {} is {} characters long (budget {})",
+                        v.option, v.field, v.length, v.limit
+                    ),
+                );
+            }
+            if let Some(stats_path) = stats {
+                let json = serde_json::to_string_pretty(&option_stats).unwrap();
+                fs::write(&stats_path, json).unwrap_or_else(|e| {
+                    eprintln!("Error writing stats: {}", e);
+                    std::process::exit(1);
+                });
+            }
+
+            if link_types {
+                for (option_type, names) in optiontypes::unknown_types(&parsed) {
+                    diagnostics.push(
+                        DiagnosticKind::UnknownOptionType,
+                        None,
+                        format!(
+                            "unknown option type `{}` used by {}",
+                            option_type,
+                            names.join(", ")
+                        ),
+                    );
+                }
+            }
+
+            let option_anchors = options::option_anchors(&parsed, &render_opts.anchor_prefix);
+            for collision in &option_anchors.collisions {
+                diagnostics.push(DiagnosticKind::AnchorCollision, None, collision.message());
+            }
+            report_diagnostics(&diagnostics, diagnostics_format);
+            if let Some(anchors_path) = anchors {
+                let json = serde_json::to_string_pretty(&option_anchors.ids).unwrap();
+                fs::write(&anchors_path, json).unwrap_or_else(|e| {
+                    eprintln!("Error writing anchors: {}", e);
+                    std::process::exit(1);
+                });
+            }
+
+            if !matches!(
+                output_format.as_str(),
+                "markdown" | "man" | "html" | "docbook" | "rst" | "asciidoc"
+            ) {
+                eprintln!("Error: unknown output format '{}'", output_format);
+                std::process::exit(1);
+            }
+
+            let dialect = markdown_dialect(dialect_name.as_deref(), &output_format);
+            let style = admonition_style(style_name.as_deref(), &output_format)
+                .or(dialect.map(|dialect| dialect.admonition_style()));
+            for opt in parsed.values_mut() {
+                if let Some(description) = &mut opt.description {
+                    *description = options::Description::Plain(convert_admonitions(
+                        description.as_str().to_string(),
+                        style,
+                    ));
+                }
+            }
+            let preamble = preamble.map(|preamble| convert_admonitions(preamble, style));
+
+            if let Some(dir) = output_dir {
+                if output_format != "man" {
+                    eprintln!("Error: --output-dir requires --output-format man");
+                    std::process::exit(1);
+                }
+                fs::create_dir_all(&dir).unwrap_or_else(|e| {
+                    eprintln!("Error creating output directory: {}", e);
+                    std::process::exit(1);
+                });
+                let depth = group_by_prefix.unwrap_or(1).max(1);
+                for (prefix, group) in options::split_by_prefix(&parsed, depth) {
+                    let name = if prefix.is_empty() {
+                        "options".to_string()
+                    } else {
+                        prefix
+                    };
+                    let markdown = options::render_options_document(
+                        &group,
+                        &name,
+                        preamble.as_deref(),
+                        &render_opts,
+                    );
+                    let page = man::render_man_page(&name, &man_section, &markdown);
+                    let path = dir.join(format!("{}.{}", name, man_section));
+                    fs::write(&path, page).unwrap_or_else(|e| {
+                        eprintln!("Error writing {}: {}", path.display(), e);
+                        std::process::exit(1);
+                    });
+                }
+                return;
+            }
+
+            let result = match template {
+                Some(_) if output_format == "docbook" => {
+                    eprintln!("Error: --template cannot be combined with --output-format docbook");
+                    std::process::exit(1);
+                }
+                None if output_format == "docbook" => {
+                    docbook::render_options(&parsed, &title, preamble.as_deref(), &render_opts)
+                }
+                Some(path) => {
+                    let context =
+                        OptionsContext::new(&parsed, &title, preamble.as_deref(), &render_opts);
+                    Template::from_file(&path)
+                        .and_then(|template| template.render(&context))
+                        .unwrap_or_else(|e| {
+                            eprintln!("Error: {}", e);
+                            std::process::exit(1);
+                        })
+                }
+                None => options::render_options_document(
+                    &parsed,
+                    &title,
+                    preamble.as_deref(),
+                    &render_opts,
+                ),
+            };
+            if validate || check_links {
+                let mut broken = linkcheck::check_anchors(&result);
+                if check_links {
+                    let checker = linkcheck::CurlChecker {
+                        timeout: link_timeout,
+                    };
+                    broken.extend(linkcheck::check_external(&result, &checker));
+                }
+                let mut diagnostics = Diagnostics::new(&display, "", args.strict);
+                for link in broken {
+                    diagnostics.push(DiagnosticKind::BrokenLink, None, link.message());
+                }
+                report_diagnostics(&diagnostics, diagnostics_format);
+            }
+            let result = match output_format.as_str() {
+                "man" => man::render_man_page(&title, &man_section, &result),
+                "html" => html::markdown_to_html(
+                    &result,
+                    &html::HtmlOptions {
+                        heading_offset,
+                        class_prefix,
+                    },
+                ),
+                "rst" => rst::markdown_to_rst(&result),
+                "asciidoc" => asciidoc::markdown_to_asciidoc(&result),
+                "markdown" => convert_dialect(result, dialect, style),
+                _ => result,
+            };
+
+            if let Some(out_path) = output {
+                fs::write(&out_path, &result).unwrap_or_else(|e| {
+                    eprintln!("Error writing output: {}", e);
+                    std::process::exit(1);
+                });
+            } else {
+                println!("{}", result);
+            }
+        }
+        Some(Command::Module {
+            file,
+            output,
+            title,
+            anchor_prefix,
+            show_internal,
+        }) => {
+            let (src, _) = read_source(&file).unwrap_or_else(|e| {
+                eprintln!("Error reading {}: {}", file.display(), e);
+                std::process::exit(1);
+            });
+            let nix = rnix::Root::parse(&src).ok().unwrap_or_else(|e| {
+                eprintln!("Error: failed to parse {}: {}", file.display(), e);
+                std::process::exit(1);
+            });
+            let display = file.display().to_string();
+            let mut declared = module::module_options(&nix, &display);
+            for name in &declared.unknown {
+                eprintln!(
+                    "warning: {}: `{}` is not declared with mkOption and is not documented",
+                    display, name
+                );
+            }
+            options::filter_options(
+                &mut declared.options,
+                &options::OptionFilter {
+                    show_internal,
+                    ..Default::default()
+                },
+            );
+            let render_opts = options::RenderOptions {
+                anchor_prefix,
+                ..Default::default()
+            };
+            let result =
+                options::render_options_document(&declared.options, &title, None, &render_opts);
+            if let Some(out_path) = output {
+                fs::write(&out_path, &result).unwrap_or_else(|e| {
+                    eprintln!("Error writing output: {}", e);
+                    std::process::exit(1);
+                });
+            } else {
+                println!("{}", result);
+            }
+        }
+        Some(Command::FileDoc {
+            file,
+            format,
+            output_dir,
+            shift_headings: shift_amount,
+            admonition_style: style_name,
+        }) => {
+            let style = admonition_style(style_name.as_deref(), "markdown");
+            let render = |doc: String| {
+                let doc = convert_admonitions(doc, style);
+                if shift_amount > 0 {
+                    shift_headings(&doc, shift_amount)
+                } else {
+                    doc
+                }
+            };
+            let doc_json = |key: &str, path: &str, doc: Option<String>| {
+                let parts = doc
+                    .as_deref()
+                    .map(filedoc::FileDoc::from_doc)
+                    .unwrap_or_default();
+                serde_json::json!({
+                    key: path,
+                    "doc": doc,
+                    "title": parts.title,
+                    "summary": parts.summary,
+                    "body": parts.body,
+                })
+            };
+
+            if file.is_dir() {
+                let docs = filedoc::collect_file_docs(&file).unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                });
+                if format == "json" {
+                    let docs: Vec<_> = docs
+                        .into_iter()
+                        .map(|d| doc_json("path", &d.path, d.doc.map(render)))
+                        .collect();
+                    println!("{}", serde_json::to_string_pretty(&docs).unwrap());
+                    return;
+                }
+                let Some(output_dir) = output_dir else {
+                    eprintln!(
+                        "Error: --output-dir is required for {} output of a directory",
+                        format
+                    );
+                    std::process::exit(1);
+                };
+                for d in docs {
+                    let Some(doc) = d.doc else {
+                        continue;
+                    };
+                    let out_path = output_dir.join(&d.path).with_extension("md");
+                    if let Some(parent) = out_path.parent() {
+                        fs::create_dir_all(parent).unwrap_or_else(|e| {
+                            eprintln!("Error creating {}: {}", parent.display(), e);
+                            std::process::exit(1);
+                        });
+                    }
+                    fs::write(&out_path, format!("{}\n", render(doc))).unwrap_or_else(|e| {
+                        eprintln!("Error writing {}: {}", out_path.display(), e);
+                        std::process::exit(1);
+                    });
+                }
+                return;
+            }
+
+            let src = fs::read_to_string(&file).unwrap_or_else(|e| {
+                eprintln!("Error reading file: {}", e);
+                std::process::exit(1);
+            });
+            let nix = rnix::Root::parse(&src).ok().expect("failed to parse input");
+            let doc = extract_file_doc(&nix).map(render);
+
+            match format.as_str() {
+                "json" => {
+                    let json_obj = doc_json("file", &file.to_string_lossy(), doc);
+                    println!("{}", serde_json::to_string_pretty(&json_obj).unwrap());
+                }
+                "plain" => {
                     if let Some(d) = doc {
                         println!("{}", d);
                     }
                 }
+                _ => {
+                    if let Some(d) = doc {
+                        println!("{}", d);
+                    }
+                }
+            }
+        }
+        Some(Command::Batch {
+            files,
+            output_dir,
+            output,
+            prefix,
+            anchor_prefix,
+            include,
+            exclude,
+            cache_dir,
+            jobs,
+            layout,
+            admonition_style: style_name,
+            sort,
+            category_heading_level,
+            entry_heading_level,
+            index,
+            locale,
+            strings,
+            default_example_language,
+        }) => {
+            if let Some(jobs) = jobs {
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(jobs)
+                    .build_global()
+                    .unwrap_or_else(|e| {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    });
+            }
+            let opts = batch::BatchOptions {
+                prefix,
+                anchor_prefix,
+                include,
+                exclude,
+                cache_dir,
+                layout: entry_layout(&layout),
+                admonition_style: admonition_style(style_name.as_deref(), "markdown"),
+                sort: sort_order(&sort),
+                heading_levels: heading_levels(category_heading_level, entry_heading_level),
+                labels: renderer_labels(
+                    locale.as_deref(),
+                    strings.as_deref(),
+                    options::Labels::default(),
+                )
+                .0,
+                default_example_language,
+                page_extension: output_dir.as_ref().map(|_| "md".to_string()),
+            };
+            let outputs = batch::expand_inputs(&files)
+                .map_err(|e| e.to_string())
+                .and_then(|inputs| batch::render_batch(&inputs, &opts))
+                .unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                });
+
+            for out in &outputs {
+                for warning in &out.warnings {
+                    eprintln!("warning: {}: {}", out.input.display(), warning);
+                }
+                for reference in &out.dangling {
+                    eprintln!(
+                        "warning: {}: {}: reference to unknown entry `{}`",
+                        out.input.display(),
+                        reference.entry,
+                        reference.target
+                    );
+                }
+            }
+
+            if let Some(dir) = output_dir {
+                batch::write_outputs(&outputs, &dir)
+                    .and_then(|()| {
+                        if !index {
+                            return Ok(());
+                        }
+                        let page = batch::render_index(&outputs, &opts, true);
+                        fs::write(dir.join("all-functions.md"), page)
+                    })
+                    .unwrap_or_else(|e| {
+                        eprintln!("Error writing output: {}", e);
+                        std::process::exit(1);
+                    });
+            } else {
+                let mut combined: String =
+                    outputs.iter().map(|out| out.markdown.as_str()).collect();
+                if index {
+                    combined.push_str(&batch::render_index(&outputs, &opts, false));
+                }
+                if let Some(out_path) = output {
+                    fs::write(&out_path, &combined).unwrap_or_else(|e| {
+                        eprintln!("Error writing output: {}", e);
+                        std::process::exit(1);
+                    });
+                } else {
+                    println!("{}", combined);
+                }
+            }
+        }
+        Some(Command::Serve {
+            files,
+            options: option_files,
+            bind,
+            port,
+            prefix,
+            anchor_prefix,
+        }) => {
+            if files.is_empty() && option_files.is_empty() {
+                eprintln!("Error: nothing to serve, pass --file or --options");
+                std::process::exit(1);
+            }
+            let listener = TcpListener::bind((bind.as_str(), port)).unwrap_or_else(|e| {
+                eprintln!("Error: could not listen on {}:{}: {}", bind, port, e);
+                std::process::exit(1);
+            });
+            let watched = files.iter().chain(&option_files).cloned().collect();
+            let opts = batch::BatchOptions {
+                prefix,
+                anchor_prefix,
+                include: vec![],
+                exclude: vec![],
+                cache_dir: None,
+                layout: entry_layout(&[]),
+                // Pages are converted to HTML, which reads nixpkgs-style admonitions.
+                admonition_style: Some(AdmonitionStyle::Nixpkgs),
+                sort: SortOrder::Source,
+                heading_levels: HeadingLevels::default(),
+                labels: EntryLabels::default(),
+                default_example_language: None,
+                page_extension: Some("html".to_string()),
+            };
+            let build = move || -> Result<Vec<serve::Page>, String> {
+                let html_opts = html::HtmlOptions::default();
+                let mut pages = vec![];
+                if !files.is_empty() {
+                    let inputs = batch::expand_inputs(&files).map_err(|e| e.to_string())?;
+                    for out in batch::render_batch(&inputs, &opts)? {
+                        for warning in &out.warnings {
+                            eprintln!("warning: {}: {}", out.input.display(), warning);
+                        }
+                        pages.push(serve::Page {
+                            title: format!("{}.{}", opts.prefix, out.category),
+                            html: html::markdown_to_html(&out.markdown, &html_opts),
+                            name: out.category,
+                        });
+                    }
+                }
+                if !option_files.is_empty() {
+                    let mut sets = vec![];
+                    for path in &option_files {
+                        let inputs = if path.is_dir() {
+                            walk::files_with_extension(path, "json").map_err(|e| e.to_string())?
+                        } else {
+                            vec![path.clone()]
+                        };
+                        for input in inputs {
+                            let parsed = options::parse_options_file(&input)?;
+                            sets.push((batch::category_for(&input), parsed));
+                        }
+                    }
+                    let (parsed, _) = options::merge_options(sets, false);
+                    let markdown = options::render_options_document(
+                        &parsed,
+                        "Module Options",
+                        None,
+                        &options::RenderOptions::default(),
+                    );
+                    pages.push(serve::Page {
+                        name: "options".to_string(),
+                        title: "Module Options".to_string(),
+                        html: html::markdown_to_html(&markdown, &html_opts),
+                    });
+                }
+                Ok(pages)
+            };
+            eprintln!("Serving documentation at http://{}:{}/", bind, port);
+            serve::serve(listener, watched, build).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            });
+        }
+        Some(Command::Mdbook {
+            files,
+            options: option_files,
+            output_dir,
+            options_title,
+            prefix,
+            anchor_prefix,
+        }) => {
+            let mut book = mdbook::Book::default();
+            if !files.is_empty() {
+                let opts = batch::BatchOptions {
+                    prefix,
+                    anchor_prefix,
+                    include: vec![],
+                    exclude: vec![],
+                    cache_dir: None,
+                    layout: entry_layout(&[]),
+                    admonition_style: None,
+                    sort: SortOrder::Source,
+                    heading_levels: HeadingLevels::default(),
+                    labels: EntryLabels::default(),
+                    default_example_language: None,
+                    page_extension: Some("md".to_string()),
+                };
+                let outputs = batch::expand_inputs(&files)
+                    .map_err(|e| e.to_string())
+                    .and_then(|inputs| batch::render_batch(&inputs, &opts))
+                    .unwrap_or_else(|e| {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    });
+                for out in outputs {
+                    for warning in &out.warnings {
+                        eprintln!("warning: {}: {}", out.input.display(), warning);
+                    }
+                    book.chapters.push(mdbook::Chapter::new(
+                        &format!("{}.{}", opts.prefix, out.category),
+                        &format!("{}.md", out.category),
+                        out.markdown,
+                    ));
+                }
+            }
+            if !option_files.is_empty() {
+                let mut sets = vec![];
+                for path in &option_files {
+                    let inputs = if path.is_dir() {
+                        walk::files_with_extension(path, "json").unwrap_or_else(|e| {
+                            eprintln!("Error reading {}: {}", path.display(), e);
+                            std::process::exit(1);
+                        })
+                    } else {
+                        vec![path.clone()]
+                    };
+                    for input in inputs {
+                        let parsed = options::parse_options_file(&input).unwrap_or_else(|e| {
+                            eprintln!("Error: {}", e);
+                            std::process::exit(1);
+                        });
+                        sets.push((batch::category_for(&input), parsed));
+                    }
+                }
+                let (parsed, conflicts) = options::merge_options(sets, false);
+                for conflict in &conflicts {
+                    eprintln!(
+                        "warning: {} is defined differently by {} and {}; using the definition from {}",
+                        conflict.name, conflict.kept, conflict.dropped, conflict.kept
+                    );
+                }
+                let render_opts = options::RenderOptions::default();
+                let mut chapter = mdbook::Chapter::new(
+                    &options_title,
+                    "options.md",
+                    format!("# {}\n\n", options_title),
+                );
+                for (namespace, group) in options::split_by_prefix(&parsed, 1) {
+                    let name = if namespace.is_empty() {
+                        "other".to_string()
+                    } else {
+                        namespace
+                    };
+                    let path = format!("options/{}.md", name);
+                    chapter
+                        .content
+                        .push_str(&format!("- [`{}`]({})\n", name, path));
+                    chapter.children.push(mdbook::Chapter::new(
+                        &name,
+                        &path,
+                        options::render_options_document(&group, &name, None, &render_opts),
+                    ));
+                }
+                book.chapters.push(chapter);
+            }
+            book.write(&output_dir).unwrap_or_else(|e| {
+                eprintln!("Error writing output: {}", e);
+                std::process::exit(1);
+            });
+        }
+        Some(Command::LinkGraph {
+            files,
+            format,
+            output,
+        }) => {
+            let mut documents = vec![];
+            for path in files {
+                let found = if path.is_dir() {
+                    walk::files_with_extension(&path, "md")
+                        .map(|found| {
+                            found
+                                .into_iter()
+                                .map(|f| {
+                                    let name = f.strip_prefix(&path).unwrap_or(&f).to_path_buf();
+                                    (name, f)
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_else(|e| {
+                            eprintln!("Error reading {}: {}", path.display(), e);
+                            std::process::exit(1);
+                        })
+                } else {
+                    vec![(path.clone(), path)]
+                };
+                for (name, file) in found {
+                    let text = fs::read_to_string(&file).unwrap_or_else(|e| {
+                        eprintln!("Error reading {}: {}", file.display(), e);
+                        std::process::exit(1);
+                    });
+                    documents.push((name.to_string_lossy().into_owned(), text));
+                }
+            }
+
+            let graph = LinkGraph::build(&documents);
+            let result = match format.as_str() {
+                "dot" => graph.to_dot(),
+                "json" => serde_json::to_string_pretty(&graph).unwrap(),
+                other => {
+                    eprintln!("Error: unknown output format '{}'", other);
+                    std::process::exit(1);
+                }
+            };
+
+            if let Some(out_path) = output {
+                fs::write(&out_path, &result).unwrap_or_else(|e| {
+                    eprintln!("Error writing output: {}", e);
+                    std::process::exit(1);
+                });
+            } else {
+                println!("{}", result);
+            }
+        }
+        Some(Command::Lint {
+            file,
+            format,
+            export,
+            strict,
+            allow,
+            allow_config,
+        }) => {
+            let mut opts = lint::LintOptions {
+                export,
+                strict,
+                allow: allow_config
+                    .map(|p| lint::load_allow_config(&p))
+                    .transpose()
+                    .unwrap_or_else(|e| {
+                        eprintln!("Error: could not read allow config: {}", e);
+                        std::process::exit(1);
+                    })
+                    .unwrap_or_default(),
+            };
+            for name in allow {
+                let Some(kind) = lint::LintKind::from_name(&name) else {
+                    eprintln!("Error: unknown check '{}'", name);
+                    std::process::exit(1);
+                };
+                opts.allow.push(lint::Allow {
+                    pattern: "*".to_string(),
+                    kind,
+                });
+            }
+
+            let diagnostics = fs::read_to_string(&file)
+                .map_err(|e| e.to_string())
+                .and_then(|src| lint::lint_source(&src, &opts))
+                .unwrap_or_else(|e| {
+                    eprintln!("Error reading {}: {}", file.display(), e);
+                    std::process::exit(1);
+                });
+
+            match format.as_str() {
+                "json" => println!("{}", serde_json::to_string_pretty(&diagnostics).unwrap()),
+                "text" => {
+                    for d in &diagnostics {
+                        println!(
+                            "{}:{}:{}: {} [{}]",
+                            file.display(),
+                            d.line,
+                            d.column,
+                            d.message(),
+                            d.kind.as_str()
+                        );
+                    }
+                }
+                other => {
+                    eprintln!("Error: unknown output format '{}'", other);
+                    std::process::exit(1);
+                }
+            }
+
+            if !diagnostics.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Coverage {
+            files,
+            format,
+            fail_under,
+        }) => {
+            let inputs = batch::expand_inputs(&files).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            });
+            let coverages: Vec<(String, lint::Coverage)> = inputs
+                .par_iter()
+                .map(|file| {
+                    fs::read_to_string(file)
+                        .map_err(|e| e.to_string())
+                        .and_then(|src| lint::coverage_source(&src, &None))
+                        .map(|coverage| (file.display().to_string(), coverage))
+                        .map_err(|e| format!("{}: {}", file.display(), e))
+                })
+                .collect::<Result<_, _>>()
+                .unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                });
+            let mut total = lint::Coverage::default();
+            for (_, coverage) in &coverages {
+                total.add(coverage);
+            }
+
+            match format.as_str() {
+                "json" => {
+                    let row = |coverage: &lint::Coverage| {
+                        let mut value = serde_json::to_value(coverage).unwrap();
+                        value["percent"] = coverage.percent().into();
+                        value
+                    };
+                    let files: Vec<_> = coverages
+                        .iter()
+                        .map(|(file, coverage)| {
+                            let mut value = row(coverage);
+                            value["file"] = file.as_str().into();
+                            value
+                        })
+                        .collect();
+                    let json_obj = serde_json::json!({
+                        "files": files,
+                        "total": row(&total),
+                    });
+                    println!("{}", serde_json::to_string_pretty(&json_obj).unwrap());
+                }
+                _ => print!("{}", lint::coverage_table(&coverages, &total)),
+            }
+
+            if let Some(threshold) = fail_under {
+                if total.percent() < threshold {
+                    eprintln!(
+                        "Error: {:.1}% of the bindings are documented, less than the required {}%",
+                        total.percent(),
+                        threshold
+                    );
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Command::TestExamples {
+            files,
+            scope,
+            evaluator,
+        }) => {
+            let evaluator = doctest::CommandEvaluator::from_name(&evaluator).unwrap_or_else(|| {
+                eprintln!("Error: unknown evaluator '{}'", evaluator);
+                std::process::exit(1);
+            });
+            let inputs = batch::expand_inputs(&files).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            });
+
+            let mut checks = vec![];
+            for input in &inputs {
+                let (src, _) = read_source(input).unwrap_or_else(|e| {
+                    eprintln!("Error reading {}: {}", input.display(), e);
+                    std::process::exit(1);
+                });
+                let opts = CollectOptions::new("lib", &batch::category_for(input));
+                let entries = nixdoc::collect_entries_from_str(&src, &opts).unwrap_or_else(|e| {
+                    eprintln!("Error: {}: {}", input.display(), e);
+                    std::process::exit(1);
+                });
+                let scope = scope
+                    .clone()
+                    .unwrap_or_else(|| doctest::default_scope(input));
+                for example in doctest::extract_examples(&entries) {
+                    checks.push((input, scope.clone(), example));
+                }
+            }
+
+            // Every evaluation starts a Nix process, so examples are checked in
+            // parallel.
+            let outcomes: Vec<doctest::Outcome> = checks
+                .par_iter()
+                .map(|(_, scope, example)| doctest::check_example(example, scope, &evaluator))
+                .collect();
+            let mut failed = 0;
+            for ((input, _, example), outcome) in checks.iter().zip(&outcomes) {
+                let reason = match outcome {
+                    doctest::Outcome::Passed => continue,
+                    doctest::Outcome::Mismatch { actual } => {
+                        format!("expected {}, got {}", example.expected, actual)
+                    }
+                    doctest::Outcome::Error(e) => e.clone(),
+                };
+                failed += 1;
+                println!(
+                    "{}: {}: `{}`: {}",
+                    input.display(),
+                    example.entry,
+                    example.expr,
+                    reason
+                );
+            }
+            println!(
+                "{} of {} examples passed",
+                checks.len() - failed,
+                checks.len()
+            );
+            if failed > 0 {
+                std::process::exit(1);
+            }
+        }
+        Some(Command::DiffJson {
+            old,
+            new,
+            format,
+            output,
+        }) => {
+            let read = |path: &PathBuf| -> JsonFormat {
+                fs::read_to_string(path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+                    .unwrap_or_else(|e| {
+                        eprintln!("Error reading {}: {}", path.display(), e);
+                        std::process::exit(1);
+                    })
+            };
+            let changeset = diff::diff_json(&read(&old), &read(&new));
+            let result = match format.as_str() {
+                "text" => changeset.summary(),
+                "json" => serde_json::to_string_pretty(&changeset).unwrap(),
+                other => {
+                    eprintln!("Error: unknown output format '{}'", other);
+                    std::process::exit(1);
+                }
+            };
+
+            if let Some(out_path) = output {
+                fs::write(&out_path, &result).unwrap_or_else(|e| {
+                    eprintln!("Error writing output: {}", e);
+                    std::process::exit(1);
+                });
+            } else {
+                println!("{}", result);
+            }
+        }
+        Some(Command::OptionsDiff {
+            old,
+            new,
+            format,
+            output,
+        }) => {
+            let read = |path: &PathBuf| {
+                options::parse_options_file(path).unwrap_or_else(|e| {
+                    eprintln!("Error reading {}: {}", path.display(), e);
+                    std::process::exit(1);
+                })
+            };
+            let changeset = diff::diff_options(&read(&old), &read(&new));
+            let result = match format.as_str() {
+                "markdown" => diff::render_option_changes(&changeset),
+                "json" => serde_json::to_string_pretty(&changeset).unwrap() + "\n",
+                other => {
+                    eprintln!("Error: unknown output format '{}'", other);
+                    std::process::exit(1);
+                }
+            };
+
+            if let Some(out_path) = output {
+                fs::write(&out_path, &result).unwrap_or_else(|e| {
+                    eprintln!("Error writing output: {}", e);
+                    std::process::exit(1);
+                });
+            } else {
+                print!("{}", result);
+            }
+        }
+        Some(Command::ModuleIndex {
+            dir,
+            title,
+            base_url,
+            output,
+        }) => {
+            let entries =
+                filedoc::collect_module_index(&dir, base_url.as_deref()).unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                });
+            let result = filedoc::render_module_index(&title, &entries);
+            if let Some(out_path) = output {
+                fs::write(&out_path, &result).unwrap_or_else(|e| {
+                    eprintln!("Error writing output: {}", e);
+                    std::process::exit(1);
+                });
+            } else {
+                println!("{}", result);
+            }
+        }
+        Some(Command::SearchIndex {
+            files,
+            options: option_files,
+            prefix,
+            anchor_prefix,
+            option_anchor_prefix,
+            format,
+            output,
+        }) => {
+            let mut documents = vec![];
+            let inputs = batch::expand_inputs(&files).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            });
+            for input in inputs {
+                let opts = CollectOptions::new(&prefix, &batch::category_for(&input));
+                let entries = fs::read_to_string(&input)
+                    .map_err(|e| e.to_string())
+                    .and_then(|source| nixdoc::collect_entries_from_str(&source, &opts))
+                    .unwrap_or_else(|e| {
+                        eprintln!("Error: {}: {}", input.display(), e);
+                        std::process::exit(1);
+                    });
+                documents.extend(search::function_documents(&entries, &anchor_prefix));
+            }
+            for path in option_files {
+                let parsed = options::parse_options_file(&path).unwrap_or_else(|e| {
+                    eprintln!("Error: {}: {}", path.display(), e);
+                    std::process::exit(1);
+                });
+                documents.extend(search::option_documents(&parsed, &option_anchor_prefix));
+            }
+
+            let result = match format.as_str() {
+                "documents" => serde_json::to_string(&documents),
+                "inverted" => serde_json::to_string(&search::InvertedIndex::new(documents)),
+                other => {
+                    eprintln!("Error: unknown index format '{}'", other);
+                    std::process::exit(1);
+                }
+            }
+            .expect("Problem converting the search index to JSON");
+            if let Some(out_path) = output {
+                fs::write(&out_path, &result).unwrap_or_else(|e| {
+                    eprintln!("Error writing output: {}", e);
+                    std::process::exit(1);
+                });
+            } else {
+                println!("{}", result);
+            }
+        }
+        Some(Command::Extract {
+            files,
+            prefix,
+            depth,
+            format,
+            output,
+        }) => {
+            let format = extract::ExtractFormat::from_name(&format).unwrap_or_else(|| {
+                eprintln!("Error: unknown extraction format '{}'", format);
+                std::process::exit(1);
+            });
+            let inputs = batch::expand_inputs(&files).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            });
+            let extracted = inputs
+                .par_iter()
+                .map(|input| {
+                    let opts = CollectOptions {
+                        file: Some(input.display().to_string()),
+                        base_dir: input.parent().map(Path::to_path_buf),
+                        depth,
+                        ..CollectOptions::new(&prefix, &batch::category_for(input))
+                    };
+                    read_source(input)
+                        .map_err(|e| e.to_string())
+                        .and_then(|(source, _)| {
+                            extract::extract_source(&input.display().to_string(), &source, &opts)
+                        })
+                        .map_err(|e| format!("{}: {}", input.display(), e))
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                });
+            let result = format
+                .encode(&extract::Extraction::new(extracted))
+                .unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                });
+            let written = match output {
+                Some(out_path) => fs::write(&out_path, &result),
+                None => std::io::stdout().write_all(&result),
+            };
+            written.unwrap_or_else(|e| {
+                eprintln!("Error writing output: {}", e);
+                std::process::exit(1);
+            });
+        }
+        Some(Command::TagIndex {
+            files,
+            options: option_files,
+            prefix,
+            anchor_prefix,
+            option_anchor_prefix,
+            function_page,
+            options_page,
+            title,
+            format,
+            output,
+        }) => {
+            let mut index = tagindex::TagIndex::default();
+            let inputs = batch::expand_inputs(&files).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            });
+            for input in inputs {
+                let opts = CollectOptions::new(&prefix, &batch::category_for(&input));
+                let entries = fs::read_to_string(&input)
+                    .map_err(|e| e.to_string())
+                    .and_then(|source| nixdoc::collect_entries_from_str(&source, &opts))
+                    .unwrap_or_else(|e| {
+                        eprintln!("Error: {}: {}", input.display(), e);
+                        std::process::exit(1);
+                    });
+                index.add_functions(&entries, &anchor_prefix, &function_page);
+            }
+            for path in option_files {
+                let parsed = options::parse_options_file(&path).unwrap_or_else(|e| {
+                    eprintln!("Error: {}: {}", path.display(), e);
+                    std::process::exit(1);
+                });
+                index.add_options(&parsed, &option_anchor_prefix, &options_page);
+            }
+            if index.is_empty() {
+                eprintln!("warning: no function or option is tagged with keywords");
+            }
+
+            let result = match format.as_str() {
+                "markdown" => index.to_markdown(&title),
+                "json" => {
+                    serde_json::to_string(&index).expect("Problem converting the index to JSON")
+                }
+                other => {
+                    eprintln!("Error: unknown index format '{}'", other);
+                    std::process::exit(1);
+                }
+            };
+            if let Some(out_path) = output {
+                fs::write(&out_path, &result).unwrap_or_else(|e| {
+                    eprintln!("Error writing output: {}", e);
+                    std::process::exit(1);
+                });
+            } else {
+                print!("{}", result);
+            }
+        }
+        Some(Command::Inventory {
+            files,
+            options: option_files,
+            prefix,
+            anchor_prefix,
+            option_anchor_prefix,
+            function_page,
+            options_page,
+            project,
+            version,
+            format,
+            output,
+        }) => {
+            let mut inventory = inventory::Inventory {
+                project,
+                version,
+                items: vec![],
+            };
+            let inputs = batch::expand_inputs(&files).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            });
+            for input in inputs {
+                let opts = CollectOptions::new(&prefix, &batch::category_for(&input));
+                let entries = fs::read_to_string(&input)
+                    .map_err(|e| e.to_string())
+                    .and_then(|source| nixdoc::collect_entries_from_str(&source, &opts))
+                    .unwrap_or_else(|e| {
+                        eprintln!("Error: {}: {}", input.display(), e);
+                        std::process::exit(1);
+                    });
+                inventory.items.extend(inventory::function_items(
+                    &entries,
+                    &anchor_prefix,
+                    &function_page,
+                ));
+            }
+            for path in option_files {
+                let parsed = options::parse_options_file(&path).unwrap_or_else(|e| {
+                    eprintln!("Error: {}: {}", path.display(), e);
+                    std::process::exit(1);
+                });
+                inventory.items.extend(inventory::option_items(
+                    &parsed,
+                    &option_anchor_prefix,
+                    &options_page,
+                ));
+            }
+
+            let result = match format.as_str() {
+                "json" => serde_json::to_vec(&inventory)
+                    .expect("Problem converting the inventory to JSON"),
+                "objects.inv" => inventory.to_objects_inv(),
+                other => {
+                    eprintln!("Error: unknown inventory format '{}'", other);
+                    std::process::exit(1);
+                }
+            };
+            let written = match output {
+                Some(out_path) => fs::write(&out_path, &result),
+                None => std::io::stdout().write_all(&result),
+            };
+            written.unwrap_or_else(|e| {
+                eprintln!("Error writing output: {}", e);
+                std::process::exit(1);
+            });
+        }
+        Some(Command::Schema) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&jsonv2::Document::schema()).unwrap()
+            );
+        }
+        Some(Command::Completions { shell }) => {
+            let mut command = Args::command();
+            let name = command.get_name().to_string();
+            clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+        }
+        Some(Command::Compat {
+            files,
+            title,
+            output,
+        }) => {
+            let mut versions = vec![];
+            for spec in files {
+                let Some((version, path)) = spec.split_once('=') else {
+                    eprintln!("Error: --file expects VERSION=PATH, got '{}'", spec);
+                    std::process::exit(1);
+                };
+                let parsed = options::parse_options_file(path.as_ref()).unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                });
+                versions.push((version.to_string(), parsed));
+            }
+
+            let labels: Vec<String> = versions.iter().map(|(v, _)| v.clone()).collect();
+            let result = compat::render_matrix(&title, &labels, &compat::build_matrix(&versions));
+
+            if let Some(out_path) = output {
+                fs::write(&out_path, &result).unwrap_or_else(|e| {
+                    eprintln!("Error writing output: {}", e);
+                    std::process::exit(1);
+                });
+            } else {
+                println!("{}", result);
             }
         }
         None => {
+            let mut args = args;
+            if let Some(spec) = &args.source {
+                let work_dir = match &args.cache_dir {
+                    Some(dir) => Ok(dir.join("sources")),
+                    None => source::default_work_dir(),
+                };
+                let path = SourceSpec::parse(spec)
+                    .and_then(|source| source.resolve(&work_dir?))
+                    .unwrap_or_else(|e| {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    });
+                args.file = Some(path);
+            }
             if args.file.is_none() {
-                eprintln!("Error: --file is required");
+                eprintln!("Error: --file or --source is required");
                 std::process::exit(1);
             }
-            let output = main_with_args(&args);
-            println!("{}", output)
+            let written = match &args.output {
+                Some(path) => fs::File::create(path).and_then(|file| {
+                    let mut out = io::BufWriter::new(file);
+                    write_with_args(&args, &mut out)?;
+                    writeln!(out)?;
+                    out.flush()
+                }),
+                None => {
+                    let mut out = io::BufWriter::new(io::stdout().lock());
+                    write_with_args(&args, &mut out)
+                        .and_then(|()| writeln!(out))
+                        .and_then(|()| out.flush())
+                }
+            };
+            written.unwrap_or_else(|e| {
+                eprintln!("Error writing output: {}", e);
+                std::process::exit(1);
+            });
         }
     }
 }
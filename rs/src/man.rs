@@ -0,0 +1,239 @@
+//! Rendering of generated markdown as roff man pages.
+//!
+//! The markdown produced for a category or an options document is converted
+//! to the `man(7)` macro package, so it can be installed and read with
+//! `man 3 lib.strings` or `man 5 configuration.nix`. Nixpkgs extensions are
+//! handled as far as a terminal can show them: `{#anchor}` attributes and
+//! `[]{#anchor}` lines are dropped, fenced divs (`::: {.example}`) are
+//! unwrapped and headings inside them become bold paragraphs. Tables are
+//! emitted for `tbl(1)`.
+
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+
+use crate::format::{div_lines, inline_anchor, strip_role, DivLine};
+
+/// Render `markdown` as a man page titled `name` in manual `section`.
+pub fn render_man_page(name: &str, section: &str, markdown: &str) -> String {
+    format!(
+        ".TH \"{}\" \"{}\"\n.SH NAME\n{}\n{}",
+        escape(name),
+        escape(section),
+        escape(name),
+        markdown_to_roff(markdown)
+    )
+}
+
+/// Convert markdown to the body of a man page.
+pub fn markdown_to_roff(markdown: &str) -> String {
+    let source = unwrap_fenced_divs(markdown);
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_HEADING_ATTRIBUTES
+        | Options::ENABLE_DEFINITION_LIST;
+
+    let mut out = String::new();
+    // Counters of the open lists, `None` for bullet lists.
+    let mut lists: Vec<Option<u64>> = vec![];
+    let mut links: Vec<String> = vec![];
+    // Whether the next paragraph continues an item and needs no break.
+    let mut item_start = false;
+    let mut in_code_block = false;
+    // Whether the text is bold already, so code needs no font change.
+    let mut in_strong = false;
+
+    for event in Parser::new_ext(&source, options) {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                line_start(&mut out);
+                out.push_str(if level == HeadingLevel::H1 {
+                    ".SH "
+                } else {
+                    ".SS "
+                });
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                // Headings consisting only of an anchor are dropped.
+                if out.ends_with(".SH ") || out.ends_with(".SS ") {
+                    out.truncate(out.len() - 4);
+                } else {
+                    out.push('\n');
+                }
+            }
+            Event::Start(Tag::Paragraph) => {
+                line_start(&mut out);
+                if item_start {
+                    item_start = false;
+                } else if lists.is_empty() {
+                    out.push_str(".PP\n");
+                } else {
+                    out.push_str(".IP\n");
+                }
+            }
+            Event::End(TagEnd::Paragraph) => out.push('\n'),
+            Event::Start(Tag::CodeBlock(_)) => {
+                line_start(&mut out);
+                if !item_start {
+                    out.push_str(if lists.is_empty() { ".PP\n" } else { ".IP\n" });
+                }
+                item_start = false;
+                in_code_block = true;
+                out.push_str(".EX\n");
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                in_code_block = false;
+                line_start(&mut out);
+                out.push_str(".EE\n");
+            }
+            Event::Start(Tag::List(start)) => {
+                line_start(&mut out);
+                if !lists.is_empty() {
+                    out.push_str(".RS\n");
+                }
+                lists.push(start);
+            }
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+                line_start(&mut out);
+                if !lists.is_empty() {
+                    out.push_str(".RE\n");
+                }
+            }
+            Event::Start(Tag::Item) => {
+                line_start(&mut out);
+                match lists.last_mut() {
+                    Some(Some(n)) => {
+                        out.push_str(&format!(".IP {}. 4\n", n));
+                        *n += 1;
+                    }
+                    _ => out.push_str(".IP \\(bu 2\n"),
+                }
+                item_start = true;
+            }
+            Event::End(TagEnd::Item) => item_start = false,
+            Event::Start(Tag::DefinitionListTitle) => {
+                line_start(&mut out);
+                out.push_str(".TP\n");
+            }
+            Event::End(TagEnd::DefinitionListTitle) => {
+                out.push('\n');
+                item_start = true;
+            }
+            Event::Start(Tag::DefinitionList) => lists.push(None),
+            Event::End(TagEnd::DefinitionList) => {
+                lists.pop();
+            }
+            Event::Start(Tag::BlockQuote(_)) => {
+                line_start(&mut out);
+                out.push_str(".RS\n");
+            }
+            Event::End(TagEnd::BlockQuote(_)) => {
+                line_start(&mut out);
+                out.push_str(".RE\n");
+            }
+            Event::Start(Tag::Table(alignments)) => {
+                line_start(&mut out);
+                out.push_str(".TS\nallbox;\n");
+                out.push_str(&vec!["l"; alignments.len()].join(" "));
+                out.push_str(".\n");
+            }
+            Event::End(TagEnd::Table) => {
+                line_start(&mut out);
+                out.push_str(".TE\n");
+            }
+            Event::End(TagEnd::TableHead) | Event::End(TagEnd::TableRow) => {
+                if out.ends_with('\t') {
+                    out.pop();
+                }
+                out.push('\n');
+            }
+            Event::End(TagEnd::TableCell) => out.push('\t'),
+            Event::Start(Tag::Emphasis) => out.push_str("\\fI"),
+            Event::Start(Tag::Strong) => {
+                in_strong = true;
+                out.push_str("\\fB");
+            }
+            Event::End(TagEnd::Emphasis) => out.push_str("\\fR"),
+            Event::End(TagEnd::Strong) => {
+                in_strong = false;
+                out.push_str("\\fR");
+            }
+            Event::Start(Tag::Link { dest_url, .. }) => links.push(dest_url.to_string()),
+            Event::End(TagEnd::Link) => {
+                // Links into the manual itself are meaningless in a terminal.
+                if let Some(url) = links.pop().filter(|url| !url.starts_with('#')) {
+                    out.push_str(&format!(" <{}>", escape(&url)));
+                }
+            }
+            Event::Code(code) => {
+                strip_role(&mut out);
+                let code = escape(&code).replace('-', "\\-");
+                if in_strong {
+                    text(&mut out, &code);
+                } else {
+                    text(&mut out, &format!("\\fB{}\\fR", code));
+                }
+            }
+            Event::Text(t) if in_code_block => {
+                for line in t.split_inclusive('\n') {
+                    text(&mut out, &escape(line).replace('-', "\\-"));
+                }
+            }
+            Event::Text(t) => text(&mut out, &escape(&t)),
+            Event::SoftBreak => out.push('\n'),
+            Event::HardBreak => {
+                line_start(&mut out);
+                out.push_str(".br\n");
+            }
+            _ => (),
+        }
+    }
+
+    out
+}
+
+/// Drop the `:::` lines of fenced divs and turn the headings inside them into
+/// bold paragraphs, so examples don't start new sections.
+fn unwrap_fenced_divs(markdown: &str) -> String {
+    let mut depth = 0usize;
+    let mut out = String::new();
+    for line in div_lines(markdown) {
+        match line {
+            DivLine::Open(_) => depth += 1,
+            DivLine::Close => depth = depth.saturating_sub(1),
+            DivLine::Text(line, false) if inline_anchor(line).is_some() => (),
+            DivLine::Text(line, in_code) => {
+                let trimmed = line.trim();
+                match trimmed.trim_start_matches('#').strip_prefix(' ') {
+                    Some(heading) if depth > 0 && !in_code && trimmed.starts_with('#') => {
+                        out.push_str(&format!("**{}**\n", heading.trim()));
+                    }
+                    _ => {
+                        out.push_str(line);
+                        out.push('\n');
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Make sure the output ends at the start of a line, so a request can follow.
+fn line_start(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+/// Append text, protecting a leading `.` or `'` from being read as a request.
+fn text(out: &mut String, text: &str) {
+    if (out.is_empty() || out.ends_with('\n')) && (text.starts_with('.') || text.starts_with('\''))
+    {
+        out.push_str("\\&");
+    }
+    out.push_str(text);
+}
+
+/// Escape backslashes for roff.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\e")
+}
@@ -0,0 +1,327 @@
+//! Rendering of generated markdown as lightweight markup languages, shared
+//! by the reStructuredText and AsciiDoc output formats.
+//!
+//! The markdown is rendered by a stack of the open elements, each with the
+//! text rendered for it so far. When an element ends, its content is handed
+//! to the [`Syntax`] of the output format, which indents, delimits or
+//! rewrites it and appends it to the element around it.
+
+use std::marker::PhantomData;
+
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+
+/// A block or inline element whose content is rendered separately, as it is
+/// indented, delimited or rewritten when the element ends.
+pub(crate) enum Frame {
+    Document,
+    Paragraph,
+    Heading {
+        level: usize,
+        id: Option<String>,
+    },
+    CodeBlock {
+        language: String,
+    },
+    BlockQuote,
+    Item {
+        marker: String,
+    },
+    DefinitionTitle,
+    Definition,
+    Link {
+        dest: String,
+    },
+    Image {
+        dest: String,
+    },
+    Emphasis,
+    Strong,
+    Table {
+        rows: Vec<Vec<String>>,
+        header: bool,
+    },
+    Cell,
+    /// A fenced div, with the class the output format renders it as.
+    Div(String),
+}
+
+/// An open list: its start number if it is ordered, and the number of items
+/// rendered so far.
+pub(crate) struct List {
+    pub start: Option<u64>,
+    pub items: usize,
+}
+
+/// The markup of an output format.
+pub(crate) trait Syntax: Sized {
+    /// Separates inline markup from adjacent word characters.
+    const SEPARATOR: &'static str;
+    /// Delimiter of emphasized text.
+    const EMPHASIS: &'static str;
+    /// Delimiter of strong text.
+    const STRONG: &'static str;
+    const HARD_BREAK: &'static str;
+    const RULE: &'static str;
+
+    /// Escape characters that start inline markup.
+    fn escape(text: &str) -> String;
+
+    /// Append a block to `buf`, the content rendered so far for `parent`.
+    fn block(parent: &Frame, buf: &mut String, block: &str);
+
+    /// Marker of the next item of the innermost of `lists`.
+    fn item_marker(lists: &[List]) -> String;
+
+    /// Target of links to `id`.
+    fn anchor(id: &str) -> String;
+
+    /// Inline literal for `code`, following the text rendered by `w` so far.
+    fn code(w: &mut Writer<Self>, code: &str);
+
+    /// Render an element that ended with its rendered `content`.
+    fn close(w: &mut Writer<Self>, frame: Frame, content: String);
+
+    /// Render the content of a table cell.
+    fn cell(content: &str) -> String {
+        content.to_string()
+    }
+
+    /// Finish a list or definition list.
+    fn end_list(_buf: &mut String) {}
+}
+
+pub(crate) struct Writer<S> {
+    /// Open elements, each with the text rendered for it so far.
+    pub stack: Vec<(Frame, String)>,
+    pub lists: Vec<List>,
+    /// Whether the last output was inline markup, after which text must
+    /// not directly continue.
+    after_markup: bool,
+    syntax: PhantomData<S>,
+}
+
+impl<S: Syntax> Writer<S> {
+    pub fn buf(&mut self) -> &mut String {
+        &mut self.stack.last_mut().expect("document frame").1
+    }
+
+    fn push(&mut self, frame: Frame) {
+        self.stack.push((frame, String::new()));
+        self.after_markup = false;
+    }
+
+    fn pop(&mut self) -> (Frame, String) {
+        self.stack.pop().expect("document frame")
+    }
+
+    /// Whether headings are nested in another element, which most formats
+    /// do not allow sections in.
+    pub fn nested(&self) -> bool {
+        self.stack.len() > 1
+    }
+
+    fn in_link(&self) -> bool {
+        self.stack
+            .iter()
+            .any(|(frame, _)| matches!(frame, Frame::Link { .. } | Frame::Image { .. }))
+    }
+
+    /// Number of open fenced divs.
+    pub fn divs(&self) -> usize {
+        self.stack
+            .iter()
+            .filter(|(frame, _)| matches!(frame, Frame::Div(_)))
+            .count()
+    }
+
+    fn text(&mut self, text: &str) {
+        if matches!(self.stack.last(), Some((Frame::CodeBlock { .. }, _))) {
+            self.buf().push_str(text);
+            return;
+        }
+        let escaped = S::escape(text);
+        if self.after_markup && escaped.starts_with(|c: char| c.is_alphanumeric()) {
+            self.buf().push_str(S::SEPARATOR);
+        }
+        self.after_markup = false;
+        self.buf().push_str(&escaped);
+    }
+
+    /// Append inline markup, separated from adjacent word characters.
+    pub fn markup(&mut self, markup: &str) {
+        if self.buf().ends_with(|c: char| c.is_alphanumeric()) {
+            self.buf().push_str(S::SEPARATOR);
+        }
+        self.buf().push_str(markup);
+        self.after_markup = true;
+    }
+
+    /// Append a block to the innermost element.
+    pub fn block(&mut self, block: &str) {
+        let (parent, buf) = self.stack.last_mut().expect("document frame");
+        S::block(parent, buf, block);
+        self.after_markup = false;
+    }
+}
+
+/// Render `source`, markdown with fenced divs turned into `<div>`s by
+/// [`crate::format::divs_to_html`], in the markup of `S`.
+pub(crate) fn render<S: Syntax>(source: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_HEADING_ATTRIBUTES
+        | Options::ENABLE_DEFINITION_LIST;
+
+    let mut w = Writer::<S> {
+        stack: vec![(Frame::Document, String::new())],
+        lists: vec![],
+        after_markup: false,
+        syntax: PhantomData,
+    };
+
+    for event in Parser::new_ext(source, options) {
+        match event {
+            Event::Start(Tag::Paragraph) => w.push(Frame::Paragraph),
+            Event::Start(Tag::Heading { level, id, .. }) => w.push(Frame::Heading {
+                level: level as usize,
+                id: id.map(|id| id.to_string()),
+            }),
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => info
+                        .split_whitespace()
+                        .next()
+                        .unwrap_or_default()
+                        .to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                w.push(Frame::CodeBlock { language });
+            }
+            Event::Start(Tag::BlockQuote(_)) => w.push(Frame::BlockQuote),
+            Event::Start(Tag::List(start)) => w.lists.push(List { start, items: 0 }),
+            Event::Start(Tag::Item) => {
+                let marker = S::item_marker(&w.lists);
+                w.push(Frame::Item { marker });
+            }
+            Event::Start(Tag::DefinitionListTitle) => w.push(Frame::DefinitionTitle),
+            Event::Start(Tag::DefinitionListDefinition) => w.push(Frame::Definition),
+            Event::Start(Tag::Link { dest_url, .. }) => w.push(Frame::Link {
+                dest: dest_url.to_string(),
+            }),
+            Event::Start(Tag::Image { dest_url, .. }) => w.push(Frame::Image {
+                dest: dest_url.to_string(),
+            }),
+            Event::Start(Tag::Emphasis) => w.push(Frame::Emphasis),
+            Event::Start(Tag::Strong) => w.push(Frame::Strong),
+            Event::Start(Tag::Table(_)) => w.push(Frame::Table {
+                rows: vec![],
+                header: false,
+            }),
+            Event::Start(Tag::TableHead | Tag::TableRow) => {
+                let head = matches!(event, Event::Start(Tag::TableHead));
+                if let Some((Frame::Table { rows, header }, _)) = w.stack.last_mut() {
+                    rows.push(vec![]);
+                    *header |= head;
+                }
+            }
+            Event::Start(Tag::TableCell) => w.push(Frame::Cell),
+            Event::Start(_) => (),
+
+            Event::End(TagEnd::Paragraph) => {
+                let (_, text) = w.pop();
+                w.block(&text);
+            }
+            Event::End(TagEnd::List(_) | TagEnd::DefinitionList) => {
+                if matches!(event, Event::End(TagEnd::List(_))) {
+                    w.lists.pop();
+                }
+                S::end_list(w.buf());
+            }
+            Event::End(TagEnd::Item) => {
+                if let Some(list) = w.lists.last_mut() {
+                    list.items += 1;
+                }
+                let (frame, content) = w.pop();
+                S::close(&mut w, frame, content);
+            }
+            Event::End(TagEnd::Emphasis | TagEnd::Strong) => {
+                let (frame, text) = w.pop();
+                let text = text.trim();
+                if text.is_empty() {
+                    continue;
+                }
+                if w.in_link() {
+                    w.buf().push_str(text);
+                    continue;
+                }
+                let delimiter = match frame {
+                    Frame::Strong => S::STRONG,
+                    _ => S::EMPHASIS,
+                };
+                w.markup(&format!("{}{}{}", delimiter, text, delimiter));
+            }
+            Event::End(TagEnd::TableCell) => {
+                let (_, cell) = w.pop();
+                if let Some((Frame::Table { rows, .. }, _)) = w.stack.last_mut() {
+                    if let Some(row) = rows.last_mut() {
+                        row.push(S::cell(cell.trim()));
+                    }
+                }
+            }
+            Event::End(
+                TagEnd::Heading(_)
+                | TagEnd::CodeBlock
+                | TagEnd::BlockQuote(_)
+                | TagEnd::DefinitionListTitle
+                | TagEnd::DefinitionListDefinition
+                | TagEnd::Link
+                | TagEnd::Image
+                | TagEnd::Table,
+            ) => {
+                let (frame, content) = w.pop();
+                S::close(&mut w, frame, content);
+            }
+            Event::End(_) => (),
+
+            Event::Text(text) => w.text(&text),
+            Event::Code(code) => {
+                if w.in_link() {
+                    w.buf().push_str(&code);
+                } else {
+                    S::code(&mut w, &code);
+                }
+            }
+            Event::Html(html) | Event::InlineHtml(html) => {
+                let html = html.trim();
+                if let Some(rest) = html.strip_prefix("<div class=\"") {
+                    let class = rest.split('"').next().unwrap_or_default();
+                    w.push(Frame::Div(class.to_string()));
+                } else if html == "</div>" {
+                    if let Some((frame, content)) =
+                        w.stack.pop_if(|(frame, _)| matches!(frame, Frame::Div(_)))
+                    {
+                        S::close(&mut w, frame, content);
+                    }
+                } else if let Some(id) = html
+                    .strip_prefix("<a id=\"")
+                    .and_then(|rest| rest.strip_suffix("\"></a>"))
+                {
+                    w.block(&S::anchor(id));
+                }
+            }
+            Event::SoftBreak => w.buf().push('\n'),
+            Event::HardBreak => w.buf().push_str(S::HARD_BREAK),
+            Event::Rule => w.block(S::RULE),
+            Event::FootnoteReference(_)
+            | Event::TaskListMarker(_)
+            | Event::InlineMath(_)
+            | Event::DisplayMath(_) => (),
+        }
+    }
+
+    while w.stack.len() > 1 {
+        let (_, content) = w.pop();
+        w.buf().push_str(&content);
+    }
+    let out = w.pop().1;
+    format!("{}\n", out.trim_end())
+}
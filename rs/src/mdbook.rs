@@ -0,0 +1,204 @@
+//! Generation of mdBook source trees.
+//!
+//! A [`Book`] is a tree of chapters, written out as one markdown file per
+//! chapter plus the `SUMMARY.md` mdBook builds its navigation from. mdBook
+//! understands `{#id}` heading attributes, but neither fenced divs nor
+//! `[]{#id}` anchors nor roles, so chapters are converted to plain
+//! CommonMark with HTML for these. Links to anchors defined in another
+//! chapter are pointed at that chapter's file, so they resolve in the book
+//! and pass mdBook's link checker.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use pulldown_cmark::{Event, Options, Parser, Tag};
+
+use crate::format::{div_lines, inline_anchor, DivLine};
+use crate::linkgraph::collect_anchors;
+
+/// A chapter of a book.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter {
+    pub title: String,
+    /// Path of the chapter's file, relative to the book's `src` directory.
+    pub path: String,
+    pub content: String,
+    pub children: Vec<Chapter>,
+}
+
+impl Chapter {
+    pub fn new(title: &str, path: &str, content: String) -> Self {
+        Self {
+            title: title.to_string(),
+            path: path.to_string(),
+            content,
+            children: vec![],
+        }
+    }
+
+    /// This chapter and all chapters below it, depth first.
+    fn flatten(&self) -> Vec<&Chapter> {
+        let mut chapters = vec![self];
+        chapters.extend(self.children.iter().flat_map(Chapter::flatten));
+        chapters
+    }
+}
+
+/// The chapters of an mdBook.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Book {
+    pub chapters: Vec<Chapter>,
+}
+
+impl Book {
+    /// The book's `SUMMARY.md`, listing all chapters in order.
+    pub fn summary(&self) -> String {
+        fn push(out: &mut String, chapter: &Chapter, depth: usize) {
+            out.push_str(&format!(
+                "{}- [{}]({})\n",
+                "  ".repeat(depth),
+                chapter.title.replace('[', "\\[").replace(']', "\\]"),
+                chapter.path
+            ));
+            for child in &chapter.children {
+                push(out, child, depth + 1);
+            }
+        }
+
+        let mut out = String::from("# Summary\n\n");
+        for chapter in &self.chapters {
+            push(&mut out, chapter, 0);
+        }
+        out
+    }
+
+    /// The markdown of every chapter as mdBook renders it, by path.
+    pub fn files(&self) -> Vec<(String, String)> {
+        let chapters: Vec<&Chapter> = self.chapters.iter().flat_map(Chapter::flatten).collect();
+
+        // The first chapter defining an anchor is where links to it go.
+        let mut defined_in: HashMap<String, &str> = HashMap::new();
+        for chapter in &chapters {
+            for anchor in collect_anchors(&chapter.content) {
+                defined_in.entry(anchor).or_insert(&chapter.path);
+            }
+        }
+
+        chapters
+            .iter()
+            .map(|chapter| {
+                let content = link_chapters(&chapter.content, &chapter.path, &defined_in);
+                (chapter.path.clone(), to_mdbook_markdown(&content))
+            })
+            .collect()
+    }
+
+    /// Write `SUMMARY.md` and all chapters into the `src` directory `dir`.
+    pub fn write(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join("SUMMARY.md"), self.summary())?;
+        for (path, content) in self.files() {
+            let path = dir.join(path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, content)?;
+        }
+        Ok(())
+    }
+}
+
+/// Point links to anchors (`#id`) of other chapters at the chapter defining
+/// them. Links to unknown anchors are left alone.
+fn link_chapters(text: &str, path: &str, defined_in: &HashMap<String, &str>) -> String {
+    let mut insertions = vec![];
+    for (event, range) in Parser::new_ext(text, Options::empty()).into_offset_iter() {
+        let Event::Start(Tag::Link { dest_url, .. }) = event else {
+            continue;
+        };
+        let Some(anchor) = dest_url.strip_prefix('#') else {
+            continue;
+        };
+        let Some(target) = defined_in.get(anchor).filter(|&&target| target != path) else {
+            continue;
+        };
+        // Reference links have no destination in their range.
+        if let Some(offset) = text[range.clone()].rfind(&format!("(#{}", anchor)) {
+            insertions.push((range.start + offset + 1, relative_path(path, target)));
+        }
+    }
+
+    let mut out = text.to_string();
+    for (offset, file) in insertions.into_iter().rev() {
+        out.insert_str(offset, &file);
+    }
+    out
+}
+
+/// Path of the chapter `to` relative to the directory of the chapter `from`.
+fn relative_path(from: &str, to: &str) -> String {
+    let depth = from.matches('/').count();
+    format!("{}{}", "../".repeat(depth), to)
+}
+
+/// Replace fenced divs by `<div>`s, inline anchors by `<a>`s and drop roles
+/// from inline code, none of which mdBook supports.
+fn to_mdbook_markdown(markdown: &str) -> String {
+    let mut out = String::new();
+    for line in div_lines(markdown) {
+        match line {
+            DivLine::Open(attrs) => {
+                let attrs = attrs.trim_start_matches('{').trim_end_matches('}');
+                let classes: Vec<&str> = attrs
+                    .split_whitespace()
+                    .filter_map(|a| a.strip_prefix('.'))
+                    .collect();
+                out.push_str("<div");
+                if !classes.is_empty() {
+                    out.push_str(&format!(" class=\"{}\"", classes.join(" ")));
+                }
+                if let Some(id) = attrs.split_whitespace().find_map(|a| a.strip_prefix('#')) {
+                    out.push_str(&format!(" id=\"{}\"", id));
+                }
+                out.push_str(">\n\n");
+            }
+            DivLine::Close => out.push_str("\n</div>\n"),
+            DivLine::Text(line, false) if inline_anchor(line).is_some() => {
+                let id = inline_anchor(line).unwrap_or_default();
+                out.push_str(&format!("<a id=\"{}\"></a>\n", id));
+            }
+            DivLine::Text(line, false) => {
+                out.push_str(&strip_roles(line));
+                out.push('\n');
+            }
+            DivLine::Text(line, true) => {
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+/// Remove roles (``{option}`foo` ``) preceding inline code.
+fn strip_roles(line: &str) -> String {
+    let mut out = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let role_len = after
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+            .unwrap_or(after.len());
+        if role_len > 0 && after[role_len..].starts_with("}`") {
+            rest = &after[role_len + 1..];
+        } else {
+            out.push('{');
+            rest = after;
+        }
+    }
+    out.push_str(rest);
+    out
+}
@@ -0,0 +1,166 @@
+//! Detection of markdown constructs that render badly.
+//!
+//! Descriptions are run through a CommonMark parser and checked for leftovers
+//! of the DocBook era, unclosed code fences and role syntax (``{option}`foo` ``)
+//! the chosen flavor does not understand.
+
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag};
+
+use crate::format::{get_fence, line_column};
+
+/// DocBook elements that were commonly used in option descriptions before the
+/// switch to markdown.
+const DOCBOOK_TAGS: &[&str] = &[
+    "citerefentry",
+    "command",
+    "emphasis",
+    "envar",
+    "filename",
+    "function",
+    "link",
+    "literal",
+    "manvolnum",
+    "option",
+    "package",
+    "para",
+    "programlisting",
+    "refentrytitle",
+    "replaceable",
+    "screen",
+    "varname",
+    "xref",
+];
+
+/// Roles understood by the nixpkgs markdown flavor.
+const NIXPKGS_ROLES: &[&str] = &["command", "env", "file", "manpage", "option", "var"];
+
+/// Markdown flavor the output is rendered with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MarkdownFlavor {
+    /// Nixpkgs-flavored markdown, which supports roles and `{#anchor}` attributes.
+    #[default]
+    Nixpkgs,
+    /// Plain CommonMark without any extensions.
+    CommonMark,
+}
+
+impl MarkdownFlavor {
+    /// Look up a flavor by its command line name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "nixpkgs" => Some(Self::Nixpkgs),
+            "commonmark" => Some(Self::CommonMark),
+            _ => None,
+        }
+    }
+
+    fn supports_role(&self, role: &str) -> bool {
+        match self {
+            Self::Nixpkgs => NIXPKGS_ROLES.contains(&role),
+            Self::CommonMark => false,
+        }
+    }
+}
+
+/// The kind of problem found in a markdown text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IssueKind {
+    /// A DocBook element such as `<literal>`.
+    DocBook(String),
+    /// A code fence that is never closed.
+    UnclosedFence,
+    /// A role the flavor does not support.
+    UnknownRole(String),
+}
+
+/// A problem found in a markdown text, with a 1-based position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkdownIssue {
+    pub line: usize,
+    pub column: usize,
+    pub kind: IssueKind,
+}
+
+impl MarkdownIssue {
+    /// Human-readable description of the issue.
+    pub fn message(&self) -> String {
+        match &self.kind {
+            IssueKind::DocBook(tag) => format!("DocBook element `<{}>` in markdown", tag),
+            IssueKind::UnclosedFence => "code fence is never closed".to_string(),
+            IssueKind::UnknownRole(role) => format!("unsupported role `{{{}}}`", role),
+        }
+    }
+}
+
+/// Check a markdown text for constructs that render badly in `flavor`.
+pub fn check_markdown(text: &str, flavor: MarkdownFlavor) -> Vec<MarkdownIssue> {
+    let mut issues = vec![];
+    let mut previous_text: Option<(String, usize)> = None;
+
+    for (event, range) in Parser::new_ext(text, Options::empty()).into_offset_iter() {
+        let mut current_text = None;
+        match event {
+            Event::Html(html) | Event::InlineHtml(html) => {
+                if let Some(tag) =
+                    html_tag_name(&html).filter(|t| DOCBOOK_TAGS.contains(&t.as_str()))
+                {
+                    issues.push(issue_at(text, range.start, IssueKind::DocBook(tag)));
+                }
+            }
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(_)))
+                if !is_closed_fence(&text[range.clone()]) =>
+            {
+                issues.push(issue_at(text, range.start, IssueKind::UnclosedFence));
+            }
+            Event::Code(_) => {
+                if let Some((prev, end)) = &previous_text {
+                    if let Some(role) = trailing_role(prev).filter(|r| !flavor.supports_role(r)) {
+                        let start = end - role.len() - 2;
+                        issues.push(issue_at(text, start, IssueKind::UnknownRole(role)));
+                    }
+                }
+            }
+            Event::Text(t) => current_text = Some((t.to_string(), range.end)),
+            _ => (),
+        }
+        previous_text = current_text;
+    }
+
+    issues
+}
+
+/// Returns the lowercased element name of an opening HTML tag.
+fn html_tag_name(html: &str) -> Option<String> {
+    let rest = html.trim_start().strip_prefix('<')?;
+    let name: String = rest
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric())
+        .collect();
+    Some(name.to_ascii_lowercase()).filter(|n| !n.is_empty())
+}
+
+/// Whether a fenced code block (including its fences) is properly closed.
+fn is_closed_fence(block: &str) -> bool {
+    let mut lines = block.lines().filter(|l| !l.trim().is_empty());
+    let Some((count, ch)) = lines.next().and_then(|l| get_fence(l.trim_start(), true)) else {
+        return true;
+    };
+    lines
+        .next_back()
+        .and_then(|l| get_fence(l.trim(), false))
+        .is_some_and(|(end_count, end_ch)| end_ch == ch && end_count >= count)
+}
+
+/// Returns the role name if the text ends in `{role}`.
+fn trailing_role(text: &str) -> Option<String> {
+    let inner = text.strip_suffix('}')?;
+    let start = inner.rfind('{')?;
+    let role = &inner[start + 1..];
+    (!role.is_empty() && role.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))
+        .then(|| role.to_string())
+}
+
+fn issue_at(text: &str, offset: usize, kind: IssueKind) -> MarkdownIssue {
+    let (line, column) = line_column(text, offset);
+    MarkdownIssue { line, column, kind }
+}
@@ -0,0 +1,119 @@
+//! In-language metadata for documented bindings.
+//!
+//! A binding whose value is an attribute set (for example a `__functor` based
+//! callable) can carry metadata next to its implementation:
+//!
+//! ```nix
+//! {
+//!   /** Doc */
+//!   foo = {
+//!     __functor = self: x: x;
+//!     __internal = true;
+//!     meta = {
+//!       maintainers = [ "alice" ];
+//!       since = "24.05";
+//!       deprecated = "use `bar` instead";
+//!     };
+//!   };
+//! }
+//! ```
+//!
+//! `__internal = true;` (or `meta.internal = true;`) hides the entry, the
+//! remaining `meta` fields are carried into the rendered output.
+
+use rnix::ast::{AttrSet, Expr, HasEntry, InterpolPart};
+use rowan::ast::AstNode;
+
+/// Metadata read from `__internal` and `meta` attributes of a binding.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct EntryMeta {
+    /// Whether the entry should be hidden from the documentation.
+    pub internal: bool,
+    /// Maintainers from `meta.maintainer` or `meta.maintainers`.
+    pub maintainers: Vec<String>,
+    /// Version the entry was introduced in, from `meta.since`.
+    pub since: Option<String>,
+    /// Deprecation notice from `meta.deprecated`. A plain `true` yields an
+    /// empty notice.
+    pub deprecated: Option<String>,
+}
+
+impl EntryMeta {
+    /// Read metadata from the value of a binding. Values that are not
+    /// attribute sets carry no metadata.
+    pub fn from_value(value: &Expr) -> Self {
+        let mut meta = EntryMeta::default();
+        let Expr::AttrSet(set) = value else {
+            return meta;
+        };
+
+        let internal = |path| {
+            attr_value(set, path)
+                .and_then(|v| literal_bool(&v))
+                .unwrap_or(false)
+        };
+        meta.internal = internal("__internal") || internal("meta.internal");
+
+        if let Some(maintainer) = attr_value(set, "meta.maintainer") {
+            meta.maintainers.extend(literal_string(&maintainer));
+        }
+        if let Some(Expr::List(list)) = attr_value(set, "meta.maintainers") {
+            meta.maintainers
+                .extend(list.items().filter_map(|item| literal_string(&item)));
+        }
+        meta.since = attr_value(set, "meta.since").and_then(|v| literal_string(&v));
+        meta.deprecated = attr_value(set, "meta.deprecated").and_then(|v| {
+            literal_string(&v).or_else(|| literal_bool(&v).filter(|b| *b).map(|_| String::new()))
+        });
+
+        meta
+    }
+}
+
+/// Find the value bound to a dotted attribute path in an attribute set.
+///
+/// Both `meta.since = ...;` and `meta = { since = ...; };` are found for the
+/// path `meta.since`.
+pub(crate) fn attr_value(set: &AttrSet, path: &str) -> Option<Expr> {
+    let wanted: Vec<&str> = path.split('.').collect();
+    set.attrpath_values().find_map(|apv| {
+        let segments: Vec<String> = apv.attrpath()?.attrs().map(|a| a.to_string()).collect();
+        if segments.len() > wanted.len() || segments.iter().zip(&wanted).any(|(a, b)| a != b) {
+            return None;
+        }
+        let value = apv.value()?;
+        if segments.len() == wanted.len() {
+            return Some(value);
+        }
+        match value {
+            Expr::AttrSet(inner) => attr_value(&inner, &wanted[segments.len()..].join(".")),
+            _ => None,
+        }
+    })
+}
+
+/// Returns the contents of a string literal without interpolations.
+pub(crate) fn literal_string(expr: &Expr) -> Option<String> {
+    let Expr::Str(s) = expr else {
+        return None;
+    };
+    s.normalized_parts()
+        .into_iter()
+        .map(|part| match part {
+            InterpolPart::Literal(lit) => Some(lit),
+            InterpolPart::Interpolation(_) => None,
+        })
+        .collect()
+}
+
+/// Returns the value of a literal `true` or `false`.
+pub(crate) fn literal_bool(expr: &Expr) -> Option<bool> {
+    match expr {
+        Expr::Ident(ident) => match ident.syntax().text().to_string().as_str() {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,357 @@
+//! Options declared by a NixOS module, read from its source.
+//!
+//! Rendering options usually starts from the options JSON of an evaluated
+//! module set. For a single module, the options can instead be read from
+//! the `options` of the attribute set the module evaluates to, without
+//! evaluating anything:
+//!
+//! ```nix
+//! { lib, ... }:
+//! {
+//!   options.services.web = {
+//!     enable = lib.mkEnableOption "the web server";
+//!     port = lib.mkOption {
+//!       type = lib.types.port;
+//!       default = 80;
+//!       description = "Port to listen on.";
+//!     };
+//!   };
+//! }
+//! ```
+//!
+//! Options are declared with `mkOption`, `mkEnableOption` or
+//! `mkPackageOption`. Types are described as the module system describes
+//! them, and defaults and examples that are not literals are shown as the
+//! expressions they are written as.
+
+use rnix::ast::{self, AttrSet, Expr, HasEntry};
+use rowan::ast::AstNode;
+
+use crate::attrpath::attr_name;
+use crate::meta::{attr_value, literal_bool, literal_string};
+use crate::options::{
+    DeclarationLoc, Description, OptionDef, OptionValue, OptionsMap, TaggedValue, Visibility,
+};
+
+/// The options declared by a module and the bindings in its `options` that
+/// do not declare any.
+#[derive(Debug, Default)]
+pub struct ModuleOptions {
+    pub options: OptionsMap,
+    /// Names of bindings that are neither option declarations nor sets of
+    /// them, e.g. options declared by helper functions.
+    pub unknown: Vec<String>,
+}
+
+/// Read the options declared by the module `root`, declared in `file`.
+pub fn module_options(root: &rnix::Root, file: &str) -> ModuleOptions {
+    let mut module = ModuleOptions::default();
+    let Some(set) = root.expr().and_then(module_set) else {
+        return module;
+    };
+    for apv in set.attrpath_values() {
+        let Some(path) = apv.attrpath().and_then(|path| segments(&path)) else {
+            continue;
+        };
+        if path.first().map(String::as_str) == Some("options") {
+            if let Some(value) = apv.value() {
+                collect_options(&value, path[1..].to_vec(), file, &mut module);
+            }
+        }
+    }
+    module
+}
+
+/// The attribute set a module evaluates to, looking through its function
+/// head, `let` bindings and `with` scopes.
+fn module_set(expr: Expr) -> Option<AttrSet> {
+    match unwrap(expr) {
+        Expr::Lambda(lambda) => module_set(lambda.body()?),
+        Expr::LetIn(let_in) => module_set(let_in.body()?),
+        Expr::AttrSet(set) => Some(set),
+        _ => None,
+    }
+}
+
+/// Look through parentheses and `with` scopes.
+fn unwrap(expr: Expr) -> Expr {
+    match expr {
+        Expr::Paren(paren) => paren.expr().map_or(Expr::Paren(paren), unwrap),
+        Expr::With(with) => with.body().map_or(Expr::With(with), unwrap),
+        expr => expr,
+    }
+}
+
+/// The names of the segments of an attribute path.
+fn segments(path: &ast::Attrpath) -> Option<Vec<String>> {
+    path.attrs().map(|attr| attr_name(&attr)).collect()
+}
+
+/// The name of the function applied in `expr` (the last attribute of
+/// `lib.mkOption`) and its arguments in order.
+fn application(expr: &Expr) -> Option<(String, Vec<Expr>)> {
+    let mut args = vec![];
+    let mut function = unwrap(expr.clone());
+    while let Expr::Apply(apply) = function {
+        args.push(apply.argument()?);
+        function = unwrap(apply.lambda()?);
+    }
+    args.reverse();
+    let name = match function {
+        Expr::Ident(ident) => ident.syntax().text().to_string(),
+        Expr::Select(select) => attr_name(&select.attrpath()?.attrs().last()?)?,
+        _ => return None,
+    };
+    Some((name, args))
+}
+
+fn collect_options(value: &Expr, path: Vec<String>, file: &str, module: &mut ModuleOptions) {
+    let value = unwrap(value.clone());
+    if let Some(mut opt) = option_declaration(&value, &path, file) {
+        opt.position = module.options.len();
+        module.options.insert(path.join("."), opt);
+        return;
+    }
+    let Expr::AttrSet(set) = value else {
+        if !path.is_empty() {
+            module.unknown.push(path.join("."));
+        }
+        return;
+    };
+    for apv in set.attrpath_values() {
+        let (Some(segments), Some(value)) =
+            (apv.attrpath().and_then(|p| segments(&p)), apv.value())
+        else {
+            continue;
+        };
+        let mut nested = path.clone();
+        nested.extend(segments);
+        collect_options(&value, nested, file, module);
+    }
+}
+
+/// The option declared by `expr`, if it is a call of `mkOption`,
+/// `mkEnableOption` or `mkPackageOption`.
+fn option_declaration(expr: &Expr, path: &[String], file: &str) -> Option<OptionDef> {
+    let (function, args) = application(expr)?;
+    let mut opt = OptionDef {
+        loc: path.to_vec(),
+        description: None,
+        option_type: None,
+        default: None,
+        example: None,
+        declarations: vec![DeclarationLoc::Path(file.to_string())],
+        read_only: false,
+        stability: None,
+        related_packages: None,
+        visible: None,
+        internal: false,
+        origin: None,
+        position: 0,
+        keywords: vec![],
+        aliases: vec![],
+    };
+    match (function.as_str(), args.as_slice()) {
+        ("mkOption", [Expr::AttrSet(set)]) => {
+            let attr = |name| attr_value(set, name);
+            opt.option_type = attr("type").map(|t| type_description(&t));
+            opt.description = attr("description").map(|d| Description::Plain(string_or_source(&d)));
+            opt.default = attr("defaultText")
+                .map(|d| literal_value(&d))
+                .or_else(|| attr("default").map(|d| literal_value(&d)));
+            opt.example = attr("example").map(|e| literal_value(&e));
+            let flag = |name| attr(name).and_then(|v| literal_bool(&v)).unwrap_or(false);
+            opt.read_only = flag("readOnly");
+            opt.internal = flag("internal");
+            if attr("visible").and_then(|v| literal_bool(&v)) == Some(false) {
+                opt.visible = Some(Visibility::Bool(false));
+            }
+        }
+        ("mkEnableOption", [name]) => {
+            opt.option_type = Some("boolean".to_string());
+            opt.description = Some(Description::Plain(format!(
+                "Whether to enable {}.",
+                string_or_source(name)
+            )));
+            opt.default = Some(OptionValue::Bool(false));
+            opt.example = Some(OptionValue::Bool(true));
+        }
+        ("mkPackageOption", [pkgs, name, rest @ ..]) => {
+            let set = match rest {
+                [Expr::AttrSet(set)] => Some(set),
+                _ => None,
+            };
+            let attr = |name| set.and_then(|set| attr_value(set, name));
+            let pkgs = pkgs.syntax().text().to_string();
+            let name = package_path(name)?;
+            let default = attr("default").and_then(|d| package_path(&d));
+            opt.option_type = Some("package".to_string());
+            let mut description = format!("The {} package to use.", name);
+            if let Some(extra) = attr("extraDescription").and_then(|e| literal_string(&e)) {
+                description.push(' ');
+                description.push_str(&extra);
+            }
+            opt.description = Some(Description::Plain(description));
+            opt.default = Some(literal_expression(&format!(
+                "{}.{}",
+                pkgs,
+                default.unwrap_or(name)
+            )));
+            opt.example = attr("example")
+                .and_then(|e| package_path(&e))
+                .map(|example| literal_expression(&format!("{}.{}", pkgs, example)));
+        }
+        _ => return None,
+    }
+    Some(opt.with_keywords())
+}
+
+/// The attribute path of a package, given as a string or a list of them.
+fn package_path(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::List(list) => list
+            .items()
+            .map(|item| literal_string(&item))
+            .collect::<Option<Vec<_>>>()
+            .map(|path| path.join(".")),
+        expr => literal_string(expr),
+    }
+}
+
+/// The contents of a string literal without trailing whitespace, or the
+/// source of any other expression.
+fn string_or_source(expr: &Expr) -> String {
+    literal_string(expr)
+        .map(|s| s.trim_end().to_string())
+        .unwrap_or_else(|| expr.syntax().text().to_string())
+}
+
+fn literal_expression(text: &str) -> OptionValue {
+    OptionValue::Tagged(TaggedValue {
+        value_type: "literalExpression".to_string(),
+        text: Some(text.to_string()),
+    })
+}
+
+/// The value of a default or example: `literalExpression` and `literalMD`
+/// as tagged values, literal strings and booleans as such, and anything
+/// else as the expression it is written as.
+fn literal_value(expr: &Expr) -> OptionValue {
+    let expr = unwrap(expr.clone());
+    if let Some((function, args)) = application(&expr) {
+        if let ("literalExpression" | "literalMD", [text]) = (function.as_str(), args.as_slice()) {
+            if let Some(text) = literal_string(text) {
+                return OptionValue::Tagged(TaggedValue {
+                    value_type: function,
+                    text: Some(text.trim_end().to_string()),
+                });
+            }
+        }
+    }
+    if let Some(string) = literal_string(&expr) {
+        return OptionValue::String(string);
+    }
+    if let Some(value) = literal_bool(&expr) {
+        return OptionValue::Bool(value);
+    }
+    literal_expression(&expr.syntax().text().to_string())
+}
+
+/// Describe a type as the module system does, e.g. `lib.types.listOf
+/// lib.types.str` as `list of string`. Types that are not known are
+/// described by their expression.
+pub fn type_description(expr: &Expr) -> String {
+    let expr = unwrap(expr.clone());
+    let source = || expr.syntax().text().to_string();
+    // Nested conjunctions are parenthesized, as in `list of (null or string)`.
+    let nested = |expr: &Expr| {
+        let description = type_description(expr);
+        if description.contains(" or ") {
+            format!("({})", description)
+        } else {
+            description
+        }
+    };
+
+    if let Some((function, args)) = application(&expr).filter(|_| matches!(expr, Expr::Apply(_))) {
+        return match (function.as_str(), args.as_slice()) {
+            ("listOf", [t]) => format!("list of {}", nested(t)),
+            ("nonEmptyListOf", [t]) => format!("non-empty (list of {})", nested(t)),
+            ("attrsOf", [t]) => format!("attribute set of {}", nested(t)),
+            ("lazyAttrsOf", [t]) => format!("lazy attribute set of {}", nested(t)),
+            ("nullOr", [t]) => format!("null or {}", type_description(t)),
+            ("either", [a, b]) => format!("{} or {}", type_description(a), type_description(b)),
+            ("oneOf", [Expr::List(list)]) => list
+                .items()
+                .map(|t| type_description(&t))
+                .collect::<Vec<_>>()
+                .join(" or "),
+            ("functionTo", [t]) => format!("function that evaluates to a(n) {}", nested(t)),
+            ("uniq" | "unique", [.., t]) => type_description(t),
+            ("enum", [Expr::List(list)]) => format!(
+                "one of {}",
+                list.items()
+                    .map(|item| item.syntax().text().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            ("submodule" | "submoduleWith", [_]) => "submodule".to_string(),
+            ("strMatching", [pattern]) => {
+                format!("string matching the pattern {}", string_or_source(pattern))
+            }
+            ("separatedString", [separator]) => match literal_string(separator) {
+                Some(separator) => format!("strings concatenated with {:?}", separator),
+                None => source(),
+            },
+            ("between", [low, high]) => format!(
+                "integer between {} and {} (both inclusive)",
+                low.syntax().text(),
+                high.syntax().text()
+            ),
+            _ => source(),
+        };
+    }
+
+    let name = match &expr {
+        Expr::Ident(ident) => ident.syntax().text().to_string(),
+        Expr::Select(select) => {
+            let attrs: Vec<String> = select
+                .attrpath()
+                .map(|path| path.attrs().filter_map(|attr| attr_name(&attr)).collect())
+                .unwrap_or_default();
+            match attrs.as_slice() {
+                [.., parent, name] if parent == "ints" => format!("ints.{}", name),
+                [.., name] => name.clone(),
+                [] => return source(),
+            }
+        }
+        _ => return source(),
+    };
+    let description = match name.as_str() {
+        "bool" => "boolean",
+        "str" | "string" => "string",
+        "nonEmptyStr" => "non-empty string",
+        "singleLineStr" => "(optionally newline-terminated) single-line string",
+        "lines" => "strings concatenated with \"\\n\"",
+        "commas" => "strings concatenated with \",\"",
+        "envVar" => "strings concatenated with \":\"",
+        "int" => "signed integer",
+        "ints.unsigned" => "unsigned integer, meaning >=0",
+        "ints.positive" => "positive integer, meaning >0",
+        "ints.u8" => "8 bit unsigned integer; between 0 and 255 (both inclusive)",
+        "ints.u16" | "port" => "16 bit unsigned integer; between 0 and 65535 (both inclusive)",
+        "ints.u32" => "32 bit unsigned integer; between 0 and 4294967295 (both inclusive)",
+        "float" => "floating point number",
+        "number" => "signed integer or floating point number",
+        "path" => "path",
+        "pathInStore" => "path in the Nix store",
+        "package" => "package",
+        "attrs" => "attribute set",
+        "anything" => "anything",
+        "raw" => "raw value",
+        "unspecified" => "unspecified value",
+        "submodule" => "submodule",
+        _ => return source(),
+    };
+    description.to_string()
+}
@@ -0,0 +1,420 @@
+//! A formatter for the Nix expressions of option values, in the style of
+//! nixfmt.
+//!
+//! Expressions are laid out on one line if they fit and otherwise broken
+//! over several lines, with one binding or list item per line:
+//!
+//! ```nix
+//! {
+//!   port = 80;
+//!   hosts = [
+//!     "a.example.org"
+//!     "b.example.org"
+//!   ];
+//! }
+//! ```
+//!
+//! Like in nixfmt, attribute sets, lists and `let` bindings written over
+//! several lines stay expanded. Strings, paths and other leaves are kept as
+//! written. Expressions with comments are not formatted, as there is no
+//! reliable place to keep the comments in.
+
+use rnix::ast::{Entry, Expr, HasEntry, Inherit, Param, Pattern};
+use rnix::SyntaxKind;
+use rowan::ast::AstNode;
+
+/// Width expressions are formatted to fit in.
+pub const DEFAULT_WIDTH: usize = 80;
+
+const INDENT: &str = "  ";
+
+/// Format a Nix expression to fit lines of `width` characters where
+/// possible. Returns `None` if the expression does not parse or contains
+/// comments.
+pub fn format_expression(text: &str, width: usize) -> Option<String> {
+    let root = rnix::Root::parse(text).ok().ok()?;
+    let has_comments = root
+        .syntax()
+        .descendants_with_tokens()
+        .any(|element| element.kind() == SyntaxKind::TOKEN_COMMENT);
+    if has_comments {
+        return None;
+    }
+    Some(Formatter { width }.format(&root.expr()?, 0, 0))
+}
+
+struct Formatter {
+    width: usize,
+}
+
+impl Formatter {
+    /// Format `expr` starting at `column`, with lines after the first
+    /// indented by `indent` levels.
+    fn format(&self, expr: &Expr, indent: usize, column: usize) -> String {
+        match flat(expr) {
+            Some(flat) if column + flat.chars().count() <= self.width => flat,
+            _ => self.broken(expr, indent, column),
+        }
+    }
+
+    /// Format `expr` over several lines.
+    fn broken(&self, expr: &Expr, indent: usize, column: usize) -> String {
+        let pad = INDENT.repeat(indent);
+        let inner = INDENT.repeat(indent + 1);
+        match expr {
+            Expr::AttrSet(set) => {
+                let open = if set.rec_token().is_some() {
+                    "rec {"
+                } else {
+                    "{"
+                };
+                format!("{}\n{}{}}}", open, self.entries(set, indent + 1), pad)
+            }
+            Expr::List(list) => {
+                let mut out = String::from("[\n");
+                for item in list.items() {
+                    out.push_str(&inner);
+                    out.push_str(&self.format(&item, indent + 1, inner.len()));
+                    out.push('\n');
+                }
+                out + &pad + "]"
+            }
+            Expr::LetIn(let_in) => {
+                let body = let_in
+                    .body()
+                    .map(|body| self.format(&body, indent, pad.len()))
+                    .unwrap_or_default();
+                format!(
+                    "let\n{}{}in\n{}{}",
+                    self.entries(let_in, indent + 1),
+                    pad,
+                    pad,
+                    body
+                )
+            }
+            Expr::Lambda(lambda) => {
+                let param = lambda
+                    .param()
+                    .map(|param| self.param(&param, indent, column))
+                    .unwrap_or_default();
+                let column = end_column(&param, column) + 2;
+                let body = lambda
+                    .body()
+                    .map(|body| self.format(&body, indent, column))
+                    .unwrap_or_default();
+                format!("{}: {}", param, body)
+            }
+            Expr::Apply(_) => {
+                let (function, args) = application(expr.clone());
+                let mut out = self.format(&function, indent, column);
+                for arg in args {
+                    out.push(' ');
+                    let column = end_column(&out, column);
+                    out.push_str(&self.format(&arg, indent, column));
+                }
+                out
+            }
+            Expr::Paren(paren) => {
+                let inner = paren
+                    .expr()
+                    .map(|inner| self.format(&inner, indent, column + 1))
+                    .unwrap_or_default();
+                format!("({})", inner)
+            }
+            Expr::BinOp(op) => {
+                let (Some(lhs), Some(rhs)) = (op.lhs(), op.rhs()) else {
+                    return verbatim(expr);
+                };
+                let operator = operator(expr);
+                let lhs = self.format(&lhs, indent, column);
+                let rhs = self.format(&rhs, indent + 1, inner.len() + operator.len() + 1);
+                format!("{}\n{}{} {}", lhs, inner, operator, rhs)
+            }
+            Expr::IfElse(if_else) => {
+                let part = |expr: Option<Expr>, indent: usize, column: usize| {
+                    expr.map(|e| self.format(&e, indent, column))
+                        .unwrap_or_default()
+                };
+                format!(
+                    "if {} then\n{}{}\n{}else\n{}{}",
+                    part(if_else.condition(), indent, column + 3),
+                    inner,
+                    part(if_else.body(), indent + 1, inner.len()),
+                    pad,
+                    inner,
+                    part(if_else.else_body(), indent + 1, inner.len())
+                )
+            }
+            Expr::With(with) => {
+                let namespace = with.namespace().map(|n| verbatim(&n)).unwrap_or_default();
+                let head = format!("with {}; ", namespace);
+                let body = with
+                    .body()
+                    .map(|body| self.format(&body, indent, end_column(&head, column)))
+                    .unwrap_or_default();
+                head + &body
+            }
+            Expr::Assert(assert) => {
+                let condition = assert.condition().map(|c| verbatim(&c)).unwrap_or_default();
+                let body = assert
+                    .body()
+                    .map(|body| self.format(&body, indent, pad.len()))
+                    .unwrap_or_default();
+                format!("assert {};\n{}{}", condition, pad, body)
+            }
+            _ => verbatim(expr),
+        }
+    }
+
+    /// The bindings of an attribute set or `let`, one per line.
+    fn entries(&self, node: &impl HasEntry, indent: usize) -> String {
+        let pad = INDENT.repeat(indent);
+        let mut out = String::new();
+        for entry in node.entries() {
+            out.push_str(&pad);
+            match entry {
+                Entry::AttrpathValue(binding) => {
+                    let head = format!(
+                        "{} = ",
+                        binding
+                            .attrpath()
+                            .map(|path| path.syntax().text().to_string())
+                            .unwrap_or_default()
+                    );
+                    let column = pad.len() + head.len();
+                    out.push_str(&head);
+                    if let Some(value) = binding.value() {
+                        out.push_str(&self.format(&value, indent, column));
+                    }
+                    out.push(';');
+                }
+                Entry::Inherit(inherit) => out.push_str(&flat_inherit(&inherit)),
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Format the parameter of a lambda, with a pattern over several lines
+    /// if it does not fit.
+    fn param(&self, param: &Param, indent: usize, column: usize) -> String {
+        let flat = flat_param(param);
+        match param {
+            Param::Pattern(pattern) if column + flat.len() + 1 > self.width => {
+                let inner = INDENT.repeat(indent + 1);
+                let mut out = String::from("{\n");
+                for item in pattern_items(pattern) {
+                    let comma = if item == "..." { "" } else { "," };
+                    out.push_str(&format!("{}{}{}\n", inner, item, comma));
+                }
+                out.push_str(&INDENT.repeat(indent));
+                out.push('}');
+                bind_pattern(pattern, out)
+            }
+            _ => flat,
+        }
+    }
+}
+
+/// `expr` on a single line, if it fits on one.
+fn flat(expr: &Expr) -> Option<String> {
+    let expanded = |expr: &Expr| expr.syntax().text().contains_char('\n');
+    match expr {
+        Expr::AttrSet(set) => {
+            let rec = if set.rec_token().is_some() {
+                "rec "
+            } else {
+                ""
+            };
+            let entries = flat_entries(set)?;
+            if entries.is_empty() {
+                return Some(format!("{}{{ }}", rec));
+            }
+            (!expanded(expr)).then(|| format!("{}{{ {} }}", rec, entries.join(" ")))
+        }
+        Expr::List(list) => {
+            let items = list.items().map(|i| flat(&i)).collect::<Option<Vec<_>>>()?;
+            if items.is_empty() {
+                return Some("[ ]".to_string());
+            }
+            (!expanded(expr)).then(|| format!("[ {} ]", items.join(" ")))
+        }
+        Expr::LetIn(let_in) => {
+            let entries = flat_entries(let_in)?;
+            let body = flat(&let_in.body()?)?;
+            (!expanded(expr)).then(|| format!("let {} in {}", entries.join(" "), body))
+        }
+        Expr::Lambda(lambda) => {
+            let param = lambda.param()?;
+            if let Param::Pattern(pattern) = &param {
+                if pattern.syntax().text().contains_char('\n') {
+                    return None;
+                }
+            }
+            Some(format!(
+                "{}: {}",
+                flat_param(&param),
+                flat(&lambda.body()?)?
+            ))
+        }
+        Expr::Apply(_) => {
+            let (function, args) = application(expr.clone());
+            let mut out = flat(&function)?;
+            for arg in args {
+                out.push(' ');
+                out.push_str(&flat(&arg)?);
+            }
+            Some(out)
+        }
+        Expr::Paren(paren) => Some(format!("({})", flat(&paren.expr()?)?)),
+        Expr::BinOp(op) => Some(format!(
+            "{} {} {}",
+            flat(&op.lhs()?)?,
+            operator(expr),
+            flat(&op.rhs()?)?
+        )),
+        Expr::IfElse(if_else) => Some(format!(
+            "if {} then {} else {}",
+            flat(&if_else.condition()?)?,
+            flat(&if_else.body()?)?,
+            flat(&if_else.else_body()?)?
+        )),
+        Expr::With(with) => Some(format!(
+            "with {}; {}",
+            flat(&with.namespace()?)?,
+            flat(&with.body()?)?
+        )),
+        Expr::Assert(assert) => Some(format!(
+            "assert {}; {}",
+            flat(&assert.condition()?)?,
+            flat(&assert.body()?)?
+        )),
+        _ => Some(verbatim(expr)).filter(|text| !text.contains('\n')),
+    }
+}
+
+/// The bindings of an attribute set or `let` on single lines.
+fn flat_entries(node: &impl HasEntry) -> Option<Vec<String>> {
+    node.entries()
+        .map(|entry| match entry {
+            Entry::AttrpathValue(binding) => Some(format!(
+                "{} = {};",
+                binding.attrpath()?.syntax().text(),
+                flat(&binding.value()?)?
+            )),
+            Entry::Inherit(inherit) => Some(flat_inherit(&inherit)),
+        })
+        .collect()
+}
+
+fn flat_inherit(inherit: &Inherit) -> String {
+    let mut out = String::from("inherit");
+    if let Some(from) = inherit.from().and_then(|from| from.expr()) {
+        out.push_str(&format!(
+            " ({})",
+            flat(&from).unwrap_or_else(|| verbatim(&from))
+        ));
+    }
+    for attr in inherit.attrs() {
+        out.push(' ');
+        out.push_str(&attr.syntax().text().to_string());
+    }
+    out + ";"
+}
+
+fn flat_param(param: &Param) -> String {
+    match param {
+        Param::IdentParam(ident) => ident.syntax().text().to_string(),
+        Param::Pattern(pattern) => {
+            let items = pattern_items(pattern);
+            let braces = if items.is_empty() {
+                "{ }".to_string()
+            } else {
+                format!("{{ {} }}", items.join(", "))
+            };
+            bind_pattern(pattern, braces)
+        }
+    }
+}
+
+/// The attributes of a pattern, with their defaults, and its ellipsis.
+fn pattern_items(pattern: &Pattern) -> Vec<String> {
+    let mut items: Vec<String> = pattern
+        .pat_entries()
+        .map(|entry| {
+            let name = entry
+                .ident()
+                .map(|i| i.syntax().text().to_string())
+                .unwrap_or_default();
+            match entry.default() {
+                Some(default) => format!(
+                    "{} ? {}",
+                    name,
+                    flat(&default).unwrap_or_else(|| verbatim(&default))
+                ),
+                None => name,
+            }
+        })
+        .collect();
+    if pattern.ellipsis_token().is_some() {
+        items.push("...".to_string());
+    }
+    items
+}
+
+/// Add the name a pattern is bound to on the side it was written on.
+fn bind_pattern(pattern: &Pattern, braces: String) -> String {
+    let Some(bind) = pattern.pat_bind() else {
+        return braces;
+    };
+    let name = bind
+        .ident()
+        .map(|i| i.syntax().text().to_string())
+        .unwrap_or_default();
+    let bind_first = pattern
+        .syntax()
+        .children_with_tokens()
+        .find(|e| e.kind() == SyntaxKind::TOKEN_L_BRACE)
+        .is_some_and(|brace| bind.syntax().text_range().start() < brace.text_range().start());
+    if bind_first {
+        format!("{}@{}", name, braces)
+    } else {
+        format!("{}@{}", braces, name)
+    }
+}
+
+/// The function and arguments of a chain of applications, `f a b`.
+fn application(mut expr: Expr) -> (Expr, Vec<Expr>) {
+    let mut args = vec![];
+    while let Expr::Apply(apply) = &expr {
+        let (Some(function), Some(arg)) = (apply.lambda(), apply.argument()) else {
+            break;
+        };
+        args.push(arg);
+        expr = function;
+    }
+    args.reverse();
+    (expr, args)
+}
+
+/// The operator of a binary operation, as written.
+fn operator(expr: &Expr) -> String {
+    expr.syntax()
+        .children_with_tokens()
+        .filter_map(|e| e.into_token())
+        .find(|t| rnix::ast::BinOpKind::from_kind(t.kind()).is_some())
+        .map(|t| t.text().to_string())
+        .unwrap_or_default()
+}
+
+fn verbatim(expr: &Expr) -> String {
+    expr.syntax().text().to_string()
+}
+
+/// The column at which `text` ends, if it starts at `column`.
+fn end_column(text: &str, column: usize) -> usize {
+    match text.rsplit_once('\n') {
+        Some((_, last)) => last.chars().count(),
+        None => column + text.chars().count(),
+    }
+}
@@ -37,14 +37,24 @@
 //! }
 //! ```
 
-use serde::Deserialize;
-use std::collections::HashMap;
-use std::fs;
+use crate::format::{code_span, HeadingLevels};
+use crate::glob::filter_name;
+use crate::mdcheck::{self, MarkdownFlavor, MarkdownIssue};
+use crate::slug::{assign_anchors, Anchors};
+use crate::tags::Stability;
+use crate::{nixfmt, optiontypes};
+use memmap2::Mmap;
+use rayon::prelude::*;
+use serde::de::{Deserializer, MapAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
 use std::path::Path;
 
 /// A value that can be either a literal expression, literal markdown, or a raw value.
 /// This matches the `_type` tagged format used by nixpkgs.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 #[serde(untagged)]
 pub enum OptionValue {
     /// A literal Nix expression: `{ _type = "literalExpression"; text = "..."; }`
@@ -65,7 +75,7 @@ pub enum OptionValue {
 }
 
 /// A tagged value with `_type` field
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct TaggedValue {
     #[serde(rename = "_type")]
     pub value_type: String,
@@ -73,7 +83,7 @@ pub struct TaggedValue {
 }
 
 /// Description can be either a plain string or an mdDoc object
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 #[serde(untagged)]
 pub enum Description {
     /// Plain string description
@@ -92,7 +102,7 @@ impl Description {
 }
 
 /// Represents a single option's metadata as parsed from JSON
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct OptionDef {
     /// The option's location as a list of path segments.
@@ -125,13 +135,143 @@ pub struct OptionDef {
     #[serde(default)]
     pub read_only: bool,
 
-    /// Related packages markdown (pre-rendered)
+    /// Maturity of the option (`experimental`, `stable` or `deprecated`)
     #[serde(default)]
-    pub related_packages: Option<String>,
+    pub stability: Option<String>,
+
+    /// Packages related to the option, as pre-rendered markdown or as
+    /// package references
+    #[serde(default)]
+    pub related_packages: Option<RelatedPackages>,
+
+    /// Whether the option is shown in documentation: `false` hides it,
+    /// `"shallow"` and `"transparent"` only affect its sub-options, which are
+    /// not listed by `optionAttrSetToDocList` anyway
+    #[serde(default)]
+    pub visible: Option<Visibility>,
+
+    /// Whether the option is internal to the module system
+    #[serde(default)]
+    pub internal: bool,
+
+    /// Name of the module set (e.g. `home-manager`) the option comes from,
+    /// set by [`merge_options`].
+    #[serde(skip)]
+    pub origin: Option<String>,
+
+    /// Position of the option in its options JSON, counting on from the
+    /// options of earlier files merged by [`merge_options`].
+    #[serde(skip)]
+    pub position: usize,
+
+    /// Keywords from a `Tags:` line or a `# Keywords` section of the
+    /// description, which they are split off by [`OptionDef::with_keywords`].
+    #[serde(skip)]
+    pub keywords: Vec<String>,
+
+    /// Names of the alias options folded into this one by [`fold_aliases`].
+    #[serde(skip)]
+    pub aliases: Vec<String>,
+}
+
+/// Value of an option's `visible` attribute.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Visibility {
+    Bool(bool),
+    /// `"shallow"` or `"transparent"`.
+    Mode(String),
+}
+
+impl OptionDef {
+    /// Split the keywords off the description, see
+    /// [`extract_keywords`](crate::tags::extract_keywords).
+    pub fn with_keywords(mut self) -> Self {
+        let Some(description) = &self.description else {
+            return self;
+        };
+        let (text, keywords) = crate::tags::extract_keywords(description.as_str());
+        if !keywords.is_empty() {
+            self.description = Some(match description {
+                Description::Plain(_) => Description::Plain(text),
+                Description::MdDoc { _type, .. } => Description::MdDoc {
+                    _type: _type.clone(),
+                    text,
+                },
+            });
+            self.keywords = keywords;
+        }
+        self
+    }
+
+    /// The option an alias option forwards to, if its whole description is
+    /// ``Alias of {option}`target`.`` as written by `mkAliasOptionModule`, or
+    /// the DocBook or plain code variants of it.
+    pub fn alias_target(&self) -> Option<&str> {
+        let rest = self
+            .description
+            .as_ref()?
+            .as_str()
+            .trim()
+            .strip_prefix("Alias of ")?;
+        let rest = rest.strip_prefix("{option}").unwrap_or(rest);
+        let (target, rest) = match rest.strip_prefix("<option>") {
+            Some(rest) => rest.split_once("</option>")?,
+            None => rest.strip_prefix('`')?.split_once('`')?,
+        };
+        (!target.is_empty() && matches!(rest, "" | ".")).then_some(target)
+    }
+
+    /// Whether the option is internal or declared invisible.
+    pub fn is_hidden(&self) -> bool {
+        self.internal || self.visible == Some(Visibility::Bool(false))
+    }
+
+    /// The flags of the option, for structured output.
+    pub fn flags(&self) -> OptionFlags {
+        OptionFlags {
+            read_only: self.read_only,
+            internal: self.internal,
+            visible: self.visible != Some(Visibility::Bool(false)),
+            experimental: self.stability.as_deref().and_then(Stability::from_name)
+                == Some(Stability::Experimental),
+        }
+    }
+
+    /// First sentence of the description, see [`summary`](crate::format::summary).
+    pub fn summary(&self) -> Option<String> {
+        crate::format::summary(self.description.as_ref()?.as_str())
+    }
+}
+
+/// Flags of an option that are rendered as badges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct OptionFlags {
+    pub read_only: bool,
+    pub internal: bool,
+    /// False if the option is declared invisible.
+    pub visible: bool,
+    pub experimental: bool,
+}
+
+impl OptionFlags {
+    /// The admonition class and notice of each badge of the flags.
+    fn badges<'a>(&self, labels: &'a Labels) -> Vec<(&'static str, &'a str)> {
+        [
+            (self.read_only, "note", &labels.read_only_notice),
+            (self.internal, "note", &labels.internal_notice),
+            (!self.visible, "note", &labels.hidden_notice),
+            (self.experimental, "warning", &labels.experimental_notice),
+        ]
+        .into_iter()
+        .filter(|(set, _, _)| *set)
+        .map(|(_, class, notice)| (class, notice.as_str()))
+        .collect()
+    }
 }
 
 /// Declaration location can be a string or an object with name and url
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 #[serde(untagged)]
 pub enum DeclarationLoc {
     /// Simple path string
@@ -156,19 +296,381 @@ impl DeclarationLoc {
     }
 }
 
+/// Packages related to an option.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum RelatedPackages {
+    /// Markdown rendered by nixpkgs' `make-options-doc`.
+    Markdown(String),
+    /// The option's `relatedPackages` as declared.
+    Packages(Vec<RelatedPackage>),
+}
+
+/// A reference to a package in `pkgs`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(from = "RawRelatedPackage")]
+pub struct RelatedPackage {
+    /// Attribute path of the package below `pkgs`.
+    pub attr_path: Vec<String>,
+    /// Name shown instead of the attribute path.
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// Markdown explaining how the package relates to the option.
+    pub comment: Option<String>,
+}
+
+/// The forms a package reference can be declared in: `"foo.bar"`,
+/// `[ "foo" "bar" ]` or `{ path = [ "foo" "bar" ]; comment = "..."; }`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawRelatedPackage {
+    Path(AttrPath),
+    Detailed {
+        #[serde(alias = "attrPath")]
+        path: AttrPath,
+        name: Option<String>,
+        description: Option<String>,
+        comment: Option<String>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AttrPath {
+    Dotted(String),
+    Segments(Vec<String>),
+}
+
+impl From<AttrPath> for Vec<String> {
+    fn from(path: AttrPath) -> Self {
+        match path {
+            AttrPath::Dotted(path) => path.split('.').map(str::to_string).collect(),
+            AttrPath::Segments(segments) => segments,
+        }
+    }
+}
+
+impl From<RawRelatedPackage> for RelatedPackage {
+    fn from(raw: RawRelatedPackage) -> Self {
+        match raw {
+            RawRelatedPackage::Path(path) => Self {
+                attr_path: path.into(),
+                name: None,
+                description: None,
+                comment: None,
+            },
+            RawRelatedPackage::Detailed {
+                path,
+                name,
+                description,
+                comment,
+            } => Self {
+                attr_path: path.into(),
+                name,
+                description,
+                comment,
+            },
+        }
+    }
+}
+
+/// Default URL of a package's entry in the package index.
+pub const DEFAULT_PACKAGE_INDEX_URL: &str =
+    "https://search.nixos.org/packages?show={attr}&query={attr}";
+
+/// Render related packages as markdown: a list of packages linked to the
+/// package index, with their descriptions and comments.
+pub fn render_related_packages(related: &RelatedPackages, opts: &RenderOptions) -> String {
+    let packages = match related {
+        RelatedPackages::Markdown(markdown) => return markdown.clone(),
+        RelatedPackages::Packages(packages) => packages,
+    };
+    let mut output = String::new();
+    for package in packages {
+        let attr = package.attr_path.join(".");
+        let label = code_span(package.name.as_deref().unwrap_or(&attr));
+        match &opts.package_index_url {
+            Some(url) => {
+                let url = url.replace("{attr}", &attr);
+                output.push_str(&format!("- [{}]({})", label, url));
+            }
+            None => output.push_str(&format!("- {}", label)),
+        }
+        if let Some(description) = &package.description {
+            output.push_str(&format!(": {}", description.trim()));
+        }
+        output.push('\n');
+        if let Some(comment) = package.comment.as_deref().map(str::trim) {
+            output.push('\n');
+            for line in comment.lines() {
+                if line.is_empty() {
+                    output.push('\n');
+                } else {
+                    output.push_str(&format!("  {}\n", line));
+                }
+            }
+            output.push('\n');
+        }
+    }
+    output.trim_end().to_string()
+}
+
 /// Parsed options from JSON
 pub type OptionsMap = HashMap<String, OptionDef>;
 
 /// Parse options JSON from a file
 pub fn parse_options_file(path: &Path) -> Result<OptionsMap, String> {
-    let content =
-        fs::read_to_string(path).map_err(|e| format!("Failed to read options file: {}", e))?;
-    parse_options_json(&content)
+    parse_options_file_with_provenance(path).map(|(options, _)| options)
 }
 
 /// Parse options JSON from a string
 pub fn parse_options_json(json: &str) -> Result<OptionsMap, String> {
-    serde_json::from_str(json).map_err(|e| format!("Failed to parse options JSON: {}", e))
+    parse_options_json_with_provenance(json).map(|(options, _)| options)
+}
+
+/// Parse options JSON from a file, together with its provenance metadata
+///
+/// Regular files are memory-mapped rather than read, as full NixOS option
+/// sets span tens of megabytes. Other files, such as `/dev/stdin` or the
+/// pipes of process substitutions, cannot be mapped and are read instead.
+pub fn parse_options_file_with_provenance(path: &Path) -> Result<(OptionsMap, Provenance), String> {
+    let read_error = |e: std::io::Error| format!("Failed to read options file: {}", e);
+    let file = File::open(path).map_err(read_error)?;
+    let is_file = file.metadata().map_err(read_error)?.is_file();
+    // SAFETY: reading the map is undefined behaviour if the file is
+    // truncated or modified while it is mapped, e.g. by another process
+    // rewriting it in place. Options files are build outputs or files the
+    // caller wrote beforehand, which are not changed while they are being
+    // documented, and the map is dropped before this function returns.
+    match is_file.then(|| unsafe { Mmap::map(&file) }) {
+        Some(Ok(map)) => parse_options_slice(&map),
+        _ => parse_options_slice(&fs::read(path).map_err(read_error)?),
+    }
+}
+
+/// Parse options JSON from a string, together with the provenance metadata
+/// stored under the [`PROVENANCE_KEY`] key, if any.
+pub fn parse_options_json_with_provenance(json: &str) -> Result<(OptionsMap, Provenance), String> {
+    parse_options_slice(json.as_bytes())
+}
+
+/// Parse options JSON from bytes. The top-level object is only split into
+/// the unparsed JSON of each option, which are then parsed in parallel
+/// without building an intermediate `serde_json::Value` of the document.
+fn parse_options_slice(json: &[u8]) -> Result<(OptionsMap, Provenance), String> {
+    let OrderedObject(mut options) =
+        serde_json::from_slice(json).map_err(|e| format!("Failed to parse options JSON: {}", e))?;
+    let provenance = options
+        .iter()
+        .position(|(name, _)| name == PROVENANCE_KEY)
+        .map(|i| serde_json::from_str(options.remove(i).1.get()))
+        .transpose()
+        .map_err(|e| format!("Failed to parse `{}`: {}", PROVENANCE_KEY, e))?
+        .unwrap_or_default();
+    let options = options
+        .into_par_iter()
+        .enumerate()
+        .map(|(position, (name, value))| {
+            serde_json::from_str(value.get())
+                .map(|opt: OptionDef| {
+                    let opt = opt.with_keywords();
+                    (name.clone(), OptionDef { position, ..opt })
+                })
+                .map_err(|e| format!("Failed to parse option {}: {}", name, e))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((options, provenance))
+}
+
+/// The members of a JSON object, in the order they are written in, with
+/// their values left unparsed.
+struct OrderedObject<'a>(Vec<(String, &'a RawValue)>);
+
+impl<'de: 'a, 'a> Deserialize<'de> for OrderedObject<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ObjectVisitor<'a>(std::marker::PhantomData<&'a ()>);
+
+        impl<'de: 'a, 'a> Visitor<'de> for ObjectVisitor<'a> {
+            type Value = OrderedObject<'a>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a JSON object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<OrderedObject<'a>, A::Error> {
+                let mut members = vec![];
+                while let Some(member) = map.next_entry()? {
+                    members.push(member);
+                }
+                Ok(OrderedObject(members))
+            }
+        }
+
+        deserializer.deserialize_map(ObjectVisitor(std::marker::PhantomData))
+    }
+}
+
+/// Top-level key of the options JSON holding [`Provenance`] metadata.
+pub const PROVENANCE_KEY: &str = "_meta";
+
+/// Where an options document comes from. Some producers embed this in the
+/// options JSON, e.g. `"_meta": { "revision": "4f7f...", "generated": "2024-05-01" }`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Provenance {
+    /// Revision of the sources the options were taken from (e.g. a nixpkgs commit).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<String>,
+    /// When the options were generated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generated: Option<String>,
+    /// Tool that generated the options.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generator: Option<String>,
+}
+
+impl Provenance {
+    pub fn is_empty(&self) -> bool {
+        self.revision.is_none() && self.generated.is_none() && self.generator.is_none()
+    }
+
+    /// Fill the fields missing in `self` from `other`.
+    pub fn or(self, other: Provenance) -> Provenance {
+        Provenance {
+            revision: self.revision.or(other.revision),
+            generated: self.generated.or(other.generated),
+            generator: self.generator.or(other.generator),
+        }
+    }
+
+    /// Sentence stating the provenance, for the end of a document.
+    pub fn footer(&self) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+        let mut footer = "Generated".to_string();
+        if let Some(revision) = &self.revision {
+            footer.push_str(&format!(" from revision `{}`", revision));
+        }
+        if let Some(generated) = &self.generated {
+            footer.push_str(&format!(" on {}", generated));
+        }
+        if let Some(generator) = &self.generator {
+            footer.push_str(&format!(" by {}", generator));
+        }
+        footer.push('.');
+        Some(footer)
+    }
+}
+
+/// An option defined differently by two merged option sets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict {
+    pub name: String,
+    /// Set whose definition is kept.
+    pub kept: String,
+    /// Set whose definition is dropped.
+    pub dropped: String,
+}
+
+/// Merge option sets, given with the name of the module set they come from.
+///
+/// Options defined identically by several sets are merged silently. For
+/// differing definitions the first set wins and a conflict is reported. With
+/// `tag_origin`, every option records the set its definition was taken from.
+pub fn merge_options(
+    sets: Vec<(String, OptionsMap)>,
+    tag_origin: bool,
+) -> (OptionsMap, Vec<MergeConflict>) {
+    let mut merged = OptionsMap::new();
+    let mut conflicts = vec![];
+    let mut offset = 0;
+    for (origin, options) in sets {
+        let len = options.len();
+        // Sorted, so conflicts are reported in a stable order.
+        let mut options: Vec<(String, OptionDef)> = options.into_iter().collect();
+        options.sort_by(|a, b| a.0.cmp(&b.0));
+        for (name, mut opt) in options {
+            opt.position += offset;
+            match merged.get(&name) {
+                Some(existing) => {
+                    opt.origin = existing.origin.clone();
+                    opt.position = existing.position;
+                    if *existing != opt {
+                        conflicts.push(MergeConflict {
+                            name,
+                            kept: existing.origin.clone().unwrap_or_default(),
+                            dropped: origin.clone(),
+                        });
+                    }
+                }
+                None => {
+                    opt.origin = Some(origin.clone());
+                    merged.insert(name, opt);
+                }
+            }
+        }
+        offset += len;
+    }
+    if !tag_origin {
+        for opt in merged.values_mut() {
+            opt.origin = None;
+        }
+    }
+    (merged, conflicts)
+}
+
+/// Which options to render.
+#[derive(Debug, Clone, Default)]
+pub struct OptionFilter {
+    /// Glob patterns of option names to include (all if empty).
+    pub include: Vec<String>,
+    /// Glob patterns of option names to exclude.
+    pub exclude: Vec<String>,
+    /// Keep internal and invisible options.
+    pub show_internal: bool,
+    /// Leave out alias options, see [`OptionDef::alias_target`].
+    pub hide_aliases: bool,
+}
+
+/// Remove the options not passing `filter`.
+pub fn filter_options(options: &mut OptionsMap, filter: &OptionFilter) {
+    options.retain(|name, opt| {
+        (filter.show_internal || !opt.is_hidden())
+            && !(filter.hide_aliases && opt.alias_target().is_some())
+            && filter_name(name, &filter.include, &filter.exclude)
+    });
+}
+
+/// Remove the alias options whose target is among `options`, and list them
+/// in the `aliases` of the target instead. Aliases of aliases are listed
+/// under the option the chain ends at.
+pub fn fold_aliases(options: &mut OptionsMap) {
+    let targets: HashMap<String, String> = options
+        .iter()
+        .filter_map(|(name, opt)| Some((name.clone(), opt.alias_target()?.to_string())))
+        .filter(|(_, target)| options.contains_key(target))
+        .collect();
+    for (alias, target) in &targets {
+        let mut target = target;
+        // Follow the chain, giving up on cycles.
+        for _ in 0..targets.len() {
+            match targets.get(target) {
+                Some(next) => target = next,
+                None => break,
+            }
+        }
+        if targets.contains_key(target) {
+            continue;
+        }
+        options.remove(alias);
+        options.get_mut(target).unwrap().aliases.push(alias.clone());
+    }
+    for opt in options.values_mut() {
+        opt.aliases.sort();
+    }
 }
 
 /// Escape special CommonMark characters
@@ -184,18 +686,33 @@ fn md_escape(text: &str) -> String {
         .replace('`', "\\`")
 }
 
+/// Apply a user-supplied tag format, substituting `{text}` and `{type}`.
+fn apply_tag_format(template: &str, tagged: &TaggedValue) -> String {
+    template
+        .replace("{type}", &tagged.value_type)
+        .replace("{text}", tagged.text.as_deref().unwrap_or(""))
+}
+
 /// Format an option value for display
-fn format_option_value(value: &OptionValue) -> String {
+pub(crate) fn format_option_value(value: &OptionValue, opts: &RenderOptions) -> String {
     match value {
         OptionValue::Tagged(tagged) => {
+            if let Some(template) = opts.tag_formats.get(&tagged.value_type) {
+                return apply_tag_format(template, tagged);
+            }
             match tagged.value_type.as_str() {
                 "literalExpression" => {
                     if let Some(text) = &tagged.text {
+                        let formatted = opts
+                            .format_expressions
+                            .then(|| nixfmt::format_expression(text, nixfmt::DEFAULT_WIDTH))
+                            .flatten();
+                        let text = formatted.as_ref().unwrap_or(text);
                         // Multi-line expressions get code blocks
                         if text.contains('\n') {
                             format!("```nix\n{}\n```", text)
                         } else {
-                            format!("`{}`", text)
+                            code_span(text)
                         }
                     } else {
                         "`...`".to_string()
@@ -235,16 +752,30 @@ fn format_option_value(value: &OptionValue) -> String {
     }
 }
 
-/// Create a sanitized anchor ID from an option name
-fn make_anchor_id(name: &str, prefix: &str) -> String {
-    let sanitized = name
-        .replace('.', "-")
-        .replace('<', "_")
-        .replace('>', "_")
-        .replace('*', "_");
+/// Create a sanitized anchor ID from an option name. Dots become dashes and
+/// any other character that is not valid in a `{#id}` attribute (e.g. the
+/// `<`, `>` and `*` of placeholders, or quotes of freeform attributes)
+/// becomes an underscore.
+pub(crate) fn make_anchor_id(name: &str, prefix: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            '.' => '-',
+            c if c.is_ascii_alphanumeric() || c == '-' || c == '_' => c,
+            _ => '_',
+        })
+        .collect();
     format!("{}{}", prefix, sanitized)
 }
 
+/// Unique anchors of all options, see [`slug`](crate::slug). Options whose
+/// sanitized names collide are disambiguated with a numeric suffix.
+pub fn option_anchors(options: &OptionsMap, anchor_prefix: &str) -> Anchors {
+    assign_anchors(options.keys().map(String::as_str), |name| {
+        make_anchor_id(name, anchor_prefix)
+    })
+}
+
 /// Options for rendering
 #[derive(Debug, Clone)]
 pub struct RenderOptions {
@@ -256,96 +787,618 @@ pub struct RenderOptions {
     pub declarations_base_url: Option<String>,
     /// Revision for GitHub links
     pub revision: Option<String>,
+    /// Formats for `_type` tagged values, keyed by tag. `{text}` and `{type}`
+    /// in the template are replaced by the value's text and tag.
+    pub tag_formats: HashMap<String, String>,
+    /// Introductory markdown for option namespaces, keyed by prefix
+    /// (e.g. `services.nginx`). Rendered as a section before the first option
+    /// of the namespace.
+    pub namespace_intros: BTreeMap<String, String>,
+    /// Prefix stripped from declaration paths. If unset, Nix store prefixes
+    /// (`/nix/store/<hash>-<name>/`) are detected and stripped.
+    pub store_path_strip: Option<String>,
+    /// Order options are rendered in.
+    pub sort: SortOrder,
+    /// Labels of the option attributes.
+    pub labels: Labels,
+    /// Group options under headings for the first this many segments of
+    /// their `loc` (e.g. `services.nginx` for 2). Options are rendered as
+    /// one flat list if unset or zero.
+    pub group_by_prefix: Option<usize>,
+    /// Group options under a heading per file declaring them, taken from
+    /// their `declarations`. Takes precedence over `group_by_prefix`.
+    pub group_by_declaration: bool,
+    /// Provenance of the options, stated in the document footer.
+    pub provenance: Provenance,
+    /// Order of the parts of a rendered option. Parts that are not listed
+    /// are omitted.
+    pub layout: Vec<OptionSection>,
+    /// URL of a package in the package index, with `{attr}` standing for
+    /// its attribute path. Related packages are not linked if unset.
+    pub package_index_url: Option<String>,
+    /// Start the document with a table of contents, nested by option prefix.
+    pub toc: bool,
+    /// End the document with an alphabetical index of all options.
+    pub index: bool,
+    /// Link the base types of option types to a types appendix following
+    /// the options.
+    pub link_types: bool,
+    /// Levels of the document title and option headings.
+    pub heading_levels: HeadingLevels,
+    /// Reformat literal Nix expressions of defaults and examples, see
+    /// [`nixfmt`].
+    pub format_expressions: bool,
+    /// Fold defaults longer than this many lines into a `<details>`
+    /// element, which GitHub and HTML output render collapsed.
+    pub fold_defaults: Option<usize>,
+    /// How alias options are rendered.
+    pub aliases: AliasStyle,
 }
 
-impl Default for RenderOptions {
-    fn default() -> Self {
-        Self {
-            anchor_prefix: "opt-".to_string(),
-            include_declarations: true,
-            declarations_base_url: None,
-            revision: None,
+/// How options that only alias another option are rendered, see
+/// [`OptionDef::alias_target`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AliasStyle {
+    /// Like any other option.
+    Full,
+    /// As their heading and a line linking to the target.
+    #[default]
+    Compact,
+    /// Listed under their target, see [`fold_aliases`].
+    Fold,
+}
+
+impl AliasStyle {
+    /// Look up a style by its command line name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "full" => Some(Self::Full),
+            "compact" => Some(Self::Compact),
+            "fold" => Some(Self::Fold),
+            _ => None,
         }
     }
 }
 
-/// Render a single option to CommonMark
-fn render_option(name: &str, opt: &OptionDef, opts: &RenderOptions) -> String {
-    let mut output = String::new();
+/// A part of a rendered option, following its heading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionSection {
+    /// The type, and whether the option is read only if the layout has no
+    /// badges.
+    Type,
+    /// Admonitions for read-only, internal, invisible and experimental
+    /// options.
+    Badges,
+    Stability,
+    Origin,
+    Default,
+    Description,
+    Example,
+    RelatedPackages,
+    Declarations,
+}
 
-    // Header with anchor
-    let anchor = make_anchor_id(name, &opts.anchor_prefix);
-    output.push_str(&format!("## `{}` {{#{}}}\n\n", name, anchor));
+impl OptionSection {
+    /// All parts, in their default order.
+    pub const ALL: [OptionSection; 9] = [
+        Self::Type,
+        Self::Badges,
+        Self::Stability,
+        Self::Origin,
+        Self::Default,
+        Self::Description,
+        Self::Example,
+        Self::RelatedPackages,
+        Self::Declarations,
+    ];
 
-    // Type and read-only status
-    if let Some(ref opt_type) = opt.option_type {
-        let ro = if opt.read_only { " *(read only)*" } else { "" };
-        output.push_str(&format!("**Type:** `{}`{}\n\n", opt_type, ro));
+    /// Look up a part by its command line name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.as_str() == name)
     }
 
-    // Default value
-    if let Some(ref default) = opt.default {
-        let formatted = format_option_value(default);
-        if formatted.contains('\n') {
-            output.push_str(&format!("**Default:**\n\n{}\n\n", formatted));
-        } else {
-            output.push_str(&format!("**Default:** {}\n\n", formatted));
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Type => "type",
+            Self::Badges => "badges",
+            Self::Stability => "stability",
+            Self::Origin => "origin",
+            Self::Default => "default",
+            Self::Description => "description",
+            Self::Example => "example",
+            Self::RelatedPackages => "related-packages",
+            Self::Declarations => "declarations",
         }
     }
 
-    // Description
-    if let Some(ref desc) = opt.description {
-        let desc_text = desc.as_str();
-        if !desc_text.is_empty() {
-            output.push_str(desc_text);
-            output.push_str("\n\n");
+    /// Parse a layout from part names, e.g. `["description", "type"]`.
+    pub fn parse_layout<S: AsRef<str>>(names: &[S]) -> Result<Vec<Self>, String> {
+        names
+            .iter()
+            .map(|name| {
+                Self::from_name(name.as_ref()).ok_or_else(|| {
+                    format!(
+                        "unknown option section '{}', expected one of: {}",
+                        name.as_ref(),
+                        Self::ALL.map(|s| s.as_str()).join(", ")
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+/// Order options and library functions are rendered in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    /// Alphabetical, but `enable` and `package` options come first within
+    /// their namespace, as in the NixOS manual.
+    #[default]
+    Priority,
+    /// Plain alphabetical order.
+    Alphabetical,
+    /// The order of the source: the options JSON, or the Nix file.
+    Source,
+}
+
+impl SortOrder {
+    /// Look up an order by its command line name: `nixos`, `alphabetical`
+    /// or `source`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "nixos" => Some(Self::Priority),
+            "alphabetical" => Some(Self::Alphabetical),
+            "source" => Some(Self::Source),
+            _ => None,
         }
     }
 
-    // Example
-    if let Some(ref example) = opt.example {
-        let formatted = format_option_value(example);
-        if formatted.contains('\n') {
-            output.push_str(&format!("**Example:**\n\n{}\n\n", formatted));
-        } else {
-            output.push_str(&format!("**Example:** {}\n\n", formatted));
+    /// Compare two names. Names in source order are compared alphabetically,
+    /// as their position is not known.
+    pub fn compare(&self, a: &str, b: &str) -> std::cmp::Ordering {
+        match self {
+            SortOrder::Priority => compare_option_names(a, b),
+            SortOrder::Alphabetical | SortOrder::Source => a.cmp(b),
         }
     }
 
-    // Related packages
-    if let Some(ref related) = opt.related_packages {
-        if !related.is_empty() {
-            output.push_str("**Related packages:**\n\n");
-            output.push_str(related);
-            output.push_str("\n\n");
+    /// The names of `options` in this order.
+    pub fn sorted_names<'a>(&self, options: &'a OptionsMap) -> Vec<&'a String> {
+        let mut names: Vec<&String> = options.keys().collect();
+        match self {
+            SortOrder::Source => names.sort_by_key(|name| (options[*name].position, *name)),
+            _ => names.sort_by(|a, b| self.compare(a, b)),
         }
+        names
     }
+}
 
-    // Declarations
-    if opts.include_declarations && !opt.declarations.is_empty() {
-        output.push_str("**Declared by:**\n\n");
-        for decl in &opt.declarations {
-            let name = decl.name();
-            if let Some(url) = decl.url() {
-                output.push_str(&format!("- [{}]({})\n", md_escape(name), url));
-            } else if let Some(ref base_url) = opts.declarations_base_url {
-                // Build URL from base + path
-                let url = if let Some(ref rev) = opts.revision {
-                    format!("{}/blob/{}/{}", base_url.trim_end_matches('/'), rev, name)
-                } else {
-                    format!("{}/blob/master/{}", base_url.trim_end_matches('/'), name)
-                };
-                output.push_str(&format!("- [{}]({})\n", md_escape(name), url));
-            } else {
-                output.push_str(&format!("- `{}`\n", name));
+/// Labels used for the attributes of a rendered option.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Labels {
+    pub option_type: String,
+    pub read_only: String,
+    pub default: String,
+    pub example: String,
+    pub related_packages: String,
+    pub declared_by: String,
+    pub origin: String,
+    pub contents: String,
+    pub index: String,
+    pub types: String,
+    /// Summary of a folded value, with `{lines}` standing for its length.
+    pub folded: String,
+    pub read_only_notice: String,
+    pub internal_notice: String,
+    pub hidden_notice: String,
+    pub experimental_notice: String,
+    pub stability: String,
+    /// Line rendered for an alias option, with `{option}` standing for a
+    /// link to its target.
+    pub alias_of: String,
+    pub aliases: String,
+}
+
+impl Labels {
+    /// Names of the labels on the command line.
+    pub const NAMES: [&str; 18] = [
+        "type",
+        "read-only",
+        "default",
+        "example",
+        "related-packages",
+        "declared-by",
+        "origin",
+        "contents",
+        "index",
+        "types",
+        "folded",
+        "read-only-notice",
+        "internal-notice",
+        "hidden-notice",
+        "experimental-notice",
+        "stability",
+        "alias-of",
+        "aliases",
+    ];
+
+    /// Set a label by its command line name.
+    pub fn set(&mut self, name: &str, text: &str) -> Result<(), String> {
+        let label = match name {
+            "type" => &mut self.option_type,
+            "read-only" => &mut self.read_only,
+            "default" => &mut self.default,
+            "example" => &mut self.example,
+            "related-packages" => &mut self.related_packages,
+            "declared-by" => &mut self.declared_by,
+            "origin" => &mut self.origin,
+            "contents" => &mut self.contents,
+            "index" => &mut self.index,
+            "types" => &mut self.types,
+            "folded" => &mut self.folded,
+            "read-only-notice" => &mut self.read_only_notice,
+            "internal-notice" => &mut self.internal_notice,
+            "hidden-notice" => &mut self.hidden_notice,
+            "experimental-notice" => &mut self.experimental_notice,
+            "stability" => &mut self.stability,
+            "alias-of" => &mut self.alias_of,
+            "aliases" => &mut self.aliases,
+            _ => {
+                return Err(format!(
+                    "unknown label '{}', expected one of: {}",
+                    name,
+                    Self::NAMES.join(", ")
+                ));
+            }
+        };
+        *label = text.to_string();
+        Ok(())
+    }
+}
+
+impl Default for Labels {
+    fn default() -> Self {
+        Self {
+            option_type: "Type".to_string(),
+            read_only: "read only".to_string(),
+            default: "Default".to_string(),
+            example: "Example".to_string(),
+            related_packages: "Related packages".to_string(),
+            declared_by: "Declared by".to_string(),
+            origin: "Module set".to_string(),
+            contents: "Contents".to_string(),
+            index: "Index".to_string(),
+            types: "Types".to_string(),
+            folded: "Show all {lines} lines".to_string(),
+            read_only_notice: "This option is read only and cannot be set in a configuration."
+                .to_string(),
+            internal_notice:
+                "This option is internal to the module system and not meant to be set by users."
+                    .to_string(),
+            hidden_notice: "This option is hidden from the documentation.".to_string(),
+            experimental_notice:
+                "This option is experimental and may change or be removed without notice."
+                    .to_string(),
+            stability: "Stability".to_string(),
+            alias_of: "Alias of {option}.".to_string(),
+            aliases: "Aliases".to_string(),
+        }
+    }
+}
+
+/// A named bundle of rendering settings matching a common downstream manual.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    /// The NixOS manual: `opt-` anchors, `enable`/`package` options first.
+    NixosManual,
+    /// The Home Manager manual: `opt-` anchors, alphabetical order.
+    HomeManager,
+    /// Plain CommonMark without anchor prefixes or declaration links.
+    Minimal,
+}
+
+impl Profile {
+    /// Look up a profile by its command line name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "nixos-manual" => Some(Self::NixosManual),
+            "home-manager" => Some(Self::HomeManager),
+            "minimal" => Some(Self::Minimal),
+            _ => None,
+        }
+    }
+
+    /// Rendering settings of the profile.
+    pub fn render_options(&self) -> RenderOptions {
+        match self {
+            Profile::NixosManual => RenderOptions::default(),
+            Profile::HomeManager => RenderOptions {
+                sort: SortOrder::Alphabetical,
+                ..Default::default()
+            },
+            Profile::Minimal => RenderOptions {
+                anchor_prefix: String::new(),
+                include_declarations: false,
+                sort: SortOrder::Alphabetical,
+                labels: Labels {
+                    related_packages: "Packages".to_string(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Markdown flavor descriptions are validated against.
+    pub fn flavor(&self) -> MarkdownFlavor {
+        match self {
+            Profile::NixosManual | Profile::HomeManager => MarkdownFlavor::Nixpkgs,
+            Profile::Minimal => MarkdownFlavor::CommonMark,
+        }
+    }
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            anchor_prefix: "opt-".to_string(),
+            include_declarations: true,
+            declarations_base_url: None,
+            revision: None,
+            tag_formats: HashMap::new(),
+            namespace_intros: BTreeMap::new(),
+            store_path_strip: None,
+            sort: SortOrder::default(),
+            labels: Labels::default(),
+            group_by_prefix: None,
+            group_by_declaration: false,
+            provenance: Provenance::default(),
+            layout: OptionSection::ALL.to_vec(),
+            package_index_url: Some(DEFAULT_PACKAGE_INDEX_URL.to_string()),
+            toc: false,
+            index: false,
+            link_types: false,
+            heading_levels: HeadingLevels::default(),
+            format_expressions: false,
+            fold_defaults: None,
+            aliases: AliasStyle::default(),
+        }
+    }
+}
+
+/// Length of the base32 hash in a Nix store path.
+const STORE_HASH_LENGTH: usize = 32;
+
+/// Strip the Nix store prefix from a declaration path, turning
+/// `/nix/store/<hash>-source/nixos/foo.nix` into `nixos/foo.nix`.
+///
+/// If `strip` is given, that directory is removed instead of a detected
+/// store prefix. Paths outside of it are returned unchanged.
+pub fn strip_store_path<'a>(path: &'a str, strip: Option<&str>) -> &'a str {
+    if let Some(prefix) = strip {
+        // `/build/src` is not a prefix of `/build/srcfoo/lib.nix`.
+        return path
+            .strip_prefix(prefix.trim_end_matches('/'))
+            .filter(|rest| rest.is_empty() || rest.starts_with('/'))
+            .map_or(path, |rest| rest.trim_start_matches('/'));
+    }
+    let Some(rest) = path.strip_prefix("/nix/store/") else {
+        return path;
+    };
+    let (entry, rest) = rest.split_once('/').unwrap_or((rest, ""));
+    let is_store_entry = entry.len() > STORE_HASH_LENGTH
+        && entry.as_bytes()[STORE_HASH_LENGTH] == b'-'
+        && entry[..STORE_HASH_LENGTH]
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric());
+    if is_store_entry && !rest.is_empty() {
+        rest
+    } else {
+        path
+    }
+}
+
+/// The displayed path of a declaration and the URL it links to, if any.
+pub(crate) fn declaration_link<'a>(
+    decl: &'a DeclarationLoc,
+    opts: &RenderOptions,
+) -> (&'a str, Option<String>) {
+    let name = strip_store_path(decl.name(), opts.store_path_strip.as_deref());
+    let url = decl.url().map(str::to_string).or_else(|| {
+        opts.declarations_base_url.as_ref().map(|base_url| {
+            // Build URL from base + path
+            let rev = opts.revision.as_deref().unwrap_or("master");
+            format!("{}/blob/{}/{}", base_url.trim_end_matches('/'), rev, name)
+        })
+    });
+    (name, url)
+}
+
+/// The anchors and bodies of all options, see [`render_option_body`].
+struct RenderedOptions<'a> {
+    anchors: Anchors,
+    bodies: HashMap<&'a str, String>,
+}
+
+impl<'a> RenderedOptions<'a> {
+    /// Render the bodies of `options`. Rendering values and descriptions
+    /// takes most of the time for large option sets, so the bodies are
+    /// rendered in parallel and only put together in order afterwards.
+    fn new(options: &'a OptionsMap, opts: &RenderOptions) -> Self {
+        let anchors = option_anchors(options, &opts.anchor_prefix);
+        let bodies = options
+            .par_iter()
+            .map(|(name, opt)| (name.as_str(), render_option_body(opt, &anchors, opts)))
+            .collect();
+        RenderedOptions { anchors, bodies }
+    }
+}
+
+/// Render a single option to CommonMark
+fn render_option(name: &str, rendered: &RenderedOptions, level: usize) -> String {
+    let mut output = String::new();
+
+    // Header with anchor
+    output.push_str(&format!(
+        "{} {} {{#{}}}\n\n",
+        "#".repeat(level),
+        code_span(name),
+        rendered.anchors.get(name).unwrap_or_default()
+    ));
+    output.push_str(&rendered.bodies[name]);
+    output
+}
+
+/// Render the parts of an option following its heading, in the order of
+/// the layout. Alias options are rendered as a single line linking to their
+/// target if the alias style is compact.
+pub(crate) fn render_option_body(
+    opt: &OptionDef,
+    anchors: &Anchors,
+    opts: &RenderOptions,
+) -> String {
+    if let Some(target) = opt
+        .alias_target()
+        .filter(|_| opts.aliases == AliasStyle::Compact)
+    {
+        let link = match anchors.get(target) {
+            Some(anchor) => format!("[{}](#{})", code_span(target), anchor),
+            None => code_span(target),
+        };
+        return format!("{}\n\n", opts.labels.alias_of.replace("{option}", &link));
+    }
+    let mut output = String::new();
+    for section in &opts.layout {
+        match section {
+            // Type and read-only status
+            OptionSection::Type => {
+                if let Some(ref opt_type) = opt.option_type {
+                    let ro = if opt.read_only && !opts.layout.contains(&OptionSection::Badges) {
+                        format!(" *({})*", opts.labels.read_only)
+                    } else {
+                        String::new()
+                    };
+                    let rendered = if opts.link_types {
+                        optiontypes::link_type(opt_type, opts)
+                    } else {
+                        code_span(opt_type)
+                    };
+                    output.push_str(&format!(
+                        "**{}:** {}{}\n\n",
+                        opts.labels.option_type, rendered, ro
+                    ));
+                }
+            }
+            OptionSection::Badges => {
+                for (class, notice) in opt.flags().badges(&opts.labels) {
+                    output.push_str(&format!("::: {{.{}}}\n{}\n:::\n\n", class, notice));
+                }
+            }
+            // Stability badge
+            OptionSection::Stability => {
+                if let Some(stability) = opt.stability.as_deref().and_then(Stability::from_name) {
+                    output.push_str(&format!(
+                        "**{}:** *{}*\n\n",
+                        opts.labels.stability,
+                        stability.as_str()
+                    ));
+                }
+            }
+            // Originating module set
+            OptionSection::Origin => {
+                if let Some(ref origin) = opt.origin {
+                    output.push_str(&format!("**{}:** `{}`\n\n", opts.labels.origin, origin));
+                }
+            }
+            OptionSection::Default => {
+                if let Some(ref default) = opt.default {
+                    output.push_str(&render_value(
+                        &opts.labels.default,
+                        default,
+                        opts.fold_defaults,
+                        opts,
+                    ));
+                }
+            }
+            OptionSection::Description => {
+                if let Some(ref desc) = opt.description {
+                    let desc_text = desc.as_str();
+                    if !desc_text.is_empty() {
+                        output.push_str(desc_text);
+                        output.push_str("\n\n");
+                    }
+                }
+                if !opt.aliases.is_empty() {
+                    let aliases: Vec<String> = opt.aliases.iter().map(|a| code_span(a)).collect();
+                    output.push_str(&format!(
+                        "**{}:** {}\n\n",
+                        opts.labels.aliases,
+                        aliases.join(", ")
+                    ));
+                }
+            }
+            OptionSection::Example => {
+                if let Some(ref example) = opt.example {
+                    output.push_str(&render_value(&opts.labels.example, example, None, opts));
+                }
+            }
+            OptionSection::RelatedPackages => {
+                if let Some(ref related) = opt.related_packages {
+                    let related = render_related_packages(related, opts);
+                    if !related.is_empty() {
+                        output.push_str(&format!("**{}:**\n\n", opts.labels.related_packages));
+                        output.push_str(&related);
+                        output.push_str("\n\n");
+                    }
+                }
+            }
+            OptionSection::Declarations => {
+                if opts.include_declarations && !opt.declarations.is_empty() {
+                    output.push_str(&format!("**{}:**\n\n", opts.labels.declared_by));
+                    for decl in &opt.declarations {
+                        let (name, url) = declaration_link(decl, opts);
+                        match url {
+                            Some(url) => {
+                                output.push_str(&format!("- [{}]({})\n", md_escape(name), url))
+                            }
+                            None => output.push_str(&format!("- {}\n", code_span(name))),
+                        }
+                    }
+                    output.push('\n');
+                }
             }
         }
-        output.push('\n');
     }
 
     output
 }
 
+/// Render a labelled default or example value, as a block if it spans
+/// several lines, folded if it spans more than `fold` lines.
+fn render_value(
+    label: &str,
+    value: &OptionValue,
+    fold: Option<usize>,
+    opts: &RenderOptions,
+) -> String {
+    let formatted = format_option_value(value, opts);
+    let lines = formatted
+        .lines()
+        .filter(|line| !line.starts_with("```"))
+        .count();
+    if fold.is_some_and(|max| lines > max) {
+        let summary = opts.labels.folded.replace("{lines}", &lines.to_string());
+        format!(
+            "**{}:**\n\n<details>\n<summary>{}</summary>\n\n{}\n\n</details>\n\n",
+            label, summary, formatted
+        )
+    } else if formatted.contains('\n') {
+        format!("**{}:**\n\n{}\n\n", label, formatted)
+    } else {
+        format!("**{}:** {}\n\n", label, formatted)
+    }
+}
+
 /// Get sort priority for an option name segment.
 /// Returns 0 for "enable*", 1 for "package*", 2 for everything else.
 fn segment_priority(segment: &str) -> u8 {
@@ -360,7 +1413,7 @@ fn segment_priority(segment: &str) -> u8 {
 
 /// Compare two option names for sorting.
 /// Sorts with enable first, then package, then alphabetically within each segment.
-fn compare_option_names(a: &str, b: &str) -> std::cmp::Ordering {
+pub(crate) fn compare_option_names(a: &str, b: &str) -> std::cmp::Ordering {
     let a_parts: Vec<&str> = a.split('.').collect();
     let b_parts: Vec<&str> = b.split('.').collect();
 
@@ -382,20 +1435,348 @@ fn compare_option_names(a: &str, b: &str) -> std::cmp::Ordering {
 
 /// Render all options to CommonMark
 pub fn render_options_to_commonmark(options: &OptionsMap, render_opts: &RenderOptions) -> String {
-    let mut output = String::new();
-
     // Sort options by name for consistent output
-    let mut names: Vec<&String> = options.keys().collect();
-    names.sort_by(|a, b| compare_option_names(a, b));
+    let names = render_opts.sort.sorted_names(options);
+    let rendered = RenderedOptions::new(options, render_opts);
 
-    for name in names {
-        if let Some(opt) = options.get(name) {
-            output.push_str(&render_option(name, opt, render_opts));
-        }
+    if render_opts.group_by_declaration {
+        return render_by_declaration(options, names, &rendered, render_opts);
+    }
+    if let Some(depth) = render_opts.group_by_prefix.filter(|d| *d > 0) {
+        return render_grouped(options, names, &rendered, depth, render_opts);
     }
 
-    output
-}
+    let mut output = String::new();
+    let mut pending_intros: Vec<(&String, &String)> = render_opts.namespace_intros.iter().collect();
+
+    for name in names {
+        // Shorter prefixes sort first, so outer namespaces are introduced first.
+        pending_intros.retain(|(prefix, intro)| {
+            if !name.starts_with(&format!("{}.", prefix)) {
+                return true;
+            }
+            output.push_str(&render_namespace_intro(prefix, intro, render_opts));
+            false
+        });
+        output.push_str(&render_option(name, &rendered, 2));
+    }
+
+    output
+}
+
+/// The prefix of an option's `loc` it is grouped under: the first `depth`
+/// segments, but never the whole `loc`.
+fn group_prefix(name: &str, opt: &OptionDef, depth: usize) -> String {
+    let loc: Vec<&str> = if opt.loc.is_empty() {
+        name.split('.').collect()
+    } else {
+        opt.loc.iter().map(String::as_str).collect()
+    };
+    loc[..depth.min(loc.len().saturating_sub(1))].join(".")
+}
+
+/// Split options by the prefix of their `loc` they would be grouped under
+/// with `group_by_prefix` set to `depth`.
+pub fn split_by_prefix(options: &OptionsMap, depth: usize) -> BTreeMap<String, OptionsMap> {
+    let mut groups: BTreeMap<String, OptionsMap> = BTreeMap::new();
+    for (name, opt) in options {
+        groups
+            .entry(group_prefix(name, opt, depth))
+            .or_default()
+            .insert(name.clone(), opt.clone());
+    }
+    groups
+}
+
+/// Render options grouped under a heading per prefix. Namespace intros of a
+/// group are rendered below its heading; options outside of any group come
+/// first.
+fn render_grouped(
+    options: &OptionsMap,
+    names: Vec<&String>,
+    rendered: &RenderedOptions,
+    depth: usize,
+    opts: &RenderOptions,
+) -> String {
+    let mut groups: BTreeMap<String, Vec<&String>> = BTreeMap::new();
+    for name in names {
+        groups
+            .entry(group_prefix(name, &options[name], depth))
+            .or_default()
+            .push(name);
+    }
+
+    let mut output = String::new();
+    for (prefix, names) in groups {
+        let level = if prefix.is_empty() {
+            2
+        } else {
+            match opts.namespace_intros.get(&prefix) {
+                Some(intro) => output.push_str(&render_namespace_intro(&prefix, intro, opts)),
+                None => {
+                    let anchor = make_anchor_id(&prefix, &format!("sec-{}", opts.anchor_prefix));
+                    output.push_str(&format!("## {} {{#{}}}\n\n", code_span(&prefix), anchor));
+                }
+            }
+            3
+        };
+        for name in names {
+            output.push_str(&render_option(name, rendered, level));
+        }
+    }
+
+    output
+}
+
+/// Render options grouped under a heading per declaring file, in the order
+/// of their paths. An option is rendered under the first file declaring it
+/// and linked to from the others; options without declarations come first.
+fn render_by_declaration(
+    options: &OptionsMap,
+    names: Vec<&String>,
+    rendered: &RenderedOptions,
+    opts: &RenderOptions,
+) -> String {
+    let mut undeclared = vec![];
+    // Files by displayed path, with their URL, the options rendered under
+    // them and the options they also declare.
+    type Group<'a> = (Option<String>, Vec<&'a String>, Vec<&'a String>);
+    let mut files: BTreeMap<&str, Group> = BTreeMap::new();
+    for name in names {
+        let declarations = &options[name].declarations;
+        if declarations.is_empty() {
+            undeclared.push(name);
+        }
+        for (i, decl) in declarations.iter().enumerate() {
+            let (path, url) = declaration_link(decl, opts);
+            let (_, own, linked) = files.entry(path).or_insert_with(|| (url, vec![], vec![]));
+            if i == 0 {
+                own.push(name);
+            } else if !own.contains(&name) && !linked.contains(&name) {
+                linked.push(name);
+            }
+        }
+    }
+
+    let mut output = String::new();
+    for name in undeclared {
+        output.push_str(&render_option(name, rendered, 2));
+    }
+    for (path, (url, own, linked)) in files {
+        let anchor = make_anchor_id(path, &format!("sec-{}decl-", opts.anchor_prefix));
+        let title = match url {
+            Some(url) => format!("[{}]({})", code_span(path), url),
+            None => code_span(path),
+        };
+        output.push_str(&format!(
+            "## {} {{#{}}}
+
+",
+            title, anchor
+        ));
+        for name in own {
+            output.push_str(&render_option(name, rendered, 3));
+        }
+        if !linked.is_empty() {
+            for name in linked {
+                let anchor = rendered.anchors.get(name).unwrap_or_default();
+                output.push_str(&format!(
+                    "- [{}](#{})
+",
+                    code_span(name),
+                    anchor
+                ));
+            }
+            output.push('\n');
+        }
+    }
+
+    output
+}
+
+/// Render the intro section of an option namespace
+fn render_namespace_intro(prefix: &str, intro: &str, opts: &RenderOptions) -> String {
+    let anchor = make_anchor_id(prefix, &format!("sec-{}", opts.anchor_prefix));
+    format!(
+        "## {} {{#{}}}\n\n{}\n\n",
+        code_span(prefix),
+        anchor,
+        intro.trim()
+    )
+}
+
+/// Load namespace intros from a directory of markdown files named after the
+/// namespace they describe (e.g. `services.nginx.md`).
+pub fn load_namespace_intros(dir: &Path) -> Result<BTreeMap<String, String>, String> {
+    let mut intros = BTreeMap::new();
+    let entries =
+        fs::read_dir(dir).map_err(|e| format!("Failed to read intro directory: {}", e))?;
+    for entry in entries {
+        let path = entry
+            .map_err(|e| format!("Failed to read intro directory: {}", e))?
+            .path();
+        if path.extension().is_some_and(|ext| ext == "md") {
+            let prefix = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let text = fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            intros.insert(prefix, text);
+        }
+    }
+    Ok(intros)
+}
+
+/// Check all option descriptions for markdown that renders badly in `flavor`.
+///
+/// Returns the offending option names with their issues, sorted like the
+/// rendered document.
+pub fn validate_descriptions(
+    options: &OptionsMap,
+    flavor: MarkdownFlavor,
+) -> Vec<(String, MarkdownIssue)> {
+    let mut names: Vec<&String> = options.keys().collect();
+    names.sort_by(|a, b| compare_option_names(a, b));
+
+    names
+        .into_iter()
+        .flat_map(|name| {
+            let desc = options[name]
+                .description
+                .as_ref()
+                .map(|d| d.as_str())
+                .unwrap_or_default();
+            mdcheck::check_markdown(desc, flavor)
+                .into_iter()
+                .map(move |issue| (name.clone(), issue))
+        })
+        .collect()
+}
+
+/// Maximum rendered lengths (in characters) for parts of an option's
+/// documentation. Exceeding a budget produces a [BudgetViolation].
+#[derive(Debug, Clone, Default)]
+pub struct Budgets {
+    pub max_default_length: Option<usize>,
+    pub max_example_length: Option<usize>,
+    pub max_description_length: Option<usize>,
+}
+
+/// An option whose default, example or description exceeds its budget.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BudgetViolation {
+    pub option: String,
+    /// The offending field: `default`, `example` or `description`.
+    pub field: &'static str,
+    pub length: usize,
+    pub limit: usize,
+}
+
+/// Summary statistics about an options set.
+#[derive(Debug, Clone, Serialize)]
+pub struct OptionsStats {
+    pub total: usize,
+    pub with_description: usize,
+    pub with_default: usize,
+    pub with_example: usize,
+    pub budget_violations: Vec<BudgetViolation>,
+    #[serde(skip_serializing_if = "Provenance::is_empty")]
+    pub provenance: Provenance,
+}
+
+/// Check the rendered default, example and description of every option
+/// against the budgets.
+pub fn check_budgets(
+    options: &OptionsMap,
+    budgets: &Budgets,
+    render_opts: &RenderOptions,
+) -> Vec<BudgetViolation> {
+    let names = render_opts.sort.sorted_names(options);
+
+    let mut violations = vec![];
+    for name in names {
+        let opt = &options[name];
+        let fields = [
+            (
+                "default",
+                opt.default
+                    .as_ref()
+                    .map(|v| format_option_value(v, render_opts)),
+                budgets.max_default_length,
+            ),
+            (
+                "example",
+                opt.example
+                    .as_ref()
+                    .map(|v| format_option_value(v, render_opts)),
+                budgets.max_example_length,
+            ),
+            (
+                "description",
+                opt.description.as_ref().map(|d| d.as_str().to_string()),
+                budgets.max_description_length,
+            ),
+        ];
+        for (field, text, limit) in fields {
+            if let (Some(text), Some(limit)) = (text, limit) {
+                let length = text.chars().count();
+                if length > limit {
+                    violations.push(BudgetViolation {
+                        option: name.clone(),
+                        field,
+                        length,
+                        limit,
+                    });
+                }
+            }
+        }
+    }
+    violations
+}
+
+/// Collect summary statistics, including budget violations.
+pub fn collect_stats(
+    options: &OptionsMap,
+    budgets: &Budgets,
+    render_opts: &RenderOptions,
+) -> OptionsStats {
+    OptionsStats {
+        total: options.len(),
+        with_description: options
+            .values()
+            .filter(|o| {
+                o.description
+                    .as_ref()
+                    .is_some_and(|d| !d.as_str().is_empty())
+            })
+            .count(),
+        with_default: options.values().filter(|o| o.default.is_some()).count(),
+        with_example: options.values().filter(|o| o.example.is_some()).count(),
+        budget_violations: check_budgets(options, budgets, render_opts),
+        provenance: render_opts.provenance.clone(),
+    }
+}
+
+/// Parse options JSON held in memory and render it as a document.
+pub fn render_options_from_str(
+    json: &str,
+    title: &str,
+    preamble: Option<&str>,
+    render_opts: &RenderOptions,
+) -> Result<String, String> {
+    let (options, provenance) = parse_options_json_with_provenance(json)?;
+    let render_opts = RenderOptions {
+        provenance: render_opts.provenance.clone().or(provenance),
+        ..render_opts.clone()
+    };
+    Ok(render_options_document(
+        &options,
+        title,
+        preamble,
+        &render_opts,
+    ))
+}
 
 /// Render options with a title and optional preamble
 pub fn render_options_document(
@@ -415,9 +1796,143 @@ pub fn render_options_document(
         output.push_str("\n\n");
     }
 
+    let anchors = option_anchors(options, &render_opts.anchor_prefix);
+    if render_opts.toc {
+        output.push_str(&render_toc(options, &anchors, render_opts));
+    }
+
     // Options
     output.push_str(&render_options_to_commonmark(options, render_opts));
 
+    if render_opts.link_types {
+        output.push_str(&optiontypes::render_types_appendix(options, render_opts));
+    }
+
+    if render_opts.index {
+        output.push_str(&render_index(options, &anchors, render_opts));
+    }
+
+    if let Some(footer) = render_opts.provenance.footer() {
+        output.push_str(&format!("---\n\n*{}*\n", footer));
+    }
+
+    render_opts.heading_levels.apply(&output)
+}
+
+/// A namespace of the table of contents, with the namespaces and options
+/// below it in the order they are rendered in.
+#[derive(Default)]
+struct TocNode<'a> {
+    /// The option named like the namespace, if any.
+    option: Option<&'a str>,
+    /// Summary of that option.
+    summary: Option<String>,
+    children: Vec<(&'a str, TocNode<'a>)>,
+}
+
+impl<'a> TocNode<'a> {
+    /// The first option in or below this namespace.
+    fn first_option(&self) -> Option<&'a str> {
+        self.option
+            .or_else(|| self.children.first()?.1.first_option())
+    }
+
+    fn write(&self, prefix: &str, depth: usize, anchors: &Anchors, out: &mut String) {
+        for (segment, child) in &self.children {
+            let name = if prefix.is_empty() {
+                segment.to_string()
+            } else {
+                format!("{}.{}", prefix, segment)
+            };
+            // Namespaces link to their first option.
+            let anchor = child
+                .first_option()
+                .and_then(|option| anchors.get(option))
+                .unwrap_or_default();
+            out.push_str(&format!(
+                "{}- [{}](#{})",
+                "  ".repeat(depth),
+                code_span(&name),
+                anchor
+            ));
+            match &child.summary {
+                Some(summary) => out.push_str(&format!(": {}\n", summary)),
+                None => out.push('\n'),
+            }
+            child.write(&name, depth + 1, anchors, out);
+        }
+    }
+}
+
+/// Render a table of contents of all options, nested by the segments of
+/// their `loc`.
+fn render_toc(options: &OptionsMap, anchors: &Anchors, opts: &RenderOptions) -> String {
+    let names = opts.sort.sorted_names(options);
+
+    let mut root = TocNode::default();
+    for name in names {
+        let opt = &options[name];
+        let loc: Vec<&str> = if opt.loc.is_empty() {
+            name.split('.').collect()
+        } else {
+            opt.loc.iter().map(String::as_str).collect()
+        };
+        let mut node = &mut root;
+        for segment in loc {
+            let pos = match node.children.iter().position(|(s, _)| *s == segment) {
+                Some(pos) => pos,
+                None => {
+                    node.children.push((segment, TocNode::default()));
+                    node.children.len() - 1
+                }
+            };
+            node = &mut node.children[pos].1;
+        }
+        node.option = Some(name);
+        node.summary = opt.summary();
+    }
+
+    let anchor = make_anchor_id("contents", &format!("sec-{}", opts.anchor_prefix));
+    let mut output = format!("## {} {{#{}}}\n\n", opts.labels.contents, anchor);
+    root.write("", 0, anchors, &mut output);
+    output.push('\n');
+    output
+}
+
+/// Render an alphabetical index of all options, under a heading per
+/// initial letter.
+fn render_index(options: &OptionsMap, anchors: &Anchors, opts: &RenderOptions) -> String {
+    let mut names: Vec<&String> = options.keys().collect();
+    names.sort_by_key(|name| (name.to_lowercase(), name.to_string()));
+
+    let section = format!("sec-{}", opts.anchor_prefix);
+    let mut output = format!(
+        "## {} {{#{}}}\n\n",
+        opts.labels.index,
+        make_anchor_id("index", &section)
+    );
+    let mut letter = None;
+    for name in names {
+        let initial = name.chars().next().map(|c| c.to_uppercase().to_string());
+        if initial != letter {
+            if letter.is_some() {
+                output.push('\n');
+            }
+            let initial_text = initial.clone().unwrap_or_default();
+            output.push_str(&format!(
+                "### {} {{#{}}}\n\n",
+                initial_text,
+                make_anchor_id(&format!("index-{}", initial_text.to_lowercase()), &section)
+            ));
+            letter = initial;
+        }
+        output.push_str(&format!(
+            "- [{}](#{})\n",
+            code_span(name),
+            anchors.get(name).unwrap_or_default()
+        ));
+    }
+    output.push('\n');
     output
 }
 
@@ -467,4 +1982,681 @@ mod tests {
         assert!(output.contains("Whether to enable test."));
         assert!(output.contains("**Example:** `true`"));
     }
+
+    #[test]
+    fn test_render_related_packages() {
+        let json = r#"{
+            "programs.foo.enable": {
+                "relatedPackages": [
+                    "foo",
+                    ["python3Packages", "foo"],
+                    {
+                        "path": ["foo-unwrapped"],
+                        "name": "foo (unwrapped)",
+                        "description": "Foo without plugins.",
+                        "comment": "Use it to build\nyour own wrapper."
+                    }
+                ]
+            },
+            "programs.bar.enable": { "relatedPackages": "- `pkgs.bar`" }
+        }"#;
+        let options = parse_options_json(json).unwrap();
+        let opts = RenderOptions::default();
+
+        let related = options["programs.foo.enable"]
+            .related_packages
+            .as_ref()
+            .unwrap();
+        assert_eq!(
+            render_related_packages(related, &opts),
+            "- [`foo`](https://search.nixos.org/packages?show=foo&query=foo)\n\
+             - [`python3Packages.foo`](https://search.nixos.org/packages?show=python3Packages.foo&query=python3Packages.foo)\n\
+             - [`foo (unwrapped)`](https://search.nixos.org/packages?show=foo-unwrapped&query=foo-unwrapped): Foo without plugins.\n\
+             \n  Use it to build\n  your own wrapper."
+        );
+        let unlinked = RenderOptions {
+            package_index_url: None,
+            ..Default::default()
+        };
+        assert!(render_related_packages(related, &unlinked).starts_with("- `foo`\n"));
+
+        let related = options["programs.bar.enable"]
+            .related_packages
+            .as_ref()
+            .unwrap();
+        assert_eq!(render_related_packages(related, &opts), "- `pkgs.bar`");
+    }
+
+    #[test]
+    fn test_strip_store_path() {
+        let store = "/nix/store/0123456789abcdfghijklmnpqrsvwxyz-source";
+        assert_eq!(
+            strip_store_path(&format!("{}/nixos/modules/foo.nix", store), None),
+            "nixos/modules/foo.nix"
+        );
+        assert_eq!(
+            strip_store_path("/nix/store/short/foo.nix", None),
+            "/nix/store/short/foo.nix"
+        );
+        assert_eq!(strip_store_path("nixos/foo.nix", None), "nixos/foo.nix");
+        assert_eq!(
+            strip_store_path("/build/src/modules/foo.nix", Some("/build/src")),
+            "modules/foo.nix"
+        );
+        assert_eq!(
+            strip_store_path("/build/src/modules/foo.nix", Some("/build/src/")),
+            "modules/foo.nix"
+        );
+        assert_eq!(
+            strip_store_path("/build/srcfoo/lib.nix", Some("/build/src")),
+            "/build/srcfoo/lib.nix"
+        );
+
+        let json = format!(
+            r#"{{ "test.enable": {{ "declarations": ["{}/modules/test.nix"] }} }}"#,
+            store
+        );
+        let opts = RenderOptions {
+            declarations_base_url: Some("https://github.com/owner/repo".to_string()),
+            ..Default::default()
+        };
+        let output = render_options_from_str(&json, "Options", None, &opts).unwrap();
+        assert!(output.contains(
+            "- [modules/test.nix](https://github.com/owner/repo/blob/master/modules/test.nix)"
+        ));
+    }
+
+    #[test]
+    fn test_profiles() {
+        let json = r#"{
+            "test.package": { "type": "package", "declarations": ["test.nix"] },
+            "test.enable": { "type": "boolean", "readOnly": true }
+        }"#;
+        let options = parse_options_json(json).unwrap();
+
+        let nixos = Profile::from_name("nixos-manual").unwrap().render_options();
+        let output = render_options_to_commonmark(&options, &nixos);
+        assert!(output.starts_with("## `test.enable` {#opt-test-enable}"));
+        assert!(output.contains("**Type:** `boolean`\n\n::: {.note}\nThis option is read only"));
+        assert!(output.contains("**Declared by:**"));
+
+        let minimal = Profile::from_name("minimal").unwrap();
+        let output = render_options_to_commonmark(&options, &minimal.render_options());
+        assert!(output.starts_with("## `test.enable` {#test-enable}"));
+        assert!(!output.contains("**Declared by:**"));
+        assert_eq!(minimal.flavor(), MarkdownFlavor::CommonMark);
+
+        let home_manager = Profile::from_name("home-manager").unwrap().render_options();
+        assert_eq!(home_manager.sort, SortOrder::Alphabetical);
+        assert!(Profile::from_name("unknown").is_none());
+    }
+
+    #[test]
+    fn test_badges() {
+        let json = r#"{
+            "test.state": { "type": "string", "readOnly": true, "internal": true },
+            "test.hidden": { "visible": false, "stability": "experimental" }
+        }"#;
+        let options = parse_options_json(json).unwrap();
+        assert_eq!(
+            options["test.hidden"].flags(),
+            OptionFlags {
+                read_only: false,
+                internal: false,
+                visible: false,
+                experimental: true,
+            }
+        );
+
+        let mut render_opts = RenderOptions::default();
+        render_opts
+            .labels
+            .set("internal-notice", "Not for users.")
+            .unwrap();
+        assert!(render_opts.labels.set("unknown", "").is_err());
+        let output = render_options_to_commonmark(&options, &render_opts);
+        assert!(output.contains(
+            "**Type:** `string`\n\n::: {.note}\nThis option is read only and cannot be set in a configuration.\n:::\n\n::: {.note}\nNot for users.\n:::\n\n"
+        ));
+        assert!(output.contains("::: {.note}\nThis option is hidden from the documentation.\n:::\n\n::: {.warning}\nThis option is experimental"));
+
+        let render_opts = RenderOptions {
+            layout: vec![OptionSection::Type],
+            ..Default::default()
+        };
+        let output = render_options_to_commonmark(&options, &render_opts);
+        assert!(output.contains("**Type:** `string` *(read only)*"));
+        assert!(!output.contains(":::"));
+    }
+
+    #[test]
+    fn test_group_by_prefix() {
+        let json = r#"{
+            "services.nginx.enable": { "loc": ["services", "nginx", "enable"] },
+            "services.nginx.virtualHosts.<name>.root": {
+                "loc": ["services", "nginx", "virtualHosts", "<name>", "root"]
+            },
+            "services.caddy.enable": { "loc": ["services", "caddy", "enable"] },
+            "networking.hostName": { "loc": ["networking", "hostName"] },
+            "enable": { "loc": ["enable"] }
+        }"#;
+        let options = parse_options_json(json).unwrap();
+        let opts = RenderOptions {
+            group_by_prefix: Some(2),
+            namespace_intros: BTreeMap::from([(
+                "services.nginx".to_string(),
+                "A web server.".to_string(),
+            )]),
+            ..Default::default()
+        };
+
+        let output = render_options_to_commonmark(&options, &opts);
+        let headings: Vec<&str> = output.lines().filter(|l| l.starts_with('#')).collect();
+        assert_eq!(
+            headings,
+            vec![
+                "## `enable` {#opt-enable}",
+                "## `networking` {#sec-opt-networking}",
+                "### `networking.hostName` {#opt-networking-hostName}",
+                "## `services.caddy` {#sec-opt-services-caddy}",
+                "### `services.caddy.enable` {#opt-services-caddy-enable}",
+                "## `services.nginx` {#sec-opt-services-nginx}",
+                "### `services.nginx.enable` {#opt-services-nginx-enable}",
+                "### `services.nginx.virtualHosts.<name>.root` {#opt-services-nginx-virtualHosts-_name_-root}",
+            ]
+        );
+        assert!(output.contains("{#sec-opt-services-nginx}\n\nA web server.\n\n###"));
+    }
+
+    #[test]
+    fn test_group_by_declaration() {
+        let json = r#"{
+            "services.foo.enable": { "declarations": ["modules/foo.nix"] },
+            "services.foo.package": { "declarations": ["modules/foo.nix"] },
+            "services.bar.enable": {
+                "declarations": ["modules/bar.nix", "modules/foo.nix"]
+            },
+            "nix.enable": {}
+        }"#;
+        let options = parse_options_json(json).unwrap();
+        let opts = RenderOptions {
+            group_by_declaration: true,
+            group_by_prefix: Some(1),
+            ..Default::default()
+        };
+
+        let output = render_options_to_commonmark(&options, &opts);
+        let headings: Vec<&str> = output
+            .lines()
+            .filter(|l| l.starts_with('#') || l.starts_with("- [`"))
+            .collect();
+        assert_eq!(
+            headings,
+            vec![
+                "## `nix.enable` {#opt-nix-enable}",
+                "## `modules/bar.nix` {#sec-opt-decl-modules_bar-nix}",
+                "### `services.bar.enable` {#opt-services-bar-enable}",
+                "## `modules/foo.nix` {#sec-opt-decl-modules_foo-nix}",
+                "### `services.foo.enable` {#opt-services-foo-enable}",
+                "### `services.foo.package` {#opt-services-foo-package}",
+                "- [`services.bar.enable`](#opt-services-bar-enable)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tag_formats() {
+        let json = r#"{
+            "test.password": {
+                "type": "string",
+                "default": { "_type": "secretRef", "text": "vault:test/password" },
+                "example": { "_type": "unknownTag", "text": "x" }
+            }
+        }"#;
+
+        let options = parse_options_json(json).unwrap();
+        let render_opts = RenderOptions {
+            tag_formats: HashMap::from([("secretRef".to_string(), "secret `{text}`".to_string())]),
+            ..Default::default()
+        };
+        let output = render_options_to_commonmark(&options, &render_opts);
+
+        assert!(output.contains("**Default:** secret `vault:test/password`"));
+        assert!(output.contains("**Example:** `<unknownTag>: x`"));
+    }
+
+    #[test]
+    fn test_format_and_fold_defaults() {
+        let json = r#"{
+            "test.settings": {
+                "default": { "_type": "literalExpression", "text": "{\n      a = 1;\n   b = [ 1 2 ];\n}" },
+                "example": { "_type": "literalExpression", "text": "{ a = 1;\n}" }
+            }
+        }"#;
+
+        let options = parse_options_json(json).unwrap();
+        let render_opts = RenderOptions {
+            format_expressions: true,
+            fold_defaults: Some(3),
+            ..Default::default()
+        };
+        let output = render_options_to_commonmark(&options, &render_opts);
+
+        assert!(output.contains(
+            "**Default:**\n\n<details>\n<summary>Show all 4 lines</summary>\n\n```nix\n{\n  a = 1;\n  b = [ 1 2 ];\n}\n```\n\n</details>\n\n"
+        ));
+        assert!(output.contains("**Example:**\n\n```nix\n{\n  a = 1;\n}\n```"));
+    }
+
+    #[test]
+    fn test_namespace_intros() {
+        let json = r#"{
+            "services.nginx.enable": { "description": "Enable nginx." },
+            "services.nginx.virtualHosts": { "description": "Virtual hosts." },
+            "services.caddy.enable": { "description": "Enable caddy." }
+        }"#;
+
+        let options = parse_options_json(json).unwrap();
+        let render_opts = RenderOptions {
+            namespace_intros: BTreeMap::from([
+                (
+                    "services.nginx".to_string(),
+                    "The nginx web server.\n".to_string(),
+                ),
+                ("services.unused".to_string(), "Never rendered.".to_string()),
+            ]),
+            ..Default::default()
+        };
+        let output = render_options_to_commonmark(&options, &render_opts);
+
+        let caddy = output.find("## `services.caddy.enable`").unwrap();
+        let intro = output
+            .find("## `services.nginx` {#sec-opt-services-nginx}\n\nThe nginx web server.\n\n")
+            .unwrap();
+        let nginx = output.find("## `services.nginx.enable`").unwrap();
+        assert!(caddy < intro && intro < nginx);
+        assert!(!output.contains("Never rendered."));
+    }
+
+    #[test]
+    fn test_budgets() {
+        let json = r#"{
+            "test.enable": {
+                "description": "Whether to enable test.",
+                "default": false
+            },
+            "test.settings": {
+                "description": "Settings.",
+                "default": { "_type": "literalExpression", "text": "{ a = 1; b = 2; c = 3; }" },
+                "example": { "_type": "literalExpression", "text": "{ }" }
+            }
+        }"#;
+
+        let options = parse_options_json(json).unwrap();
+        let budgets = Budgets {
+            max_default_length: Some(10),
+            max_description_length: Some(20),
+            ..Default::default()
+        };
+        let stats = collect_stats(&options, &budgets, &RenderOptions::default());
+
+        assert_eq!(stats.total, 2);
+        assert_eq!(stats.with_example, 1);
+        assert_eq!(
+            stats.budget_violations,
+            vec![
+                BudgetViolation {
+                    option: "test.enable".to_string(),
+                    field: "description",
+                    length: 23,
+                    limit: 20,
+                },
+                BudgetViolation {
+                    option: "test.settings".to_string(),
+                    field: "default",
+                    length: 26,
+                    limit: 10,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_descriptions() {
+        let json = r#"{
+            "test.enable": {
+                "description": "Whether to enable <literal>test</literal>."
+            },
+            "test.package": {
+                "description": "See {option}`test.enable` and {foo}`bar`.\n\n```nix\npkgs.test"
+            },
+            "test.settings": {
+                "description": "Fine."
+            }
+        }"#;
+
+        let options = parse_options_json(json).unwrap();
+        let issues: Vec<(String, String, usize, usize)> =
+            validate_descriptions(&options, MarkdownFlavor::Nixpkgs)
+                .into_iter()
+                .map(|(name, i)| (name, i.message(), i.line, i.column))
+                .collect();
+
+        assert_eq!(
+            issues,
+            vec![
+                (
+                    "test.enable".to_string(),
+                    "DocBook element `<literal>` in markdown".to_string(),
+                    1,
+                    19
+                ),
+                (
+                    "test.package".to_string(),
+                    "unsupported role `{foo}`".to_string(),
+                    1,
+                    31
+                ),
+                (
+                    "test.package".to_string(),
+                    "code fence is never closed".to_string(),
+                    3,
+                    1
+                ),
+            ]
+        );
+
+        let commonmark = validate_descriptions(&options, MarkdownFlavor::CommonMark);
+        assert_eq!(commonmark.len(), 4);
+    }
+
+    #[test]
+    fn test_sort_orders() {
+        let first =
+            parse_options_json(r#"{ "zeta.enable": {}, "alpha.package": {}, "alpha.enable": {} }"#)
+                .unwrap();
+        let second = parse_options_json(r#"{ "beta": {}, "alpha.enable": {} }"#).unwrap();
+        let (merged, _) = merge_options(
+            vec![("a".to_string(), first), ("b".to_string(), second)],
+            false,
+        );
+
+        let names = |order: SortOrder| order.sorted_names(&merged);
+        assert_eq!(
+            names(SortOrder::Source),
+            ["zeta.enable", "alpha.package", "alpha.enable", "beta"]
+        );
+        assert_eq!(
+            names(SortOrder::Alphabetical),
+            ["alpha.enable", "alpha.package", "beta", "zeta.enable"]
+        );
+        assert_eq!(
+            names(SortOrder::Priority),
+            ["alpha.enable", "alpha.package", "beta", "zeta.enable"]
+        );
+        assert_eq!(SortOrder::from_name("nixos"), Some(SortOrder::Priority));
+    }
+
+    #[test]
+    fn test_merge_options() {
+        let nixos = parse_options_json(
+            r#"{
+                "shared.enable": { "type": "boolean", "description": "Shared." },
+                "services.foo.enable": { "type": "boolean", "description": "NixOS foo." }
+            }"#,
+        )
+        .unwrap();
+        let home = parse_options_json(
+            r#"{
+                "shared.enable": { "type": "boolean", "description": "Shared." },
+                "services.foo.enable": { "type": "boolean", "description": "Home foo." },
+                "programs.bar.enable": { "type": "boolean" }
+            }"#,
+        )
+        .unwrap();
+        let sets = vec![
+            ("nixos".to_string(), nixos),
+            ("home-manager".to_string(), home),
+        ];
+
+        let (merged, conflicts) = merge_options(sets.clone(), true);
+
+        assert_eq!(merged.len(), 3);
+        assert_eq!(
+            conflicts,
+            vec![MergeConflict {
+                name: "services.foo.enable".to_string(),
+                kept: "nixos".to_string(),
+                dropped: "home-manager".to_string(),
+            }]
+        );
+        assert_eq!(
+            merged["programs.bar.enable"].origin.as_deref(),
+            Some("home-manager")
+        );
+        assert_eq!(merged["shared.enable"].origin.as_deref(), Some("nixos"));
+        let rendered = render_options_to_commonmark(&merged, &RenderOptions::default());
+        assert!(rendered.contains("**Module set:** `home-manager`"));
+
+        let (untagged, _) = merge_options(sets, false);
+        assert!(untagged.values().all(|opt| opt.origin.is_none()));
+    }
+
+    #[test]
+    fn test_provenance() {
+        let json = r#"{
+            "_meta": { "revision": "4f7f1d2", "generated": "2024-05-01" },
+            "test.enable": { "loc": ["test", "enable"], "type": "boolean" }
+        }"#;
+
+        let (options, provenance) = parse_options_json_with_provenance(json).unwrap();
+        assert_eq!(options.len(), 1);
+        assert_eq!(provenance.revision.as_deref(), Some("4f7f1d2"));
+
+        let render_opts = RenderOptions {
+            provenance: Provenance {
+                generator: Some("docgen".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let rendered = render_options_from_str(json, "Options", None, &render_opts).unwrap();
+        assert!(rendered
+            .ends_with("---\n\n*Generated from revision `4f7f1d2` on 2024-05-01 by docgen.*\n"));
+    }
+
+    #[test]
+    fn test_hostile_option_names() {
+        use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+
+        let names = [
+            "services.foo.`bar`",
+            "services.foo.a``b",
+            "services.foo.\"*.example.com\"",
+            "services.foo.<name>.*",
+            "services.foo.'{#x}'",
+        ];
+        let options: OptionsMap = names
+            .iter()
+            .map(|name| (name.to_string(), serde_json::from_str("{}").unwrap()))
+            .collect();
+        let rendered = render_options_to_commonmark(&options, &RenderOptions::default());
+
+        let mut headings = vec![];
+        let mut ids = vec![];
+        let mut in_heading = false;
+        for event in Parser::new_ext(&rendered, Options::ENABLE_HEADING_ATTRIBUTES) {
+            match event {
+                Event::Start(Tag::Heading { id, .. }) => {
+                    in_heading = true;
+                    ids.push(id.map(|id| id.to_string()).unwrap_or_default());
+                }
+                Event::End(TagEnd::Heading(_)) => in_heading = false,
+                Event::Code(code) if in_heading => headings.push(code.to_string()),
+                Event::Text(text) if in_heading => panic!("stray heading text {:?}", text),
+                _ => (),
+            }
+        }
+
+        let mut expected = names.to_vec();
+        expected.sort_by(|a, b| SortOrder::default().compare(a, b));
+        assert_eq!(headings, expected);
+        assert!(ids.contains(&"opt-services-foo-__-example-com_".to_string()));
+        assert!(ids.iter().all(|id| {
+            id.chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_".contains(c))
+        }));
+    }
+
+    #[test]
+    fn test_option_layout() {
+        let json = r#"{
+            "test.enable": {
+                "loc": ["test", "enable"],
+                "description": "Whether to enable test.",
+                "type": "boolean",
+                "default": false,
+                "declarations": ["modules/test.nix"]
+            }
+        }"#;
+        let opts = RenderOptions {
+            layout: OptionSection::parse_layout(&["description", "type"]).unwrap(),
+            ..Default::default()
+        };
+
+        let rendered = render_options_from_str(json, "Options", None, &opts).unwrap();
+
+        assert!(rendered.ends_with("Whether to enable test.\n\n**Type:** `boolean`\n\n"));
+        assert!(!rendered.contains("Default"));
+        assert!(OptionSection::parse_layout(&["declared-by"]).is_err());
+    }
+
+    #[test]
+    fn test_filter_options() {
+        let json = r#"{
+            "services.nginx.enable": { "type": "boolean" },
+            "services.nginx.package": { "type": "package" },
+            "services.nginx.internalState": { "internal": true },
+            "services.nginx.hidden": { "visible": false },
+            "services.nginx.virtualHosts": { "visible": "shallow" },
+            "services.caddy.enable": { "type": "boolean" }
+        }"#;
+        let options = parse_options_json(json).unwrap();
+        let filtered = |filter: OptionFilter| {
+            let mut options = options.clone();
+            filter_options(&mut options, &filter);
+            let mut names: Vec<String> = options.into_keys().collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(
+            filtered(OptionFilter {
+                include: vec!["services.nginx.*".to_string()],
+                exclude: vec!["*.package".to_string()],
+                ..Default::default()
+            }),
+            vec!["services.nginx.enable", "services.nginx.virtualHosts"]
+        );
+        assert_eq!(
+            filtered(OptionFilter {
+                include: vec!["services.nginx.*".to_string()],
+                show_internal: true,
+                ..Default::default()
+            })
+            .len(),
+            5
+        );
+    }
+
+    #[test]
+    fn test_aliases() {
+        let json = r#"{
+            "networking.hostName": { "description": "The host name." },
+            "networking.host": { "description": "Alias of {option}`networking.hostName`." },
+            "net.host": { "description": "Alias of <option>networking.host</option>." },
+            "gone.name": { "description": "Alias of `removed.name`." },
+            "other": { "description": "Alias of `x`, kept for now." }
+        }"#;
+        let options = parse_options_json(json).unwrap();
+        assert_eq!(
+            options["networking.host"].alias_target(),
+            Some("networking.hostName")
+        );
+        assert_eq!(options["net.host"].alias_target(), Some("networking.host"));
+        assert_eq!(options["other"].alias_target(), None);
+
+        let render_opts = RenderOptions {
+            include_declarations: false,
+            ..Default::default()
+        };
+        let output = render_options_to_commonmark(&options, &render_opts);
+        assert!(output.contains(
+            "## `networking.host` {#opt-networking-host}\n\n\
+             Alias of [`networking.hostName`](#opt-networking-hostName).\n\n"
+        ));
+        assert!(output.contains("## `gone.name` {#opt-gone-name}\n\nAlias of `removed.name`.\n\n"));
+
+        let mut folded = options.clone();
+        fold_aliases(&mut folded);
+        let mut names: Vec<&String> = folded.keys().collect();
+        names.sort();
+        assert_eq!(names, ["gone.name", "networking.hostName", "other"]);
+        assert_eq!(
+            render_options_to_commonmark(&folded, &render_opts),
+            "## `gone.name` {#opt-gone-name}\n\nAlias of `removed.name`.\n\n\
+             ## `networking.hostName` {#opt-networking-hostName}\n\n\
+             The host name.\n\n\
+             **Aliases:** `net.host`, `networking.host`\n\n\
+             ## `other` {#opt-other}\n\nAlias of `x`, kept for now.\n\n"
+        );
+
+        let mut hidden = options.clone();
+        filter_options(
+            &mut hidden,
+            &OptionFilter {
+                hide_aliases: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(hidden.len(), 2);
+    }
+
+    #[test]
+    fn test_toc_and_index() {
+        let json = r#"{
+            "services.nginx.package": {
+                "loc": ["services", "nginx", "package"],
+                "description": "The nginx package to use, e.g. `pkgs.nginx`.\nIt is wrapped."
+            },
+            "services.nginx.enable": { "loc": ["services", "nginx", "enable"] },
+            "boot.kernel": { "loc": ["boot", "kernel"] }
+        }"#;
+        let options = parse_options_json(json).unwrap();
+        let render_opts = RenderOptions {
+            toc: true,
+            index: true,
+            ..Default::default()
+        };
+        let output = render_options_document(&options, "Options", None, &render_opts);
+
+        assert!(output.contains(
+            "## Contents {#sec-opt-contents}\n\n\
+             - [`boot`](#opt-boot-kernel)\n\
+             \x20 - [`boot.kernel`](#opt-boot-kernel)\n\
+             - [`services`](#opt-services-nginx-enable)\n\
+             \x20 - [`services.nginx`](#opt-services-nginx-enable)\n\
+             \x20   - [`services.nginx.enable`](#opt-services-nginx-enable)\n\
+             \x20   - [`services.nginx.package`](#opt-services-nginx-package): \
+             The nginx package to use, e.g. `pkgs.nginx`.\n\n"
+        ));
+        assert!(output.ends_with(
+            "## Index {#sec-opt-index}\n\n\
+             ### B {#sec-opt-index-b}\n\n\
+             - [`boot.kernel`](#opt-boot-kernel)\n\n\
+             ### S {#sec-opt-index-s}\n\n\
+             - [`services.nginx.enable`](#opt-services-nginx-enable)\n\
+             - [`services.nginx.package`](#opt-services-nginx-package)\n\n"
+        ));
+    }
 }
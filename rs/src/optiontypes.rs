@@ -0,0 +1,363 @@
+//! Links from option types to a reference of the types.
+//!
+//! Option types are descriptions built by the module system, such as
+//! `null or (list of string)`. They are split into the combinators (`null
+//! or`, `list of`) and the base types they combine (`string`), so every base
+//! type can link to its section of a "Types" appendix. Base types that are
+//! not known are reported, so the appendix can be extended for them.
+
+use std::collections::BTreeMap;
+
+use crate::format::code_span;
+use crate::options::{make_anchor_id, OptionsMap, RenderOptions};
+
+/// A base type of the module system, as described in option types.
+#[derive(Debug, PartialEq, Eq)]
+pub struct BaseType {
+    /// Heading of the type's section.
+    pub name: &'static str,
+    /// Part of the type's anchor.
+    pub slug: &'static str,
+    /// Descriptions that are exactly this type.
+    exact: &'static [&'static str],
+    /// Starts of descriptions of this type, e.g. `one of `.
+    prefixes: &'static [&'static str],
+    /// Markdown describing the type.
+    pub description: &'static str,
+}
+
+/// The base types of the module system's `lib.types`.
+pub const BASE_TYPES: &[BaseType] = &[
+    BaseType {
+        name: "boolean",
+        slug: "boolean",
+        exact: &["boolean", "bool"],
+        prefixes: &[],
+        description: "`true` or `false`.",
+    },
+    BaseType {
+        name: "string",
+        slug: "string",
+        exact: &["string", "str"],
+        prefixes: &[],
+        description: "A string. Definitions in several modules are not merged, so it can only be defined once.",
+    },
+    BaseType {
+        name: "non-empty string",
+        slug: "non-empty-string",
+        exact: &["non-empty string"],
+        prefixes: &[],
+        description: "A string containing more than whitespace.",
+    },
+    BaseType {
+        name: "single-line string",
+        slug: "single-line-string",
+        exact: &[
+            "single-line string",
+            "(optionally newline-terminated) single-line string",
+        ],
+        prefixes: &[],
+        description: "A string without line breaks, except for one at its end.",
+    },
+    BaseType {
+        name: "concatenated strings",
+        slug: "concatenated-strings",
+        exact: &[],
+        prefixes: &["strings concatenated with "],
+        description: "A string. Definitions in several modules are joined with the given separator.",
+    },
+    BaseType {
+        name: "string matching a pattern",
+        slug: "string-pattern",
+        exact: &[],
+        prefixes: &["string matching the pattern "],
+        description: "A string matching a regular expression.",
+    },
+    BaseType {
+        name: "integer",
+        slug: "integer",
+        exact: &[
+            "signed integer",
+            "integer",
+            "int",
+            "unsigned integer, meaning >=0",
+            "positive integer, meaning >0",
+        ],
+        prefixes: &["integer between ", "8 bit ", "16 bit ", "32 bit "],
+        description: "A whole number, possibly restricted to a range.",
+    },
+    BaseType {
+        name: "floating point number",
+        slug: "float",
+        exact: &["floating point number", "float"],
+        prefixes: &["number between "],
+        description: "A number with a fractional part, possibly restricted to a range.",
+    },
+    BaseType {
+        name: "path",
+        slug: "path",
+        exact: &["path", "absolute path", "path in the Nix store"],
+        prefixes: &[],
+        description: "A file system path, or a string holding one. Absolute paths start with `/`, store paths are in the Nix store.",
+    },
+    BaseType {
+        name: "package",
+        slug: "package",
+        exact: &["package"],
+        prefixes: &[],
+        description: "A derivation, such as a package of `pkgs`.",
+    },
+    BaseType {
+        name: "attribute set",
+        slug: "attribute-set",
+        exact: &["attribute set"],
+        prefixes: &[],
+        description: "An attribute set. Definitions in several modules are merged.",
+    },
+    BaseType {
+        name: "submodule",
+        slug: "submodule",
+        exact: &["submodule"],
+        prefixes: &["open submodule of "],
+        description: "An attribute set of the options declared by a module.",
+    },
+    BaseType {
+        name: "null",
+        slug: "null",
+        exact: &["null"],
+        prefixes: &[],
+        description: "The value `null`, usually meaning that a setting is left unset.",
+    },
+    BaseType {
+        name: "enumeration",
+        slug: "enumeration",
+        exact: &[],
+        prefixes: &["one of "],
+        description: "One of the listed values.",
+    },
+    BaseType {
+        name: "anything",
+        slug: "anything",
+        exact: &["anything"],
+        prefixes: &[],
+        description: "Any value. Attribute sets and lists are merged.",
+    },
+    BaseType {
+        name: "raw value",
+        slug: "raw-value",
+        exact: &["raw value", "unspecified value"],
+        prefixes: &[],
+        description: "Any value, which is neither checked nor merged.",
+    },
+    BaseType {
+        name: "serializable value",
+        slug: "serializable-value",
+        exact: &["JSON value", "TOML value", "YAML value", "YAML 1.1 value"],
+        prefixes: &[],
+        description: "A value that can be written in the named format.",
+    },
+];
+
+/// Combinators of types, followed by the type they combine.
+const COMBINATORS: &[&str] = &[
+    "lazy attribute set of ",
+    "attribute set of ",
+    "unique list of ",
+    "list of ",
+    "non-empty ",
+    "function that evaluates to a(n) ",
+];
+
+/// A part of a type description.
+#[derive(Debug, PartialEq, Eq)]
+pub enum TypePart<'a> {
+    /// Part of a combinator, e.g. `list of ` or `(`.
+    Text(&'a str),
+    /// A known base type.
+    Known(&'a str, &'static BaseType),
+    /// A base type that is not known.
+    Unknown(&'a str),
+}
+
+/// Split a type description into combinators and base types.
+pub fn parse_type(description: &str) -> Vec<TypePart<'_>> {
+    let mut parts = vec![];
+    parse_into(description, &mut parts);
+    parts
+}
+
+fn parse_into<'a>(description: &'a str, parts: &mut Vec<TypePart<'a>>) {
+    let alternatives = split_alternatives(description);
+    if alternatives.len() > 1 {
+        for (i, alternative) in alternatives.into_iter().enumerate() {
+            if i > 0 {
+                parts.push(TypePart::Text(" or "));
+            }
+            parse_into(alternative, parts);
+        }
+        return;
+    }
+
+    if let Some(inner) = description
+        .strip_prefix('(')
+        .and_then(|rest| rest.strip_suffix(')'))
+        .filter(|inner| closing_paren(description) == Some(inner.len() + 1))
+    {
+        parts.push(TypePart::Text("("));
+        parse_into(inner, parts);
+        parts.push(TypePart::Text(")"));
+        return;
+    }
+
+    if let Some(base) = base_type(description) {
+        parts.push(TypePart::Known(description, base));
+        return;
+    }
+
+    for combinator in COMBINATORS {
+        if let Some(rest) = description.strip_prefix(combinator) {
+            parts.push(TypePart::Text(combinator));
+            parse_into(rest, parts);
+            return;
+        }
+    }
+
+    parts.push(TypePart::Unknown(description));
+}
+
+/// Split a description at the `or`s that are not in parentheses or quotes.
+fn split_alternatives(description: &str) -> Vec<&str> {
+    let mut alternatives = vec![];
+    let mut depth = 0usize;
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in description.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth = depth.saturating_sub(1),
+            ' ' if !quoted && depth == 0 && description[i..].starts_with(" or ") => {
+                // `one of "a", "b"` ends the description.
+                if base_type(&description[start..i]).is_some_and(|t| t.slug == "enumeration") {
+                    break;
+                }
+                alternatives.push(&description[start..i]);
+                start = i + " or ".len();
+            }
+            _ => (),
+        }
+    }
+    alternatives.push(&description[start..]);
+    alternatives
+}
+
+/// The offset of the parenthesis closing the one `description` starts with.
+fn closing_paren(description: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut quoted = false;
+    for (i, c) in description.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+/// The base type a description is, if it is a known one.
+fn base_type(description: &str) -> Option<&'static BaseType> {
+    BASE_TYPES.iter().find(|base| {
+        base.exact.contains(&description)
+            || base.prefixes.iter().any(|p| description.starts_with(p))
+    })
+}
+
+/// Anchor of the appendix section of a base type.
+pub fn type_anchor(base: &BaseType, opts: &RenderOptions) -> String {
+    make_anchor_id(
+        &format!("types-{}", base.slug),
+        &format!("sec-{}", opts.anchor_prefix),
+    )
+}
+
+/// Render a type description with its base types linking to their sections
+/// of the types appendix.
+pub fn link_type(description: &str, opts: &RenderOptions) -> String {
+    parse_type(description)
+        .into_iter()
+        .map(|part| match part {
+            TypePart::Text(text) => text.to_string(),
+            TypePart::Known(text, base) => {
+                format!("[{}](#{})", code_span(text), type_anchor(base, opts))
+            }
+            TypePart::Unknown(text) => code_span(text),
+        })
+        .collect()
+}
+
+/// Render the types appendix, describing the base types used by `options`.
+pub fn render_types_appendix(options: &OptionsMap, opts: &RenderOptions) -> String {
+    let used: Vec<&BaseType> = BASE_TYPES
+        .iter()
+        .filter(|base| {
+            options.values().any(|opt| {
+                opt.option_type.as_deref().is_some_and(|t| {
+                    parse_type(t)
+                        .iter()
+                        .any(|part| matches!(part, TypePart::Known(_, b) if b == base))
+                })
+            })
+        })
+        .collect();
+    if used.is_empty() {
+        return String::new();
+    }
+
+    let mut output = format!(
+        "## {} {{#{}}}\n\n",
+        opts.labels.types,
+        make_anchor_id("types", &format!("sec-{}", opts.anchor_prefix))
+    );
+    for base in used {
+        output.push_str(&format!(
+            "### {} {{#{}}}\n\n{}\n\n",
+            base.name,
+            type_anchor(base, opts),
+            base.description
+        ));
+    }
+    output
+}
+
+/// The base types of `options` that are not known, with the options using
+/// them, sorted.
+pub fn unknown_types(options: &OptionsMap) -> BTreeMap<String, Vec<String>> {
+    let mut unknown: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (name, opt) in options {
+        for part in opt
+            .option_type
+            .as_deref()
+            .map(parse_type)
+            .unwrap_or_default()
+        {
+            if let TypePart::Unknown(text) = part {
+                unknown
+                    .entry(text.to_string())
+                    .or_default()
+                    .push(name.clone());
+            }
+        }
+    }
+    for names in unknown.values_mut() {
+        names.sort();
+        names.dedup();
+    }
+    unknown
+}
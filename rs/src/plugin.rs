@@ -0,0 +1,229 @@
+//! Custom doc-comment tags.
+//!
+//! Custom tags, registered in
+//! [`CollectOptions::custom_tags`](crate::CollectOptions::custom_tags)
+//! besides the builtin `@stability`, `@deprecated`, `@since` and `@allow`
+//! (see [`lint`](crate::lint)), become fields of
+//! their entry, holding the tag's value as a string, or the values of a
+//! repeated tag as a list of strings. For
+//!
+//! ```nix
+//! /**
+//!   Frobnicate a value.
+//!
+//!   @platform linux darwin
+//! */
+//! ```
+//!
+//! the entry gets the field `"platform": "linux darwin"`. [`TagPlugin`]s
+//! parse these strings into structured values, e.g. `["linux", "darwin"]`,
+//! and [`TagTemplates`] render the fields of an entry as markdown following
+//! its examples.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::commonmark::ManualEntry;
+use crate::tags::Tag;
+use crate::template::Template;
+
+/// Tags with a meaning of their own, which are not fields.
+pub const BUILTIN_TAGS: [&str; 4] = ["stability", "deprecated", "since", "allow"];
+
+/// The fields of the custom tags of a doc comment.
+pub fn tag_fields(tags: &[Tag]) -> BTreeMap<String, Value> {
+    let mut fields: BTreeMap<String, Value> = BTreeMap::new();
+    for tag in tags
+        .iter()
+        .filter(|t| !BUILTIN_TAGS.contains(&t.name.as_str()))
+    {
+        let value = Value::String(tag.value.clone());
+        match fields.remove(&tag.name) {
+            Some(Value::Array(mut values)) => {
+                values.push(value);
+                fields.insert(tag.name.clone(), Value::Array(values));
+            }
+            Some(first) => {
+                fields.insert(tag.name.clone(), Value::Array(vec![first, value]));
+            }
+            None => {
+                fields.insert(tag.name.clone(), value);
+            }
+        }
+    }
+    fields
+}
+
+/// A field of an entry, as given to plugins.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TagField {
+    /// Identifier of the entry, e.g. `lib.strings.concat`.
+    pub entry: String,
+    pub tag: String,
+    pub value: Value,
+}
+
+/// Parses the fields of custom tags into structured values.
+///
+/// Closures taking a single field implement this trait, e.g. to split a
+/// field into words:
+///
+/// ```
+/// use nixdoc::plugin::{TagField, TagPlugin};
+/// use serde_json::Value;
+///
+/// let platforms = |field: &TagField| -> Result<Value, String> {
+///     match (field.tag.as_str(), &field.value) {
+///         ("platform", Value::String(s)) => Ok(s.split_whitespace().collect()),
+///         _ => Ok(field.value.clone()),
+///     }
+/// };
+/// let field = TagField {
+///     entry: "lib.strings.concat".to_string(),
+///     tag: "platform".to_string(),
+///     value: Value::from("linux darwin"),
+/// };
+/// assert_eq!(platforms.parse(&[field]).unwrap(), vec![Value::from(vec!["linux", "darwin"])]);
+/// ```
+pub trait TagPlugin {
+    /// The new values of `fields`, in the same order. Fields a plugin does
+    /// not handle keep their value.
+    fn parse(&self, fields: &[TagField]) -> Result<Vec<Value>, String>;
+}
+
+impl<F> TagPlugin for F
+where
+    F: Fn(&TagField) -> Result<Value, String>,
+{
+    fn parse(&self, fields: &[TagField]) -> Result<Vec<Value>, String> {
+        fields.iter().map(self).collect()
+    }
+}
+
+/// A plugin running an external command, which reads the fields as a JSON
+/// array of objects with `entry`, `tag` and `value` keys from its standard
+/// input and prints a JSON array of their new values.
+#[derive(Debug, Clone)]
+pub struct CommandPlugin {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl CommandPlugin {
+    /// A plugin running a command line, split at whitespace.
+    pub fn from_command_line(command: &str) -> Option<Self> {
+        let mut words = command.split_whitespace().map(String::from);
+        Some(Self {
+            program: words.next()?,
+            args: words.collect(),
+        })
+    }
+}
+
+impl TagPlugin for CommandPlugin {
+    fn parse(&self, fields: &[TagField]) -> Result<Vec<Value>, String> {
+        let input = serde_json::to_vec(fields).map_err(|e| e.to_string())?;
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("could not run {}: {}", self.program, e))?;
+        // The input is written from a thread of its own, as a plugin may
+        // print output before reading all of its input and block once the
+        // output pipe is full. A plugin exiting without reading its input is
+        // reported by its status or output below.
+        let stdin = child.stdin.take();
+        let writer = thread::spawn(move || stdin.map(|mut stdin| stdin.write_all(&input)));
+        let output = child
+            .wait_with_output()
+            .map_err(|e| format!("could not run {}: {}", self.program, e))?;
+        let _ = writer.join();
+        if !output.status.success() {
+            return Err(format!(
+                "{} failed: {}",
+                self.program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        let values: Vec<Value> = serde_json::from_slice(&output.stdout)
+            .map_err(|e| format!("{} printed invalid JSON: {}", self.program, e))?;
+        if values.len() != fields.len() {
+            return Err(format!(
+                "{} printed {} values for {} fields",
+                self.program,
+                values.len(),
+                fields.len()
+            ));
+        }
+        Ok(values)
+    }
+}
+
+/// Parse the fields of `entries` with `plugins`, one after the other.
+pub fn apply_plugins(
+    entries: &mut [ManualEntry],
+    plugins: &[Box<dyn TagPlugin>],
+) -> Result<(), String> {
+    for plugin in plugins {
+        let fields: Vec<TagField> = entries
+            .iter()
+            .flat_map(|entry| {
+                let ident = entry.get_ident_title().0;
+                entry.fields.iter().map(move |(tag, value)| TagField {
+                    entry: ident.clone(),
+                    tag: tag.clone(),
+                    value: value.clone(),
+                })
+            })
+            .collect();
+        if fields.is_empty() {
+            return Ok(());
+        }
+        let mut values = plugin.parse(&fields)?.into_iter();
+        for entry in entries.iter_mut() {
+            for value in entry.fields.values_mut() {
+                if let Some(new) = values.next() {
+                    *value = new;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Templates rendering the fields of tags, keyed by tag. A template gets
+/// the field's `tag`, its `value` and the identifier of its `entry`.
+#[derive(Default)]
+pub struct TagTemplates(pub BTreeMap<String, Template>);
+
+impl TagTemplates {
+    /// Render the fields of `entries` that have a template into their
+    /// `rendered_fields`.
+    pub fn render(&self, entries: &mut [ManualEntry]) -> Result<(), String> {
+        for entry in entries {
+            let ident = entry.get_ident_title().0;
+            for (tag, value) in &entry.fields {
+                let Some(template) = self.0.get(tag) else {
+                    continue;
+                };
+                let field = TagField {
+                    entry: ident.clone(),
+                    tag: tag.clone(),
+                    value: value.clone(),
+                };
+                let rendered = template
+                    .render(&field)
+                    .map_err(|e| format!("@{} of {}: {}", tag, ident, e))?;
+                entry.rendered_fields.push(rendered.trim().to_string());
+            }
+        }
+        Ok(())
+    }
+}
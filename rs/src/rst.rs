@@ -0,0 +1,221 @@
+//! Rendering of generated markdown as reStructuredText for Sphinx.
+//!
+//! Headings with an `{#anchor}` attribute and `[]{#anchor}` lines are
+//! preceded by a label of the same name (`.. _anchor:`), so links into the
+//! documentation keep their targets; links to `#anchor`s become `:ref:`s.
+//! Code blocks become `code-block` directives, tables `list-table`s, and
+//! fenced divs admonitions (`::: {.warning}` → `.. warning::`) or
+//! containers. RST does not allow sections inside of directives or lists,
+//! so headings there become `rubric`s. Roles (``{file}`foo` ``) become
+//! Sphinx roles where one exists and literals otherwise.
+
+use crate::format::{divs_to_html, strip_role};
+use crate::markup::{render, Frame, List, Syntax, Writer};
+
+/// Underline characters of the heading levels, as commonly used by Sphinx
+/// projects.
+const UNDERLINES: [char; 6] = ['=', '-', '~', '^', '"', '\''];
+
+/// reStructuredText markup, for [`render`].
+struct Rst;
+
+impl Syntax for Rst {
+    const SEPARATOR: &'static str = "\\ ";
+    const EMPHASIS: &'static str = "*";
+    const STRONG: &'static str = "**";
+    const HARD_BREAK: &'static str = "\n";
+    const RULE: &'static str = "----";
+
+    fn escape(text: &str) -> String {
+        escape(text)
+    }
+
+    /// Append a block, separated from the previous one by a blank line.
+    fn block(_parent: &Frame, buf: &mut String, block: &str) {
+        if !buf.is_empty() && !buf.ends_with("\n\n") {
+            buf.push_str(if buf.ends_with('\n') { "\n" } else { "\n\n" });
+        }
+        buf.push_str(block.trim_end_matches('\n'));
+        buf.push_str("\n\n");
+    }
+
+    fn item_marker(lists: &[List]) -> String {
+        match lists.last() {
+            Some(List {
+                start: Some(start),
+                items,
+            }) => format!("{}. ", start + *items as u64),
+            _ => "- ".to_string(),
+        }
+    }
+
+    fn anchor(id: &str) -> String {
+        format!(".. _{}:", id)
+    }
+
+    fn code(w: &mut Writer<Self>, code: &str) {
+        let role = strip_role(w.buf());
+        let markup = match role.as_deref() {
+            Some("file") => format!(":file:`{}`", code),
+            Some("command") => format!(":command:`{}`", code),
+            Some("envar" | "env") => format!(":envvar:`{}`", code),
+            _ => literal(code),
+        };
+        w.markup(&markup);
+    }
+
+    fn close(w: &mut Writer<Self>, frame: Frame, content: String) {
+        match frame {
+            Frame::Heading { level, id } => {
+                let title = content.trim().replace('\n', " ");
+                if let Some(id) = id {
+                    w.block(&Self::anchor(&id));
+                }
+                if w.nested() {
+                    w.block(&format!(".. rubric:: {}", title));
+                } else {
+                    let underline = UNDERLINES[(level - 1).min(5)];
+                    let width = title.chars().count().max(1);
+                    w.block(&format!(
+                        "{}\n{}",
+                        title,
+                        underline.to_string().repeat(width)
+                    ));
+                }
+            }
+            Frame::CodeBlock { language } => {
+                let language = if language.is_empty() {
+                    "text"
+                } else {
+                    &language
+                };
+                w.block(&format!(
+                    ".. code-block:: {}\n\n{}",
+                    language,
+                    indent(content.trim_end_matches('\n'), "   ")
+                ));
+            }
+            Frame::BlockQuote => w.block(&indent(content.trim_end(), "   ")),
+            Frame::Item { marker } => {
+                let content = indent(content.trim(), &" ".repeat(marker.len()));
+                let buf = w.buf();
+                if !buf.is_empty() && !buf.ends_with('\n') {
+                    buf.push_str("\n\n");
+                }
+                buf.push_str(&marker);
+                buf.push_str(content.trim_start());
+                buf.push('\n');
+            }
+            Frame::DefinitionTitle => {
+                let buf = w.buf();
+                if !buf.is_empty() && !buf.ends_with("\n\n") {
+                    buf.push('\n');
+                }
+                buf.push_str(content.trim());
+                buf.push('\n');
+            }
+            Frame::Definition => {
+                let buf = w.buf();
+                buf.push_str(&indent(content.trim(), "   "));
+                buf.push('\n');
+            }
+            Frame::Link { dest } => {
+                let text = content.trim();
+                let link = match dest.strip_prefix('#') {
+                    Some(id) if text.is_empty() => format!(":ref:`{}`", id),
+                    Some(id) => format!(":ref:`{} <{}>`", text, id),
+                    None if text.is_empty() || text == dest => format!("`<{}>`__", dest),
+                    None => format!("`{} <{}>`__", text, dest),
+                };
+                w.markup(&link);
+            }
+            Frame::Image { dest } => {
+                let alt = if content.trim().is_empty() {
+                    dest.clone()
+                } else {
+                    content.trim().to_string()
+                };
+                w.markup(&format!("`{} <{}>`__", alt, dest));
+            }
+            Frame::Table { rows, header } => {
+                let mut table = String::from(".. list-table::\n");
+                if header {
+                    table.push_str("   :header-rows: 1\n");
+                }
+                table.push('\n');
+                for row in rows {
+                    for (i, cell) in row.iter().enumerate() {
+                        let marker = if i == 0 { "   * - " } else { "     - " };
+                        table.push_str(marker);
+                        table.push_str(indent(cell, "       ").trim_start());
+                        table.push('\n');
+                    }
+                }
+                w.block(&table);
+            }
+            Frame::Div(directive) => {
+                w.block(&format!(
+                    ".. {}\n\n{}",
+                    directive,
+                    indent(content.trim_end(), "   ")
+                ));
+            }
+            _ => (),
+        }
+    }
+
+    fn end_list(buf: &mut String) {
+        if !buf.ends_with("\n\n") {
+            buf.push('\n');
+        }
+    }
+}
+
+/// Convert markdown to reStructuredText.
+pub fn markdown_to_rst(markdown: &str) -> String {
+    let source = divs_to_html(markdown, |class| match class {
+        "note" | "tip" | "warning" | "important" | "caution" => Some(format!("{}::", class)),
+        "example" => Some("container:: example".to_string()),
+        _ => None,
+    });
+    render::<Rst>(&source)
+}
+
+/// Indent all non-empty lines of `text`.
+fn indent(text: &str, prefix: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                String::new()
+            } else {
+                format!("{}{}", prefix, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Inline literal for `code`. Literals cannot start or end with a space.
+fn literal(code: &str) -> String {
+    format!("``{}``", code.trim())
+}
+
+/// Escape characters that start inline markup.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' | '*' | '`' | '|' => {
+                out.push('\\');
+                out.push(c);
+            }
+            // A trailing underscore makes a word a reference.
+            '_' if !chars.peek().is_some_and(|next| next.is_alphanumeric()) => {
+                out.push_str("\\_");
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
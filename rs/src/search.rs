@@ -0,0 +1,167 @@
+//! Search indexes for static documentation sites.
+//!
+//! The index lists one document per function and option, with its anchor as
+//! `id` and its description as plain text, so a site can build a
+//! [lunr](https://lunrjs.com/) or elasticlunr index on load:
+//!
+//! ```js
+//! const idx = lunr(function () {
+//!   this.ref("id");
+//!   this.field("name");
+//!   this.field("text");
+//!   documents.forEach((doc) => this.add(doc));
+//! });
+//! ```
+//!
+//! Sites without a search library can use the [`InvertedIndex`] instead,
+//! which maps every term to the documents containing it.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use pulldown_cmark::{Event, Parser, TagEnd};
+use serde::Serialize;
+
+use crate::commonmark::ManualEntry;
+use crate::options::{option_anchors, OptionFlags, OptionsMap};
+
+/// What a search document describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentKind {
+    Function,
+    Option,
+}
+
+/// A searchable function or option.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SearchDocument {
+    /// Anchor of the entry, e.g. `function-library-lib.strings.concat`.
+    pub id: String,
+    pub kind: DocumentKind,
+    /// Full name, e.g. `lib.strings.concat` or `services.nginx.enable`.
+    pub name: String,
+    /// Type signature of a function or type of an option.
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub doc_type: Option<String>,
+    /// Description with markdown markup removed.
+    pub text: String,
+    /// Flags of an option.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flags: Option<OptionFlags>,
+}
+
+/// Search documents for the entries of a category.
+pub fn function_documents(entries: &[ManualEntry], anchor_prefix: &str) -> Vec<SearchDocument> {
+    entries
+        .iter()
+        .map(|entry| {
+            let (ident, title) = entry.get_ident_title();
+            SearchDocument {
+                id: format!("{}{}", anchor_prefix, ident),
+                kind: DocumentKind::Function,
+                name: title,
+                doc_type: entry.fn_type.as_ref().map(|t| t.trim().to_string()),
+                text: plain_text(&entry.description.join("\n\n")),
+                flags: None,
+            }
+        })
+        .collect()
+}
+
+/// Search documents for options, sorted by name.
+pub fn option_documents(options: &OptionsMap, anchor_prefix: &str) -> Vec<SearchDocument> {
+    let mut names: Vec<&String> = options.keys().collect();
+    names.sort();
+    let anchors = option_anchors(options, anchor_prefix);
+    names
+        .into_iter()
+        .map(|name| {
+            let opt = &options[name];
+            SearchDocument {
+                id: anchors.get(name).unwrap_or_default().to_string(),
+                kind: DocumentKind::Option,
+                name: name.clone(),
+                doc_type: opt.option_type.clone(),
+                text: opt
+                    .description
+                    .as_ref()
+                    .map(|d| plain_text(d.as_str()))
+                    .unwrap_or_default(),
+                flags: Some(opt.flags()),
+            }
+        })
+        .collect()
+}
+
+/// Documents together with an index from terms to the positions of the
+/// documents containing them in their name, type or text.
+#[derive(Debug, Clone, Serialize)]
+pub struct InvertedIndex {
+    pub documents: Vec<SearchDocument>,
+    pub terms: BTreeMap<String, Vec<usize>>,
+}
+
+impl InvertedIndex {
+    pub fn new(documents: Vec<SearchDocument>) -> Self {
+        let mut terms: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for (i, doc) in documents.iter().enumerate() {
+            let mut doc_terms = BTreeSet::new();
+            // Full names are indexed too, so `lib.strings.concat` finds the
+            // entry as a whole.
+            doc_terms.insert(doc.name.to_lowercase());
+            for text in [Some(&doc.name), doc.doc_type.as_ref(), Some(&doc.text)]
+                .into_iter()
+                .flatten()
+            {
+                doc_terms.extend(tokenize(text));
+            }
+            for term in doc_terms {
+                terms.entry(term).or_default().push(i);
+            }
+        }
+        Self { documents, terms }
+    }
+}
+
+/// Split text into lowercase terms of at least two characters. Camel case
+/// names are split as well, so `concatStrings` yields `concatstrings`,
+/// `concat` and `strings`.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut terms = vec![];
+    for word in text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+    {
+        terms.push(word.to_lowercase());
+        let mut part = String::new();
+        for c in word.chars() {
+            if c.is_uppercase() && !part.is_empty() {
+                terms.push(part.to_lowercase());
+                part.clear();
+            }
+            part.push(c);
+        }
+        if part.len() < word.len() {
+            terms.push(part.to_lowercase());
+        }
+    }
+    terms.retain(|t| t.chars().count() >= 2);
+    terms.dedup();
+    terms
+}
+
+/// The text of a markdown document without markup.
+pub(crate) fn plain_text(markdown: &str) -> String {
+    let mut text = String::new();
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Text(t) | Event::Code(t) => text.push_str(&t),
+            Event::End(
+                TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough | TagEnd::Link,
+            ) => (),
+            Event::SoftBreak | Event::HardBreak | Event::End(_) => text.push(' '),
+            _ => (),
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
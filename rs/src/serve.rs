@@ -0,0 +1,247 @@
+//! A local HTTP server previewing rendered documentation.
+//!
+//! Pages are rendered in memory by a caller-supplied build function and
+//! served as standalone HTML documents with a navigation bar. The watched
+//! inputs are polled for changes; on a change the site is rebuilt and open
+//! pages reload themselves, as they poll the site's version.
+//!
+//! Only `GET` requests for the index (`/`), pages (`/<name>.html`) and the
+//! version (`/__nixdoc/version`) are answered; this is a preview tool, not
+//! a general purpose web server.
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// How often the watched inputs are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Path answering with the current version of the site.
+const VERSION_PATH: &str = "/__nixdoc/version";
+
+/// Reloads the page when the site's version changes.
+const RELOAD_SCRIPT: &str = r#"<script>
+(function () {
+  var version = null;
+  setInterval(function () {
+    fetch("/__nixdoc/version")
+      .then(function (r) { return r.text(); })
+      .then(function (v) {
+        if (version !== null && v !== version) location.reload();
+        version = v;
+      })
+      .catch(function () {});
+  }, 1000);
+})();
+</script>"#;
+
+const STYLE: &str = "<style>
+body { font-family: sans-serif; max-width: 60rem; margin: 0 auto; padding: 1rem; line-height: 1.5; }
+nav a { margin-right: 1rem; }
+pre { background: #f4f4f4; padding: 0.5rem; overflow-x: auto; }
+.error { color: #b00; white-space: pre-wrap; }
+</style>";
+
+/// A rendered page of the site.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page {
+    /// Name of the page, served as `/<name>.html`.
+    pub name: String,
+    pub title: String,
+    /// The page's content as an HTML fragment.
+    pub html: String,
+}
+
+/// The pages of the site as of its last build, or the error the build
+/// failed with.
+#[derive(Debug, Clone)]
+pub struct Site {
+    /// Incremented on every rebuild.
+    pub version: u64,
+    pub pages: Result<Vec<Page>, String>,
+}
+
+/// An HTTP response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    fn html(status: u16, body: String) -> Self {
+        Self {
+            status,
+            content_type: "text/html; charset=utf-8",
+            body,
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+impl Site {
+    /// The response to a `GET` request for `path`.
+    pub fn respond(&self, path: &str) -> Response {
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        if path == VERSION_PATH {
+            return Response {
+                status: 200,
+                content_type: "text/plain; charset=utf-8",
+                body: self.version.to_string(),
+            };
+        }
+        let pages = match &self.pages {
+            Ok(pages) => pages,
+            Err(e) => {
+                let body = format!("<p class=\"error\">{}</p>", escape(e));
+                return Response::html(500, self.document("Build failed", &body));
+            }
+        };
+        if path == "/" || path == "/index.html" {
+            let mut body = String::from("<ul>\n");
+            for page in pages {
+                body.push_str(&format!(
+                    "<li><a href=\"/{}.html\">{}</a></li>\n",
+                    escape(&page.name),
+                    escape(&page.title)
+                ));
+            }
+            body.push_str("</ul>\n");
+            return Response::html(200, self.document("Documentation", &body));
+        }
+        let page = path
+            .strip_prefix('/')
+            .and_then(|p| p.strip_suffix(".html"))
+            .and_then(|name| pages.iter().find(|page| page.name == name));
+        match page {
+            Some(page) => Response::html(200, self.document(&page.title, &page.html)),
+            None => Response::html(404, self.document("Not found", "<p>No such page.</p>")),
+        }
+    }
+
+    /// Wrap a fragment into a standalone document with navigation.
+    fn document(&self, title: &str, body: &str) -> String {
+        let mut nav = String::from("<nav><a href=\"/\">Index</a>");
+        for page in self.pages.iter().flatten() {
+            nav.push_str(&format!(
+                "<a href=\"/{}.html\">{}</a>",
+                escape(&page.name),
+                escape(&page.title)
+            ));
+        }
+        nav.push_str("</nav>");
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n{}\n</head>\n<body>\n{}\n<main>\n{}\n</main>\n{}\n</body>\n</html>\n",
+            escape(title),
+            STYLE,
+            nav,
+            body,
+            RELOAD_SCRIPT
+        )
+    }
+}
+
+/// Serve the site built by `build` on `listener`, rebuilding it whenever a
+/// file in `watched` (files or directories) changes. Runs until the
+/// listener fails.
+pub fn serve<F>(listener: TcpListener, watched: Vec<PathBuf>, build: F) -> io::Result<()>
+where
+    F: Fn() -> Result<Vec<Page>, String> + Send + 'static,
+{
+    let site = Arc::new(RwLock::new(Site {
+        version: 0,
+        pages: build(),
+    }));
+
+    let watcher_site = Arc::clone(&site);
+    thread::spawn(move || {
+        let mut last = fingerprint(&watched);
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let current = fingerprint(&watched);
+            if current != last {
+                last = current;
+                let pages = build();
+                let mut site = watcher_site.write().unwrap_or_else(|e| e.into_inner());
+                site.version += 1;
+                site.pages = pages;
+            }
+        }
+    });
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let site = Arc::clone(&site);
+        // Failing connections only affect the client that made them.
+        thread::spawn(move || {
+            let _ = handle(stream, &site);
+        });
+    }
+    Ok(())
+}
+
+fn handle(mut stream: TcpStream, site: &RwLock<Site>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // The headers are of no interest, but must be read before responding.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && !header.trim().is_empty() {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(path)) => site.read().unwrap_or_else(|e| e.into_inner()).respond(path),
+        _ => Response::html(405, String::new()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.reason(),
+        response.content_type,
+        response.body.len()
+    )?;
+    stream.write_all(response.body.as_bytes())?;
+    stream.flush()
+}
+
+/// Modification times of the files in `paths`, descending into directories.
+/// Files that cannot be read are recorded without a time, so their
+/// (dis)appearance is noticed too.
+fn fingerprint(paths: &[PathBuf]) -> Vec<(PathBuf, Option<SystemTime>)> {
+    let mut files = vec![];
+    let mut pending = paths.to_vec();
+    while let Some(path) = pending.pop() {
+        match fs::read_dir(&path) {
+            Ok(entries) => pending.extend(entries.flatten().map(|entry| entry.path())),
+            Err(_) => {
+                let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+                files.push((path, modified));
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
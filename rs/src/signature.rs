@@ -0,0 +1,502 @@
+//! Type signatures of documented functions.
+//!
+//! RFC145 doc comments state the type of a function in a `# Type` section
+//! holding a code block with a Haskell-like signature:
+//!
+//! ```text
+//! foldl' :: (b -> a -> b) -> b -> [a] -> b
+//! ```
+//!
+//! The signature is parsed into a [`TypeSignature`], which is included in the
+//! JSON output, and the code block is re-rendered from it. Signatures too
+//! long for a single line are broken before every arrow, so the arguments
+//! line up:
+//!
+//! ```text
+//! foldl' :: (b -> a -> b)
+//!        -> b
+//!        -> [a]
+//!        -> b
+//! ```
+//!
+//! Signatures that do not parse are left as written.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::format::get_fence;
+
+/// Signatures longer than this are rendered with one argument per line.
+pub const MAX_SIGNATURE_WIDTH: usize = 80;
+
+/// A parsed type signature, e.g. `map :: (a -> b) -> [a] -> [b]`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct TypeSignature {
+    /// Name before `::`, if given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub ty: TypeExpr,
+}
+
+/// A type in a signature.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum TypeExpr {
+    /// A named type (`String`) or type variable (`a`).
+    Name { name: String },
+    /// A list of elements of a type (`[a]`).
+    List { element: Box<TypeExpr> },
+    /// An attribute set with known attributes (`{ name :: String; ... }`).
+    Attrs {
+        fields: Vec<AttrField>,
+        /// Whether other attributes are allowed (`...`).
+        open: bool,
+    },
+    /// A function (`a -> b`).
+    Function {
+        argument: Box<TypeExpr>,
+        result: Box<TypeExpr>,
+    },
+    /// A type applied to arguments (`AttrsOf a`).
+    Apply {
+        constructor: Box<TypeExpr>,
+        arguments: Vec<TypeExpr>,
+    },
+    /// One of several types (`String | Null`).
+    Union { alternatives: Vec<TypeExpr> },
+}
+
+/// An attribute of an attribute set type.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct AttrField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: TypeExpr,
+    /// Whether the attribute may be missing (`name ? :: String`).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub optional: bool,
+}
+
+impl TypeSignature {
+    /// Parse a signature, returning `None` if it is not one.
+    pub fn parse(text: &str) -> Option<Self> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let name = match parser.tokens.get(1) {
+            Some(Token::DoubleColon) => match parser.next()? {
+                Token::Name(name) => {
+                    parser.next();
+                    Some(name)
+                }
+                _ => return None,
+            },
+            _ => None,
+        };
+        let ty = parser.function()?;
+        parser.at_end().then_some(Self { name, ty })
+    }
+
+    /// The argument types of the (curried) function, in order.
+    pub fn arguments(&self) -> Vec<&TypeExpr> {
+        let mut arguments = vec![];
+        let mut ty = &self.ty;
+        while let TypeExpr::Function { argument, result } = ty {
+            arguments.push(argument.as_ref());
+            ty = result;
+        }
+        arguments
+    }
+
+    /// The type returned after applying all arguments.
+    pub fn result(&self) -> &TypeExpr {
+        let mut ty = &self.ty;
+        while let TypeExpr::Function { result, .. } = ty {
+            ty = result;
+        }
+        ty
+    }
+
+    /// Render the signature, on a single line if it fits into `width`
+    /// characters and with one argument per line otherwise.
+    pub fn render(&self, width: usize) -> String {
+        let line = self.to_string();
+        let arguments = self.arguments();
+        if line.chars().count() <= width || arguments.is_empty() {
+            return line;
+        }
+
+        let (first, indent) = match &self.name {
+            Some(name) => (
+                format!("{} :: ", name),
+                " ".repeat(name.chars().count() + 1),
+            ),
+            None => ("   ".to_string(), String::new()),
+        };
+        let parts: Vec<String> = arguments
+            .into_iter()
+            .map(|arg| arg.argument_string())
+            .chain([self.result().to_string()])
+            .collect();
+        let mut out = format!("{}{}", first, parts[0]);
+        for part in &parts[1..] {
+            out.push_str(&format!("\n{}-> {}", indent, part));
+        }
+        out
+    }
+}
+
+impl TypeExpr {
+    /// Names of all named types, excluding type variables (lowercase names
+    /// such as `a`).
+    pub fn type_names(&self) -> BTreeSet<&str> {
+        let mut names = BTreeSet::new();
+        self.collect_names(&mut names);
+        names
+    }
+
+    fn collect_names<'a>(&'a self, names: &mut BTreeSet<&'a str>) {
+        match self {
+            TypeExpr::Name { name } => {
+                if !name.starts_with(char::is_lowercase) {
+                    names.insert(name);
+                }
+            }
+            TypeExpr::List { element } => element.collect_names(names),
+            TypeExpr::Attrs { fields, .. } => {
+                fields.iter().for_each(|f| f.ty.collect_names(names));
+            }
+            TypeExpr::Function { argument, result } => {
+                argument.collect_names(names);
+                result.collect_names(names);
+            }
+            TypeExpr::Apply {
+                constructor,
+                arguments,
+            } => {
+                constructor.collect_names(names);
+                arguments.iter().for_each(|a| a.collect_names(names));
+            }
+            TypeExpr::Union { alternatives } => {
+                alternatives.iter().for_each(|a| a.collect_names(names));
+            }
+        }
+    }
+
+    /// The type as the argument of a function, parenthesized if it is a
+    /// function itself.
+    fn argument_string(&self) -> String {
+        match self {
+            TypeExpr::Function { .. } => format!("({})", self),
+            _ => self.to_string(),
+        }
+    }
+
+    /// The type as an argument of a type application.
+    fn atom_string(&self) -> String {
+        match self {
+            TypeExpr::Function { .. } | TypeExpr::Apply { .. } | TypeExpr::Union { .. } => {
+                format!("({})", self)
+            }
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for TypeSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{} :: {}", name, self.ty),
+            None => write!(f, "{}", self.ty),
+        }
+    }
+}
+
+impl fmt::Display for TypeExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeExpr::Name { name } => write!(f, "{}", name),
+            TypeExpr::List { element } => write!(f, "[{}]", element),
+            TypeExpr::Attrs { fields, open } => {
+                let mut parts: Vec<String> = fields
+                    .iter()
+                    .map(|field| {
+                        let optional = if field.optional { " ?" } else { "" };
+                        format!("{}{} :: {}", field.name, optional, field.ty)
+                    })
+                    .collect();
+                if *open {
+                    parts.push("...".to_string());
+                }
+                if parts.is_empty() {
+                    write!(f, "{{ }}")
+                } else {
+                    write!(f, "{{ {} }}", parts.join("; "))
+                }
+            }
+            TypeExpr::Function { argument, result } => {
+                write!(f, "{} -> {}", argument.argument_string(), result)
+            }
+            TypeExpr::Apply {
+                constructor,
+                arguments,
+            } => {
+                write!(f, "{}", constructor.atom_string())?;
+                for argument in arguments {
+                    write!(f, " {}", argument.atom_string())?;
+                }
+                Ok(())
+            }
+            TypeExpr::Union { alternatives } => {
+                let parts: Vec<String> = alternatives
+                    .iter()
+                    .map(|a| match a {
+                        TypeExpr::Function { .. } => format!("({})", a),
+                        _ => a.to_string(),
+                    })
+                    .collect();
+                write!(f, "{}", parts.join(" | "))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Name(String),
+    DoubleColon,
+    Arrow,
+    Pipe,
+    Question,
+    Ellipsis,
+    Separator,
+    Open(char),
+    Close(char),
+}
+
+fn tokenize(text: &str) -> Option<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            ':' if chars.peek() == Some(&':') => {
+                chars.next();
+                Token::DoubleColon
+            }
+            // `name : Type` is a common variant of `name :: Type` in fields.
+            ':' => Token::DoubleColon,
+            '-' if chars.peek() == Some(&'>') => {
+                chars.next();
+                Token::Arrow
+            }
+            '.' if chars.next_if_eq(&'.').is_some() && chars.next_if_eq(&'.').is_some() => {
+                Token::Ellipsis
+            }
+            '|' => Token::Pipe,
+            '?' => Token::Question,
+            ';' | ',' => Token::Separator,
+            '(' | '[' | '{' => Token::Open(c),
+            ')' | ']' | '}' => Token::Close(c),
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut name = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || "_'.".contains(*c)) {
+                    name.push(c);
+                }
+                if name.ends_with('.') {
+                    return None;
+                }
+                Token::Name(name)
+            }
+            _ => return None,
+        };
+        tokens.push(token);
+    }
+    Some(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let found = self.peek() == Some(token);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    /// `union ('->' function)?`
+    fn function(&mut self) -> Option<TypeExpr> {
+        let argument = self.union()?;
+        if self.eat(&Token::Arrow) {
+            let result = self.function()?;
+            return Some(TypeExpr::Function {
+                argument: Box::new(argument),
+                result: Box::new(result),
+            });
+        }
+        Some(argument)
+    }
+
+    /// `apply ('|' apply)*`
+    fn union(&mut self) -> Option<TypeExpr> {
+        let mut alternatives = vec![self.apply()?];
+        while self.eat(&Token::Pipe) {
+            alternatives.push(self.apply()?);
+        }
+        Some(if alternatives.len() == 1 {
+            alternatives.remove(0)
+        } else {
+            TypeExpr::Union { alternatives }
+        })
+    }
+
+    /// `atom atom*`
+    fn apply(&mut self) -> Option<TypeExpr> {
+        let constructor = self.atom()?;
+        let mut arguments = vec![];
+        while matches!(self.peek(), Some(Token::Name(_) | Token::Open(_))) {
+            arguments.push(self.atom()?);
+        }
+        Some(if arguments.is_empty() {
+            constructor
+        } else {
+            TypeExpr::Apply {
+                constructor: Box::new(constructor),
+                arguments,
+            }
+        })
+    }
+
+    fn atom(&mut self) -> Option<TypeExpr> {
+        match self.next()? {
+            Token::Name(name) => Some(TypeExpr::Name { name }),
+            Token::Open('(') => {
+                let ty = self.function()?;
+                self.eat(&Token::Close(')')).then_some(ty)
+            }
+            Token::Open('[') => {
+                let element = self.function()?;
+                self.eat(&Token::Close(']')).then_some(TypeExpr::List {
+                    element: Box::new(element),
+                })
+            }
+            Token::Open('{') => self.attrs(),
+            _ => None,
+        }
+    }
+
+    /// The fields of an attribute set type, after the opening brace.
+    fn attrs(&mut self) -> Option<TypeExpr> {
+        let mut fields = vec![];
+        let mut open = false;
+        loop {
+            match self.next()? {
+                Token::Close('}') => return Some(TypeExpr::Attrs { fields, open }),
+                Token::Ellipsis => open = true,
+                Token::Separator => (),
+                Token::Name(name) => {
+                    let optional = self.eat(&Token::Question);
+                    if !self.eat(&Token::DoubleColon) {
+                        return None;
+                    }
+                    let ty = self.function()?;
+                    fields.push(AttrField { name, ty, optional });
+                }
+                _ => return None,
+            }
+        }
+    }
+}
+
+/// Whether `line` is a `# Type` heading of any level.
+fn is_type_heading(line: &str) -> bool {
+    let trimmed = line.trim();
+    let level = trimmed.chars().take_while(|&c| c == '#').count();
+    level > 0
+        && trimmed[level..].starts_with(' ')
+        && trimmed[level..].trim().eq_ignore_ascii_case("type")
+}
+
+/// Whether a doc comment has a `# Type` section, which
+/// [`extract_signature`] may not be able to parse.
+pub fn has_type_section(doc: &str) -> bool {
+    doc.lines().any(is_type_heading)
+}
+
+/// Find the signature in the `# Type` section (of any heading level) of a doc
+/// comment, re-render its code block and return the signature with the
+/// updated doc comment.
+///
+/// Type names with an entry in `links` are listed below the code block as
+/// links to their documentation.
+pub fn extract_signature(
+    doc: &str,
+    links: &HashMap<String, String>,
+) -> Option<(String, TypeSignature)> {
+    let lines: Vec<&str> = doc.split_inclusive('\n').collect();
+    let heading = lines.iter().position(|line| is_type_heading(line))?;
+
+    // The code block must directly follow the heading, possibly after blank
+    // lines.
+    let open = heading
+        + 1
+        + lines[heading + 1..]
+            .iter()
+            .position(|line| !line.trim().is_empty())?;
+    let fence_line = lines[open].trim();
+    if !(fence_line.starts_with("```") || fence_line.starts_with("~~~")) {
+        return None;
+    }
+    let (count, ch) = get_fence(fence_line, true)?;
+    let close = open
+        + 1
+        + lines[open + 1..].iter().position(|line| {
+            get_fence(line.trim(), false).is_some_and(|(c, h)| h == ch && c >= count)
+        })?;
+
+    let body: Vec<&str> = lines[open + 1..close].iter().map(|l| l.trim()).collect();
+    let signature = TypeSignature::parse(&body.join(" "))?;
+
+    let indent = &lines[open][..lines[open].len() - lines[open].trim_start().len()];
+    let mut out: String = lines[..=open].concat();
+    for line in signature.render(MAX_SIGNATURE_WIDTH).lines() {
+        out.push_str(&format!("{}{}\n", indent, line));
+    }
+    out.push_str(lines[close]);
+    let linked: Vec<String> = signature
+        .ty
+        .type_names()
+        .into_iter()
+        .filter_map(|name| Some(format!("[`{}`]({})", name, links.get(name)?)))
+        .collect();
+    if !linked.is_empty() {
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+        out.push_str(&format!("\n{}Types: {}\n", indent, linked.join(", ")));
+    }
+    out.push_str(&lines[close + 1..].concat());
+    Some((out, signature))
+}
@@ -0,0 +1,111 @@
+//! Collision-free anchor IDs.
+//!
+//! Anchors are derived from names by replacing characters that are not
+//! allowed in IDs, so different names can end up with the same anchor:
+//! `foo.bar-baz` and `foo.bar.baz` both become `opt-foo-bar-baz`. The
+//! [`Slugger`] hands out anchors in sorted name order; the first name keeps
+//! its anchor and later ones get a numeric suffix (`opt-foo-bar-baz-2`), so
+//! the assignment does not depend on the order of the input.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::Serialize;
+
+/// Two names whose anchors collided.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AnchorCollision {
+    /// The anchor both names map to.
+    pub anchor: String,
+    /// The name keeping the anchor.
+    pub owner: String,
+    /// The name that was given a different anchor.
+    pub name: String,
+    /// The anchor assigned to `name` instead.
+    pub assigned: String,
+}
+
+impl AnchorCollision {
+    pub fn message(&self) -> String {
+        format!(
+            "`{}` and `{}` both have the anchor `{}`; using `{}` for `{}`",
+            self.owner, self.name, self.anchor, self.assigned, self.name
+        )
+    }
+}
+
+/// Final anchors of a set of names.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Anchors {
+    /// Anchor of every name.
+    pub ids: BTreeMap<String, String>,
+    pub collisions: Vec<AnchorCollision>,
+}
+
+impl Anchors {
+    /// The anchor of `name`, if it was assigned one.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.ids.get(name).map(String::as_str)
+    }
+}
+
+/// Hands out unique anchors.
+#[derive(Debug, Clone, Default)]
+pub struct Slugger {
+    /// Owner of every anchor handed out so far.
+    owners: HashMap<String, String>,
+    /// Anchors that would be derived from the names, which suffixed anchors
+    /// must not take.
+    reserved: HashSet<String>,
+}
+
+impl Slugger {
+    /// Reserve anchors that names are yet to be assigned, so suffixed anchors
+    /// never take them.
+    pub fn reserve<'a>(&mut self, anchors: impl IntoIterator<Item = &'a str>) {
+        self.reserved
+            .extend(anchors.into_iter().map(str::to_string));
+    }
+
+    /// Assign `anchor` to `name`, or a suffixed variant of it if it is taken
+    /// by another name.
+    pub fn assign(&mut self, name: &str, anchor: &str) -> (String, Option<AnchorCollision>) {
+        let Some(owner) = self.owners.get(anchor).filter(|owner| *owner != name) else {
+            self.owners.insert(anchor.to_string(), name.to_string());
+            return (anchor.to_string(), None);
+        };
+        let owner = owner.clone();
+        let assigned = (2..)
+            .map(|n| format!("{}-{}", anchor, n))
+            .find(|a| !self.owners.contains_key(a) && !self.reserved.contains(a))
+            .unwrap_or_default();
+        self.owners.insert(assigned.clone(), name.to_string());
+        let collision = AnchorCollision {
+            anchor: anchor.to_string(),
+            owner,
+            name: name.to_string(),
+            assigned: assigned.clone(),
+        };
+        (assigned, Some(collision))
+    }
+}
+
+/// Assign unique anchors to `names`, deriving them with `make_anchor`.
+pub fn assign_anchors<'a>(
+    names: impl IntoIterator<Item = &'a str>,
+    make_anchor: impl Fn(&str) -> String,
+) -> Anchors {
+    let mut names: Vec<&str> = names.into_iter().collect();
+    names.sort_unstable();
+    names.dedup();
+    let derived: Vec<String> = names.iter().map(|name| make_anchor(name)).collect();
+
+    let mut slugger = Slugger::default();
+    slugger.reserve(derived.iter().map(String::as_str));
+    let mut anchors = Anchors::default();
+    for (name, anchor) in names.into_iter().zip(&derived) {
+        let (assigned, collision) = slugger.assign(name, anchor);
+        anchors.ids.insert(name.to_string(), assigned);
+        anchors.collisions.extend(collision);
+    }
+    anchors
+}
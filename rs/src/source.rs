@@ -0,0 +1,301 @@
+//! Providers of the Nix sources to document.
+//!
+//! Besides local paths, sources can be fetched from a tarball or a git
+//! repository, so upstream libraries can be documented without a local
+//! checkout. Sources are given as flake-style references with the file to
+//! document as fragment:
+//!
+//! - `./lib/strings.nix` or `path:./lib#strings.nix`: a local path
+//! - `github:NixOS/nixpkgs/nixos-24.05#lib/strings.nix`: a GitHub repository at
+//!   a branch, tag or commit (the default branch if omitted)
+//! - `https://example.com/lib.tar.gz#strings.nix`, optionally prefixed with
+//!   `tarball+`: a tarball
+//! - `git+https://example.com/lib.git?ref=main&rev=<commit>#strings.nix`: a git
+//!   repository
+//!
+//! Tarballs are fetched with `curl` and unpacked with `tar`, repositories are
+//! fetched with `git`. Fetched trees are kept in the work directory and reused
+//! by later runs, so references to branches are not updated until the work
+//! directory is cleared.
+//!
+//! URLs, refs and revisions are passed to these commands as arguments, so
+//! values starting with `-`, which they would take for options, are
+//! rejected. The path of the file may not leave the fetched tree.
+
+use std::fs;
+use std::hash::Hasher;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+use crate::commonmark::Span;
+use crate::format::Fnv1a;
+
+/// A way of making a source tree available on disk.
+pub trait SourceProvider: std::fmt::Debug {
+    /// Make the source tree available below `work_dir` and return its root.
+    fn fetch(&self, work_dir: &Path) -> Result<PathBuf, String>;
+}
+
+/// A local file or directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalSource {
+    pub path: PathBuf,
+}
+
+impl SourceProvider for LocalSource {
+    fn fetch(&self, _work_dir: &Path) -> Result<PathBuf, String> {
+        if !self.path.exists() {
+            return Err(format!("{} does not exist", self.path.display()));
+        }
+        Ok(self.path.clone())
+    }
+}
+
+/// A tarball fetched over HTTP. A single top-level directory in the tarball
+/// is stripped, as in flakes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TarballSource {
+    pub url: String,
+}
+
+impl SourceProvider for TarballSource {
+    fn fetch(&self, work_dir: &Path) -> Result<PathBuf, String> {
+        let dest = fetched_dir(work_dir, &self.url, |tmp| {
+            let archive = tmp.join("archive");
+            run(Command::new("curl")
+                .args(["--fail", "--silent", "--show-error", "--location"])
+                .arg("--output")
+                .arg(&archive)
+                .arg("--url")
+                .arg(&self.url))?;
+            let tree = tmp.join("tree");
+            fs::create_dir_all(&tree).map_err(|e| e.to_string())?;
+            run(Command::new("tar")
+                .arg("-xf")
+                .arg(&archive)
+                .arg("-C")
+                .arg(&tree))?;
+            fs::remove_file(&archive).map_err(|e| e.to_string())
+        })?;
+
+        let tree = dest.join("tree");
+        let entries: Vec<PathBuf> = fs::read_dir(&tree)
+            .map_err(|e| e.to_string())?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .collect();
+        match entries.as_slice() {
+            [single] if single.is_dir() => Ok(single.clone()),
+            _ => Ok(tree),
+        }
+    }
+}
+
+/// A git repository at a ref or revision (`HEAD` if neither is given).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitSource {
+    pub url: String,
+    pub git_ref: Option<String>,
+    pub rev: Option<String>,
+}
+
+impl SourceProvider for GitSource {
+    fn fetch(&self, work_dir: &Path) -> Result<PathBuf, String> {
+        let target = self
+            .rev
+            .as_deref()
+            .or(self.git_ref.as_deref())
+            .unwrap_or("HEAD");
+        fetched_dir(work_dir, &format!("{}#{}", self.url, target), |tmp| {
+            let git = |args: &[&str]| run(Command::new("git").arg("-C").arg(tmp).args(args));
+            git(&["init", "--quiet"])?;
+            git(&["fetch", "--quiet", "--depth", "1", "--", &self.url, target])?;
+            git(&["checkout", "--quiet", "FETCH_HEAD"])
+        })
+    }
+}
+
+/// A parsed source reference: a provider and the path of the file within the
+/// provided tree.
+#[derive(Debug)]
+pub struct SourceSpec {
+    pub provider: Box<dyn SourceProvider>,
+    /// Path within the source tree; the tree itself is the file if unset.
+    pub subpath: Option<String>,
+}
+
+impl SourceSpec {
+    /// Parse a source reference, see the [module documentation](self).
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (location, subpath) = match spec.split_once('#') {
+            Some((location, subpath)) => (location, Some(subpath.to_string())),
+            None => (spec, None),
+        };
+
+        let provider: Box<dyn SourceProvider> = if let Some(repo) = location.strip_prefix("github:")
+        {
+            let parts: Vec<&str> = repo.split('/').collect();
+            let (owner, name, rev) = match parts.as_slice() {
+                [owner, name] => (owner, name, "HEAD"),
+                [owner, name, rev] => (owner, name, *rev),
+                _ => return Err(format!("expected github:OWNER/REPO[/REF], got '{}'", spec)),
+            };
+            Box::new(TarballSource {
+                url: format!(
+                    "https://github.com/{}/{}/archive/{}.tar.gz",
+                    owner, name, rev
+                ),
+            })
+        } else if let Some(url) = location.strip_prefix("git+") {
+            let (url, query) = url.split_once('?').unwrap_or((url, ""));
+            let mut source = GitSource {
+                url: url.to_string(),
+                git_ref: None,
+                rev: None,
+            };
+            check_argument(url, spec)?;
+            for param in query.split('&').filter(|p| !p.is_empty()) {
+                match param.split_once('=') {
+                    Some(("ref", value)) => source.git_ref = Some(check_argument(value, spec)?),
+                    Some(("rev", value)) => source.rev = Some(check_argument(value, spec)?),
+                    _ => return Err(format!("unknown parameter '{}' in '{}'", param, spec)),
+                }
+            }
+            Box::new(source)
+        } else if let Some(url) = location.strip_prefix("tarball+") {
+            Box::new(TarballSource {
+                url: check_argument(url, spec)?,
+            })
+        } else if location.starts_with("https://") || location.starts_with("http://") {
+            Box::new(TarballSource {
+                url: location.to_string(),
+            })
+        } else {
+            Box::new(LocalSource {
+                path: PathBuf::from(location.strip_prefix("path:").unwrap_or(location)),
+            })
+        };
+
+        Ok(Self { provider, subpath })
+    }
+
+    /// Fetch the source and return the path of the file to document.
+    pub fn resolve(&self, work_dir: &Path) -> Result<PathBuf, String> {
+        if let Some(subpath) = &self.subpath {
+            let escapes = Path::new(subpath)
+                .components()
+                .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+            if escapes {
+                return Err(format!(
+                    "'{}' is not a relative path within the source",
+                    subpath
+                ));
+            }
+        }
+        let root = self.provider.fetch(work_dir)?;
+        let path = match &self.subpath {
+            Some(subpath) => root.join(subpath),
+            None => root,
+        };
+        if !path.exists() {
+            return Err(format!("{} does not exist in the source", path.display()));
+        }
+        Ok(path)
+    }
+}
+
+/// Links to the lines of an entry in a hosted copy of its source.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceLinks {
+    /// URL the path of a file is appended to, or a template with `{path}`,
+    /// `{revision}`, `{line}` and `{end_line}` placeholders.
+    pub base_url: String,
+    /// Revision (e.g. a commit) inserted for `{revision}`, or between the
+    /// base URL and the path if the URL has no placeholders.
+    pub revision: Option<String>,
+}
+
+impl SourceLinks {
+    /// Link to the lines `span` covers in `path`. Without placeholders the
+    /// link has the form `<base_url>/<revision>/<path>#L<line>-L<end_line>`,
+    /// which is understood by GitHub, GitLab and Gitea (for a base URL
+    /// ending in `/blob` or `/src/commit`).
+    pub fn url(&self, path: &str, span: &Span) -> String {
+        let template = if self.base_url.contains('{') {
+            self.base_url.clone()
+        } else if self.revision.is_some() {
+            format!(
+                "{}/{{revision}}/{{path}}#L{{line}}-L{{end_line}}",
+                self.base_url.trim_end_matches('/')
+            )
+        } else {
+            format!(
+                "{}/{{path}}#L{{line}}-L{{end_line}}",
+                self.base_url.trim_end_matches('/')
+            )
+        };
+        let path = path.strip_prefix("./").unwrap_or(path);
+        template
+            .replace("{revision}", self.revision.as_deref().unwrap_or("HEAD"))
+            .replace("{path}", path.trim_start_matches('/'))
+            .replace("{line}", &span.start_line.to_string())
+            .replace("{end_line}", &span.end_line.to_string())
+    }
+}
+
+/// The value of a spec passed to `git` or `curl`, rejecting values they would
+/// take for options.
+fn check_argument(value: &str, spec: &str) -> Result<String, String> {
+    if value.is_empty() || value.starts_with('-') {
+        return Err(format!("invalid value '{}' in '{}'", value, spec));
+    }
+    Ok(value.to_string())
+}
+
+/// Directory fetched sources are kept in unless a cache directory is given:
+/// `$XDG_CACHE_HOME/nixdoc/sources`, or `~/.cache/nixdoc/sources`. Unlike a
+/// directory in the shared temporary directory, it cannot be filled with
+/// trees of their own by other users.
+pub fn default_work_dir() -> Result<PathBuf, String> {
+    let cache = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| Some(PathBuf::from(std::env::var_os("HOME")?).join(".cache")))
+        .ok_or("cannot find a cache directory for fetched sources, set --cache-dir")?;
+    Ok(cache.join("nixdoc").join("sources"))
+}
+
+/// Return the directory a source identified by `key` is fetched into, calling
+/// `fetch` with a fresh directory unless it was fetched before.
+fn fetched_dir(
+    work_dir: &Path,
+    key: &str,
+    fetch: impl FnOnce(&Path) -> Result<(), String>,
+) -> Result<PathBuf, String> {
+    let mut hasher = Fnv1a::default();
+    hasher.write(key.as_bytes());
+    let dest = work_dir.join(format!("{:016x}", hasher.finish()));
+    if dest.exists() {
+        return Ok(dest);
+    }
+
+    // Fetch into a temporary directory first, so an interrupted fetch is not
+    // mistaken for a complete one by later runs.
+    let tmp = work_dir.join(format!("{:016x}.tmp", hasher.finish()));
+    let _ = fs::remove_dir_all(&tmp);
+    fs::create_dir_all(&tmp).map_err(|e| format!("{}: {}", tmp.display(), e))?;
+    fetch(&tmp).map_err(|e| format!("failed to fetch {}: {}", key, e))?;
+    fs::rename(&tmp, &dest).map_err(|e| e.to_string())?;
+    Ok(dest)
+}
+
+/// Run a command, turning a failure into an error with its output.
+fn run(command: &mut Command) -> Result<(), String> {
+    let output = command
+        .output()
+        .map_err(|e| format!("could not run {:?}: {}", command.get_program(), e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
@@ -0,0 +1,90 @@
+//! An index of functions and options by keyword.
+//!
+//! Categories group functions by the file they are defined in, which is too
+//! coarse for large libraries. Entries and options can be tagged with
+//! keywords in a `Tags:` line or a `# Keywords` section of their doc comment
+//! or description (see [`extract_keywords`](crate::tags::extract_keywords)),
+//! and the index lists everything tagged with a keyword under a heading of
+//! its own:
+//!
+//! ```markdown
+//! ## network {#tag-network}
+//!
+//! - [`lib.fetchers.fetchurl`](fetchers.html#function-library-lib.fetchers.fetchurl)
+//! - [`networking.hostName`](options.html#opt-networking-hostName) (option)
+//! ```
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::commonmark::ManualEntry;
+use crate::format::code_span;
+use crate::inventory::{function_items, option_items, InventoryItem};
+use crate::options::{make_anchor_id, OptionsMap};
+use crate::search::DocumentKind;
+use crate::slug::assign_anchors;
+
+/// The functions and options of every keyword, sorted by name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct TagIndex {
+    tags: BTreeMap<String, Vec<InventoryItem>>,
+}
+
+impl TagIndex {
+    fn insert(&mut self, keyword: &str, item: &InventoryItem) {
+        let items = self.tags.entry(keyword.to_string()).or_default();
+        if let Err(i) = items.binary_search_by(|other| other.name.cmp(&item.name)) {
+            items.insert(i, item.clone());
+        }
+    }
+
+    /// Add the tagged entries of a category, documented on the page `page`,
+    /// see [`function_items`].
+    pub fn add_functions(&mut self, entries: &[ManualEntry], anchor_prefix: &str, page: &str) {
+        let items = function_items(entries, anchor_prefix, page);
+        for (entry, item) in entries.iter().zip(&items) {
+            for keyword in &entry.keywords {
+                self.insert(keyword, item);
+            }
+        }
+    }
+
+    /// Add the tagged options, documented on the page `page`.
+    pub fn add_options(&mut self, options: &OptionsMap, anchor_prefix: &str, page: &str) {
+        for item in option_items(options, anchor_prefix, page) {
+            for keyword in &options[&item.name].keywords {
+                self.insert(keyword, &item);
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// The index as a markdown page titled `title`, with a section per
+    /// keyword.
+    pub fn to_markdown(&self, title: &str) -> String {
+        let anchors = assign_anchors(self.tags.keys().map(String::as_str), |keyword| {
+            make_anchor_id(keyword, "tag-")
+        });
+        let mut output = format!("# {} {{#sec-tags}}\n", title);
+        for (keyword, items) in &self.tags {
+            output.push_str(&format!(
+                "\n## {} {{#{}}}\n\n",
+                keyword,
+                anchors.get(keyword).unwrap_or_default()
+            ));
+            for item in items {
+                output.push_str(&format!("- [{}]({})", code_span(&item.name), item.uri));
+                if item.kind == DocumentKind::Option {
+                    output.push_str(" (option)");
+                }
+                output.push('\n');
+            }
+        }
+        output
+    }
+}
@@ -0,0 +1,307 @@
+//! `@tag value` annotations in doc comments.
+//!
+//! Lines of the form `@name value` outside of code blocks are removed from
+//! the doc comment and returned separately, if they name a builtin tag or a
+//! custom tag registered for [`plugin`](crate::plugin)s:
+//!
+//! ```nix
+//! /**
+//!   Frobnicate a value.
+//!
+//!   @stability experimental
+//! */
+//! ```
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::format::get_fence;
+use crate::plugin::BUILTIN_TAGS;
+
+/// A single `@name value` annotation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tag {
+    pub name: String,
+    pub value: String,
+}
+
+/// Split a doc comment into its text without tag lines and the tags it contains.
+/// Lines naming neither a builtin nor one of the `custom` tags are kept, as
+/// they may be part of the text.
+pub fn extract_tags(doc: &str, custom: &[String]) -> (String, Vec<Tag>) {
+    let mut text = String::new();
+    let mut tags = vec![];
+    let mut fence: Option<(usize, char)> = None;
+
+    for line in doc.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if let Some((count, ch)) = fence {
+            if get_fence(trimmed, false).is_some_and(|(c, h)| h == ch && c >= count) {
+                fence = None;
+            }
+        } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = get_fence(trimmed, true);
+        } else if let Some(tag) = parse_tag(trimmed)
+            .filter(|tag| BUILTIN_TAGS.contains(&tag.name.as_str()) || custom.contains(&tag.name))
+        {
+            tags.push(tag);
+            continue;
+        }
+        text.push_str(line);
+    }
+
+    if tags.is_empty() {
+        return (doc.to_string(), tags);
+    }
+    (collapse_blank_lines(&text), tags)
+}
+
+/// Parse a `@name value` line. Names consist of alphanumerics and dashes.
+fn parse_tag(line: &str) -> Option<Tag> {
+    let rest = line.strip_prefix('@')?;
+    let (name, value) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return None;
+    }
+    Some(Tag {
+        name: name.to_string(),
+        value: value.trim().to_string(),
+    })
+}
+
+/// Split a `# Deprecated` section (of any heading level) off a doc comment,
+/// returning the remaining text and the section's text, which is empty if the
+/// heading is not followed by any text. The section ends at the next heading
+/// of the same or a higher level.
+pub fn extract_deprecated_section(doc: &str) -> (String, Option<String>) {
+    extract_section(doc, "deprecated")
+}
+
+/// Split the version an entry is available since off a doc comment, from a
+/// `Since: 24.05` line or a `# Since` section whose first line is the
+/// version.
+pub fn extract_since(doc: &str) -> (String, Option<String>) {
+    if let (text, Some(since)) = extract_line(doc, "Since:") {
+        return (text, Some(since));
+    }
+
+    match extract_section(doc, "since") {
+        (text, Some(section)) => {
+            let version = section.lines().next().map(|v| v.trim().to_string());
+            (text, version.filter(|v| !v.is_empty()))
+        }
+        (text, None) => (text, None),
+    }
+}
+
+/// Split the keywords of an entry off a doc comment, from a `Tags: a, b`
+/// line or a `# Keywords` or `# Tags` section listing them separated by
+/// commas or as a bullet list. Keywords are returned in the order they are
+/// listed in, without duplicates.
+pub fn extract_keywords(doc: &str) -> (String, Vec<String>) {
+    let (text, list) = match extract_line(doc, "Tags:") {
+        (_, None) => match extract_section(doc, "keywords") {
+            (_, None) => extract_section(doc, "tags"),
+            extracted => extracted,
+        },
+        extracted => extracted,
+    };
+    let mut keywords: Vec<String> = vec![];
+    for keyword in list.as_deref().unwrap_or_default().split([',', '\n']) {
+        let keyword = keyword.trim();
+        let keyword = keyword
+            .strip_prefix("- ")
+            .or_else(|| keyword.strip_prefix("* "))
+            .unwrap_or(keyword)
+            .trim()
+            .trim_matches('`');
+        if !keyword.is_empty() && !keywords.iter().any(|k| k == keyword) {
+            keywords.push(keyword.to_string());
+        }
+    }
+    (text, keywords)
+}
+
+/// Split the first non-empty line starting with `prefix` (outside of code
+/// blocks) off a doc comment, returning the remaining text and the rest of
+/// the line, see [`extract_since`].
+fn extract_line(doc: &str, prefix: &str) -> (String, Option<String>) {
+    let mut text = String::new();
+    let mut value = None;
+    let mut fence: Option<(usize, char)> = None;
+    for line in doc.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if let Some((count, ch)) = fence {
+            if get_fence(trimmed, false).is_some_and(|(c, h)| h == ch && c >= count) {
+                fence = None;
+            }
+        } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = get_fence(trimmed, true);
+        } else if let Some(rest) = trimmed
+            .strip_prefix(prefix)
+            .map(str::trim)
+            .filter(|v| value.is_none() && !v.is_empty())
+        {
+            value = Some(rest.to_string());
+            continue;
+        }
+        text.push_str(line);
+    }
+    match value {
+        Some(value) => (collapse_blank_lines(&text), Some(value)),
+        None => (doc.to_string(), None),
+    }
+}
+
+/// Split a `# Throws` or `# Errors` section off a doc comment, returning the
+/// remaining text and the conditions under which the entry fails: the items
+/// of a bullet list, or the section's text if it has no list.
+pub fn extract_throws(doc: &str) -> (String, Vec<String>) {
+    let (text, section) = match extract_section(doc, "throws") {
+        (_, None) => extract_section(doc, "errors"),
+        extracted => extracted,
+    };
+    let mut conditions: Vec<String> = vec![];
+    for line in section.as_deref().unwrap_or_default().lines() {
+        let trimmed = line.trim();
+        if let Some(item) = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "))
+            .filter(|_| !line.starts_with([' ', '\t']))
+        {
+            conditions.push(item.trim().to_string());
+        } else if trimmed.is_empty() {
+            continue;
+        } else if let Some(condition) = conditions.last_mut() {
+            condition.push(' ');
+            condition.push_str(trimmed);
+        } else {
+            conditions.push(trimmed.to_string());
+        }
+    }
+    (text, conditions)
+}
+
+/// Split the section with the heading `title` (compared case-insensitively)
+/// off a doc comment, see [`extract_deprecated_section`].
+fn extract_section(doc: &str, title: &str) -> (String, Option<String>) {
+    fn heading_level(line: &str) -> Option<(usize, &str)> {
+        let level = line.chars().take_while(|&c| c == '#').count();
+        (level > 0 && line[level..].starts_with(' ')).then(|| (level, line[level..].trim()))
+    }
+
+    let mut text = String::new();
+    let mut section: Option<(usize, String)> = None;
+    let mut notice = None;
+    let mut fence: Option<(usize, char)> = None;
+    for line in doc.split_inclusive('\n') {
+        let trimmed = line.trim();
+        let heading = if let Some((count, ch)) = fence {
+            if get_fence(trimmed, false).is_some_and(|(c, h)| h == ch && c >= count) {
+                fence = None;
+            }
+            None
+        } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = get_fence(trimmed, true);
+            None
+        } else {
+            heading_level(trimmed)
+        };
+
+        if let (Some((level, _)), Some((section_level, _))) = (heading, &section) {
+            if level <= *section_level {
+                notice = section.take().map(|(_, t)| t.trim().to_string());
+            }
+        }
+        match (heading, &mut section) {
+            (_, Some((_, body))) => body.push_str(line),
+            (Some((level, heading_title)), None)
+                if notice.is_none() && heading_title.eq_ignore_ascii_case(title) =>
+            {
+                section = Some((level, String::new()));
+            }
+            _ => text.push_str(line),
+        }
+    }
+    if let Some((_, body)) = section {
+        notice = Some(body.trim().to_string());
+    }
+
+    match notice {
+        Some(notice) => (collapse_blank_lines(&text), Some(notice)),
+        None => (doc.to_string(), None),
+    }
+}
+
+/// Removing tag lines can leave runs of blank lines behind; collapse them so
+/// paragraph splitting isn't affected.
+pub(crate) fn collapse_blank_lines(text: &str) -> String {
+    let mut out = String::new();
+    let mut blank_run = 0;
+    for line in text.trim().lines() {
+        if line.trim().is_empty() {
+            blank_run += 1;
+            if blank_run > 1 {
+                continue;
+            }
+        } else {
+            blank_run = 0;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.trim_end().to_string()
+}
+
+/// Compare two versions like `23.11` or `1.2.0-pre` by their numeric
+/// components, falling back to comparing non-numeric components as text.
+pub fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    fn components(version: &str) -> Vec<&str> {
+        version
+            .trim()
+            .trim_start_matches('v')
+            .split(['.', '-', '+'])
+            .collect()
+    }
+
+    let (a, b) = (components(a), components(b));
+    for (x, y) in a.iter().zip(&b) {
+        let ordering = match (x.parse::<u64>(), y.parse::<u64>()) {
+            (Ok(x), Ok(y)) => x.cmp(&y),
+            _ => x.cmp(y),
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+/// Maturity of an API, from a `@stability` tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Stability {
+    Experimental,
+    Stable,
+    Deprecated,
+}
+
+impl Stability {
+    /// Parse a stability level, returning `None` for unknown levels.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "experimental" => Some(Self::Experimental),
+            "stable" => Some(Self::Stable),
+            "deprecated" => Some(Self::Deprecated),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Experimental => "experimental",
+            Self::Stable => "stable",
+            Self::Deprecated => "deprecated",
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! User-supplied output layouts.
+//!
+//! `--template <path>` replaces the built-in markdown layout with a
+//! [Handlebars](https://handlebarsjs.com/) template. Function documentation is
+//! rendered with the [version 2 JSON document](crate::jsonv2::Document) as
+//! context, option documentation with an [`OptionsContext`]:
+//!
+//! ```handlebars
+//! # {{title}}
+//!
+//! {{#each options}}
+//! ## `{{name}}` {#{{id}}}
+//!
+//! {{description}}
+//! {{#if default}}**Default:** {{default}}{{/if}}
+//! {{/each}}
+//! ```
+//!
+//! Output is not HTML-escaped, since templates usually produce markdown.
+
+use std::fs;
+use std::path::Path;
+
+use handlebars::Handlebars;
+use serde::Serialize;
+
+use crate::options::{
+    declaration_link, format_option_value, option_anchors, render_related_packages, OptionsMap,
+    Provenance, RenderOptions,
+};
+
+const TEMPLATE_NAME: &str = "template";
+
+/// A compiled output template.
+pub struct Template {
+    registry: Handlebars<'static>,
+}
+
+impl Template {
+    /// Compile a template from its source.
+    pub fn new(source: &str) -> Result<Self, String> {
+        let mut registry = Handlebars::new();
+        registry.register_escape_fn(handlebars::no_escape);
+        registry
+            .register_template_string(TEMPLATE_NAME, source)
+            .map_err(|e| format!("Failed to parse template: {}", e))?;
+        Ok(Self { registry })
+    }
+
+    /// Read and compile a template file.
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let source =
+            fs::read_to_string(path).map_err(|e| format!("Failed to read template: {}", e))?;
+        Self::new(&source)
+    }
+
+    /// Render the template with the given context.
+    pub fn render<T: Serialize>(&self, context: &T) -> Result<String, String> {
+        self.registry
+            .render(TEMPLATE_NAME, context)
+            .map_err(|e| format!("Failed to render template: {}", e))
+    }
+}
+
+/// Template context of an options document.
+#[derive(Debug, Clone, Serialize)]
+pub struct OptionsContext {
+    pub title: String,
+    pub preamble: Option<String>,
+    /// Options in rendering order.
+    pub options: Vec<OptionContext>,
+    pub provenance: Provenance,
+    /// Rendered provenance sentence, as used by the built-in footer.
+    pub footer: Option<String>,
+}
+
+/// Template context of a single option. Values are formatted as they would
+/// be by the built-in layout.
+#[derive(Debug, Clone, Serialize)]
+pub struct OptionContext {
+    pub name: String,
+    /// Anchor identifier (e.g. `opt-services-nginx-enable`).
+    pub id: String,
+    pub loc: Vec<String>,
+    pub description: Option<String>,
+    /// First sentence of the description.
+    pub summary: Option<String>,
+    #[serde(rename = "type")]
+    pub option_type: Option<String>,
+    pub default: Option<String>,
+    pub example: Option<String>,
+    pub read_only: bool,
+    pub internal: bool,
+    /// False if the option is declared invisible.
+    pub visible: bool,
+    pub experimental: bool,
+    pub stability: Option<String>,
+    /// Related packages, rendered as markdown.
+    pub related_packages: Option<String>,
+    /// Module set the option comes from, if tagged.
+    pub origin: Option<String>,
+    /// Empty if declarations are disabled.
+    pub declarations: Vec<DeclarationContext>,
+}
+
+/// A declaration of an option, with its store prefix stripped.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeclarationContext {
+    pub name: String,
+    pub url: Option<String>,
+}
+
+impl OptionsContext {
+    pub fn new(
+        options: &OptionsMap,
+        title: &str,
+        preamble: Option<&str>,
+        opts: &RenderOptions,
+    ) -> Self {
+        let names = opts.sort.sorted_names(options);
+        let anchors = option_anchors(options, &opts.anchor_prefix);
+
+        let options = names
+            .into_iter()
+            .map(|name| {
+                let opt = &options[name];
+                let declarations = if opts.include_declarations {
+                    opt.declarations
+                        .iter()
+                        .map(|decl| {
+                            let (name, url) = declaration_link(decl, opts);
+                            DeclarationContext {
+                                name: name.to_string(),
+                                url,
+                            }
+                        })
+                        .collect()
+                } else {
+                    vec![]
+                };
+                OptionContext {
+                    name: name.clone(),
+                    id: anchors.get(name).unwrap_or_default().to_string(),
+                    loc: opt.loc.clone(),
+                    description: opt.description.as_ref().map(|d| d.as_str().to_string()),
+                    summary: opt.summary(),
+                    option_type: opt.option_type.clone(),
+                    default: opt.default.as_ref().map(|v| format_option_value(v, opts)),
+                    example: opt.example.as_ref().map(|v| format_option_value(v, opts)),
+                    read_only: opt.read_only,
+                    internal: opt.internal,
+                    visible: opt.flags().visible,
+                    experimental: opt.flags().experimental,
+                    stability: opt.stability.clone(),
+                    related_packages: opt
+                        .related_packages
+                        .as_ref()
+                        .map(|related| render_related_packages(related, opts)),
+                    origin: opt.origin.clone(),
+                    declarations,
+                }
+            })
+            .collect();
+
+        Self {
+            title: title.to_string(),
+            preamble: preamble.map(str::to_string),
+            options,
+            provenance: opts.provenance.clone(),
+            footer: opts.provenance.footer(),
+        }
+    }
+}
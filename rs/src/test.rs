@@ -1,11 +1,64 @@
-use rnix;
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::{
-    Args, ManualEntry, collect_entries, extract_file_doc, format::shift_headings, main_with_args,
-    options, retrieve_description,
+use crate::{cli_json, main_with_args, report_entry_problems, Args};
+use clap::{CommandFactory, Parser};
+use nixdoc::{
+    batch, collect_entries, collect_entries_with_duplicates, comment,
+    commonmark::{
+        disambiguate_identifiers, hash_anchor, sort_entries, write_entries, write_entries_to,
+        write_entries_with, EntryLabels, EntrySection, ManualEntry,
+    },
+    diagnostics::{Diagnostics, DiagnosticsFormat},
+    diff, extract_file_doc,
+    filedoc::{self, FileDoc},
+    format::{self, shift_headings},
+    glob::glob_match,
+    harness,
+    linkgraph::LinkGraph,
+    lint,
+    lint::LintKind,
+    locale::Locale,
+    options::{self, SortOrder},
+    read_source, retrieve_description,
+    template::{OptionsContext, Template},
+    xref, CollectOptions, Duplicate,
 };
+use rowan::ast::AstNode;
+
+mod admonition;
+mod arguments;
+mod asciidoc;
+mod attrpath;
+mod cache;
+mod compat;
+mod diagnostics;
+mod dialect;
+mod docbook;
+mod doctest;
+mod eval;
+mod examples;
+mod extract;
+mod html;
+mod inventory;
+mod linkcheck;
+mod locale;
+mod locs;
+mod man;
+mod mdbook;
+mod module;
+mod nixfmt;
+mod optiontypes;
+mod plugin;
+mod rst;
+mod search;
+mod serve;
+mod signature;
+mod slug;
+mod source;
+mod tagindex;
+mod tags;
 
 fn make_args(file: &str, category: &str, description: &str, locs: Option<&str>) -> Args {
     Args {
@@ -13,11 +66,56 @@ fn make_args(file: &str, category: &str, description: &str, locs: Option<&str>)
         prefix: String::from("lib"),
         anchor_prefix: String::from("function-library-"),
         json_output: false,
+        json_version: 1,
         category: category.to_string(),
         description: description.to_string(),
+        infer_category: false,
         file: Some(PathBuf::from(file)),
         locs: locs.map(PathBuf::from),
+        locations: false,
+        locs_format: String::from("nixpkgs"),
+        eval: false,
+        eval_scope: None,
+        default_since: None,
+        added_after: None,
+        tags: vec![],
+        plugins: vec![],
+        tag_templates: vec![],
+        type_links: None,
+        source_base_url: None,
+        revision: None,
         export: None,
+        include: vec![],
+        exclude: vec![],
+        wrapper: vec![],
+        depth: 0,
+        hash_anchors: false,
+        deprecated_appendix: false,
+        sort: String::from("source"),
+        summary_table: false,
+        follow_imports: false,
+        cache_dir: None,
+        source: None,
+        layout: vec![],
+        strict: false,
+        diagnostics_format: String::from("human"),
+        validate_links: false,
+        check_links: false,
+        link_timeout: 10,
+        locale: None,
+        strings: None,
+        default_example_language: None,
+        template: None,
+        output: None,
+        output_format: String::from("markdown"),
+        man_section: String::from("3"),
+        heading_offset: 0,
+        class_prefix: String::from("nixdoc-"),
+        admonition_style: None,
+        markdown_dialect: None,
+        category_heading_level: 1,
+        entry_heading_level: 2,
+        dump_cli_json: false,
     }
 }
 
@@ -42,11 +140,56 @@ fn test_main_minimal() {
         prefix: String::new(),
         anchor_prefix: String::new(),
         json_output: false,
+        json_version: 1,
         category: String::new(),
         description: String::new(),
+        infer_category: false,
         file: Some(PathBuf::from("test/strings.nix")),
         locs: Some(PathBuf::from("test/strings.json")),
+        locations: false,
+        locs_format: String::from("nixpkgs"),
+        eval: false,
+        eval_scope: None,
+        default_since: None,
+        added_after: None,
+        tags: vec![],
+        plugins: vec![],
+        tag_templates: vec![],
+        type_links: None,
+        source_base_url: None,
+        revision: None,
         export: None,
+        include: vec![],
+        exclude: vec![],
+        wrapper: vec![],
+        depth: 0,
+        hash_anchors: false,
+        deprecated_appendix: false,
+        sort: String::from("source"),
+        summary_table: false,
+        follow_imports: false,
+        cache_dir: None,
+        source: None,
+        layout: vec![],
+        strict: false,
+        diagnostics_format: String::from("human"),
+        validate_links: false,
+        check_links: false,
+        link_timeout: 10,
+        locale: None,
+        strings: None,
+        default_example_language: None,
+        template: None,
+        output: None,
+        output_format: String::from("markdown"),
+        man_section: String::from("3"),
+        heading_offset: 0,
+        class_prefix: String::from("nixdoc-"),
+        admonition_style: None,
+        markdown_dialect: None,
+        category_heading_level: 1,
+        entry_heading_level: 2,
+        dump_cli_json: false,
     };
 
     let output = main_with_args(&args);
@@ -61,11 +204,56 @@ fn test_json_output() {
         prefix: String::from("lib"),
         anchor_prefix: String::from("function-library-"),
         json_output: true,
+        json_version: 1,
         category: String::from("strings"),
         description: String::from("string manipulation functions"),
+        infer_category: false,
         file: Some(PathBuf::from("test/strings.nix")),
         locs: Some(PathBuf::from("test/strings.json")),
+        locations: false,
+        locs_format: String::from("nixpkgs"),
+        eval: false,
+        eval_scope: None,
+        default_since: None,
+        added_after: None,
+        tags: vec![],
+        plugins: vec![],
+        tag_templates: vec![],
+        type_links: None,
+        source_base_url: None,
+        revision: None,
         export: None,
+        include: vec![],
+        exclude: vec![],
+        wrapper: vec![],
+        depth: 0,
+        hash_anchors: false,
+        deprecated_appendix: false,
+        sort: String::from("source"),
+        summary_table: false,
+        follow_imports: false,
+        cache_dir: None,
+        source: None,
+        layout: vec![],
+        strict: false,
+        diagnostics_format: String::from("human"),
+        validate_links: false,
+        check_links: false,
+        link_timeout: 10,
+        locale: None,
+        strings: None,
+        default_example_language: None,
+        template: None,
+        output: None,
+        output_format: String::from("markdown"),
+        man_section: String::from("3"),
+        heading_offset: 0,
+        class_prefix: String::from("nixdoc-"),
+        admonition_style: None,
+        markdown_dialect: None,
+        category_heading_level: 1,
+        entry_heading_level: 2,
+        dump_cli_json: false,
     };
 
     let output = main_with_args(&args);
@@ -73,6 +261,47 @@ fn test_json_output() {
     insta::assert_snapshot!(output);
 }
 
+#[test]
+fn test_include_exclude() {
+    let mut args = make_args("test/strings.nix", "strings", "", None);
+    args.include = vec!["concat*".to_string()];
+    args.exclude = vec!["*Map*".to_string()];
+
+    let output = main_with_args(&args);
+
+    assert!(output.contains("lib.strings.concatStrings`"));
+    assert!(!output.contains("lib.strings.concatMapStrings`"));
+    assert!(!output.contains("lib.strings.hasPrefix`"));
+}
+
+#[test]
+fn test_infer_category() {
+    let mut args = make_args("test/infer-category.nix", "", "", None);
+    args.infer_category = true;
+
+    let output = main_with_args(&args);
+
+    assert!(output.starts_with(
+        "# Inferred category functions {#sec-functions-library-infer-category}\nThe category is named after this file.\n"
+    ));
+    assert!(output.contains("lib.infer-category.id"));
+
+    args.description = String::from("Overridden");
+    assert!(
+        main_with_args(&args).starts_with("# Overridden {#sec-functions-library-infer-category}\n")
+    );
+}
+
+#[test]
+fn test_glob_match() {
+    assert!(glob_match("*", ""));
+    assert!(glob_match("concat*", "concatMapStrings"));
+    assert!(glob_match("*Map*", "concatMapStrings"));
+    assert!(glob_match("map?", "mapA"));
+    assert!(!glob_match("map?", "map"));
+    assert!(!glob_match("concat*Sep", "concatMapStrings"));
+}
+
 #[test]
 fn test_arg_formatting() {
     let mut output = String::from("");
@@ -81,7 +310,20 @@ fn test_arg_formatting() {
     let prefix = "lib";
     let category = "options";
 
-    for entry in collect_entries(nix, prefix, category, &Default::default(), &None) {
+    for entry in collect_entries(nix, &CollectOptions::new(prefix, category)) {
+        entry.write_section("function-library-", &mut output);
+    }
+
+    insta::assert_snapshot!(output);
+}
+
+#[test]
+fn test_pattern_args() {
+    let mut output = String::from("");
+    let src = fs::read_to_string("test/pattern-args.nix").unwrap();
+    let nix = rnix::Root::parse(&src).ok().expect("failed to parse input");
+
+    for entry in collect_entries(nix, &CollectOptions::new("lib", "strings")) {
         entry.write_section("function-library-", &mut output);
     }
 
@@ -96,7 +338,7 @@ fn test_inherited_exports() {
     let prefix = "lib";
     let category = "let";
 
-    for entry in collect_entries(nix, prefix, category, &Default::default(), &None) {
+    for entry in collect_entries(nix, &CollectOptions::new(prefix, category)) {
         entry.write_section("function-library-", &mut output);
     }
 
@@ -111,7 +353,7 @@ fn test_doc_comment() {
     let prefix = "lib";
     let category = "debug";
 
-    for entry in collect_entries(nix, prefix, category, &Default::default(), &None) {
+    for entry in collect_entries(nix, &CollectOptions::new(prefix, category)) {
         entry.write_section("function-library-", &mut output);
     }
 
@@ -136,16 +378,85 @@ fn test_headings() {
     insta::assert_snapshot!(output);
 }
 
+#[test]
+fn test_sort_entries() {
+    let src = fs::read_to_string("test/sections.nix").unwrap();
+    let mut entries =
+        nixdoc::collect_entries_from_str(&src, &CollectOptions::new("lib", "sections")).unwrap();
+    let names = |entries: &[ManualEntry]| -> Vec<String> {
+        entries.iter().map(|e| e.name.clone()).collect()
+    };
+
+    sort_entries(&mut entries, SortOrder::Source);
+    assert_eq!(
+        names(&entries),
+        ["first", "assertMsg", "assertOneOf", "concat"]
+    );
+    // Entries stay in their sections.
+    entries.reverse();
+    sort_entries(&mut entries, SortOrder::Alphabetical);
+    assert_eq!(
+        names(&entries),
+        ["concat", "assertMsg", "assertOneOf", "first"]
+    );
+}
+
+#[test]
+fn test_summary() {
+    assert_eq!(
+        format::summary("Join strings, e.g. `a. b` or\n`c`, by `.`. More.").as_deref(),
+        Some("Join strings, e.g. `a. b` or `c`, by `.`.")
+    );
+    assert_eq!(
+        format::summary("# Title\n\n::: {.note}\nA note.\n:::\n\nNo sentence end").as_deref(),
+        Some("No sentence end")
+    );
+    assert_eq!(format::summary("- a list.\n\n```\ncode.\n```"), None);
+}
+
+#[test]
+fn test_shift_headings_ignores_code_and_html() {
+    let src =
+        "# Title\n\n```\n# comment\n```\n\n    # indented\n\n<div>\n# raw\n</div>\n\n> ## Quoted\n";
+    assert_eq!(
+        shift_headings(src, 1),
+        "## Title\n\n```\n# comment\n```\n\n    # indented\n\n<div>\n# raw\n</div>\n\n> ### Quoted\n"
+    );
+}
+
+#[test]
+fn test_shift_setext_headings() {
+    let src = "Title\n=====\n\nA multi-line\nsubtitle {#sub}\n---\n\nC#\n==\n\n> Quoted\n> ---\n\n```\nnot a heading\n---\n```\n\nText\n\n---\n";
+    assert_eq!(
+        shift_headings(src, 2),
+        "### Title\n\n#### A multi-line subtitle {#sub}\n\n### C# #\n\n> #### Quoted\n\n```\nnot a heading\n---\n```\n\nText\n\n---\n"
+    );
+    assert_eq!(shift_headings(src, 0), src);
+}
+
+#[test]
+fn test_heading_levels() {
+    let src = "# Strings {#sec-strings}\n\n## `concat` {#concat}\n\n### Inputs\n\n```\n# comment\n```\n\n###### Deep\n";
+    let levels = format::HeadingLevels::new(2, 4).unwrap();
+    assert_eq!(
+        levels.apply(src),
+        "## Strings {#sec-strings}\n\n#### `concat` {#concat}\n\n##### Inputs\n\n```\n# comment\n```\n\n###### Deep\n"
+    );
+    assert_eq!(format::HeadingLevels::default().apply(src), src);
+    assert!(format::HeadingLevels::new(3, 3).is_err());
+    assert!(format::HeadingLevels::new(1, 7).is_err());
+}
+
 #[test]
 fn test_doc_comment_section_description() {
     let src = fs::read_to_string("test/doc-comment-sec-heading.nix").unwrap();
     let nix = rnix::Root::parse(&src).ok().expect("failed to parse input");
     let prefix = "lib";
     let category = "debug";
-    let desc = retrieve_description(&nix, &"Debug", category);
-    let mut output = String::from(desc) + "\n";
+    let desc = retrieve_description(&nix, "Debug", category);
+    let mut output = desc + "\n";
 
-    for entry in collect_entries(nix, prefix, category, &Default::default(), &None) {
+    for entry in collect_entries(nix, &CollectOptions::new(prefix, category)) {
         entry.write_section("function-library-", &mut output);
     }
 
@@ -158,27 +469,126 @@ fn test_doc_comment_no_duplicate_arguments() {
     let nix = rnix::Root::parse(&src).ok().expect("failed to parse input");
     let prefix = "lib";
     let category = "debug";
-    let desc = retrieve_description(&nix, &"Debug", category);
-    let mut output = String::from(desc) + "\n";
+    let desc = retrieve_description(&nix, "Debug", category);
+    let mut output = desc + "\n";
 
-    for entry in collect_entries(nix, prefix, category, &Default::default(), &None) {
+    for entry in collect_entries(nix, &CollectOptions::new(prefix, category)) {
         entry.write_section("function-library-", &mut output);
     }
 
     insta::assert_snapshot!(output);
 }
 
+#[test]
+fn test_entry_layout() {
+    let source = r#"
+      {
+        /** Add one. */
+        inc = x: x + 1;
+      }
+    "#;
+    let entries = nixdoc::collect_entries_from_str(
+        source,
+        &CollectOptions {
+            file: Some("inc.nix".to_string()),
+            line_locations: true,
+            ..CollectOptions::new("lib", "trivial")
+        },
+    )
+    .unwrap();
+    let layout = EntrySection::parse_layout(&["location", "description"]).unwrap();
+
+    let mut output = String::new();
+    write_entries_with(
+        entries.clone(),
+        "function-library-",
+        &layout,
+        &EntryLabels::default(),
+        &mut output,
+    );
+    let mut written = vec![];
+    write_entries_to(
+        entries,
+        "function-library-",
+        &layout,
+        &EntryLabels::default(),
+        &mut written,
+    )
+    .unwrap();
+    assert_eq!(String::from_utf8(written).unwrap(), output);
+
+    assert_eq!(
+        output,
+        "## `lib.trivial.inc` {#function-library-lib.trivial.inc}\n\n\
+         Located at `inc.nix:4`.\n\n\
+         Add one.\n\n"
+    );
+    assert!(EntrySection::parse_layout(&["examples"]).is_err());
+}
+
+#[test]
+fn test_locale() {
+    let source = r#"
+      {
+        /** The answer. */
+        answer = 42;
+      }
+    "#;
+    let entries = nixdoc::collect_entries_from_str(
+        source,
+        &CollectOptions {
+            file: Some("answer.nix".to_string()),
+            line_locations: true,
+            ..CollectOptions::new("lib", "trivial")
+        },
+    )
+    .unwrap();
+    let mut labels = Locale::German.entry_labels();
+    labels
+        .set("located-at", "Zu finden in {location}.")
+        .unwrap();
+    let layout = EntrySection::parse_layout(&["value", "location"]).unwrap();
+
+    let mut output = String::new();
+    write_entries_with(entries, "function-library-", &layout, &labels, &mut output);
+    assert_eq!(
+        output,
+        "## `lib.trivial.answer` {#function-library-lib.trivial.answer}\n\n\
+         **Wert:** `42`\n\n\
+         Zu finden in `answer.nix:4`.\n\n"
+    );
+}
+
 #[test]
 fn test_empty_prefix() {
     let test_entry = ManualEntry {
         args: vec![],
+        constant: None,
+        arguments: vec![],
+        throws: vec![],
         category: "test".to_string(),
         location: None,
         description: vec![],
         example: None,
+        summary: None,
+        examples: vec![],
         fn_type: None,
+        signature: None,
         name: "mapSimple'".to_string(),
+        ident: None,
         prefix: "".to_string(),
+        maintainers: vec![],
+        keywords: vec![],
+        since: None,
+        deprecated: None,
+        stability: None,
+        fields: Default::default(),
+        rendered_fields: vec![],
+        section: None,
+        hash_anchor: None,
+        span: None,
+        source_url: None,
+        raw_doc: None,
     };
 
     let (ident, title) = test_entry.get_ident_title();
@@ -195,11 +605,103 @@ fn test_patterns() {
     let prefix = "lib";
     let category = "debug";
 
-    for entry in collect_entries(nix, prefix, category, &Default::default(), &None) {
+    for entry in collect_entries(nix, &CollectOptions::new(prefix, category)) {
+        entry.write_section("function-library-", &mut output);
+    }
+
+    insta::assert_snapshot!(output);
+}
+
+#[test]
+fn test_meta_attributes() {
+    let mut output = String::from("");
+    let src = fs::read_to_string("test/meta.nix").unwrap();
+    let nix = rnix::Root::parse(&src).ok().expect("failed to parse input");
+    let prefix = "lib";
+    let category = "meta";
+
+    for entry in collect_entries(nix, &CollectOptions::new(prefix, category)) {
+        entry.write_section("function-library-", &mut output);
+    }
+
+    insta::assert_snapshot!(output);
+}
+
+#[test]
+fn test_stability_tags() {
+    let src = fs::read_to_string("test/stability.nix").unwrap();
+    let nix = rnix::Root::parse(&src).ok().expect("failed to parse input");
+
+    let entries = collect_entries(nix, &CollectOptions::new("lib", "stability"));
+    let json = serde_json::to_string_pretty(&entries).unwrap();
+    let mut output = String::new();
+    for entry in entries {
+        entry.write_section("function-library-", &mut output);
+    }
+
+    insta::assert_snapshot!(json + "\n\n" + &output);
+}
+
+#[test]
+fn test_constants() {
+    let src = fs::read_to_string("test/constants.nix").unwrap();
+    let nix = rnix::Root::parse(&src).ok().expect("failed to parse input");
+
+    let entries = collect_entries(nix, &CollectOptions::new("lib", "constants"));
+    let mut output = String::new();
+    for entry in entries {
+        entry.write_section("function-library-", &mut output);
+    }
+
+    insta::assert_snapshot!(output);
+}
+
+#[test]
+fn test_since() {
+    let src = fs::read_to_string("test/since.nix").unwrap();
+    let nix = rnix::Root::parse(&src).ok().expect("failed to parse input");
+
+    let entries = collect_entries(nix, &CollectOptions::new("lib", "since"));
+    let mut output = String::new();
+    for entry in entries {
+        entry.write_section("function-library-", &mut output);
+    }
+
+    insta::assert_snapshot!(output);
+}
+
+#[test]
+fn test_argument_comments() {
+    let mut output = String::from("");
+    let src = fs::read_to_string("test/argument-comments.nix").unwrap();
+    let nix = rnix::Root::parse(&src).ok().expect("failed to parse input");
+
+    for entry in collect_entries(nix, &CollectOptions::new("lib", "arguments")) {
+        entry.write_section("function-library-", &mut output);
+    }
+
+    insta::assert_snapshot!(output);
+}
+
+#[test]
+fn test_functor() {
+    let mut output = String::from("");
+    let src = fs::read_to_string("test/functor.nix").unwrap();
+    let nix = rnix::Root::parse(&src).ok().expect("failed to parse input");
+    let opts = CollectOptions {
+        depth: 1,
+        ..CollectOptions::new("lib", "functor")
+    };
+
+    for entry in collect_entries(nix, &opts) {
         entry.write_section("function-library-", &mut output);
     }
 
     insta::assert_snapshot!(output);
+
+    let diagnostics = lint::lint_source(&src, &Default::default()).unwrap();
+    let names: Vec<&str> = diagnostics.iter().map(|d| d.name.as_str()).collect();
+    assert_eq!(names, vec!["undocumented", "nested"]);
 }
 
 #[test]
@@ -210,7 +712,7 @@ fn test_let_ident() {
     let prefix = "lib";
     let category = "math";
 
-    for entry in collect_entries(nix, prefix, category, &Default::default(), &None) {
+    for entry in collect_entries(nix, &CollectOptions::new(prefix, category)) {
         entry.write_section("function-library-", &mut output);
     }
 
@@ -225,13 +727,39 @@ fn test_let_ident_chained() {
     let prefix = "lib";
     let category = "math";
 
-    for entry in collect_entries(nix, prefix, category, &Default::default(), &None) {
+    for entry in collect_entries(nix, &CollectOptions::new(prefix, category)) {
         entry.write_section("function-library-", &mut output);
     }
 
     insta::assert_snapshot!(output);
 }
 
+#[test]
+fn test_assert_with() {
+    let names = |src: &str| -> Vec<String> {
+        let entries =
+            nixdoc::collect_entries_from_str(src, &CollectOptions::new("lib", "scopes")).unwrap();
+        entries.into_iter().map(|entry| entry.name).collect()
+    };
+    let sources = [
+        "let check = true; in assert check; { /** Documented. */ foo = x: x; }",
+        "{ lib }: with lib; { /** Documented. */ foo = x: x; }",
+        "with (import ./lib.nix { }); { /** Documented. */ foo = x: x; }",
+        "assert builtins.isAttrs { }; with { }; { /** Documented. */ foo = x: x; }",
+        "let self = with lib; { /** Documented. */ foo = x: x; }; in assert true; self",
+        "assert (let a = 1; in a == 1); { /** Documented. */ foo = x: x; }",
+    ];
+    for src in sources {
+        assert_eq!(names(src), ["foo"], "{}", src);
+    }
+
+    let src = "with (import ./lib.nix { }); { /** Documented. */ foo = x: x; bar = 1; }";
+    let nix = rnix::Root::parse(src).ok().unwrap();
+    let coverage = lint::coverage_source(src, &None).unwrap();
+    assert_eq!((coverage.total, coverage.documented), (2, 1));
+    assert!(nixdoc::unresolved_inherits(&nix, &CollectOptions::new("lib", "scopes")).is_empty());
+}
+
 #[test]
 fn test_export_flag() {
     let mut output = String::from("");
@@ -241,12 +769,15 @@ fn test_export_flag() {
     let category = "export";
 
     // With --export, we specify which let bindings to document
-    let export_list = Some(vec![
-        "exportedFunc".to_string(),
-        "anotherExported".to_string(),
-    ]);
+    let opts = CollectOptions {
+        export: Some(vec![
+            "exportedFunc".to_string(),
+            "anotherExported".to_string(),
+        ]),
+        ..CollectOptions::new(prefix, category)
+    };
 
-    for entry in collect_entries(nix, prefix, category, &Default::default(), &export_list) {
+    for entry in collect_entries(nix, &opts) {
         entry.write_section("function-library-", &mut output);
     }
 
@@ -263,6 +794,7 @@ fn test_options_rendering() {
         include_declarations: true,
         declarations_base_url: Some("https://github.com/example/repo".to_string()),
         revision: Some("main".to_string()),
+        ..Default::default()
     };
 
     let output = options::render_options_document(
@@ -275,6 +807,24 @@ fn test_options_rendering() {
     insta::assert_snapshot!(output);
 }
 
+#[test]
+fn test_module_options() {
+    let src = fs::read_to_string("test/module.nix").unwrap();
+    let nix = rnix::Root::parse(&src).ok().unwrap();
+    let mut declared = nixdoc::module::module_options(&nix, "test/module.nix");
+    assert_eq!(declared.unknown, vec!["services.web.extraConfig"]);
+
+    options::filter_options(&mut declared.options, &Default::default());
+    let output = options::render_options_document(
+        &declared.options,
+        "Module Options",
+        None,
+        &Default::default(),
+    );
+
+    insta::assert_snapshot!(output);
+}
+
 #[test]
 fn test_file_doc_extraction() {
     // Test file-level doc comment extraction
@@ -290,8 +840,1021 @@ fn test_file_doc_extraction() {
 fn test_file_doc_no_doc() {
     // A file without a file-level doc comment
     let src = "{ foo = 1; }";
-    let nix = rnix::Root::parse(&src).ok().expect("failed to parse input");
+    let nix = rnix::Root::parse(src).ok().expect("failed to parse input");
 
     let doc = extract_file_doc(&nix);
     assert!(doc.is_none());
 }
+
+#[test]
+fn test_file_doc_structure() {
+    let doc = "# Strings\n\nString manipulation functions.\n\n```nix\n# not a heading\n\nfoo\n```\n\n## Details\n\nMore text.";
+
+    let parts = FileDoc::from_doc(doc);
+    assert_eq!(parts.title.as_deref(), Some("Strings"));
+    assert_eq!(
+        parts.summary.as_deref(),
+        Some("String manipulation functions.")
+    );
+    assert_eq!(
+        parts.body.as_deref(),
+        Some("```nix\n# not a heading\n\nfoo\n```\n\n## Details\n\nMore text.")
+    );
+
+    let untitled = FileDoc::from_doc("Just a summary.");
+    assert_eq!(untitled.title, None);
+    assert_eq!(untitled.summary.as_deref(), Some("Just a summary."));
+    assert_eq!(untitled.body, None);
+}
+
+#[test]
+fn test_corpus() {
+    let renderer = harness::MarkdownRenderer::default();
+    let report = harness::run_corpus("test/corpus".as_ref(), &renderer).unwrap();
+
+    assert_eq!(report.results.len(), 2);
+    assert!(report.is_success(), "{}", report.summary());
+}
+
+#[test]
+fn test_link_graph() {
+    let documents = vec![
+        (
+            "index.md".to_string(),
+            "# Index {#index}\n\nSee [strings](lib/strings.md#strings-concat) and [below](#index).\n"
+                .to_string(),
+        ),
+        (
+            "lib/strings.md".to_string(),
+            "## `concat` {#strings-concat}\n\n```\n{#not-an-anchor}\n```\n\n[Back](../index.md) [gone](../missing.md)\n"
+                .to_string(),
+        ),
+        ("orphan.md".to_string(), "# Orphan {#orphan}\n".to_string()),
+    ];
+
+    let graph = LinkGraph::build(&documents);
+
+    assert_eq!(graph.documents[1].anchors, vec!["strings-concat"]);
+    let links: Vec<(&str, &str, Option<&str>, bool, bool)> = graph
+        .links
+        .iter()
+        .map(|l| {
+            (
+                l.from.as_str(),
+                l.to.as_str(),
+                l.anchor.as_deref(),
+                l.internal,
+                l.resolved,
+            )
+        })
+        .collect();
+    assert_eq!(
+        links,
+        vec![
+            (
+                "index.md",
+                "lib/strings.md",
+                Some("strings-concat"),
+                false,
+                true
+            ),
+            ("index.md", "index.md", Some("index"), true, true),
+            ("lib/strings.md", "index.md", None, false, true),
+            ("lib/strings.md", "missing.md", None, false, false),
+        ]
+    );
+    assert_eq!(graph.orphans, vec!["orphan.md"]);
+    assert!(graph
+        .to_dot()
+        .contains("\"lib/strings.md\" -> \"missing.md\" [style=dashed];"));
+
+    // Decomposed accents are kept, not escaped as `\u{301}`.
+    let quoted = LinkGraph::build(&[("cafe\u{301} \"a\\b\".md".to_string(), String::new())]);
+    assert_eq!(
+        quoted.to_dot(),
+        "digraph manual {\n  \"cafe\u{301} \\\"a\\\\b\\\".md\" [label=\"cafe\u{301} \\\"a\\\\b\\\".md (0 anchors)\"];\n}\n"
+    );
+}
+
+#[test]
+fn test_batch() {
+    let inputs = batch::expand_inputs(&[PathBuf::from("test/corpus/*.nix")]).unwrap();
+    assert_eq!(
+        inputs,
+        vec![
+            PathBuf::from("test/corpus/lists.nix"),
+            PathBuf::from("test/corpus/trivial.nix")
+        ]
+    );
+    let overlapping = batch::expand_inputs(&[
+        PathBuf::from("test/corpus/trivial.nix"),
+        PathBuf::from("test/corpus/*.nix"),
+    ])
+    .unwrap();
+    assert_eq!(
+        overlapping,
+        vec![
+            PathBuf::from("test/corpus/trivial.nix"),
+            PathBuf::from("test/corpus/lists.nix")
+        ]
+    );
+
+    let outputs = batch::render_batch(&inputs, &batch::BatchOptions::default()).unwrap();
+    let categories: Vec<&str> = outputs.iter().map(|o| o.category.as_str()).collect();
+    assert_eq!(categories, vec!["lists", "trivial"]);
+    assert_eq!(
+        outputs[1].markdown,
+        fs::read_to_string("test/corpus/trivial.md").unwrap()
+    );
+}
+
+#[test]
+fn test_batch_category_collision() {
+    let dir = std::env::temp_dir().join(format!("nixdoc-batch-stems-{}", std::process::id()));
+    for sub in ["a", "b"] {
+        fs::create_dir_all(dir.join(sub)).unwrap();
+        fs::write(
+            dir.join(sub).join("default.nix"),
+            format!("{{ /** From {}. */ f{} = x: x; }}", sub, sub),
+        )
+        .unwrap();
+    }
+
+    let inputs = batch::expand_inputs(std::slice::from_ref(&dir)).unwrap();
+    assert_eq!(inputs.len(), 2);
+    let err = batch::render_batch(&inputs, &batch::BatchOptions::default()).unwrap_err();
+    assert!(err.ends_with("would both be documented as category 'default'"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_batch_index() {
+    let inputs = batch::expand_inputs(&[PathBuf::from("test/corpus/*.nix")]).unwrap();
+    let opts = batch::BatchOptions::default();
+    let outputs = batch::render_batch(&inputs, &opts).unwrap();
+
+    let index = batch::render_index(&outputs, &opts, true);
+    assert!(index.starts_with("# All functions {#sec-functions-library-lib-all}\n\n## C\n\n"));
+    assert!(index.contains(
+        "## H\n\n- [`lib.lists.head`](lists.md#function-library-lib.lists.head): Return the first element of a list.\n"
+    ));
+    let names: Vec<&str> = index
+        .lines()
+        .filter_map(|line| line.strip_prefix("- [`")?.split('`').next())
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            "lib.trivial.const",
+            "lib.lists.head",
+            "lib.trivial.id",
+            "lib.lists.map"
+        ]
+    );
+
+    let combined = batch::render_index(&outputs, &opts, false);
+    assert!(combined.contains("(#function-library-lib.trivial.id)"));
+}
+
+#[test]
+fn test_batch_cache() {
+    let dir = std::env::temp_dir().join(format!("nixdoc-cache-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let inputs = vec![PathBuf::from("test/corpus/trivial.nix")];
+    let opts = batch::BatchOptions {
+        cache_dir: Some(dir.clone()),
+        ..Default::default()
+    };
+
+    let first = batch::render_batch(&inputs, &opts).unwrap();
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    let second = batch::render_batch(&inputs, &opts).unwrap();
+    assert_eq!(first[0].markdown, second[0].markdown);
+    assert_eq!(
+        second[0].markdown,
+        fs::read_to_string("test/corpus/trivial.md").unwrap()
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_batch_references() {
+    let dir = std::env::temp_dir().join(format!("nixdoc-batch-xref-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("strings.nix"),
+        "{ /** Joins strings, see [](#lib.lists.concat). */ join = x: x; }",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("lists.nix"),
+        "{ /** Concatenates lists, unlike {ref}`lib.lists.missing`. */ concat = x: x; }",
+    )
+    .unwrap();
+
+    let inputs = batch::expand_inputs(std::slice::from_ref(&dir)).unwrap();
+    let outputs = batch::render_batch(&inputs, &batch::BatchOptions::default()).unwrap();
+    let strings = outputs.iter().find(|o| o.category == "strings").unwrap();
+    assert!(strings
+        .markdown
+        .contains("[`lib.lists.concat`](#function-library-lib.lists.concat)"));
+    assert!(strings.dangling.is_empty());
+    let lists = outputs.iter().find(|o| o.category == "lists").unwrap();
+    assert_eq!(lists.dangling[0].target, "lib.lists.missing");
+
+    // Written to a page per category, references link to the other page.
+    let opts = batch::BatchOptions {
+        page_extension: Some("md".to_string()),
+        ..Default::default()
+    };
+    let outputs = batch::render_batch(&inputs, &opts).unwrap();
+    let strings = outputs.iter().find(|o| o.category == "strings").unwrap();
+    assert!(strings
+        .markdown
+        .contains("[`lib.lists.concat`](lists.md#function-library-lib.lists.concat)"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_collect_entries_from_str() {
+    let source = r#"
+      {
+        /** Add one. */
+        inc = x: x + 1;
+
+        /** Subtract one. */
+        dec = x: x - 1;
+      }
+    "#;
+    let opts = CollectOptions {
+        exclude: vec!["dec".to_string()],
+        ..CollectOptions::new("lib", "math")
+    };
+
+    let entries = nixdoc::collect_entries_from_str(source, &opts).unwrap();
+    let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["inc"]);
+    assert_eq!(entries[0].description, vec!["Add one."]);
+
+    assert!(nixdoc::collect_entries_from_str("{ broken", &opts).is_err());
+}
+
+#[test]
+fn test_throws() {
+    let source = r#"
+      {
+        /**
+          Take the first element of a list.
+
+          # Throws
+
+          - If `list` is empty.
+        */
+        head = list: builtins.head list;
+      }
+    "#;
+    let entries =
+        nixdoc::collect_entries_from_str(source, &CollectOptions::new("lib", "lists")).unwrap();
+    assert_eq!(entries[0].throws, vec!["If `list` is empty."]);
+
+    let json = serde_json::to_value(nixdoc::jsonv2::Entry::from_manual_entry(
+        entries[0].clone(),
+        "function-library-",
+    ))
+    .unwrap();
+    assert_eq!(json["throws"], serde_json::json!(["If `list` is empty."]));
+
+    let mut output = String::new();
+    entries[0]
+        .clone()
+        .write_section("function-library-", &mut output);
+    assert!(output.contains("**Throws:**\n\n- If `list` is empty.\n\n"));
+}
+
+#[test]
+fn test_render_options_from_str() {
+    let json = r#"{ "test.enable": { "type": "boolean", "description": "Enable." } }"#;
+
+    let output =
+        options::render_options_from_str(json, "Options", None, &options::RenderOptions::default())
+            .unwrap();
+
+    assert!(output.starts_with("# Options\n\n## `test.enable` {#opt-test-enable}"));
+    assert!(options::render_options_from_str("{", "Options", None, &Default::default()).is_err());
+}
+
+#[test]
+fn test_wrapper_functions() {
+    let mut output = String::from("");
+    let src = fs::read_to_string("test/wrappers.nix").unwrap();
+    let nix = rnix::Root::parse(&src).ok().expect("failed to parse input");
+    let opts = CollectOptions {
+        wrappers: vec!["lib.flip".to_string(), "*memoize".to_string()],
+        ..CollectOptions::new("lib", "wrappers")
+    };
+
+    for entry in collect_entries(nix, &opts) {
+        entry.write_section("function-library-", &mut output);
+    }
+
+    insta::assert_snapshot!(output);
+}
+
+#[test]
+fn test_nested_attrsets() {
+    let src = fs::read_to_string("test/nested.nix").unwrap();
+    let collect = |depth| {
+        let nix = rnix::Root::parse(&src).ok().expect("failed to parse input");
+        let opts = CollectOptions {
+            depth,
+            ..CollectOptions::new("lib", "")
+        };
+        collect_entries(nix, &opts)
+            .into_iter()
+            .map(|e| e.name)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(collect(0), vec!["strings"]);
+    assert_eq!(collect(1), vec!["strings", "strings.concat", "lists.map"]);
+    assert_eq!(
+        collect(2),
+        vec![
+            "strings",
+            "strings.concat",
+            "lists.map",
+            "lists.unsafe.head"
+        ]
+    );
+
+    let mut output = String::from("");
+    let nix = rnix::Root::parse(&src).ok().expect("failed to parse input");
+    let opts = CollectOptions {
+        depth: 2,
+        ..CollectOptions::new("lib", "")
+    };
+    for entry in collect_entries(nix, &opts) {
+        entry.write_section("function-library-", &mut output);
+    }
+    insta::assert_snapshot!(output);
+}
+
+#[test]
+fn test_lint() {
+    let src = fs::read_to_string("test/lint.nix").unwrap();
+    let diagnostics = lint::lint_source(&src, &Default::default()).unwrap();
+
+    let found: Vec<(&str, usize, LintKind)> = diagnostics
+        .iter()
+        .map(|d| (d.name.as_str(), d.line, d.kind))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("undocumented", 7, LintKind::MissingDoc),
+            ("empty", 10, LintKind::EmptyDoc),
+            ("legacy", 13, LintKind::LegacyComment),
+            ("legacyBlock", 16, LintKind::LegacyComment),
+        ]
+    );
+    assert_eq!(diagnostics[0].column, 3);
+    assert_eq!(
+        serde_json::to_value(&diagnostics[2]).unwrap()["kind"],
+        "legacy-comment"
+    );
+}
+
+#[test]
+fn test_coverage() {
+    let src = fs::read_to_string("test/lint.nix").unwrap();
+    let coverage = lint::coverage_source(&src, &None).unwrap();
+
+    assert_eq!(
+        coverage,
+        lint::Coverage {
+            total: 5,
+            documented: 3,
+            undocumented: 2,
+            legacy: 2,
+            rfc145: 1,
+        }
+    );
+    assert_eq!(coverage.percent(), 60.0);
+    assert_eq!(lint::Coverage::default().percent(), 100.0);
+    assert_eq!(
+        lint::coverage_table(&[("lint.nix".to_string(), coverage)], &coverage),
+        "\
+File      Total  Documented  Undocumented  Legacy  RFC145  Coverage
+lint.nix      5           3             2       2       1     60.0%
+total         5           3             2       2       1     60.0%
+"
+    );
+}
+
+#[test]
+fn test_lint_update() {
+    let src = "{ /** A. */ a = x: x; } // { b = x: x; }";
+
+    let diagnostics = lint::lint_source(src, &Default::default()).unwrap();
+    let found: Vec<(&str, LintKind)> = diagnostics
+        .iter()
+        .map(|d| (d.name.as_str(), d.kind))
+        .collect();
+    assert_eq!(found, vec![("b", LintKind::MissingDoc)]);
+
+    let coverage = lint::coverage_source(src, &None).unwrap();
+    assert_eq!(
+        coverage,
+        lint::Coverage {
+            total: 2,
+            documented: 1,
+            undocumented: 1,
+            legacy: 0,
+            rfc145: 1,
+        }
+    );
+    assert_eq!(coverage.percent(), 50.0);
+}
+
+#[test]
+fn test_ignore_marker() {
+    let src = fs::read_to_string("test/ignore.nix").unwrap();
+    let opts = CollectOptions {
+        depth: 1,
+        ..CollectOptions::new("lib", "ignore")
+    };
+
+    let names: Vec<String> = nixdoc::collect_entries_from_str(&src, &opts)
+        .unwrap()
+        .into_iter()
+        .map(|e| e.name)
+        .collect();
+    assert_eq!(names, vec!["inc", "strings.concat", "dec2"]);
+
+    let linted: Vec<String> = lint::lint_source(&src, &Default::default())
+        .unwrap()
+        .into_iter()
+        .map(|d| d.name)
+        .collect();
+    assert_eq!(linted, vec!["strings", "dec"]);
+}
+
+#[test]
+fn test_lint_allow() {
+    let src = fs::read_to_string("test/lint-allow.nix").unwrap();
+    let names = |opts: &lint::LintOptions| {
+        lint::lint_source(&src, opts)
+            .unwrap()
+            .into_iter()
+            .map(|d| (d.name, d.kind))
+            .collect::<Vec<_>>()
+    };
+
+    let strict = lint::LintOptions {
+        strict: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        names(&strict),
+        vec![
+            ("noExample".to_string(), LintKind::MissingExample),
+            ("legacy".to_string(), LintKind::LegacyComment),
+        ]
+    );
+    assert!(names(&Default::default()).contains(&("legacy".to_string(), LintKind::LegacyComment)));
+
+    let configured = lint::LintOptions {
+        allow: vec![lint::Allow {
+            pattern: "leg*".to_string(),
+            kind: LintKind::LegacyComment,
+        }],
+        ..strict
+    };
+    assert_eq!(
+        names(&configured),
+        vec![("noExample".to_string(), LintKind::MissingExample)]
+    );
+}
+
+#[test]
+fn test_diff_json() {
+    let old = r#"{"version":1,"entries":[
+        {"prefix":"lib","category":"strings","location":null,"name":"concat","fn_type":null,"description":["Concatenate."],"example":null,"args":[]},
+        {"prefix":"lib","category":"strings","location":null,"name":"old","fn_type":null,"description":[],"example":null,"args":[]}
+    ]}"#;
+    let new = r#"{"version":1,"entries":[
+        {"prefix":"lib","category":"strings","location":null,"name":"concat","fn_type":null,"description":["Concatenate two strings."],"example":null,"args":[],"since":"24.05"},
+        {"prefix":"lib","category":"strings","location":null,"name":"new","fn_type":null,"description":[],"example":null,"args":[]}
+    ]}"#;
+
+    let changeset = diff::diff_json(
+        &serde_json::from_str(old).unwrap(),
+        &serde_json::from_str(new).unwrap(),
+    );
+
+    assert_eq!(changeset.added, vec!["lib.strings.new"]);
+    assert_eq!(changeset.removed, vec!["lib.strings.old"]);
+    assert_eq!(changeset.changed.len(), 1);
+    let fields: Vec<&str> = changeset.changed[0]
+        .fields
+        .iter()
+        .map(|f| f.field.as_str())
+        .collect();
+    assert_eq!(fields, vec!["description", "since"]);
+    assert_eq!(changeset.changed[0].fields[1].old, serde_json::Value::Null);
+    assert_eq!(
+        changeset.summary(),
+        "+ lib.strings.new\n- lib.strings.old\n~ lib.strings.concat: description, since\n"
+    );
+}
+
+#[test]
+fn test_diff_options() {
+    let old = options::parse_options_json(
+        r#"{
+        "services.foo.enable": {"type": "boolean", "default": false, "description": "Whether to enable foo."},
+        "services.foo.port": {"type": "signed integer", "default": 80, "description": "Port."},
+        "services.foo.user": {"type": "string", "description": "User."},
+        "services.foo.internal": {"type": "string", "internal": true}
+    }"#,
+    )
+    .unwrap();
+    let new = options::parse_options_json(
+        r#"{
+        "services.foo.enable": {"type": "boolean", "default": false, "description": "Whether to enable foo."},
+        "services.foo.port": {"type": "16 bit unsigned integer; between 0 and 65535 (both inclusive)", "default": {"_type": "literalExpression", "text": "8080"}, "description": "Port to listen on."},
+        "services.foo.group": {"type": "string", "description": "Group."}
+    }"#,
+    )
+    .unwrap();
+
+    let changeset = diff::diff_options(&old, &new);
+    assert_eq!(changeset.added, vec!["services.foo.group"]);
+    assert_eq!(changeset.removed, vec!["services.foo.user"]);
+    assert_eq!(
+        diff::render_option_changes(&changeset),
+        "## Added options\n\n- `services.foo.group`\n\n\
+         ## Removed options\n\n- `services.foo.user`\n\n\
+         ## Changed options\n\n\
+         - `services.foo.port`: type changed from `signed integer` to `16 bit unsigned integer; between 0 and 65535 (both inclusive)`; default changed from `80` to `8080`; description changed\n"
+    );
+    assert_eq!(
+        diff::render_option_changes(&diff::diff_options(&new, &new)),
+        "No options changed.\n"
+    );
+}
+
+#[test]
+fn test_inherit_from() {
+    let mut output = String::from("");
+    let src = fs::read_to_string("test/inherit/default.nix").unwrap();
+    let nix = rnix::Root::parse(&src).ok().expect("failed to parse input");
+    let opts = CollectOptions {
+        file: Some("test/inherit/default.nix".to_string()),
+        line_locations: true,
+        base_dir: Some(PathBuf::from("test/inherit")),
+        ..CollectOptions::new("lib", "inherit")
+    };
+
+    for entry in collect_entries(nix, &opts) {
+        entry.write_section("function-library-", &mut output);
+    }
+
+    insta::assert_snapshot!(output);
+}
+
+#[test]
+fn test_disambiguate_identifiers() {
+    let src = "{\n  /** Prime. */\n  foo' = x: x;\n  /** Literal. */\n  foo-prime = x: x;\n  /** Other. */\n  bar = x: x;\n}\n";
+    let mut entries =
+        nixdoc::collect_entries_from_str(src, &CollectOptions::new("lib", "strings")).unwrap();
+    let collisions = disambiguate_identifiers(&mut entries);
+
+    let idents: Vec<String> = entries.iter().map(|e| e.get_ident_title().0).collect();
+    assert_eq!(
+        idents,
+        [
+            "lib.strings.foo-prime",
+            "lib.strings.foo-prime-2",
+            "lib.strings.bar"
+        ]
+    );
+    assert_eq!(collisions.len(), 1);
+    assert_eq!(collisions[0].name, "lib.strings.foo-prime");
+    assert_eq!(collisions[0].assigned, "lib.strings.foo-prime-2");
+}
+
+#[test]
+fn test_rec_scope() {
+    let mut output = String::from("");
+    let src = fs::read_to_string("test/rec.nix").unwrap();
+    let nix = rnix::Root::parse(&src).ok().expect("failed to parse input");
+    let opts = CollectOptions {
+        depth: 1,
+        ..CollectOptions::new("lib", "rec")
+    };
+
+    for entry in collect_entries(nix, &opts) {
+        entry.write_section("function-library-", &mut output);
+    }
+
+    insta::assert_snapshot!(output);
+}
+
+#[test]
+fn test_attr_names() {
+    let mut output = String::from("");
+    let src = fs::read_to_string("test/attr-names.nix").unwrap();
+    let nix = rnix::Root::parse(&src).ok().expect("failed to parse input");
+    let dynamic: Vec<String> = nixdoc::attrpath::dynamic_bindings(nix.syntax())
+        .into_iter()
+        .map(|binding| binding.attrpath)
+        .collect();
+    assert_eq!(dynamic, vec!["${name}"]);
+
+    let opts = CollectOptions {
+        depth: 1,
+        ..CollectOptions::new("lib", "names")
+    };
+    for entry in collect_entries(nix, &opts) {
+        entry.write_section("function-library-", &mut output);
+    }
+
+    insta::assert_snapshot!(output);
+}
+
+#[test]
+fn test_doc_comment_sections() {
+    let mut output = String::from("");
+    let src = fs::read_to_string("test/sections.nix").unwrap();
+    let nix = rnix::Root::parse(&src).ok().expect("failed to parse input");
+    let entries = collect_entries(nix, &CollectOptions::new("lib", "sections"));
+
+    write_entries(entries, "function-library-", &mut output);
+
+    insta::assert_snapshot!(output);
+}
+
+#[test]
+fn test_json_v2_output() {
+    let mut args = make_args("test/stability.nix", "stability", "Stability", None);
+    args.json_output = true;
+    args.json_version = 2;
+
+    let output: serde_json::Value = serde_json::from_str(&main_with_args(&args)).unwrap();
+
+    let parse = |version: &str| Args::try_parse_from(["nixdoc", "--json-version", version]);
+    assert_eq!(parse("2").unwrap().json_version, 2);
+    assert!(parse("3").is_err());
+    assert!(parse("0").is_err());
+
+    assert_eq!(output["version"], 2);
+    assert_eq!(output["category"]["name"], "stability");
+    let entry = &output["entries"][0];
+    assert_eq!(entry["id"], "lib.stability.fresh");
+    assert_eq!(entry["span"]["start_line"], 11);
+    assert!(entry["markdown"]["rendered"]
+        .as_str()
+        .unwrap()
+        .starts_with("## `lib.stability.fresh`"));
+    assert_eq!(entry["args"][0]["kind"], "flat");
+
+    // Every property of the output is described by the schema.
+    let schema = serde_json::to_value(nixdoc::jsonv2::Document::schema()).unwrap();
+    let entry_schema = &schema["$defs"]["Entry"]["properties"];
+    for key in entry.as_object().unwrap().keys() {
+        assert!(
+            entry_schema.get(key).is_some(),
+            "{} missing from schema",
+            key
+        );
+    }
+}
+
+#[test]
+fn test_template_output() {
+    let mut args = make_args("test/stability.nix", "stability", "Stability", None);
+    args.template = Some(PathBuf::from("test/templates/functions.hbs"));
+
+    let output = main_with_args(&args);
+
+    insta::assert_snapshot!(output);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_options_file_not_mappable() {
+    // Character devices and pipes cannot be memory-mapped, so they are read.
+    let err = options::parse_options_file(Path::new("/dev/null")).unwrap_err();
+    assert!(err.starts_with("Failed to parse options JSON"), "{}", err);
+}
+
+#[test]
+fn test_options_template() {
+    let parsed = options::parse_options_file(Path::new("test/options.json")).unwrap();
+    let render_opts = options::RenderOptions {
+        declarations_base_url: Some("https://example.com/repo".to_string()),
+        ..Default::default()
+    };
+    let context = OptionsContext::new(&parsed, "Module Options", None, &render_opts);
+    let template = Template::from_file(Path::new("test/templates/options.hbs")).unwrap();
+
+    insta::assert_snapshot!(template.render(&context).unwrap());
+    assert!(Template::new("{{#each options}}").is_err());
+}
+
+#[test]
+fn test_duplicate_bindings() {
+    let src = fs::read_to_string("test/duplicates.nix").unwrap();
+    let nix = rnix::Root::parse(&src).ok().expect("failed to parse input");
+    let opts = CollectOptions {
+        file: Some("test/duplicates.nix".to_string()),
+        line_locations: true,
+        ..CollectOptions::new("lib", "duplicates")
+    };
+
+    let (entries, duplicates) = collect_entries_with_duplicates(nix, &opts);
+
+    assert_eq!(
+        duplicates,
+        vec![Duplicate {
+            name: "increment".to_string(),
+            shadowed: "test/duplicates.nix:5".to_string(),
+            effective: "test/duplicates.nix:16".to_string(),
+        }]
+    );
+    let mut output = String::new();
+    for entry in entries {
+        entry.write_section("function-library-", &mut output);
+    }
+    insta::assert_snapshot!(output);
+}
+
+#[test]
+fn test_strict_duplicate_bindings() {
+    let src = fs::read_to_string("test/duplicates.nix").unwrap();
+    let nix = rnix::Root::parse(&src).ok().expect("failed to parse input");
+    let opts = CollectOptions {
+        file: Some("test/duplicates.nix".to_string()),
+        line_locations: true,
+        ..CollectOptions::new("lib", "duplicates")
+    };
+    let (mut entries, duplicates) = collect_entries_with_duplicates(nix, &opts);
+
+    let mut lenient = Diagnostics::new("test/duplicates.nix", &src, false);
+    report_entry_problems(&mut entries, &duplicates, &mut lenient);
+    assert!(!lenient.is_empty());
+    assert!(!lenient.has_errors());
+
+    let mut strict = Diagnostics::new("test/duplicates.nix", &src, true);
+    report_entry_problems(&mut entries, &duplicates, &mut strict);
+    assert!(strict.has_errors());
+    assert_eq!(
+        strict.render(DiagnosticsFormat::Human),
+        "error[duplicate-binding]: `increment` is defined at test/duplicates.nix:5 and again at \
+         test/duplicates.nix:16; documenting the latter\n  --> test/duplicates.nix:16:3\n"
+    );
+}
+
+#[test]
+fn test_man_output() {
+    let mut args = make_args("test/strings.nix", "strings", "String manipulation", None);
+    args.output_format = String::from("man");
+
+    let output = main_with_args(&args);
+
+    insta::assert_snapshot!(output);
+}
+
+#[test]
+fn test_html_fragment() {
+    let mut args = make_args("test/strings.nix", "strings", "String manipulation", None);
+    args.output_format = String::from("html");
+    args.heading_offset = 1;
+    args.class_prefix = String::from("doc-");
+
+    let output = main_with_args(&args);
+
+    assert!(!output.contains("<html"));
+    insta::assert_snapshot!(output);
+}
+
+#[test]
+fn test_hash_anchors() {
+    let mut args = make_args("test/strings.nix", "strings", "", None);
+    args.hash_anchors = true;
+    let output = main_with_args(&args);
+
+    let anchor = hash_anchor("strings", "concatStrings");
+    assert!(output.contains(&format!(
+        "{{#function-library-lib.strings.concatStrings}}\n\n[]{{#{}}}\n\n",
+        anchor
+    )));
+
+    // The anchor does not depend on the category prefix.
+    args.prefix = String::from("utils");
+    assert!(main_with_args(&args).contains(&anchor));
+    assert_ne!(anchor, hash_anchor("lists", "concatStrings"));
+    assert_eq!(anchor.len(), "id-".len() + 12);
+}
+
+#[test]
+fn test_collect_file_docs() {
+    let docs = filedoc::collect_file_docs(Path::new("test/modules")).unwrap();
+    let paths: Vec<&str> = docs.iter().map(|d| d.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        ["default.nix", "programs/editor.nix", "services/web.nix"]
+    );
+    assert_eq!(
+        docs[0].doc.as_deref(),
+        Some("# Module tree\n\nImports every module of the tree.")
+    );
+    assert_eq!(docs[1].doc, None);
+}
+
+#[test]
+fn test_module_index() {
+    let entries = filedoc::collect_module_index(
+        Path::new("test/modules"),
+        Some("https://example.com/tree/main/"),
+    )
+    .unwrap();
+
+    assert_eq!(entries.len(), 3);
+    insta::assert_snapshot!(filedoc::render_module_index("Module index", &entries));
+}
+
+#[test]
+fn test_follow_imports() {
+    let mut args = make_args("test/imports/default.nix", "imports", "", None);
+    args.follow_imports = true;
+    args.locations = true;
+
+    let output = main_with_args(&args);
+
+    insta::assert_snapshot!(output);
+}
+
+#[test]
+fn test_deprecated() {
+    let mut args = make_args("test/deprecated.nix", "strings", "", None);
+    args.deprecated_appendix = true;
+
+    let output = main_with_args(&args);
+
+    insta::assert_snapshot!(output);
+}
+
+#[test]
+fn test_cross_references() {
+    let src = fs::read_to_string("test/xref.nix").unwrap();
+    let nix = rnix::Root::parse(&src).ok().expect("failed to parse input");
+    let mut entries = collect_entries(nix, &CollectOptions::new("lib", "xref"));
+    let known: HashMap<String, String> = entries
+        .iter()
+        .map(|e| e.get_ident_title().0)
+        .chain(["lib.lists.concat".to_string()])
+        .map(|ident| (ident, String::new()))
+        .collect();
+
+    let dangling = xref::resolve_references(&mut entries, &known, "", "lib", "function-library-");
+
+    assert_eq!(
+        dangling,
+        vec![xref::DanglingReference {
+            entry: "lib.xref.join".to_string(),
+            target: "lib.xref.missing".to_string(),
+        }]
+    );
+    let mut output = String::new();
+    write_entries(entries, "function-library-", &mut output);
+    insta::assert_snapshot!(output);
+}
+
+#[test]
+fn test_oversized_doc_comment() {
+    let line = "A line of a very long description.\n";
+    let doc = line.repeat(comment::MAX_DOC_COMMENT_LEN / line.len() + 10);
+    let src = format!("{{ /** {} */ foo = x: x; /** Short. */ bar = x: x; }}", doc);
+    let nix = rnix::Root::parse(&src).ok().unwrap();
+
+    let oversized = comment::oversized_doc_comments(nix.syntax());
+    assert_eq!(oversized.len(), 1);
+    assert_eq!(oversized[0].offset, 2);
+
+    let entries = collect_entries(nix, &CollectOptions::new("lib", "long"));
+    let description = entries[0].description.join("\n\n");
+    assert!(description.len() <= comment::MAX_DOC_COMMENT_LEN);
+    assert!(description.ends_with("description."));
+    assert_eq!(entries[1].description, vec!["Short."]);
+}
+
+#[test]
+fn test_unresolved_inherits() {
+    let src = r#"{ lib }:
+let
+  /** Bound. */
+  bound = x: x;
+  inherit (lib) head;
+in
+{
+  inherit bound head missing;
+  inherit (lib) tail;
+  inherit (import ./missing.nix) gone;
+  inherit (import ./imports/double.nix) double;
+}"#;
+    let nix = rnix::Root::parse(src).ok().unwrap();
+    let opts = CollectOptions {
+        base_dir: Some(PathBuf::from("test")),
+        ..CollectOptions::new("lib", "inherits")
+    };
+
+    let unresolved: Vec<_> = nixdoc::unresolved_inherits(&nix, &opts)
+        .into_iter()
+        .map(|inherit| (inherit.name, inherit.from))
+        .collect();
+    assert_eq!(
+        unresolved,
+        vec![
+            ("missing".to_string(), None),
+            (
+                "gone".to_string(),
+                Some("(import ./missing.nix)".to_string())
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_read_source_lossy() {
+    let path = std::env::temp_dir().join(format!("nixdoc-lossy-{}.nix", std::process::id()));
+    fs::write(&path, b"{ /** Caf\xe9 */ foo = x: x; }").unwrap();
+    let (source, lossy) = read_source(&path).unwrap();
+    assert!(lossy);
+    assert!(source.contains("Caf\u{fffd}"));
+    fs::remove_file(&path).unwrap();
+
+    assert!(!read_source(Path::new("test/strings.nix")).unwrap().1);
+}
+
+#[test]
+fn test_cli_introspection() {
+    let cli = cli_json(&mut Args::command());
+    assert_eq!(cli["name"], "nixdoc");
+    let completions = cli["subcommands"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|command| command["name"] == "completions")
+        .unwrap();
+    let shell = &completions["arguments"][0];
+    assert_eq!(shell["name"], "shell");
+    assert_eq!(shell["positional"], true);
+    assert!(shell["possible_values"]
+        .as_array()
+        .unwrap()
+        .contains(&"zsh".into()));
+
+    let mut script = vec![];
+    clap_complete::generate(
+        clap_complete::Shell::Bash,
+        &mut Args::command(),
+        "nixdoc",
+        &mut script,
+    );
+    let script = String::from_utf8(script).unwrap();
+    assert!(script.contains("--admonition-style"));
+    assert!(script.contains("completions"));
+}
+
+proptest::proptest! {
+    #[test]
+    fn prop_shift_headings_keeps_lines(text in "(#{0,8}[ a-z`~]{0,6}\n){0,20}", levels in 0usize..usize::MAX) {
+        let shifted = shift_headings(&text, levels);
+        proptest::prop_assert_eq!(shifted.lines().count(), text.lines().count());
+    }
+
+    #[test]
+    fn prop_handle_indentation_trims(text in "\\PC{0,200}") {
+        if let Some(dedented) = format::handle_indentation(&text) {
+            proptest::prop_assert_eq!(dedented.trim(), dedented.as_str());
+            proptest::prop_assert!(!dedented.is_empty());
+        }
+    }
+
+    #[test]
+    fn prop_line_column_any_offset(text in "\\PC{0,50}", offset in 0usize..200) {
+        let (line, column) = format::line_column(&text, offset);
+        proptest::prop_assert!(line >= 1 && column >= 1);
+    }
+
+    #[test]
+    fn prop_collect_entries_never_panics(body in "(/\\*\\*[^*]{0,40}\\*/ |[a-z]{1,3} = [a-z{}:;]{1,6}; |# [^\n]{0,10}\n){0,12}") {
+        let source = format!("{{ {} }}", body);
+        let _ = nixdoc::collect_entries_from_str(&source, &CollectOptions::new("lib", "prop"));
+    }
+}
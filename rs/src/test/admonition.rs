@@ -0,0 +1,31 @@
+use nixdoc::admonition::*;
+
+const NIXPKGS: &str = "Intro.\n\n::: {.warning}\nCareful.\n\n```nix\n:::\n```\n:::\n\n::: {.example}\n::: {.tip}\nNested.\n:::\n:::\n";
+
+#[test]
+fn test_convert_admonitions() {
+    let github = convert_admonitions(NIXPKGS, AdmonitionStyle::GitHub);
+    assert_eq!(
+        github,
+        "Intro.\n\n> [!WARNING]\n> Careful.\n>\n> ```nix\n> :::\n> ```\n\n::: {.example}\n> [!TIP]\n> Nested.\n:::\n"
+    );
+    let myst = convert_admonitions(&github, AdmonitionStyle::Myst);
+    assert_eq!(
+        myst,
+        "Intro.\n\n````{warning}\nCareful.\n\n```nix\n:::\n```\n````\n\n::: {.example}\n```{tip}\nNested.\n```\n:::\n"
+    );
+    assert_eq!(
+        convert_admonitions(&myst, AdmonitionStyle::Nixpkgs),
+        NIXPKGS
+    );
+}
+
+#[test]
+fn test_unknown_and_unclosed_blocks_are_kept() {
+    let markdown =
+        "> [!DANGER]\n> Not an alert.\n\n::: {.note #id}\nKept.\n:::\n\n::: {.note}\nUnclosed.";
+    assert_eq!(
+        convert_admonitions(markdown, AdmonitionStyle::GitHub),
+        markdown
+    );
+}
@@ -0,0 +1,69 @@
+use nixdoc::arguments::*;
+use nixdoc::commonmark::{Argument, SingleArg};
+
+fn doc(name: &str, ty: Option<&str>, description: &str) -> ArgumentDoc {
+    ArgumentDoc {
+        name: name.to_string(),
+        ty: ty.map(str::to_string),
+        description: description.to_string(),
+    }
+}
+
+#[test]
+fn test_parse_arguments_section() {
+    let comment = "Map a function.\n\n# Inputs\n\n`f` (`a -> b`)\n\n: Function to map\n\n  Called once per element.\n\nlist :: [a]\n: List of values\nto map over\n\n- `acc`: Ignored\n\n## Details\n\nStill part of the section.\n\n# Examples\n\n`x`\n: not an argument\n";
+    assert_eq!(
+        parse_arguments_section(comment),
+        vec![
+            doc(
+                "f",
+                Some("a -> b"),
+                "Function to map\n\nCalled once per element."
+            ),
+            doc("list", Some("[a]"), "List of values\nto map over"),
+            doc("acc", None, "Ignored"),
+        ]
+    );
+    assert_eq!(
+        parse_arguments_section(
+            "# Arguments\n\n- `pref` (`String`): Prefix to check for\n- `str`: Input string\n"
+        ),
+        vec![
+            doc("pref", Some("String"), "Prefix to check for"),
+            doc("str", None, "Input string"),
+        ]
+    );
+    assert!(parse_arguments_section("No arguments.\n\n`x`\n: Value\n").is_empty());
+}
+
+#[test]
+fn test_check_arguments() {
+    let single = |name: &str| SingleArg {
+        name: name.to_string(),
+        doc: None,
+        default: None,
+    };
+    let args = vec![
+        Argument::Flat(single("f")),
+        Argument::Pattern {
+            args: vec![single("name"), single("value")],
+            ellipsis: false,
+            bind: None,
+        },
+    ];
+    assert_eq!(
+        check_arguments(
+            &[
+                doc("f", None, ""),
+                doc("name", None, ""),
+                doc("g", None, "")
+            ],
+            &args
+        ),
+        vec![
+            ArgumentMismatch::Undocumented("value".to_string()),
+            ArgumentMismatch::Unknown("g".to_string()),
+        ]
+    );
+    assert!(check_arguments(&[], &args).is_empty());
+}
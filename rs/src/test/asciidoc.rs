@@ -0,0 +1,28 @@
+use nixdoc::asciidoc::*;
+
+#[test]
+fn test_markdown_to_asciidoc() {
+    let markdown = "# Strings {#sec-strings}\n\nSee [](#opt-foo), [the manual](https://nixos.org) and {file}`/etc/foo`s.\n\n- one\n  - nested\n- `two`\n\n::: {.warning}\nA {name} of 2*3 with *this*.\n:::\n\n```nix\n{ a = 1; }\n```\n\n| a | b |\n|---|---|\n| 1 | x\\|y |\n";
+    assert_eq!(
+        markdown_to_asciidoc(markdown),
+        "[[sec-strings]]\n\
+         == Strings\n\n\
+         See <<opt-foo>>, link:https://nixos.org[the manual] and `+/etc/foo+`{empty}s.\n\n\
+         * one\n** nested\n* `+two+`\n\n\
+         [WARNING]\n====\nA \\{name} of 2{asterisk}3 with _this_.\n====\n\n\
+         [source,nix]\n----\n{ a = 1; }\n----\n\n\
+         [%header]\n|===\n|a |b\n\n|1 |x\\|y\n|===\n"
+    );
+}
+
+#[test]
+fn test_nested_blocks() {
+    let markdown = "## `lib.foo` {#function-library-lib.foo}\n\n::: {.example #ex}\n# usage example\n\n```nix\nfoo 1\n```\n:::\n\n1. First\n\n   ```\n   code\n   ```\n2. Second\n";
+    assert_eq!(
+        markdown_to_asciidoc(markdown),
+        "[[function-library-lib.foo]]\n\
+         === `+lib.foo+`\n\n\
+         ====\n[discrete]\n== usage example\n\n[source,nix]\n----\nfoo 1\n----\n====\n\n\
+         . First\n+\n----\ncode\n----\n. Second\n"
+    );
+}
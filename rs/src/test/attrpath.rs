@@ -0,0 +1,43 @@
+use nixdoc::attrpath::*;
+use rnix::ast;
+use rowan::ast::AstNode;
+
+fn names(src: &str) -> Vec<Option<String>> {
+    let root = rnix::Root::parse(src).tree();
+    root.syntax()
+        .descendants()
+        .filter_map(ast::AttrpathValue::cast)
+        .map(|apv| attrpath_name(&apv.attrpath().unwrap()))
+        .collect()
+}
+
+#[test]
+fn test_attrpath_name() {
+    assert_eq!(
+        names(
+            r#"{ foo.bar = 1; "baz" = 2; "foo bar" = 3; ${"in"} = 4; "a\"b" = 5; ${x} = 6; "x${y}" = 7; }"#
+        ),
+        vec![
+            Some("foo.bar".to_string()),
+            Some("baz".to_string()),
+            Some("\"foo bar\"".to_string()),
+            Some("\"in\"".to_string()),
+            Some("\"a\\\"b\"".to_string()),
+            None,
+            None,
+        ]
+    );
+}
+
+#[test]
+fn test_dynamic_bindings() {
+    let src = "{\n  /** Documented. */\n  ${name} = 1;\n  ${other} = 2;\n  /** Fine. */\n  \"a b\" = 3;\n}";
+    let root = rnix::Root::parse(src).tree();
+    assert_eq!(
+        dynamic_bindings(root.syntax()),
+        vec![DynamicBinding {
+            attrpath: "${name}".to_string(),
+            offset: 25,
+        }]
+    );
+}
@@ -0,0 +1,34 @@
+use std::fs;
+
+use nixdoc::cache::*;
+use nixdoc::CollectOptions;
+
+#[test]
+fn test_cache_key() {
+    let dir = std::env::temp_dir().join(format!("nixdoc-cache-key-{}", std::process::id()));
+    let cache = EntryCache::new(&dir);
+    let cached = || fs::read_dir(&dir).map(|files| files.count()).unwrap_or(0);
+    let opts = CollectOptions::new("lib", "strings");
+    let source = "{ /** Doc */ foo = x: x; }";
+
+    cache.collect(source, &opts).unwrap();
+    cache.collect(source, &opts).unwrap();
+    assert_eq!(cached(), 1);
+    cache.collect("{ foo = x: x; }", &opts).unwrap();
+    assert_eq!(cached(), 2);
+    let nested = CollectOptions {
+        depth: 1,
+        ..opts.clone()
+    };
+    cache.collect(source, &nested).unwrap();
+    assert_eq!(cached(), 3);
+
+    // Entries of sources following imports are never cached.
+    let following = CollectOptions {
+        follow_imports: true,
+        ..opts
+    };
+    cache.collect(source, &following).unwrap();
+    assert_eq!(cached(), 3);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,60 @@
+use nixdoc::compat::*;
+use nixdoc::options::parse_options_json;
+
+#[test]
+fn test_compat_matrix() {
+    let versions = vec![
+        (
+            "23.11".to_string(),
+            parse_options_json(
+                r#"{
+                    "foo.enable": { "type": "boolean", "default": false },
+                    "foo.old": { "type": "string" }
+                }"#,
+            )
+            .unwrap(),
+        ),
+        (
+            "24.05".to_string(),
+            parse_options_json(
+                r#"{
+                    "foo.enable": { "type": "boolean", "default": true },
+                    "foo.port": { "type": "signed integer" }
+                }"#,
+            )
+            .unwrap(),
+        ),
+        (
+            "24.11".to_string(),
+            parse_options_json(
+                r#"{
+                    "foo.enable": { "type": "boolean", "default": true },
+                    "foo.port": { "type": "16 bit unsigned integer; between 0 and 65535 (both inclusive)" }
+                }"#,
+            )
+            .unwrap(),
+        ),
+    ];
+
+    let matrix = build_matrix(&versions);
+    let names: Vec<&str> = matrix.iter().map(|h| h.name.as_str()).collect();
+    assert_eq!(names, vec!["foo.enable", "foo.old", "foo.port"]);
+    assert_eq!(matrix[0].present, vec![true, true, true]);
+    assert_eq!(
+        matrix[0].changes,
+        vec![Change {
+            version: "24.05".to_string(),
+            field: "default",
+            from: "`false`".to_string(),
+            to: "`true`".to_string(),
+        }]
+    );
+    assert_eq!(matrix[1].present, vec![true, false, false]);
+    assert_eq!(matrix[2].changes.len(), 1);
+
+    let labels: Vec<String> = versions.iter().map(|(v, _)| v.clone()).collect();
+    let output = render_matrix("Compatibility", &labels, &matrix);
+    assert!(output.contains("| Option | 23.11 | 24.05 | 24.11 | Changes |"));
+    assert!(output.contains("| `foo.enable` | ✓ | ✓ | ✓ | 24.05: default `false` → `true` |"));
+    assert!(output.contains("| `foo.old` | ✓ |  |  |  |"));
+}
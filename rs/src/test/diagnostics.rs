@@ -0,0 +1,40 @@
+use nixdoc::diagnostics::*;
+
+#[test]
+fn renders_like_rustc() {
+    let mut diagnostics = Diagnostics::new("lib.nix", "{\n  ${name} = 1;\n}\n", false);
+    diagnostics.at_offset(
+        DiagnosticKind::DynamicName,
+        4,
+        "`${name}` has a dynamic name and is not documented".to_string(),
+    );
+    diagnostics.push(
+        DiagnosticKind::MissingLocation,
+        None,
+        "`lib.foo` has no location data".to_string(),
+    );
+    assert!(!diagnostics.has_errors());
+    assert_eq!(
+        diagnostics.render(DiagnosticsFormat::Human),
+        "warning[dynamic-name]: `${name}` has a dynamic name and is not documented\n  \
+         --> lib.nix:2:3\n\
+         warning[missing-location]: `lib.foo` has no location data\n  --> lib.nix\n"
+    );
+}
+
+#[test]
+fn strict_diagnostics_are_errors() {
+    let mut diagnostics = Diagnostics::new("lib.nix", "{ }", true);
+    diagnostics.push(
+        DiagnosticKind::UnresolvedInherit,
+        Some((1, 3)),
+        "`foo` is inherited from nowhere".to_string(),
+    );
+    assert!(diagnostics.has_errors());
+    assert_eq!(
+        diagnostics.render(DiagnosticsFormat::Json),
+        r#"{"severity":"error","code":"unresolved-inherit","message":"`foo` is inherited from nowhere","file":"lib.nix","line":1,"column":3}"#
+            .to_string()
+            + "\n"
+    );
+}
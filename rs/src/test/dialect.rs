@@ -0,0 +1,34 @@
+use nixdoc::dialect::*;
+
+const NIXPKGS: &str = "## `lib.concat` {#function-library-lib.concat}\n\n[]{#concat}\n\n`a`\n\n: First list\n\n::: {.warning}\nCareful.\n:::\n\n::: {.example #function-library-example-lib.concat}\n# `lib.concat` usage example\n\n```nix\n## {#not-an-anchor}\nconcat [ 1 ] [ 2 ]\n```\n:::\n\n| Name | Default | Description |\n| ---- | ------- | ----------- |\n| `a` |  | Either \\| or |\n";
+
+fn convert(dialect: MarkdownDialect) -> String {
+    convert_markdown(NIXPKGS, dialect, dialect.admonition_style())
+}
+
+#[test]
+fn test_nixpkgs_is_kept() {
+    assert_eq!(convert(MarkdownDialect::Nixpkgs), NIXPKGS);
+}
+
+#[test]
+fn test_gfm() {
+    assert_eq!(
+        convert(MarkdownDialect::Gfm),
+        "## <a id=\"function-library-lib.concat\"></a>`lib.concat`\n\n<a id=\"concat\"></a>\n\n- `a`\n\n  First list\n\n> [!WARNING]\n> Careful.\n\n<a id=\"function-library-example-lib.concat\"></a>\n\n**`lib.concat` usage example**\n\n```nix\n## {#not-an-anchor}\nconcat [ 1 ] [ 2 ]\n```\n\n| Name | Default | Description |\n| ---- | ------- | ----------- |\n| `a` |  | Either \\| or |\n"
+    );
+}
+
+#[test]
+fn test_commonmark_tables() {
+    assert!(convert(MarkdownDialect::CommonMark)
+        .ends_with("```\n\n- `a`: **Description:** Either | or\n"));
+}
+
+#[test]
+fn test_myst() {
+    assert_eq!(
+        convert(MarkdownDialect::Myst),
+        "(function-library-lib.concat)=\n## `lib.concat`\n\n(concat)=\n\n`a`\n\n: First list\n\n```{warning}\nCareful.\n```\n\n::::{admonition} `lib.concat` usage example\n:class: example\n:name: function-library-example-lib.concat\n\n```nix\n## {#not-an-anchor}\nconcat [ 1 ] [ 2 ]\n```\n::::\n\n| Name | Default | Description |\n| ---- | ------- | ----------- |\n| `a` |  | Either \\| or |\n"
+    );
+}
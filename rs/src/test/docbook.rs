@@ -0,0 +1,24 @@
+use nixdoc::docbook::*;
+
+#[test]
+fn test_markdown_to_docbook() {
+    let markdown = "See {option}`services.foo.enable` and [](#opt-bar).\n\n- one\n- `two`\n\n::: {.warning}\nCareful.\n:::\n\n```nix\n{ a = 1; }\n```\n";
+    assert_eq!(
+        markdown_to_docbook(markdown),
+        "<para>See <option>services.foo.enable</option> and <xref linkend=\"opt-bar\"/>.</para>\n\
+         <itemizedlist>\n\
+         <listitem><para>one</para>\n</listitem>\n\
+         <listitem><para><literal>two</literal></para>\n</listitem>\n\
+         </itemizedlist>\n\
+         <warning>\n<para>Careful.</para>\n</warning>\n\
+         <programlisting language=\"nix\">{ a = 1; }\n</programlisting>\n"
+    );
+}
+
+#[test]
+fn test_escape() {
+    assert_eq!(
+        markdown_to_docbook("a < b && \"c\""),
+        "<para>a &lt; b &amp;&amp; &quot;c&quot;</para>\n"
+    );
+}
@@ -0,0 +1,126 @@
+use std::path::Path;
+
+use nixdoc::doctest::*;
+use nixdoc::{collect_entries_from_str, CollectOptions};
+
+/// Evaluates `with SCOPE; (EXPR)` by looking up EXPR in a table.
+struct TableEvaluator(Vec<(&'static str, &'static str)>);
+
+impl Evaluator for TableEvaluator {
+    fn eval(&self, expr: &str) -> Result<String, String> {
+        let expr = expr.strip_prefix("with scope; ").unwrap_or(expr);
+        self.0
+            .iter()
+            .find(|(e, _)| *e == expr)
+            .map(|(_, v)| v.to_string())
+            .ok_or_else(|| format!("error: cannot evaluate {}", expr))
+    }
+}
+
+#[test]
+fn test_default_scope() {
+    assert_eq!(
+        default_scope(Path::new("/src/my lib/\"${x}\\.nix")),
+        "(let file = import (/. + \"/src/my lib/\\\"\\${x}\\\\.nix\"); \
+         in if builtins.isFunction file then file { } else file)"
+    );
+    let relative = default_scope(Path::new("test/strings.nix"));
+    let cwd = std::env::current_dir().unwrap();
+    assert!(relative.contains(&format!("(/. + \"{}/test/strings.nix\")", cwd.display())));
+}
+
+#[test]
+fn test_extract_examples() {
+    let source = r#"{
+  /**
+    Concatenate.
+
+    # Examples
+
+    :::{.example}
+    ## usage
+
+    ```nix
+    concat "a" "b"
+    => "ab"
+
+    # Lists too
+    concat
+      [ 1 ]
+      [ 2 ]
+    => [
+      1
+      2
+    ]
+    ```
+
+    ```
+    concat x
+    ```
+    :::
+
+    # Type
+
+    ```
+    => not an example
+    ```
+  */
+  concat = a: b: a + b;
+}
+"#;
+    let entries = collect_entries_from_str(source, &CollectOptions::new("lib", "strings")).unwrap();
+    let examples: Vec<_> = extract_examples(&entries)
+        .into_iter()
+        .map(|example| (example.entry, example.expr, example.expected))
+        .collect();
+    assert_eq!(
+        examples,
+        vec![
+            (
+                "lib.strings.concat".to_string(),
+                "concat \"a\" \"b\"".to_string(),
+                "\"ab\"".to_string()
+            ),
+            (
+                "lib.strings.concat".to_string(),
+                "# Lists too\nconcat\n  [ 1 ]\n  [ 2 ]".to_string(),
+                "[\n1\n2\n]".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_check_example() {
+    let evaluator = TableEvaluator(vec![
+        ("(add 1 2)", "3"),
+        ("(range 1 2)", "[ 1 2 ]"),
+        ("(range 1 2) == ([1 2])", "true"),
+        ("(sub 1 2)", "-1"),
+        ("(sub 1 2) == (1)", "false"),
+    ]);
+    let example = |expr: &str, expected: &str| Example {
+        entry: "lib.math.add".to_string(),
+        expr: expr.to_string(),
+        expected: expected.to_string(),
+    };
+
+    assert_eq!(
+        check_example(&example("add 1 2", "3"), "scope", &evaluator),
+        Outcome::Passed
+    );
+    assert_eq!(
+        check_example(&example("range 1 2", "[1 2]"), "scope", &evaluator),
+        Outcome::Passed
+    );
+    assert_eq!(
+        check_example(&example("sub 1 2", "1"), "scope", &evaluator),
+        Outcome::Mismatch {
+            actual: "-1".to_string()
+        }
+    );
+    assert!(matches!(
+        check_example(&example("mul 1 2", "2"), "scope", &evaluator),
+        Outcome::Error(_)
+    ));
+}
@@ -0,0 +1,45 @@
+use nixdoc::doctest::Evaluator;
+use nixdoc::eval::*;
+use nixdoc::CollectOptions;
+
+struct JsonEvaluator(String);
+
+impl Evaluator for JsonEvaluator {
+    fn eval(&self, _expr: &str) -> Result<String, String> {
+        Ok(self.0.clone())
+    }
+}
+
+#[test]
+fn test_evaluated_entries() {
+    let src = "let\n  /** Add one. */\n  inc = x: x + 1;\n  undocumented = 1;\nin\nlib.genAttrs [ \"a\" ] (_: inc) // { inherit inc undocumented; }\n";
+    let dir = std::env::temp_dir().join(format!("nixdoc-eval-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("generated.nix");
+    std::fs::write(&file, src).unwrap();
+    let path = file.canonicalize().unwrap();
+
+    let json = format!(
+        r#"{{
+            "a": null,
+            "inc": {{ "file": "{0}", "line": 3, "column": 3 }},
+            "undocumented": {{ "file": "{0}", "line": 4, "column": 3 }},
+            "elsewhere": {{ "file": "/lib/attrsets.nix", "line": 1, "column": 1 }}
+        }}"#,
+        path.display()
+    );
+    let attrs = evaluate_attrs(&JsonEvaluator(json), "{ }").unwrap();
+    assert_eq!(attrs.len(), 4);
+
+    let root = rnix::Root::parse(src).ok().unwrap();
+    let opts = CollectOptions::new("lib", "generated");
+    let (entries, undefined) = evaluated_entries(&root, &file, &attrs, &opts);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(
+        entries.iter().map(|e| e.name.as_str()).collect::<Vec<_>>(),
+        vec!["inc"]
+    );
+    assert_eq!(entries[0].description, vec!["Add one."]);
+    assert_eq!(undefined, vec!["a", "elsewhere"]);
+}
@@ -0,0 +1,59 @@
+use nixdoc::examples::*;
+
+fn example(title: Option<&str>, body: &str) -> ExampleDoc {
+    ExampleDoc {
+        title: title.map(str::to_string),
+        body: body.to_string(),
+        language: None,
+    }
+}
+
+#[test]
+fn test_split_examples() {
+    let doc = "Concatenate.\n\n# Example: basic usage\n\n```nix\nconcat \"a\" \"b\"\n```\n\n## Example: lists\n\n```nix\n# not a heading\nconcat [ 1 ] [ 2 ]\n```\n\n# Type\n\n```\nconcat :: a -> a -> a\n```\n\n# Examples\n\n:::{.example #ex}\n## `concat` usage example\n\n```nix\nconcat x\n```\n:::\n\n```nix\nconcat y\n```\n";
+    let (text, examples) = split_examples(doc);
+
+    assert_eq!(
+        text,
+        "Concatenate.\n\n# Type\n\n```\nconcat :: a -> a -> a\n```"
+    );
+    assert_eq!(
+        examples,
+        vec![
+            example(Some("basic usage"), "```nix\nconcat \"a\" \"b\"\n```"),
+            example(
+                Some("lists"),
+                "```nix\n# not a heading\nconcat [ 1 ] [ 2 ]\n```"
+            ),
+            example(Some("`concat` usage example"), "```nix\nconcat x\n```"),
+            example(None, "```nix\nconcat y\n```"),
+        ]
+    );
+}
+
+#[test]
+fn test_no_examples() {
+    let doc = "Just text.\n\n\n# Notes\n\nExample: not a heading.\n";
+    assert_eq!(split_examples(doc), (doc.to_string(), vec![]));
+}
+
+#[test]
+fn test_example_languages() {
+    let doc = "Build.\n\n# Example (bash): building\n\n```\nnix-build\n```\n\n# Examples (json)\n\n```\n{}\n```\n\n## Example: nested\n\n```nix\n[]\n```\n\n# Example\n\nSee `x`:\n\n````\n```\nx\n```\n````\n";
+    let (_, mut examples) = split_examples(doc);
+    let languages: Vec<_> = examples.iter().map(|e| e.language.as_deref()).collect();
+    assert_eq!(languages, [Some("bash"), Some("json"), Some("json"), None]);
+
+    for example in &mut examples {
+        apply_language(example, Some("nix"));
+    }
+    assert_eq!(examples[0].body, "```bash\nnix-build\n```");
+    assert_eq!(examples[1].body, "```json\n{}\n```");
+    assert_eq!(examples[2].body, "```nix\n[]\n```");
+    assert_eq!(examples[3].body, "See `x`:\n\n````nix\n```\nx\n```\n````");
+    assert_eq!(examples[3].language.as_deref(), Some("nix"));
+
+    let mut untyped = example(None, "```\nx\n```");
+    apply_language(&mut untyped, None);
+    assert_eq!(untyped, example(None, "```\nx\n```"));
+}
@@ -0,0 +1,52 @@
+use nixdoc::extract::*;
+use nixdoc::CollectOptions;
+
+#[test]
+fn keeps_doc_comments_as_written() {
+    let source = r#"
+      /** Helpers. */
+      {
+        /**
+          Add one.
+
+          # Example
+
+          ```nix
+          inc 1
+          ```
+        */
+        inc = x: x + 1;
+
+        /** Strings. */
+        "a.b".c = "c";
+      }
+    "#;
+    let opts = CollectOptions {
+        depth: 1,
+        ..CollectOptions::new("lib", "trivial")
+    };
+    let file = extract_source("trivial.nix", source, &opts).unwrap();
+    assert_eq!(file.doc.as_deref(), Some("Helpers."));
+    assert_eq!(file.entries[0].attr_path, ["lib", "trivial", "inc"]);
+    assert_eq!(
+        file.entries[0].doc,
+        "Add one.\n\n# Example\n\n```nix\ninc 1\n```"
+    );
+    assert_eq!(file.entries[0].span.unwrap().start_line, 13);
+    assert_eq!(
+        file.entries[1].attr_path,
+        ["lib", "trivial", "\"a.b\"", "c"]
+    );
+}
+
+#[test]
+fn encodes_cbor() {
+    let extraction = Extraction::new(vec![]);
+    assert_eq!(
+        ExtractFormat::Json.encode(&extraction).unwrap(),
+        br#"{"version":1,"files":[]}"#
+    );
+    let cbor = ExtractFormat::Cbor.encode(&extraction).unwrap();
+    let decoded: serde_json::Value = ciborium::from_reader(cbor.as_slice()).unwrap();
+    assert_eq!(decoded, serde_json::json!({"version": 1, "files": []}));
+}
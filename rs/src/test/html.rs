@@ -0,0 +1,19 @@
+use nixdoc::html::*;
+
+#[test]
+fn test_fenced_divs_and_roles() {
+    let markdown = "::: {.example #ex}\n# Example\n\n```nix\n:::\n```\n:::\n\nSee {option}`foo`.\n";
+    let opts = HtmlOptions {
+        heading_offset: 7,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        markdown_to_html(markdown, &opts),
+        "<div class=\"nixdoc-div nixdoc-example\" id=\"ex\">\n\
+         <h6 class=\"nixdoc-h6\">Example</h6>\n\
+         <pre class=\"nixdoc-pre\"><code class=\"nixdoc-code nixdoc-language-nix\">:::\n</code></pre>\n\
+         </div>\n\
+         <p class=\"nixdoc-p\">See <code class=\"nixdoc-code nixdoc-role-option\">foo</code>.</p>\n"
+    );
+}
@@ -0,0 +1,42 @@
+use std::io::Read;
+
+use flate2::read::ZlibDecoder;
+use nixdoc::inventory::*;
+
+#[test]
+fn test_objects_inv() {
+    let options = nixdoc::options::parse_options_json(
+        r#"{ "services.nginx.enable": { "type": "boolean" } }"#,
+    )
+    .unwrap();
+    let entries = nixdoc::collect_entries_from_str(
+        "{ /** Concatenate. */ concat = a: b: a + b; }",
+        &nixdoc::CollectOptions::new("lib", "strings"),
+    )
+    .unwrap();
+    let inventory = Inventory {
+        project: "nixpkgs".to_string(),
+        version: "24.05".to_string(),
+        items: function_items(&entries, "function-library-", "{category}.html")
+            .into_iter()
+            .chain(option_items(&options, "opt-", "options.html"))
+            .collect(),
+    };
+    assert_eq!(
+        inventory.items[0].uri,
+        "strings.html#function-library-lib.strings.concat"
+    );
+
+    let bytes = inventory.to_objects_inv();
+    let header = "# Sphinx inventory version 2\n# Project: nixpkgs\n# Version: 24.05\n# The remainder of this file is compressed using zlib.\n";
+    assert!(bytes.starts_with(header.as_bytes()));
+    let mut lines = String::new();
+    ZlibDecoder::new(&bytes[header.len()..])
+        .read_to_string(&mut lines)
+        .unwrap();
+    assert_eq!(
+        lines,
+        "lib.strings.concat nix:function 1 strings.html#function-library-$ -\n\
+         services.nginx.enable nix:option 1 options.html#opt-services-nginx-enable -\n"
+    );
+}
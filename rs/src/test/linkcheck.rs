@@ -0,0 +1,40 @@
+use nixdoc::linkcheck::*;
+
+#[test]
+fn finds_missing_anchors() {
+    let markdown = "## `foo` {#opt-foo}\n\nSee [foo](#opt-foo), [bar](#opt-bar), \
+                    [bar again](#opt-bar) and [elsewhere](other.md#opt-baz).\n\n\
+                    ```\n[not a link](#opt-code)\n```\n";
+    assert_eq!(
+        check_anchors(markdown),
+        vec![BrokenLink {
+            target: "#opt-bar".to_string(),
+            reason: "no such anchor in the document".to_string(),
+        }]
+    );
+}
+
+struct Reachable(&'static str);
+
+impl LinkChecker for Reachable {
+    fn check(&self, url: &str) -> Result<(), String> {
+        if url == self.0 {
+            Ok(())
+        } else {
+            Err("404".to_string())
+        }
+    }
+}
+
+#[test]
+fn checks_external_links_once() {
+    let markdown = "[a](https://example.com/a) [b](https://example.com/b) \
+                    [b](https://example.com/b) [c](#c) [d](mailto:d@example.com)";
+    assert_eq!(
+        check_external(markdown, &Reachable("https://example.com/a")),
+        vec![BrokenLink {
+            target: "https://example.com/b".to_string(),
+            reason: "404".to_string(),
+        }]
+    );
+}
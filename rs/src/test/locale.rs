@@ -0,0 +1,24 @@
+use nixdoc::locale::*;
+
+#[test]
+fn overrides_translations() {
+    let mut entry_labels = Locale::German.entry_labels();
+    let mut option_labels = Locale::German.option_labels();
+    parse_strings(
+        "[functions]\nlocated-at = \"Zu finden in {location}.\"\n\n[options]\ndefault = \"Vorgabe\"\n",
+        &mut entry_labels,
+        &mut option_labels,
+    )
+    .unwrap();
+    assert_eq!(entry_labels.located_at, "Zu finden in {location}.");
+    assert_eq!(entry_labels.entry_type, "Typ");
+    assert_eq!(option_labels.default, "Vorgabe");
+
+    assert!(parse_strings(
+        "[functions]\nnope = \"\"",
+        &mut entry_labels,
+        &mut option_labels
+    )
+    .is_err());
+    assert!(parse_strings("[other]\n", &mut entry_labels, &mut option_labels).is_err());
+}
@@ -0,0 +1,19 @@
+use nixdoc::locs::*;
+
+#[test]
+fn test_structured_locs() {
+    let json = r#"{
+        "lib.strings.concat": { "file": "lib/strings.nix", "line": 42, "column": 3, "url": "https://example.org/strings.nix#L42" },
+        "lib.strings.join": { "file": "lib/strings.nix", "column": 3 }
+    }"#;
+    let locs = parse_locs(json, LocsFormat::Structured).unwrap();
+    assert_eq!(
+        locs["lib.strings.concat"],
+        "[`lib/strings.nix:42:3`](https://example.org/strings.nix#L42)"
+    );
+    assert_eq!(locs["lib.strings.join"], "`lib/strings.nix`");
+
+    assert!(parse_locs(json, LocsFormat::Nixpkgs).is_err());
+    let nixpkgs = parse_locs(r#"{ "lib.id": "[id](url)" }"#, LocsFormat::Nixpkgs).unwrap();
+    assert_eq!(nixpkgs["lib.id"], "[id](url)");
+}
@@ -0,0 +1,17 @@
+use nixdoc::man::*;
+
+#[test]
+fn test_markdown_to_roff() {
+    let markdown = "## `foo` {#foo}\n\nSee {option}`bar` and [baz](https://example.com).\n\n\
+        ::: {.example}\n# Example\n\n```nix\n# comment\n.hidden\n```\n:::\n\n\
+        `x`\n\n: The \\ argument\n";
+
+    assert_eq!(
+        markdown_to_roff(markdown),
+        ".SS \\fBfoo\\fR\n\
+         .PP\nSee \\fBbar\\fR and baz <https://example.com>.\n\
+         .PP\n\\fBExample\\fR\n\
+         .PP\n.EX\n# comment\n\\&.hidden\n.EE\n\
+         .TP\n\\fBx\\fR\nThe \\e argument\n"
+    );
+}
@@ -0,0 +1,35 @@
+use nixdoc::mdbook::*;
+
+#[test]
+fn test_summary_and_links() {
+    let mut options = Chapter::new("Module Options", "options.md", "# Module Options\n".into());
+    options.children.push(Chapter::new(
+        "services",
+        "options/services.md",
+        "## `services.foo.enable` {#opt-services.foo.enable}\n\nSee [](#function-library-lib.strings.concat).\n".into(),
+    ));
+    let book = Book {
+        chapters: vec![
+            Chapter::new(
+                "lib.strings",
+                "strings.md",
+                "## `lib.strings.concat` {#function-library-lib.strings.concat}\n\n::: {.example #ex-concat}\nUse {option}`services.foo.enable`, [see](#opt-services.foo.enable).\n:::\n\n[]{#strings-end}\n".into(),
+            ),
+            options,
+        ],
+    };
+
+    assert_eq!(
+        book.summary(),
+        "# Summary\n\n- [lib.strings](strings.md)\n- [Module Options](options.md)\n  - [services](options/services.md)\n"
+    );
+    let files = book.files();
+    assert_eq!(
+        files[0].1,
+        "## `lib.strings.concat` {#function-library-lib.strings.concat}\n\n<div class=\"example\" id=\"ex-concat\">\n\nUse `services.foo.enable`, [see](options/services.md#opt-services.foo.enable).\n\n</div>\n\n<a id=\"strings-end\"></a>\n"
+    );
+    assert_eq!(
+        files[2].1,
+        "## `services.foo.enable` {#opt-services.foo.enable}\n\nSee [](../strings.md#function-library-lib.strings.concat).\n"
+    );
+}
@@ -0,0 +1,24 @@
+use nixdoc::module::*;
+
+fn describe(src: &str) -> String {
+    let root = rnix::Root::parse(src).ok().unwrap();
+    type_description(&root.expr().unwrap())
+}
+
+#[test]
+fn test_type_description() {
+    assert_eq!(describe("lib.types.bool"), "boolean");
+    assert_eq!(describe("types.listOf types.str"), "list of string");
+    assert_eq!(
+        describe("with lib.types; listOf (nullOr str)"),
+        "list of (null or string)"
+    );
+    assert_eq!(
+        describe("types.nullOr (types.either types.int (types.attrsOf types.ints.positive))"),
+        "null or signed integer or attribute set of positive integer, meaning >0"
+    );
+    assert_eq!(describe(r#"types.enum [ "a" "b" ]"#), r#"one of "a", "b""#);
+    assert_eq!(describe("types.submodule { }"), "submodule");
+    assert_eq!(describe("myTypes.thing"), "myTypes.thing");
+    assert_eq!(describe("mkThing 1"), "mkThing 1");
+}
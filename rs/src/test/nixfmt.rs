@@ -0,0 +1,41 @@
+use nixdoc::nixfmt::*;
+
+fn format(text: &str) -> String {
+    format_expression(text, 40).unwrap()
+}
+
+#[test]
+fn test_flat() {
+    assert_eq!(
+        format("{a=1;b=[ \"x\"   \"y\" ];}"),
+        "{ a = 1; b = [ \"x\" \"y\" ]; }"
+    );
+    assert_eq!(format("{ }"), "{ }");
+    assert_eq!(format("x:   {y}:y"), "x: { y }: y");
+}
+
+#[test]
+fn test_broken() {
+    assert_eq!(
+        format("{ hosts = [ \"a.example.org\" \"b.example.org\" ]; port = 80; }"),
+        "{\n  hosts = [\n    \"a.example.org\"\n    \"b.example.org\"\n  ];\n  port = 80;\n}"
+    );
+    assert_eq!(
+        format("with pkgs; [\n      hello\n  git ]"),
+        "with pkgs; [\n  hello\n  git\n]"
+    );
+    assert_eq!(
+        format("{ pkgs, lib, config, options, modulesPath, ... }@args: {\n x = 1; }"),
+        "{\n  pkgs,\n  lib,\n  config,\n  options,\n  modulesPath,\n  ...\n}@args: {\n  x = 1;\n}"
+    );
+    assert_eq!(
+        format("let\n  a = 1;\nin { inherit a; b = a + 1; }"),
+        "let\n  a = 1;\nin\n{ inherit a; b = a + 1; }"
+    );
+}
+
+#[test]
+fn test_not_formatted() {
+    assert_eq!(format_expression("{ a = 1; # one\n}", 40), None);
+    assert_eq!(format_expression("{ a = ", 40), None);
+}
@@ -0,0 +1,72 @@
+use std::collections::BTreeMap;
+
+use nixdoc::options::RenderOptions;
+use nixdoc::optiontypes::*;
+
+fn shape(description: &str) -> String {
+    parse_type(description)
+        .into_iter()
+        .map(|part| match part {
+            TypePart::Text(text) => text.to_string(),
+            TypePart::Known(_, base) => format!("<{}>", base.slug),
+            TypePart::Unknown(text) => format!("?{}?", text),
+        })
+        .collect()
+}
+
+#[test]
+fn test_parse_type() {
+    assert_eq!(
+        shape("null or (list of string)"),
+        "<null> or (list of <string>)"
+    );
+    assert_eq!(
+        shape("attribute set of (submodule)"),
+        "attribute set of (<submodule>)"
+    );
+    assert_eq!(
+        shape(r#"null or one of "a or b", "c""#),
+        "<null> or <enumeration>"
+    );
+    assert_eq!(
+        shape("integer between 0 and 100 (both inclusive) or floating point number"),
+        "<integer> or <float>"
+    );
+    assert_eq!(
+        shape("non-empty (list of (custom thing))"),
+        "non-empty (list of (?custom thing?))"
+    );
+    assert_eq!(
+        shape("function that evaluates to a(n) package"),
+        "function that evaluates to a(n) <package>"
+    );
+}
+
+#[test]
+fn test_link_type_and_appendix() {
+    let options = nixdoc::options::parse_options_json(
+        r#"{
+            "a": { "type": "null or (list of string)" },
+            "b": { "type": "boolean" },
+            "c": { "type": "list of my thing" }
+        }"#,
+    )
+    .unwrap();
+    let opts = RenderOptions::default();
+
+    assert_eq!(
+        link_type("null or (list of string)", &opts),
+        "[`null`](#sec-opt-types-null) or (list of [`string`](#sec-opt-types-string))"
+    );
+    assert_eq!(
+        render_types_appendix(&options, &opts),
+        "## Types {#sec-opt-types}\n\n\
+         ### boolean {#sec-opt-types-boolean}\n\n`true` or `false`.\n\n\
+         ### string {#sec-opt-types-string}\n\nA string. Definitions in several modules are not merged, so it can only be defined once.\n\n\
+         ### null {#sec-opt-types-null}\n\nThe value `null`, usually meaning that a setting is left unset.\n\n"
+    );
+    assert_eq!(
+        unknown_types(&options),
+        BTreeMap::from([("my thing".to_string(), vec!["c".to_string()])])
+    );
+}
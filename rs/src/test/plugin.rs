@@ -0,0 +1,88 @@
+use std::collections::BTreeMap;
+
+use nixdoc::plugin::*;
+use nixdoc::template::Template;
+use nixdoc::{collect_entries_from_str, CollectOptions};
+use serde_json::Value;
+
+const SOURCE: &str = "{\n  /**\n    Frobnicate.\n\n    @platform linux darwin\n    @maintainer alice\n    @maintainer bob\n    @stability stable\n    @allow missing-example\n  */\n  frobnicate = x: x;\n}\n";
+
+fn options() -> CollectOptions {
+    CollectOptions {
+        custom_tags: vec!["platform".to_string(), "maintainer".to_string()],
+        ..CollectOptions::new("lib", "misc")
+    }
+}
+
+#[test]
+fn test_tag_fields() {
+    let entries = collect_entries_from_str(SOURCE, &options()).unwrap();
+    assert_eq!(
+        serde_json::to_value(&entries[0].fields).unwrap(),
+        serde_json::json!({ "maintainer": ["alice", "bob"], "platform": "linux darwin" })
+    );
+
+    // Tags that are not registered stay in the text.
+    let entries = collect_entries_from_str(SOURCE, &CollectOptions::new("lib", "misc")).unwrap();
+    assert!(entries[0].fields.is_empty());
+    assert_eq!(
+        entries[0].description,
+        vec![
+            "Frobnicate.",
+            "@platform linux darwin\n@maintainer alice\n@maintainer bob"
+        ]
+    );
+}
+
+#[test]
+fn test_plugins_and_templates() {
+    let mut entries = collect_entries_from_str(SOURCE, &options()).unwrap();
+    let split = |field: &TagField| -> Result<Value, String> {
+        match (field.tag.as_str(), &field.value) {
+            ("platform", Value::String(s)) => Ok(s.split_whitespace().collect()),
+            _ => Ok(field.value.clone()),
+        }
+    };
+    apply_plugins(&mut entries, &[Box::new(split)]).unwrap();
+    assert_eq!(
+        entries[0].fields["platform"],
+        serde_json::json!(["linux", "darwin"])
+    );
+
+    let templates = TagTemplates(BTreeMap::from([(
+        "platform".to_string(),
+        Template::new("**Platforms:** {{#each value}}`{{this}}` {{/each}}").unwrap(),
+    )]));
+    templates.render(&mut entries).unwrap();
+    let mut output = String::new();
+    entries
+        .remove(0)
+        .write_section("function-library-", &mut output);
+    assert!(output.contains("**Platforms:** `linux` `darwin`\n\n"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_command_plugin() {
+    // Prints a single value, whatever the input.
+    let plugin = CommandPlugin {
+        program: "sh".to_string(),
+        args: vec!["-c".to_string(), "cat >/dev/null; echo '[1]'".to_string()],
+    };
+    let field = TagField {
+        entry: "lib.misc.frobnicate".to_string(),
+        tag: "revision".to_string(),
+        value: Value::from("1"),
+    };
+    let fields = vec![field.clone()];
+    assert_eq!(plugin.parse(&fields).unwrap(), vec![Value::from(1)]);
+    assert!(plugin.parse(&[field.clone(), field.clone()]).is_err());
+
+    // Echoes more than a pipe holds before reading all of its input.
+    let plugin = CommandPlugin {
+        program: "cat".to_string(),
+        args: vec![],
+    };
+    let fields = vec![field; 10_000];
+    assert_eq!(plugin.parse(&fields).unwrap().len(), fields.len());
+}
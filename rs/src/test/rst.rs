@@ -0,0 +1,27 @@
+use nixdoc::rst::*;
+
+#[test]
+fn test_markdown_to_rst() {
+    let markdown = "# Strings {#sec-strings}\n\nSee [](#opt-foo) and {file}`/etc/foo`s.\n\n- one\n- `two`\n\n::: {.warning}\nCareful_ with *this*.\n:::\n\n```nix\n{ a = 1; }\n```\n\n| a | b |\n|---|---|\n| 1 | 2 |\n";
+    assert_eq!(
+        markdown_to_rst(markdown),
+        ".. _sec-strings:\n\n\
+         Strings\n=======\n\n\
+         See :ref:`opt-foo` and :file:`/etc/foo`\\ s.\n\n\
+         - one\n- ``two``\n\n\
+         .. warning::\n\n   Careful\\_ with *this*.\n\n\
+         .. code-block:: nix\n\n   { a = 1; }\n\n\
+         .. list-table::\n   :header-rows: 1\n\n   * - a\n     - b\n   * - 1\n     - 2\n"
+    );
+}
+
+#[test]
+fn test_nested_headings_become_rubrics() {
+    let markdown = "## `lib.foo` {#function-library-lib.foo}\n\n::: {.example #ex}\n# usage example\n\n```nix\nfoo 1\n```\n:::\n";
+    assert_eq!(
+        markdown_to_rst(markdown),
+        ".. _function-library-lib.foo:\n\n\
+         ``lib.foo``\n-----------\n\n\
+         .. container:: example\n\n   .. rubric:: usage example\n\n   .. code-block:: nix\n\n      foo 1\n"
+    );
+}
@@ -0,0 +1,42 @@
+use nixdoc::options::parse_options_json;
+use nixdoc::search::*;
+
+#[test]
+fn test_tokenize() {
+    assert_eq!(
+        tokenize("Concatenate `concatStrings` in lib.strings, a b"),
+        vec![
+            "concatenate",
+            "concatstrings",
+            "concat",
+            "strings",
+            "in",
+            "lib",
+            "strings"
+        ]
+    );
+}
+
+#[test]
+fn test_inverted_index() {
+    let options = parse_options_json(
+        r#"{
+            "services.nginx.enable": {
+                "description": "Whether to enable *nginx*.",
+                "type": "boolean"
+            },
+            "services.nginx.package": {
+                "description": "The [nginx](https://nginx.org) package.",
+                "type": "boolean"
+            }
+        }"#,
+    )
+    .unwrap();
+    let index = InvertedIndex::new(option_documents(&options, "opt-"));
+
+    assert_eq!(index.documents[0].text, "Whether to enable nginx.");
+    assert_eq!(index.terms["nginx"], vec![0, 1]);
+    assert_eq!(index.terms["boolean"], vec![0, 1]);
+    assert_eq!(index.terms["services.nginx.enable"], vec![0]);
+    assert!(!index.terms.contains_key("https"));
+}
@@ -0,0 +1,38 @@
+use nixdoc::serve::*;
+
+fn site(pages: Result<Vec<Page>, String>) -> Site {
+    Site { version: 3, pages }
+}
+
+#[test]
+fn test_respond() {
+    let site = site(Ok(vec![Page {
+        name: "strings".to_string(),
+        title: "String functions".to_string(),
+        html: "<p>Concatenate.</p>".to_string(),
+    }]));
+
+    let index = site.respond("/");
+    assert_eq!(index.status, 200);
+    assert!(index
+        .body
+        .contains("<li><a href=\"/strings.html\">String functions</a></li>"));
+
+    let page = site.respond("/strings.html?x=1");
+    assert_eq!(page.status, 200);
+    assert!(page.body.contains("<title>String functions</title>"));
+    assert!(page.body.contains("<p>Concatenate.</p>"));
+    assert!(page.body.contains("/__nixdoc/version"));
+
+    assert_eq!(site.respond("/__nixdoc/version").body, "3");
+    assert_eq!(site.respond("/lists.html").status, 404);
+}
+
+#[test]
+fn test_respond_build_error() {
+    let site = site(Err("parse error in <lists.nix>".to_string()));
+    let response = site.respond("/lists.html");
+    assert_eq!(response.status, 500);
+    assert!(response.body.contains("parse error in &lt;lists.nix&gt;"));
+    assert_eq!(site.respond("/__nixdoc/version").status, 200);
+}
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+use nixdoc::signature::*;
+
+#[test]
+fn test_parse_signature() {
+    let sig = TypeSignature::parse("foldl' :: (b -> a -> b) -> b -> [a] -> b").unwrap();
+    assert_eq!(sig.name.as_deref(), Some("foldl'"));
+    assert_eq!(sig.arguments().len(), 3);
+    assert_eq!(sig.result(), &TypeExpr::Name { name: "b".into() });
+    assert_eq!(sig.to_string(), "foldl' :: (b -> a -> b) -> b -> [a] -> b");
+
+    for text in [
+        "mapAttrs :: (String -> a -> b) -> AttrsOf a -> AttrsOf b",
+        "optional :: Bool -> a -> [a]",
+        "makeOverridable :: (AttrSet -> a) -> AttrSet -> a",
+        "getExe :: { meta.mainProgram ? :: String; ... } -> String",
+        "toInt :: String -> Int | Null",
+        "String -> [String]",
+    ] {
+        assert_eq!(TypeSignature::parse(text).unwrap().to_string(), text);
+    }
+
+    assert_eq!(
+        TypeSignature::parse("f :: { a : Int, b :: [String] } -> Int")
+            .unwrap()
+            .to_string(),
+        "f :: { a :: Int; b :: [String] } -> Int"
+    );
+    assert!(TypeSignature::parse("f :: (a -> b").is_none());
+    assert!(TypeSignature::parse("a -> ").is_none());
+    assert!(TypeSignature::parse("See `lib.foo`.").is_none());
+}
+
+#[test]
+fn test_render_aligned() {
+    let sig = TypeSignature::parse("foldl' :: (b -> a -> b) -> b -> [a] -> b").unwrap();
+    assert_eq!(
+        sig.render(20),
+        "foldl' :: (b -> a -> b)\n       -> b\n       -> [a]\n       -> b"
+    );
+    let sig = TypeSignature::parse("(b -> a -> b) -> b").unwrap();
+    assert_eq!(sig.render(10), "   (b -> a -> b)\n-> b");
+}
+
+#[test]
+fn test_extract_signature() {
+    let doc = "Add.\n\n## Type\n\n```\nadd ::\n  Int -> Int -> Int\n```\n\n## Example\n";
+    let links = HashMap::from([("Int".to_string(), "#sec-int".to_string())]);
+    let (doc, sig) = extract_signature(doc, &links).unwrap();
+    assert_eq!(sig.arguments().len(), 2);
+    assert_eq!(
+        doc,
+        "Add.\n\n## Type\n\n```\nadd :: Int -> Int -> Int\n```\n\nTypes: [`Int`](#sec-int)\n\n## Example\n"
+    );
+}
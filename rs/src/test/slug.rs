@@ -0,0 +1,29 @@
+use nixdoc::slug::*;
+
+#[test]
+fn test_assign_anchors() {
+    let make = |name: &str| format!("opt-{}", name.replace(['.', '-'], "-"));
+    // `a.b-2` takes `opt-a-b-2` itself, so the second `opt-a-b` is `-3`.
+    let anchors = assign_anchors(["a.b.c", "a.b-c", "a.b", "a-b", "a.b-2"], make);
+    assert_eq!(anchors.get("a-b"), Some("opt-a-b"));
+    assert_eq!(anchors.get("a.b"), Some("opt-a-b-3"));
+    assert_eq!(anchors.get("a.b-2"), Some("opt-a-b-2"));
+    assert_eq!(anchors.get("a.b-c"), Some("opt-a-b-c"));
+    assert_eq!(anchors.get("a.b.c"), Some("opt-a-b-c-2"));
+    assert_eq!(
+        anchors.collisions[0],
+        AnchorCollision {
+            anchor: "opt-a-b".to_string(),
+            owner: "a-b".to_string(),
+            name: "a.b".to_string(),
+            assigned: "opt-a-b-3".to_string(),
+        }
+    );
+    assert_eq!(anchors.collisions.len(), 2);
+
+    // The assignment does not depend on the input order.
+    assert_eq!(
+        assign_anchors(["a.b-2", "a-b", "a.b.c", "a.b", "a.b-c"], make),
+        anchors
+    );
+}
@@ -0,0 +1,83 @@
+use std::path::{Path, PathBuf};
+
+use nixdoc::commonmark::Span;
+use nixdoc::source::*;
+
+#[test]
+fn test_parse_source() {
+    let spec = SourceSpec::parse("github:NixOS/nixpkgs/nixos-24.05#lib/strings.nix").unwrap();
+    assert_eq!(spec.subpath.as_deref(), Some("lib/strings.nix"));
+    assert_eq!(
+        format!("{:?}", spec.provider),
+        "TarballSource { url: \"https://github.com/NixOS/nixpkgs/archive/nixos-24.05.tar.gz\" }"
+    );
+
+    let spec = SourceSpec::parse("git+https://example.com/lib.git?ref=main&rev=abc#a.nix").unwrap();
+    assert_eq!(
+        format!("{:?}", spec.provider),
+        "GitSource { url: \"https://example.com/lib.git\", git_ref: Some(\"main\"), rev: Some(\"abc\") }"
+    );
+
+    assert!(SourceSpec::parse("github:NixOS").is_err());
+    assert!(SourceSpec::parse("git+https://example.com/lib.git?dir=lib").is_err());
+    // Values git or curl would take for options.
+    assert!(SourceSpec::parse("git+--upload-pack=touch /tmp/x#a.nix").is_err());
+    assert!(SourceSpec::parse("git+https://example.com/lib.git?ref=--upload-pack=x").is_err());
+    assert!(SourceSpec::parse("tarball+-K/etc/passwd#a.nix").is_err());
+}
+
+#[test]
+fn test_source_links() {
+    let span = Span {
+        start_line: 10,
+        start_column: 3,
+        end_line: 14,
+        end_column: 5,
+        start_byte: 120,
+        end_byte: 180,
+    };
+    let links = SourceLinks {
+        base_url: "https://github.com/NixOS/nixpkgs/blob/".to_string(),
+        revision: Some("abc123".to_string()),
+    };
+    assert_eq!(
+        links.url("./lib/strings.nix", &span),
+        "https://github.com/NixOS/nixpkgs/blob/abc123/lib/strings.nix#L10-L14"
+    );
+    let links = SourceLinks {
+        base_url: "https://example.com/{path}?rev={revision}#line-{line}".to_string(),
+        revision: None,
+    };
+    assert_eq!(
+        links.url("lib/strings.nix", &span),
+        "https://example.com/lib/strings.nix?rev=HEAD#line-10"
+    );
+}
+
+#[test]
+fn test_local_source() {
+    let work_dir = Path::new("unused");
+    let spec = SourceSpec::parse("path:test#strings.nix").unwrap();
+    assert_eq!(
+        spec.resolve(work_dir).unwrap(),
+        PathBuf::from("test/strings.nix")
+    );
+    assert_eq!(
+        SourceSpec::parse("test/strings.nix")
+            .unwrap()
+            .resolve(work_dir)
+            .unwrap(),
+        PathBuf::from("test/strings.nix")
+    );
+    assert!(SourceSpec::parse("path:test#missing.nix")
+        .unwrap()
+        .resolve(work_dir)
+        .is_err());
+    for subpath in ["../Cargo.toml", "/etc/passwd", "modules/../../Cargo.toml"] {
+        let err = SourceSpec::parse(&format!("path:test#{}", subpath))
+            .unwrap()
+            .resolve(work_dir)
+            .unwrap_err();
+        assert!(err.contains("not a relative path"), "{}", err);
+    }
+}
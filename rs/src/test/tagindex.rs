@@ -0,0 +1,52 @@
+use nixdoc::options::parse_options_json;
+use nixdoc::tagindex::*;
+use nixdoc::{collect_entries_from_str, CollectOptions};
+
+#[test]
+fn groups_by_keyword() {
+    let entries = collect_entries_from_str(
+        r#"{
+          /** Fetch a URL.
+
+             Tags: network, download */
+          fetchurl = url: url;
+          /** Resolve a host.
+
+             Tags: network */
+          resolve = host: host;
+          /** Untagged. */
+          id = x: x;
+        }"#,
+        &CollectOptions::new("lib", "fetchers"),
+    )
+    .unwrap();
+    let options = parse_options_json(
+        r##"{
+          "networking.hostName": { "description": "The host name.\n\n# Keywords\n\n- network" },
+          "boot.enable": { "description": "Boot." }
+        }"##,
+    )
+    .unwrap();
+    assert_eq!(
+        options["networking.hostName"]
+            .description
+            .as_ref()
+            .unwrap()
+            .as_str(),
+        "The host name."
+    );
+
+    let mut index = TagIndex::default();
+    index.add_functions(&entries, "function-library-", "{category}.html");
+    index.add_options(&options, "opt-", "options.html");
+    assert_eq!(
+        index.to_markdown("Tags"),
+        "# Tags {#sec-tags}\n\n\
+         ## download {#tag-download}\n\n\
+         - [`lib.fetchers.fetchurl`](fetchers.html#function-library-lib.fetchers.fetchurl)\n\n\
+         ## network {#tag-network}\n\n\
+         - [`lib.fetchers.fetchurl`](fetchers.html#function-library-lib.fetchers.fetchurl)\n\
+         - [`lib.fetchers.resolve`](fetchers.html#function-library-lib.fetchers.resolve)\n\
+         - [`networking.hostName`](options.html#opt-networking-hostName) (option)\n"
+    );
+}
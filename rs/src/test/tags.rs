@@ -0,0 +1,79 @@
+use nixdoc::tags::*;
+
+#[test]
+fn test_extract_tags() {
+    let doc =
+        "Frobnicate.\n\n@stability experimental\n@platform linux\n@param x\n\n```\n@since 1.0\n```";
+    let (text, tags) = extract_tags(doc, &["platform".to_string()]);
+    assert_eq!(text, "Frobnicate.\n\n@param x\n\n```\n@since 1.0\n```");
+    let names: Vec<&str> = tags.iter().map(|tag| tag.name.as_str()).collect();
+    assert_eq!(names, vec!["stability", "platform"]);
+}
+
+#[test]
+fn test_extract_deprecated_section() {
+    let doc = "Frobnicate.\n\n# Deprecated\n\nUse `lib.bar` instead.\n\n```\n# not a heading\n```\n\n## Details\n\nMore.\n\n# Example\n\nfoo";
+    let (text, notice) = extract_deprecated_section(doc);
+    assert_eq!(text, "Frobnicate.\n\n# Example\n\nfoo");
+    assert_eq!(
+        notice.as_deref(),
+        Some("Use `lib.bar` instead.\n\n```\n# not a heading\n```\n\n## Details\n\nMore.")
+    );
+
+    let (_, notice) = extract_deprecated_section("Frobnicate.\n\n## Deprecated");
+    assert_eq!(notice.as_deref(), Some(""));
+    assert_eq!(extract_deprecated_section("Frobnicate.").1, None);
+}
+
+#[test]
+fn test_extract_since() {
+    let (text, since) = extract_since("Frobnicate.\n\nSince: 24.05\n\n```\nSince: 1.0\n```");
+    assert_eq!(text, "Frobnicate.\n\n```\nSince: 1.0\n```");
+    assert_eq!(since.as_deref(), Some("24.05"));
+
+    let (text, since) = extract_since("Frobnicate.\n\n# Since\n\n23.11\n\n# Example\n\nfoo");
+    assert_eq!(text, "Frobnicate.\n\n# Example\n\nfoo");
+    assert_eq!(since.as_deref(), Some("23.11"));
+
+    assert_eq!(extract_since("Frobnicate.").1, None);
+}
+
+#[test]
+fn test_extract_keywords() {
+    let (text, keywords) =
+        extract_keywords("Fetch a URL.\n\nTags: network, `fetch`, network\n\n```\nTags: no\n```");
+    assert_eq!(text, "Fetch a URL.\n\n```\nTags: no\n```");
+    assert_eq!(keywords, vec!["network", "fetch"]);
+
+    let doc = "Fetch a URL.\n\n### Keywords\n\n- network\n- http, https\n\n### Example\n\nfoo";
+    let (text, keywords) = extract_keywords(doc);
+    assert_eq!(text, "Fetch a URL.\n\n### Example\n\nfoo");
+    assert_eq!(keywords, vec!["network", "http", "https"]);
+
+    assert_eq!(extract_keywords("Fetch.\n\n# Tags\n\nnet").1, vec!["net"]);
+    assert!(extract_keywords("Fetch.").1.is_empty());
+}
+
+#[test]
+fn test_extract_throws() {
+    let doc = "Take the head.\n\n# Throws\n\n- If `list` is\n  empty.\n- If `list` is not a list.\n\n# Example\n\nfoo";
+    let (text, conditions) = extract_throws(doc);
+    assert_eq!(text, "Take the head.\n\n# Example\n\nfoo");
+    assert_eq!(
+        conditions,
+        vec!["If `list` is empty.", "If `list` is not a list."]
+    );
+
+    let (_, conditions) = extract_throws("Parse.\n\n## Errors\n\nAborts on\ninvalid input.");
+    assert_eq!(conditions, vec!["Aborts on invalid input."]);
+    assert!(extract_throws("Parse.").1.is_empty());
+}
+
+#[test]
+fn test_compare_versions() {
+    use std::cmp::Ordering;
+    assert_eq!(compare_versions("23.11", "24.05"), Ordering::Less);
+    assert_eq!(compare_versions("1.10", "1.9"), Ordering::Greater);
+    assert_eq!(compare_versions("v1.2", "1.2"), Ordering::Equal);
+    assert_eq!(compare_versions("1.2", "1.2.1"), Ordering::Less);
+}
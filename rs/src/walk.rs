@@ -0,0 +1,31 @@
+//! Recursive discovery of input files.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Recursively find all files below `root` with the given extension, sorted
+/// by path. Hidden directories (starting with `.`) are skipped.
+pub fn files_with_extension(root: &Path, extension: &str) -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let hidden = path
+                .file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with('.'));
+            if path.is_dir() {
+                if !hidden {
+                    pending.push(path);
+                }
+            } else if path.extension().is_some_and(|ext| ext == extension) {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
@@ -0,0 +1,120 @@
+//! Cross-references between documented entries.
+//!
+//! Doc comments can refer to other entries by name, either as an empty link
+//! (`[](#lib.strings.concat)`) or with the `ref` role
+//! (``{ref}`lib.strings.concat` ``). Both are turned into links to the
+//! entry's anchor. Empty links to other anchors (e.g. `[](#sec-foo)`) are left
+//! alone, so only targets starting with the category prefix (`lib.`) or the
+//! anchor prefix are treated as references.
+
+use std::collections::HashMap;
+
+use crate::commonmark::{get_identifier, ManualEntry};
+use crate::format::get_fence;
+
+/// A reference to an entry that does not exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DanglingReference {
+    /// Title of the entry containing the reference.
+    pub entry: String,
+    /// The referenced name.
+    pub target: String,
+}
+
+/// Resolve the references in the descriptions of `entries`, which are
+/// documented on `page`. `known` maps the identifiers (e.g.
+/// `lib.strings.concat`) references may point to to the page documenting
+/// them; references to entries of other pages link to that page.
+///
+/// Dangling references are rendered as inline code and returned.
+pub fn resolve_references(
+    entries: &mut [ManualEntry],
+    known: &HashMap<String, String>,
+    page: &str,
+    prefix: &str,
+    anchor_prefix: &str,
+) -> Vec<DanglingReference> {
+    let mut dangling = vec![];
+    for entry in entries.iter_mut() {
+        let (_, title) = entry.get_ident_title();
+        let mut fence = None;
+        let mut targets = vec![];
+        for paragraph in &mut entry.description {
+            *paragraph = paragraph
+                .lines()
+                .map(|line| {
+                    let trimmed = line.trim_start();
+                    if let Some((count, ch)) = fence {
+                        if get_fence(trimmed, false).is_some_and(|(c, h)| h == ch && c >= count) {
+                            fence = None;
+                        }
+                        return line.to_string();
+                    }
+                    if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                        fence = get_fence(trimmed, true);
+                        return line.to_string();
+                    }
+                    resolve_line(line, known, page, prefix, anchor_prefix, &mut targets)
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+        entry.summary = crate::format::summary(&entry.description.join("\n\n"));
+        dangling.extend(targets.into_iter().map(|target| DanglingReference {
+            entry: title.clone(),
+            target,
+        }));
+    }
+    dangling
+}
+
+/// Resolve the references of a single line, collecting dangling targets.
+fn resolve_line(
+    line: &str,
+    known: &HashMap<String, String>,
+    page: &str,
+    prefix: &str,
+    anchor_prefix: &str,
+    dangling: &mut Vec<String>,
+) -> String {
+    let mut out = String::new();
+    let mut rest = line;
+    loop {
+        let link = rest.find("[](#");
+        let role = rest.find("{ref}`");
+        let (start, open, close) = match (link, role) {
+            (Some(l), Some(r)) if r < l => (r, "{ref}`", "`"),
+            (Some(l), _) => (l, "[](#", ")"),
+            (None, Some(r)) => (r, "{ref}`", "`"),
+            (None, None) => break,
+        };
+        let after = &rest[start + open.len()..];
+        let Some(len) = after.find(close) else {
+            break;
+        };
+        let target = &after[..len];
+        let name = target.strip_prefix(anchor_prefix).unwrap_or(target);
+        let is_reference = open == "{ref}`"
+            || (!anchor_prefix.is_empty() && target.starts_with(anchor_prefix))
+            || (!prefix.is_empty() && target.starts_with(&format!("{}.", prefix)));
+        out.push_str(&rest[..start]);
+        if !is_reference {
+            out.push_str(&rest[start..start + open.len() + len + close.len()]);
+        } else {
+            let ident = get_identifier("", "", name);
+            if let Some(target_page) = known.get(&ident) {
+                let target_page = if target_page == page { "" } else { target_page };
+                out.push_str(&format!(
+                    "[`{}`]({}#{}{})",
+                    name, target_page, anchor_prefix, ident
+                ));
+            } else {
+                dangling.push(name.to_string());
+                out.push_str(&format!("`{}`", name));
+            }
+        }
+        rest = &after[len + close.len()..];
+    }
+    out.push_str(rest);
+    out
+}
@@ -0,0 +1,33 @@
+{
+  /**
+    Arguments documented by regular comments.
+  */
+  legacy =
+    # The value to return.
+    x:
+    {
+      # Whether to frobnicate.
+      frobnicate ? false,
+    }:
+    x;
+
+  /**
+    Arguments described in prose.
+
+    # Inputs
+
+    Takes two values and returns the first.
+  */
+  prose = x: y: x;
+
+  /**
+    Arguments partly documented in a section.
+
+    # Arguments
+
+    `x`
+
+    : The value to return.
+  */
+  partial = x: y: x;
+}
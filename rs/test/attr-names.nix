@@ -0,0 +1,38 @@
+let
+  name = "generated";
+
+  /**
+    Bound with a string name.
+  */
+  "with space" = x: x;
+in
+{
+  /**
+    A quoted identifier is shown without quotes.
+  */
+  "plain" = x: x;
+
+  /**
+    Names that are not identifiers stay quoted.
+  */
+  "foo bar" = x: y: x;
+
+  /**
+    Keywords must be quoted, too.
+  */
+  ${"in"} = true;
+
+  /**
+    Only known when evaluating, so skipped.
+  */
+  ${name} = x: x;
+
+  inherit "with space";
+
+  nested = {
+    /**
+      A member of a string-named nested set.
+    */
+    "a.b" = 1;
+  };
+}
@@ -0,0 +1,30 @@
+{
+  /**
+    The largest integer.
+  */
+  maxInt = 9223372036854775807;
+
+  /**
+    The smallest integer.
+  */
+  minInt = -9223372036854775807 - 1;
+
+  /**
+    Greeting printed on login.
+  */
+  motd = "Welcome to ${hostName}!";
+
+  /**
+    Exit codes of the installer.
+  */
+  exitCodes = {
+    success = 0;
+    failure = 1;
+    interrupted = 130;
+  };
+
+  /**
+    Apply a function to every element of a list.
+  */
+  map = builtins.map;
+}
@@ -0,0 +1,18 @@
+/**
+  List manipulation functions.
+*/
+{ lib }:
+let
+  /**
+    Apply a function to every element of a list.
+  */
+  map = f: list: builtins.map f list;
+in
+{
+  inherit map;
+
+  /**
+    Return the first element of a list.
+  */
+  head = { list, ... }: builtins.head list;
+}
@@ -0,0 +1,23 @@
+/**
+  Trivial functions.
+*/
+{
+  /**
+    The identity function.
+  */
+  id = x: x;
+
+  /**
+    Return the first of two arguments.
+  */
+  const =
+    /**
+      Value to return
+    */
+    x:
+    /**
+      Ignored value
+    */
+    y:
+    x;
+}
@@ -0,0 +1,27 @@
+{
+  /**
+    Concatenate two strings.
+
+    @deprecated since 24.05, use `lib.strings.concat` instead
+  */
+  append = a: b: a + b;
+
+  /**
+    Repeat a string.
+
+    # Deprecated
+
+    Use `lib.strings.replicate`, which takes the count first.
+
+    # Example
+
+    ```nix
+    repeat "ab" 2
+    => "abab"
+    ```
+  */
+  repeat = s: n: s;
+
+  /** Concatenate two strings. */
+  concat = a: b: a + b;
+}
@@ -3,19 +3,19 @@
     Doc-comment
   */
   omited =
-    # Not visible
+    # The argument
     arg: 1;
 
   /**
     Doc-comment
   */
   multiple =
-    # Not visible
+    # The first argument
     arg:
-    # Not visible
+    # The second argument
     foo:
     /**
-      Not visible
+      The third argument
     */
     bar:
     1;
@@ -0,0 +1,22 @@
+{
+  /**
+    Adds one to a number.
+  */
+  increment = x: x + 1;
+
+  /**
+    Returns its argument unchanged.
+  */
+  id = x: x;
+}
+// {
+  /**
+    Adds one to a number, saturating at `max`.
+  */
+  increment = max: x: if x >= max then max else x + 1;
+
+  /**
+    Ignores its second argument.
+  */
+  const = x: _: x;
+}
@@ -0,0 +1,42 @@
+{
+  /**
+    Documented on the set.
+  */
+  onSet = {
+    __functor = self: x: y: x + y;
+    value = 1;
+  };
+
+  documentedOnFunctor = {
+    /**
+      Documented on the functor.
+
+      # Arguments
+
+      `name`
+      : The name to greet.
+    */
+    __functor = self: { name, greeting ? "Hello" }: "${greeting}, ${name}!";
+  };
+
+  /**
+    Takes no arguments besides the set itself.
+  */
+  constant = {
+    __functor = self: self.value;
+    value = 42;
+  };
+
+  undocumented = {
+    __functor = self: x: x;
+  };
+
+  nested = {
+    inner = {
+      /**
+        A callable set one level down.
+      */
+      __functor = self: x: x;
+    };
+  };
+}
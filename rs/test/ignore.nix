@@ -0,0 +1,27 @@
+{
+  /** Add one. */
+  inc = x: x + 1;
+
+  # nixdoc-ignore
+  /** Escape hatch, subject to change. */
+  unsafeInc = x: x + 1;
+
+  # nixdoc-ignore
+  internals = {
+    /** Not documented either. */
+    helper = x: x;
+  };
+
+  strings = {
+    /** Concatenate two strings. */
+    concat = a: b: a + b;
+
+    # nixdoc-ignore
+    unsafeConcat = a: b: a + b;
+  };
+
+  dec = x: x - 1; # nixdoc-ignore
+
+  /** Subtract two. */
+  dec2 = x: x - 2;
+}
@@ -0,0 +1,7 @@
+{ lib }:
+
+/**
+  Clamp a number to a range.
+*/
+low: high: x:
+if x < low then low else if x > high then high else x
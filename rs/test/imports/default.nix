@@ -0,0 +1,14 @@
+{
+  double = import ./double.nix;
+
+  clamp = import ./clamp.nix { lib = null; };
+
+  /**
+    Documented at the binding, which takes precedence.
+  */
+  twice = import ./double.nix;
+
+  undocumented = import ./undocumented.nix;
+
+  missing = import ./missing.nix;
+}
@@ -0,0 +1,11 @@
+/**
+  Double a number.
+
+  # Example
+
+  ```nix
+  double 2
+  => 4
+  ```
+*/
+x: x * 2
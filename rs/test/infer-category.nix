@@ -0,0 +1,11 @@
+/**
+  # Inferred category functions
+
+  The category is named after this file.
+*/
+{
+  /**
+    Identity.
+  */
+  id = x: x;
+}
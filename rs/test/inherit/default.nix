@@ -0,0 +1,29 @@
+let
+  strings = import ./strings.nix;
+
+  helpers = {
+    /**
+      Identity function.
+    */
+    id = x: x;
+
+    nested = {
+      /**
+        Constant function.
+      */
+      const = x: y: x;
+    };
+  };
+in
+{
+  inherit (import ./lists.nix) map;
+  inherit (strings) concat;
+  inherit (helpers) id;
+  inherit (helpers.nested) const;
+  inherit (import ./missing.nix) nothing;
+
+  identity = helpers.id;
+  constant = helpers.nested.const;
+  join = strings.concat;
+  missing = helpers.missing;
+}
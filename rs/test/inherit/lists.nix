@@ -0,0 +1,8 @@
+{
+  inherit (import ./default.nix) id;
+
+  /**
+    Apply `f` to every element of `list`.
+  */
+  map = f: list: builtins.map f list;
+}
@@ -0,0 +1,11 @@
+{
+  /**
+    Concatenate two strings.
+  */
+  concat = a: b: a + b;
+
+  /**
+    Not inherited.
+  */
+  upper = s: s;
+}
@@ -0,0 +1,39 @@
+/**
+  Helpers with relaxed documentation rules.
+
+  @allow empty-doc
+*/
+{
+  /**
+    Has an example.
+
+    # Example
+
+    ```nix
+    withExample 1
+    => 1
+    ```
+  */
+  withExample = x: x;
+
+  /**
+    No example, but it is allowed.
+
+    @allow missing-example
+  */
+  allowedNoExample = x: x;
+
+  /**
+    No example.
+  */
+  noExample = x: x;
+
+  /** */
+  empty = x: x;
+
+  # @allow missing-doc
+  undocumented = x: x;
+
+  # Legacy comment.
+  legacy = x: x;
+}
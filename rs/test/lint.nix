@@ -0,0 +1,21 @@
+{
+  /**
+    Documented.
+  */
+  documented = x: x;
+
+  undocumented = x: x;
+
+  /** */
+  empty = x: x;
+
+  # Documented with a legacy comment.
+  legacy = x: x;
+
+  /* Also a legacy comment. */
+  legacyBlock = x: x;
+
+  hidden = {
+    __internal = true;
+  };
+}
@@ -0,0 +1,36 @@
+{
+  /**
+    A callable set with metadata.
+  */
+  memoized = {
+    __functor = self: x: x;
+    meta = {
+      maintainers = [
+        "alice"
+        "bob"
+      ];
+      since = "24.05";
+      deprecated = "use `plain` instead";
+    };
+  };
+
+  /**
+    Marked as internal with a sibling attribute, so it is hidden.
+  */
+  hidden = {
+    __functor = self: x: x;
+    __internal = true;
+  };
+
+  /**
+    Marked as internal through `meta`, so it is hidden.
+  */
+  alsoHidden = {
+    meta.internal = true;
+  };
+
+  /**
+    A plain function without metadata.
+  */
+  plain = x: x;
+}
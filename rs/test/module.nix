@@ -0,0 +1,63 @@
+{
+  config,
+  lib,
+  pkgs,
+  ...
+}:
+let
+  cfg = config.services.web;
+in
+{
+  options.services.web = {
+    enable = lib.mkEnableOption "the web server";
+
+    package = lib.mkPackageOption pkgs "nginx" { };
+
+    port = lib.mkOption {
+      type = lib.types.port;
+      default = 80;
+      description = "Port to listen on.";
+    };
+
+    virtualHosts = lib.mkOption {
+      type = with lib.types; attrsOf (submodule { options.root = mkOption { type = path; }; });
+      default = { };
+      example = lib.literalExpression ''
+        {
+          "example.org".root = ./www;
+        }
+      '';
+      description = ''
+        Virtual hosts to serve.
+
+        Each host serves the files below its `root`.
+      '';
+    };
+
+    logLevel = lib.mkOption {
+      type = lib.types.nullOr (lib.types.enum [
+        "info"
+        "debug"
+      ]);
+      default = null;
+      defaultText = lib.literalMD "`info` for production systems";
+      description = "How much to log.";
+    };
+
+    stateDir = lib.mkOption {
+      type = lib.types.str;
+      default = "/var/lib/web";
+      readOnly = true;
+      description = "Directory of the server's state.";
+    };
+
+    secret = lib.mkOption {
+      type = lib.types.str;
+      internal = true;
+    };
+
+    extraConfig = mkExtraConfigOption "web";
+  };
+
+  config = lib.mkIf cfg.enable { };
+}
@@ -0,0 +1,11 @@
+/**
+  # Module tree
+
+  Imports every module of the tree.
+*/
+{
+  imports = [
+    ./services/web.nix
+    ./programs/editor.nix
+  ];
+}
@@ -0,0 +1,4 @@
+{ lib, ... }:
+{
+  options.programs.editor.name = lib.mkOption { type = lib.types.str; };
+}
@@ -0,0 +1,12 @@
+/**
+  # Web server
+
+  Runs a web server for static files,
+  with optional TLS | HTTP/2 support.
+
+  More details follow here.
+*/
+{ lib, ... }:
+{
+  options.services.web.enable = lib.mkEnableOption "the web server";
+}
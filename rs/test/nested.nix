@@ -0,0 +1,33 @@
+{
+  /**
+    String manipulation functions.
+  */
+  strings = {
+    /**
+      Concatenate two strings.
+    */
+    concat = a: b: a + b;
+  };
+
+  lists = {
+    /**
+      Apply `f` to every element of `list`.
+    */
+    map = f: list: builtins.map f list;
+
+    unsafe = {
+      /**
+        First element of `list`.
+      */
+      head = list: builtins.head list;
+    };
+
+    hidden = {
+      __internal = true;
+      /**
+        Not documented, the set is internal.
+      */
+      secret = x: x;
+    };
+  };
+}
@@ -0,0 +1,19 @@
+{
+  /**
+    Build a greeting.
+  */
+  greet =
+    {
+      /**
+        Who to greet
+      */
+      name,
+      /**
+        Greeting word, e.g. `Hello` | `Hi`
+      */
+      greeting ? "Hello",
+      punctuation ? "!",
+      ...
+    }@args:
+    "${greeting}, ${name}${punctuation}";
+}
@@ -0,0 +1,35 @@
+{ lib }:
+rec {
+  /**
+    Arithmetic helpers.
+  */
+  helpers = {
+    /**
+      Double a number.
+    */
+    double = x: x * 2;
+  };
+
+  inherit (helpers) double;
+
+  /**
+    Functions on lists.
+  */
+  lists = rec {
+    internal = {
+      /**
+        Return the first element of a list.
+      */
+      head = list: builtins.elemAt list 0;
+    };
+
+    inherit (internal) head;
+  };
+
+  # Self-references are not followed.
+  loop = {
+    inherit (loop) x;
+  };
+
+  inherit (loop) x;
+}
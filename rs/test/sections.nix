@@ -0,0 +1,29 @@
+{
+  /**
+    Not in any section.
+  */
+  first = x: x;
+
+  /**
+    ## Assertions
+
+    Functions for checking conditions.
+  */
+
+  /**
+    Throw `msg` unless `pred` holds.
+  */
+  assertMsg = pred: msg: pred || throw msg;
+
+  /**
+    Check that `val` is one of `xs`.
+  */
+  assertOneOf = name: val: xs: true;
+
+  /** ## Strings */
+
+  /**
+    Concatenate two strings.
+  */
+  concat = a: b: a + b;
+}
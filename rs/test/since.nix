@@ -0,0 +1,36 @@
+{
+  /**
+    Introduced in a `Since:` line.
+
+    Since: 24.05
+  */
+  fromLine = x: x;
+
+  /**
+    Introduced in a `# Since` section.
+
+    # Since
+
+    23.11
+
+    # Example
+
+    ```nix
+    fromSection 1
+    => 1
+    ```
+  */
+  fromSection = x: x;
+
+  /**
+    Introduced in a tag.
+
+    @since 24.11
+  */
+  fromTag = x: x;
+
+  /**
+    Without a version.
+  */
+  unversioned = x: x;
+}
@@ -0,0 +1,26 @@
+{
+  /**
+    A new function whose interface may still change.
+
+    @stability experimental
+
+    ```nix
+    # @stability inside code blocks is left alone
+    ```
+  */
+  fresh = x: x;
+
+  /**
+    A well-established function.
+
+    @stability stable
+  */
+  settled = x: x;
+
+  /**
+    Unknown stability levels are dropped.
+
+    @stability wobbly
+  */
+  unknown = x: x;
+}
@@ -0,0 +1,16 @@
+{
+  /**
+    Apply `f` to `x`.
+  */
+  applyTo = lib.flip (f: x: f x);
+
+  /**
+    Expensive lookup, cached.
+  */
+  lookup = internal.memoize { size = 10; } ({ key, default ? null }: key);
+
+  /**
+    Not a known wrapper, so no arguments are shown.
+  */
+  other = lib.unknown (a: a);
+}
@@ -0,0 +1,17 @@
+{
+  /**
+    Concatenate two strings. See also [](#lib.xref.join) and
+    {ref}`lib.lists.concat`, or the [](#sec-strings) section.
+
+    ```nix
+    # [](#lib.xref.unknown) is left alone in code
+    ```
+  */
+  concat = a: b: a + b;
+
+  /**
+    Join strings with a separator, like [](#function-library-lib.xref.concat).
+    Refers to {ref}`lib.xref.missing`.
+  */
+  join = sep: list: builtins.concatStringsSep sep list;
+}